@@ -1,27 +1,43 @@
-use pichu::Markdown;
+use pichu::{Collection, Markdown, RenderCache};
 use serde::Deserialize;
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Hash, Deserialize)]
 struct Blogpost {
     title: String,
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Keep the parsed posts and the render cache around between rebuilds, so
+    // only changed posts are reparsed and re-rendered.
+    let mut blog = Collection::<Markdown<Blogpost>>::markdown("examples/content/blog/*.md")?;
+    let cache = RenderCache::new();
+    build(&blog, &cache)?;
+
     pichu::watch(vec!["examples/content", "examples/assets"], |paths| {
         println!("Paths changed: {:?}", paths);
-        if let Err(e) = build() {
-            eprintln!("Build error: {}", e);
+        match blog.update(&paths) {
+            Ok(true) => {
+                if let Err(e) = build(&blog, &cache) {
+                    eprintln!("Build error: {}", e);
+                }
+            }
+            Ok(false) => {}
+            Err(e) => eprintln!("Parse error: {}", e),
         }
     })?;
     Ok(())
 }
 
-fn build() -> Result<(), Box<dyn std::error::Error>> {
-    let _blog = pichu::glob("examples/content/blog/*.md")?
-        .parse_markdown::<Blogpost>()?
-        .render_each(render_blogpost, |post| {
-            format!("examples/dist/maud/{}/index.html", post.basename)
-        })?;
+fn build(
+    blog: &Collection<Markdown<Blogpost>>,
+    cache: &RenderCache,
+) -> Result<(), Box<dyn std::error::Error>> {
+    blog.parsed().render_each_cached(
+        cache,
+        |post| (post.frontmatter.clone(), post.markdown.clone()),
+        render_blogpost,
+        |post| format!("examples/dist/watch/{}/index.html", post.basename),
+    )?;
 
     Ok(())
 }
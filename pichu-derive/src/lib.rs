@@ -0,0 +1,217 @@
+//! Derive macros for [pichu](https://docs.rs/pichu), re-exported by its
+//! `derive` feature.
+
+#![deny(warnings)]
+#![deny(clippy::pedantic, clippy::unwrap_used)]
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parse_macro_input, spanned::Spanned, Attribute, Data, DataStruct, DeriveInput, Error, Field,
+    Fields, FieldsNamed, Ident, LitStr, Type,
+};
+
+/// The accessors of the `Page` trait that can be derived from fields.
+const ACCESSORS: [&str; 6] = ["title", "url", "date", "updated", "summary", "tags"];
+
+/// Derive `pichu::Page` from the fields of a frontmatter struct. See the
+/// documentation of the re-export in pichu.
+#[proc_macro_derive(Page, attributes(page))]
+pub fn derive_page(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let Data::Struct(DataStruct {
+        fields: Fields::Named(fields),
+        ..
+    }) = &input.data
+    else {
+        return Err(Error::new(
+            input.span(),
+            "Page can only be derived for structs with named fields",
+        ));
+    };
+    let url_format = url_format(&input.attrs)?;
+    let accessors = accessors(fields)?;
+    let field = |name: &str| {
+        accessors
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, field)| *field)
+    };
+
+    let title = field("title").ok_or_else(|| {
+        Error::new(
+            input.span(),
+            "missing title, add a `title` field or mark one with #[page(title)]",
+        )
+    })?;
+    let title_ident = &title.ident;
+    let url = match (&url_format, field("url")) {
+        (Some(format), _) => url_from_format(format)?,
+        (None, Some(field)) => {
+            let ident = &field.ident;
+            if last_segment(&field.ty).is_some_and(|segment| segment == "UrlPath") {
+                quote! { ::std::clone::Clone::clone(&self.#ident) }
+            } else {
+                url_from_expr(&quote! { &self.#ident })
+            }
+        }
+        (None, None) => {
+            return Err(Error::new(
+                input.span(),
+                "missing url, add #[page(url = \"/blog/{slug}/\")] or a `url` field",
+            ))
+        }
+    };
+    let optional = |name: &str| {
+        field(name).map(|field| {
+            let ident = &field.ident;
+            let method = Ident::new(name, field.span());
+            let value = if last_segment(&field.ty).is_some_and(|segment| segment == "Option") {
+                quote! { ::std::option::Option::as_deref(&self.#ident) }
+            } else {
+                quote! { ::std::option::Option::Some(&self.#ident) }
+            };
+            quote! {
+                fn #method(&self) -> ::std::option::Option<&str> {
+                    #value
+                }
+            }
+        })
+    };
+    let date = optional("date");
+    let updated = optional("updated");
+    let summary = optional("summary");
+    let tags = field("tags").map(|field| {
+        let ident = &field.ident;
+        quote! {
+            fn tags(&self) -> &[::std::string::String] {
+                &self.#ident
+            }
+        }
+    });
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics ::pichu::Page for #name #ty_generics #where_clause {
+            fn title(&self) -> &str {
+                &self.#title_ident
+            }
+
+            fn url(&self) -> ::pichu::UrlPath {
+                #url
+            }
+
+            #date
+            #updated
+            #summary
+            #tags
+        }
+    })
+}
+
+/// The format string of `#[page(url = "...")]` on the struct, if any.
+fn url_format(attrs: &[Attribute]) -> Result<Option<LitStr>, Error> {
+    let mut format = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("page")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("url") {
+                format = Some(meta.value()?.parse::<LitStr>()?);
+                Ok(())
+            } else {
+                Err(meta.error("expected `url = \"...\"`"))
+            }
+        })?;
+    }
+    Ok(format)
+}
+
+/// The field used for each accessor: the one marked with its name, or else
+/// the one with its name.
+fn accessors(fields: &FieldsNamed) -> Result<Vec<(String, &Field)>, Error> {
+    let mut accessors = Vec::<(String, &Field)>::new();
+    for field in &fields.named {
+        let mut marked = Vec::new();
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("page"))
+        {
+            attr.parse_nested_meta(|meta| {
+                match ACCESSORS.iter().find(|name| meta.path.is_ident(name)) {
+                    Some(name) => {
+                        marked.push((*name).to_string());
+                        Ok(())
+                    }
+                    None => Err(meta.error(format!("expected one of {}", ACCESSORS.join(", ")))),
+                }
+            })?;
+        }
+        accessors.retain(|(name, _)| !marked.contains(name));
+        accessors.extend(marked.into_iter().map(|name| (name, field)));
+    }
+    for field in &fields.named {
+        let Some(ident) = &field.ident else { continue };
+        let name = ident.to_string();
+        if ACCESSORS.contains(&name.as_str()) && !accessors.iter().any(|(n, _)| *n == name) {
+            accessors.push((name, field));
+        }
+    }
+    Ok(accessors)
+}
+
+/// The URL path from a format string with field names in braces.
+fn url_from_format(format: &LitStr) -> Result<TokenStream2, Error> {
+    let value = format.value();
+    let mut args = Vec::new();
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find('{') {
+        rest = &rest[start + 1..];
+        if let Some(escaped) = rest.strip_prefix('{') {
+            rest = escaped;
+            continue;
+        }
+        let end = rest
+            .find('}')
+            .ok_or_else(|| Error::new(format.span(), "unclosed `{` in url"))?;
+        let name = rest[..end].split(':').next().unwrap_or_default();
+        let ident = syn::parse_str::<Ident>(name)
+            .map_err(|_| Error::new(format.span(), format!("`{name}` isn't a field name")))?;
+        if !args.contains(&ident) {
+            args.push(ident);
+        }
+        rest = &rest[end + 1..];
+    }
+    Ok(url_from_expr(&quote! {
+        ::std::format!(#format, #(#args = self.#args),*)
+    }))
+}
+
+fn url_from_expr(expr: &TokenStream2) -> TokenStream2 {
+    quote! {
+        match ::pichu::UrlPath::new(#expr) {
+            ::std::result::Result::Ok(url) => url,
+            ::std::result::Result::Err(e) => ::std::panic!("{e}"),
+        }
+    }
+}
+
+/// The name of the last path segment of a type, e.g. `Option`.
+fn last_segment(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map(|segment| segment.ident.to_string()),
+        _ => None,
+    }
+}
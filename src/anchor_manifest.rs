@@ -0,0 +1,330 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt, fmt::Write as _, fs, io, path::Path};
+
+use crate::{
+    html::{elements, escape, unescape},
+    output::write_atomic,
+    Error, UrlPath,
+};
+
+/// A heading with an `id`, and the IDs it had in earlier builds.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Heading {
+    id: String,
+    text: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    aliases: Vec<String>,
+}
+
+/// The heading IDs of every page of a build, to find fragment links like
+/// `/blog/post/#setup` that a new build breaks.
+///
+/// ```no_run
+/// use pichu::AnchorManifest;
+///
+/// let previous = AnchorManifest::load("anchors.json")?;
+/// let mut current = AnchorManifest::scan("dist")?;
+/// for change in current.compare(&previous) {
+///     eprintln!("warning: {change}");
+/// }
+/// current.write_aliases("dist")?;
+/// current.save("anchors.json")?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct AnchorManifest {
+    pages: BTreeMap<String, Vec<Heading>>,
+}
+
+/// A heading ID of the previous build that is gone, from
+/// [`AnchorManifest::compare`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnchorChange {
+    /// The URL path of the page.
+    pub url: String,
+    /// The ID in the previous build.
+    pub id: String,
+    /// The ID of the same heading in the current build, if it was found.
+    pub renamed_to: Option<String>,
+}
+
+impl fmt::Display for AnchorChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.renamed_to {
+            Some(to) => write!(f, "{}#{} was renamed to #{to}", self.url, self.id),
+            None => write!(f, "{}#{} was removed", self.url, self.id),
+        }
+    }
+}
+
+impl AnchorManifest {
+    /// Load a manifest previously written with [`AnchorManifest::save`].
+    /// Returns an empty manifest if the file doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or isn't a valid manifest.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Write the manifest to disk as a JSON object of URL paths to their
+    /// headings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(path.as_ref(), json.as_bytes())
+    }
+
+    /// Collect the headings with an `id` of every HTML page in the output
+    /// directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be read.
+    pub fn scan(out_dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let out_dir = out_dir.as_ref();
+        let pattern =
+            Path::new(&glob::Pattern::escape(&out_dir.to_string_lossy())).join("**/*.html");
+        let pages = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+        let pages = pages
+            .par_iter()
+            .map(|page| {
+                let url = UrlPath::from_output(out_dir, page)?;
+                Ok((
+                    url.as_str().to_string(),
+                    headings(&fs::read_to_string(page)?),
+                ))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Self { pages })
+    }
+
+    /// The heading IDs of the page at the URL path, in document order.
+    #[must_use]
+    pub fn ids(&self, url: &UrlPath) -> Vec<&str> {
+        self.pages
+            .get(url.as_str())
+            .into_iter()
+            .flatten()
+            .map(|heading| heading.id.as_str())
+            .collect()
+    }
+
+    /// Compare with the manifest of the previous build, returning the
+    /// heading IDs that disappeared from pages that still exist.
+    ///
+    /// A missing ID counts as renamed if a new heading on the page has the
+    /// same text, or else is at the same position. Renamed IDs, and the
+    /// aliases carried over from the previous manifest, become aliases for
+    /// [`AnchorManifest::write_aliases`].
+    pub fn compare(&mut self, previous: &Self) -> Vec<AnchorChange> {
+        let mut changes = Vec::new();
+        for (url, old) in &previous.pages {
+            let Some(new) = self.pages.get_mut(url) else {
+                continue;
+            };
+            let is_new = |id: &str| !old.iter().any(|heading| heading.id == id);
+            for (index, heading) in old.iter().enumerate() {
+                let target = new
+                    .iter()
+                    .position(|h| h.id == heading.id)
+                    .or_else(|| {
+                        new.iter()
+                            .position(|h| h.text == heading.text && is_new(&h.id))
+                    })
+                    .or_else(|| new.get(index).filter(|h| is_new(&h.id)).map(|_| index));
+                let Some(target) = target else {
+                    changes.push(AnchorChange {
+                        url: url.clone(),
+                        id: heading.id.clone(),
+                        renamed_to: None,
+                    });
+                    continue;
+                };
+
+                let mut aliases = heading.aliases.clone();
+                if new[target].id != heading.id {
+                    changes.push(AnchorChange {
+                        url: url.clone(),
+                        id: heading.id.clone(),
+                        renamed_to: Some(new[target].id.clone()),
+                    });
+                    aliases.push(heading.id.clone());
+                }
+                for alias in aliases {
+                    // A heading may have taken the ID back.
+                    if !new
+                        .iter()
+                        .any(|h| h.id == alias || h.aliases.contains(&alias))
+                    {
+                        new[target].aliases.push(alias);
+                    }
+                }
+            }
+        }
+        changes
+    }
+
+    /// Insert an empty `<span>` with each alias ID before its heading in the
+    /// output, so fragment links to renamed headings keep working.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be read or written.
+    pub fn write_aliases(&self, out_dir: impl AsRef<Path>) -> Result<(), Error> {
+        let out_dir = out_dir.as_ref();
+        self.pages
+            .par_iter()
+            .filter(|(_, headings)| headings.iter().any(|h| !h.aliases.is_empty()))
+            .try_for_each(|(url, headings)| {
+                let path = UrlPath::new(url)?.to_output(out_dir);
+                let html = fs::read_to_string(&path)?;
+                let mut insertions = Vec::new();
+                for (name, attributes, _) in elements(&html) {
+                    if !is_heading(&name) {
+                        continue;
+                    }
+                    let Some((_, id)) = attributes
+                        .iter()
+                        .find(|(attribute, _)| attribute.eq_ignore_ascii_case("id"))
+                    else {
+                        continue;
+                    };
+                    let Some(heading) = headings.iter().find(|h| h.id == unescape(id)) else {
+                        continue;
+                    };
+                    // Attribute values are slices of the document.
+                    let offset = id.as_ptr() as usize - html.as_ptr() as usize;
+                    let start = html[..offset].rfind('<').unwrap_or_default();
+                    let mut spans = String::new();
+                    for alias in &heading.aliases {
+                        let alias = escape(alias);
+                        if !html.contains(&format!("id=\"{alias}\"")) {
+                            let _ = write!(spans, "<span id=\"{alias}\"></span>");
+                        }
+                    }
+                    insertions.push((start, spans));
+                }
+                if insertions.iter().all(|(_, spans)| spans.is_empty()) {
+                    return Ok(());
+                }
+
+                let mut rewritten = html.clone();
+                for (start, spans) in insertions.into_iter().rev() {
+                    rewritten.insert_str(start, &spans);
+                }
+                fs::write(path, rewritten)?;
+                Ok(())
+            })
+    }
+}
+
+fn is_heading(name: &str) -> bool {
+    matches!(name, "h1" | "h2" | "h3" | "h4" | "h5" | "h6")
+}
+
+/// The headings with an `id` in a page, with their text content.
+fn headings(html: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    for (name, attributes, text) in elements(html) {
+        if !is_heading(&name) {
+            continue;
+        }
+        let Some((_, id)) = attributes
+            .iter()
+            .find(|(attribute, _)| attribute.eq_ignore_ascii_case("id"))
+        else {
+            continue;
+        };
+        // The text is a slice of the document, running to the end tag.
+        let start = text.as_ptr() as usize - html.as_ptr() as usize;
+        let content = &html[start..];
+        let content = &content[..content.find(&format!("</{name}")).unwrap_or(content.len())];
+        let mut stripped = String::new();
+        let mut in_tag = false;
+        for c in content.chars() {
+            match c {
+                '<' => in_tag = true,
+                '>' => in_tag = false,
+                c if !in_tag => stripped.push(c),
+                _ => {}
+            }
+        }
+        headings.push(Heading {
+            id: unescape(id),
+            text: unescape(&stripped)
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" "),
+            aliases: Vec::new(),
+        });
+    }
+    headings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_anchor_manifest() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_anchor_manifest");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(
+            dir.join("post/index.html"),
+            r##"<h1 id="post">Post</h1><h2 id="setup">Setup <a href="#setup">#</a></h2>
+<h2 id="usage">Usage</h2><h2 id="faq">FAQ</h2>"##,
+        )?;
+        let previous = AnchorManifest::scan(&dir)?;
+        assert_eq!(
+            previous.ids(&UrlPath::new("/post/")?),
+            ["post", "setup", "usage", "faq"]
+        );
+        previous.save(dir.join("anchors.json"))?;
+
+        crate::write(
+            dir.join("post/index.html"),
+            r#"<h1 id="post">Post</h1><h2 id="installation">Installation</h2>
+<h2 id="getting-started">Usage</h2>"#,
+        )?;
+        let mut current = AnchorManifest::scan(&dir)?;
+        let changes = current.compare(&AnchorManifest::load(dir.join("anchors.json"))?);
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "/post/#setup was renamed to #installation",
+                "/post/#usage was renamed to #getting-started",
+                "/post/#faq was removed"
+            ]
+        );
+
+        current.write_aliases(&dir)?;
+        current.write_aliases(&dir)?;
+        assert_eq!(
+            fs::read_to_string(dir.join("post/index.html"))?,
+            r#"<h1 id="post">Post</h1><span id="setup"></span><h2 id="installation">Installation</h2>
+<span id="usage"></span><h2 id="getting-started">Usage</h2>"#
+        );
+
+        // Aliases are carried over to the next build.
+        let mut next = AnchorManifest::scan(&dir)?;
+        assert!(next.compare(&current).is_empty());
+        assert_eq!(next, current);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
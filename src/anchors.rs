@@ -0,0 +1,211 @@
+use comrak::{
+    adapters::{HeadingAdapter, HeadingMeta},
+    nodes::Sourcepos,
+    Anchorizer,
+};
+use std::{
+    collections::HashSet,
+    fmt,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+use crate::{html::escape, sync::lock};
+
+type SlugFn = dyn Fn(&str) -> String + Send + Sync;
+
+/// The markup of heading anchors and footnote back-references, see
+/// [`MarkdownOptions::anchors`](crate::MarkdownOptions::anchors).
+///
+/// Headings get an `id` and, if enabled, a visible permalink after the text:
+///
+/// ```html
+/// <h2 id="post-setup">Setup <a href="#post-setup" class="permalink" aria-label="Permalink: Setup">#</a></h2>
+/// ```
+#[derive(Clone, Default)]
+pub struct Anchors {
+    id_prefix: String,
+    slug_fn: Option<Arc<SlugFn>>,
+    permalink: Option<(String, String)>,
+    backref_label: Option<String>,
+    backref_symbol: Option<String>,
+}
+
+impl Anchors {
+    /// Create the default anchors: heading IDs without a prefix and no
+    /// permalink.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Prefix heading IDs, e.g. to avoid clashes with IDs used by the layout.
+    #[must_use]
+    pub fn id_prefix(mut self, id_prefix: impl Into<String>) -> Self {
+        self.id_prefix = id_prefix.into();
+        self
+    }
+
+    /// Create heading IDs from the heading text with the function, instead
+    /// of GitHub's rules. Duplicate IDs get a `-1`, `-2`, … suffix.
+    #[must_use]
+    pub fn slug_fn(mut self, slug_fn: impl Fn(&str) -> String + Send + Sync + 'static) -> Self {
+        self.slug_fn = Some(Arc::new(slug_fn));
+        self
+    }
+
+    /// Add a visible permalink with the given symbol, e.g. `#`, and class
+    /// after the heading text.
+    #[must_use]
+    pub fn permalink(mut self, symbol: impl Into<String>, class: impl Into<String>) -> Self {
+        self.permalink = Some((symbol.into(), class.into()));
+        self
+    }
+
+    /// Set the `aria-label` of footnote back-references, with `{n}`
+    /// replaced by the reference, e.g. `Back to content {n}`.
+    /// Defaults to `Back to reference {n}`.
+    #[must_use]
+    pub fn footnote_backref_label(mut self, label: impl Into<String>) -> Self {
+        self.backref_label = Some(label.into());
+        self
+    }
+
+    /// Set the text of footnote back-references, defaults to `↩`.
+    #[must_use]
+    pub fn footnote_backref_symbol(mut self, symbol: impl Into<String>) -> Self {
+        self.backref_symbol = Some(symbol.into());
+        self
+    }
+
+    /// Replace the back-reference labels and symbols in rendered HTML.
+    pub(crate) fn rewrite_backrefs(&self, html: String) -> String {
+        const LABEL: &str = "aria-label=\"Back to reference ";
+        if self.backref_label.is_none() && self.backref_symbol.is_none() {
+            return html;
+        }
+
+        let mut rewritten = String::with_capacity(html.len());
+        let mut rest = html.as_str();
+        while let Some(start) = rest.find(LABEL) {
+            let after = &rest[start + LABEL.len()..];
+            let Some(end) = after.find("\">↩") else {
+                break;
+            };
+            let n = &after[..end];
+            rewritten.push_str(&rest[..start]);
+            rewritten.push_str("aria-label=\"");
+            if let Some(label) = &self.backref_label {
+                rewritten.push_str(&escape(&label.replace("{n}", n)));
+            } else {
+                rewritten.push_str("Back to reference ");
+                rewritten.push_str(n);
+            }
+            rewritten.push_str("\">");
+            rewritten.push_str(self.backref_symbol.as_deref().unwrap_or("↩"));
+            rest = &after[end + "\">↩".len()..];
+        }
+        rewritten.push_str(rest);
+        rewritten
+    }
+}
+
+impl fmt::Debug for Anchors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Anchors")
+            .field("id_prefix", &self.id_prefix)
+            .field("permalink", &self.permalink)
+            .field("backref_label", &self.backref_label)
+            .field("backref_symbol", &self.backref_symbol)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Renders headings of a single document, keeping track of the IDs used.
+pub(crate) struct AnchorAdapter<'a> {
+    anchors: &'a Anchors,
+    state: Mutex<AnchorState>,
+}
+
+#[derive(Default)]
+struct AnchorState {
+    anchorizer: Anchorizer,
+    seen: HashSet<String>,
+    current: String,
+}
+
+impl<'a> AnchorAdapter<'a> {
+    pub(crate) fn new(anchors: &'a Anchors) -> Self {
+        Self {
+            anchors,
+            state: Mutex::default(),
+        }
+    }
+
+    fn id(&self, state: &mut AnchorState, text: &str) -> String {
+        let slug = match &self.anchors.slug_fn {
+            None => return state.anchorizer.anchorize(text.to_string()),
+            Some(slug_fn) => slug_fn(text),
+        };
+        let mut id = slug.clone();
+        let mut suffix = 0;
+        while !state.seen.insert(id.clone()) {
+            suffix += 1;
+            id = format!("{slug}-{suffix}");
+        }
+        id
+    }
+}
+
+impl HeadingAdapter for AnchorAdapter<'_> {
+    fn enter(
+        &self,
+        output: &mut dyn Write,
+        heading: &HeadingMeta,
+        _sourcepos: Option<Sourcepos>,
+    ) -> io::Result<()> {
+        // A poisoned lock only means rendering another heading panicked, the
+        // IDs seen so far are still valid.
+        let mut state = lock(&self.state);
+        let id = format!(
+            "{}{}",
+            self.anchors.id_prefix,
+            self.id(&mut state, &heading.content)
+        );
+        write!(output, "<h{} id=\"{}\">", heading.level, escape(&id))?;
+        state.current = id;
+        Ok(())
+    }
+
+    fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+        if let Some((symbol, class)) = &self.anchors.permalink {
+            let state = lock(&self.state);
+            write!(
+                output,
+                " <a href=\"#{}\" class=\"{}\" aria-label=\"Permalink: {}\">{symbol}</a>",
+                escape(&state.current),
+                escape(class),
+                escape(&heading.content),
+            )?;
+        }
+        write!(output, "</h{}>", heading.level)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rewrite_backrefs() {
+        let html = r##"<a href="#fnref-1" class="footnote-backref" aria-label="Back to reference 1">↩</a> <a href="#fnref-1-2" aria-label="Back to reference 1-2">↩<sup class="footnote-ref">2</sup></a>"##;
+        assert_eq!(Anchors::new().rewrite_backrefs(html.to_string()), html);
+        assert_eq!(
+            Anchors::new()
+                .footnote_backref_label("Zurück zu {n}")
+                .footnote_backref_symbol("↑")
+                .rewrite_backrefs(html.to_string()),
+            r##"<a href="#fnref-1" class="footnote-backref" aria-label="Zurück zu 1">↑</a> <a href="#fnref-1-2" aria-label="Zurück zu 1-2">↑<sup class="footnote-ref">2</sup></a>"##
+        );
+    }
+}
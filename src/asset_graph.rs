@@ -0,0 +1,217 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Write,
+    fs,
+    path::Path,
+};
+
+use crate::{html::tags, AssetManifest, ContentTypes, Error, UrlPath};
+
+/// Which pages reference which assets of an [`AssetManifest`], found by
+/// scanning the `href` and `src` attributes of `<link>`, `<script>`,
+/// `<img>` and `<source>` tags in the generated HTML.
+/// Useful for preload hints, finding unused assets and deciding which pages
+/// to rebuild when an asset changes.
+///
+/// ```no_run
+/// use pichu::{AssetGraph, AssetManifest, UrlPath};
+///
+/// let manifest = AssetManifest::load("dist/manifest.json")?;
+/// let graph = AssetGraph::from_output("dist", &manifest)?;
+/// for name in graph.unused(&manifest) {
+///     eprintln!("warning: {name} isn't used by any page");
+/// }
+/// let hints = graph.preload_hints(&UrlPath::root(), &manifest);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AssetGraph {
+    /// Asset names by page.
+    assets: BTreeMap<UrlPath, BTreeSet<String>>,
+}
+
+impl AssetGraph {
+    /// Scan every HTML page in the output directory for references to the
+    /// assets registered in the manifest.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or a page cannot be read.
+    pub fn from_output(out_dir: impl AsRef<Path>, manifest: &AssetManifest) -> Result<Self, Error> {
+        let out_dir = out_dir.as_ref();
+        let names = manifest
+            .entries()
+            .into_iter()
+            .map(|(name, url)| (url, name))
+            .collect::<HashMap<_, _>>();
+        let pattern = out_dir.join("**").join("*.html");
+        let mut graph = Self::default();
+        for path in glob::glob(&pattern.to_string_lossy())? {
+            let path = path?;
+            let page = UrlPath::from_output(out_dir, &path)?;
+            let assets = references(&page, &fs::read_to_string(&path)?)
+                .into_iter()
+                .filter_map(|url| names.get(&url).cloned())
+                .collect();
+            graph.assets.insert(page, assets);
+        }
+        Ok(graph)
+    }
+
+    /// The names of the assets a page references.
+    pub fn assets(&self, page: &UrlPath) -> impl Iterator<Item = &str> {
+        self.assets
+            .get(page)
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// The pages referencing an asset, e.g. to rebuild them when it changes.
+    pub fn pages<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a UrlPath> {
+        self.assets
+            .iter()
+            .filter(move |(_, assets)| assets.contains(name))
+            .map(|(page, _)| page)
+    }
+
+    /// The names of the assets in the manifest no page references.
+    #[must_use]
+    pub fn unused(&self, manifest: &AssetManifest) -> Vec<String> {
+        let used = self.assets.values().flatten().collect::<BTreeSet<_>>();
+        manifest
+            .entries()
+            .into_keys()
+            .filter(|name| !used.contains(name))
+            .collect()
+    }
+
+    /// `<link rel="preload">` tags for the stylesheets, scripts, fonts and
+    /// images a page references, to be placed in its `<head>`.
+    #[must_use]
+    pub fn preload_hints(&self, page: &UrlPath, manifest: &AssetManifest) -> String {
+        let content_types = ContentTypes::new();
+        let mut hints = String::new();
+        for name in self.assets(page) {
+            let Some(url) = manifest.get(name) else {
+                continue;
+            };
+            let mime = content_types.get(&url).mime;
+            let kind = match mime.as_str() {
+                "text/css" => "style",
+                "text/javascript" => "script",
+                mime if mime.starts_with("font/") => "font",
+                mime if mime.starts_with("image/") => "image",
+                _ => continue,
+            };
+            let crossorigin = if kind == "font" { " crossorigin" } else { "" };
+            let _ = writeln!(
+                hints,
+                r#"<link rel="preload" href="{url}" as="{kind}"{crossorigin}>"#
+            );
+        }
+        hints
+    }
+}
+
+/// The local URLs referenced by a page, resolved against the page's URL and
+/// without query or fragment.
+fn references(page: &UrlPath, html: &str) -> BTreeSet<String> {
+    let dir = if page.is_dir() {
+        page.as_str()
+    } else {
+        page.as_str().rsplit_once('/').map_or("/", |(dir, _)| dir)
+    };
+    let mut urls = BTreeSet::new();
+    for (tag, attributes) in tags(html) {
+        if !matches!(tag.as_str(), "link" | "script" | "img" | "source") {
+            continue;
+        }
+        for (name, value) in attributes {
+            if !(name.eq_ignore_ascii_case("href") || name.eq_ignore_ascii_case("src")) {
+                continue;
+            }
+            let path = value.split(['?', '#']).next().unwrap_or_default();
+            let is_external = path.starts_with("//")
+                || path
+                    .split('/')
+                    .next()
+                    .is_some_and(|first| first.contains(':'));
+            if path.is_empty() || is_external {
+                continue;
+            }
+            if let Some(url) = resolve(dir, path) {
+                urls.insert(url);
+            }
+        }
+    }
+    urls
+}
+
+/// Resolve a relative URL path in the directory `dir`.
+fn resolve(dir: &str, path: &str) -> Option<String> {
+    let joined = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("{dir}/{path}")
+    };
+    let mut segments = Vec::new();
+    for segment in joined.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            segment => segments.push(segment),
+        }
+    }
+    Some(format!("/{}", segments.join("/")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_asset_graph() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_asset_graph");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(
+            dir.join("index.html"),
+            r#"<link rel="stylesheet" href="/main.4f2a.css"><script src="app.9c1e.js?v=1"></script>"#,
+        )?;
+        crate::write(
+            dir.join("blog/hello/index.html"),
+            r#"<link rel="stylesheet" href="../../main.4f2a.css"><img src="cover.png"><a href="/app.9c1e.js">Source</a>"#,
+        )?;
+        let manifest = AssetManifest::new();
+        manifest.register("main.css", "/main.4f2a.css");
+        manifest.register("app.js", "/app.9c1e.js");
+        manifest.register("font.woff2", "/font.77aa.woff2");
+        manifest.register("cover.png", "/blog/hello/cover.png");
+
+        let graph = AssetGraph::from_output(&dir, &manifest)?;
+        let root = UrlPath::root();
+        let hello = UrlPath::new("/blog/hello/")?;
+        assert_eq!(
+            graph.assets(&root).collect::<Vec<_>>(),
+            ["app.js", "main.css"]
+        );
+        assert_eq!(
+            graph.assets(&hello).collect::<Vec<_>>(),
+            ["cover.png", "main.css"]
+        );
+        assert_eq!(graph.pages("main.css").collect::<Vec<_>>(), [&root, &hello]);
+        assert_eq!(graph.unused(&manifest), ["font.woff2"]);
+        assert_eq!(
+            graph.preload_hints(&root, &manifest),
+            "<link rel=\"preload\" href=\"/app.9c1e.js\" as=\"script\">\n<link rel=\"preload\" href=\"/main.4f2a.css\" as=\"style\">\n"
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use rayon::prelude::*;
+use std::{fs, path::Path};
+
+use crate::{html::tags, BuildProfile, Error, UrlPath, UrlStyle};
+
+/// The attributes holding URLs that [`rewrite_links`] rewrites.
+const URL_ATTRIBUTES: [&str; 5] = ["href", "src", "srcset", "action", "poster"];
+
+/// How [`rewrite_links`] writes links to the site itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LinkStyle {
+    /// Root-relative, e.g. `/about/`, so the site works on any host, e.g.
+    /// preview deploys.
+    Relative,
+    /// Absolute with the base URL's origin, e.g. `https://example.com/about/`.
+    Absolute,
+}
+
+impl BuildProfile {
+    /// The link style to build with: absolute for production builds,
+    /// relative otherwise.
+    #[must_use]
+    pub fn link_style(self) -> LinkStyle {
+        match self {
+            Self::Dev => LinkStyle::Relative,
+            Self::Prod => LinkStyle::Absolute,
+        }
+    }
+}
+
+/// Rewrite the links to the site in rendered HTML: links to the host of the
+/// base URL, over `http`, `https` or protocol-relative, become root-relative
+/// with [`LinkStyle::Relative`], and root-relative links become absolute
+/// with [`LinkStyle::Absolute`]. Only `href`, `src`, `srcset`, `action` and
+/// `poster` attributes are rewritten, so e.g. `og:url` stays absolute.
+///
+/// ```
+/// use pichu::LinkStyle;
+///
+/// let html = r#"<a href="https://example.com/about/">About</a>"#;
+/// let html = pichu::rewrite_links(html, "https://example.com", LinkStyle::Relative);
+/// assert_eq!(html, r#"<a href="/about/">About</a>"#);
+/// ```
+#[must_use]
+pub fn rewrite_links(html: &str, base_url: &str, style: LinkStyle) -> String {
+    let Some((scheme, host)) = base_url.split_once("://") else {
+        return html.to_string();
+    };
+    let host = host.split('/').next().unwrap_or_default();
+    let origin = format!("{scheme}://{host}");
+    let rewrite = |url: &str| -> Option<String> {
+        match style {
+            LinkStyle::Relative => {
+                let rest = ["https://", "http://", "//"]
+                    .iter()
+                    .find_map(|prefix| url.strip_prefix(prefix))?
+                    .strip_prefix(host)?;
+                match rest.chars().next() {
+                    None => Some("/".to_string()),
+                    Some('/') => Some(rest.to_string()),
+                    Some('?' | '#') => Some(format!("/{rest}")),
+                    Some(_) => None,
+                }
+            }
+            LinkStyle::Absolute => {
+                (url.starts_with('/') && !url.starts_with("//")).then(|| format!("{origin}{url}"))
+            }
+        }
+    };
+    replace_urls(html, rewrite)
+}
+
+/// Rewrite root-relative links to pages in rendered HTML to the URL style,
+/// e.g. `/about.html` to `/about/` with [`UrlStyle::Pretty`], keeping
+/// queries and fragments, so links flagged by
+/// [`check_links`](crate::check_links) follow it. Other files, like
+/// `/feed.xml`, and links to other hosts are kept.
+///
+/// ```
+/// use pichu::UrlStyle;
+///
+/// let html = r#"<a href="/about.html#team">About</a>"#;
+/// let html = pichu::restyle_links(html, UrlStyle::Pretty);
+/// assert_eq!(html, r#"<a href="/about/#team">About</a>"#);
+/// ```
+#[must_use]
+pub fn restyle_links(html: &str, style: UrlStyle) -> String {
+    replace_urls(html, |url| {
+        if !url.starts_with('/') || url.starts_with("//") {
+            return None;
+        }
+        let (path, rest) = url.split_at(url.find(['?', '#']).unwrap_or(url.len()));
+        let restyled = UrlPath::new(path).ok()?.with_style(style);
+        (restyled.as_str() != path).then(|| format!("{restyled}{rest}"))
+    })
+}
+
+/// Replace the URLs in the URL attributes of the HTML for which `rewrite`
+/// returns a new one.
+fn replace_urls(html: &str, rewrite: impl Fn(&str) -> Option<String>) -> String {
+    let mut replacements = Vec::new();
+    for (_, attributes) in tags(html) {
+        for (name, value) in attributes {
+            if !URL_ATTRIBUTES
+                .iter()
+                .any(|attribute| name.eq_ignore_ascii_case(attribute))
+            {
+                continue;
+            }
+            let replacement = if name.eq_ignore_ascii_case("srcset") {
+                let mut rewritten = false;
+                let candidates = value
+                    .split(',')
+                    .map(|candidate| {
+                        let candidate = candidate.trim();
+                        let (url, descriptor) =
+                            candidate.split_once(' ').unwrap_or((candidate, ""));
+                        match rewrite(url) {
+                            Some(url) => {
+                                rewritten = true;
+                                format!("{url} {descriptor}").trim_end().to_string()
+                            }
+                            None => candidate.to_string(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                rewritten.then_some(candidates)
+            } else {
+                rewrite(value)
+            };
+            if let Some(replacement) = replacement {
+                // Attribute values are slices of the document.
+                let start = value.as_ptr() as usize - html.as_ptr() as usize;
+                replacements.push((start, start + value.len(), replacement));
+            }
+        }
+    }
+
+    let mut rewritten = String::with_capacity(html.len());
+    let mut end = 0;
+    for (start, value_end, replacement) in replacements {
+        rewritten.push_str(&html[end..start]);
+        rewritten.push_str(&replacement);
+        end = value_end;
+    }
+    rewritten.push_str(&html[end..]);
+    rewritten
+}
+
+/// Run [`rewrite_links`] on every HTML page in the output directory, as a
+/// post-processing pass after a build.
+///
+/// ```no_run
+/// use pichu::BuildProfile;
+///
+/// let profile = BuildProfile::from_env();
+/// pichu::rewrite_links_in("dist", "https://example.com", profile.link_style())?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if a page cannot be read or written.
+pub fn rewrite_links_in(
+    out_dir: impl AsRef<Path>,
+    base_url: &str,
+    style: LinkStyle,
+) -> Result<(), Error> {
+    let pattern =
+        Path::new(&glob::Pattern::escape(&out_dir.as_ref().to_string_lossy())).join("**/*.html");
+    let pages = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+    pages.par_iter().try_for_each(|page| -> Result<(), Error> {
+        let html = fs::read_to_string(page)?;
+        let rewritten = rewrite_links(&html, base_url, style);
+        if rewritten != html {
+            fs::write(page, rewritten)?;
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_restyle_links() {
+        let html = r#"<a href="/blog/hello.html?x=1">Hello</a> <a href="/docs/index.html">Docs</a>
+<a href="/feed.xml">Feed</a> <a href="https://other.org/a.html">Other</a> <a href="a.html">A</a>"#;
+        assert_eq!(
+            restyle_links(html, UrlStyle::Pretty),
+            r#"<a href="/blog/hello/?x=1">Hello</a> <a href="/docs/">Docs</a>
+<a href="/feed.xml">Feed</a> <a href="https://other.org/a.html">Other</a> <a href="a.html">A</a>"#
+        );
+        assert_eq!(
+            restyle_links(
+                r#"<a href="/blog/#top">Blog</a> <a href="/">Home</a>"#,
+                UrlStyle::Html
+            ),
+            r#"<a href="/blog.html#top">Blog</a> <a href="/">Home</a>"#
+        );
+    }
+
+    #[test]
+    fn test_rewrite_links() {
+        let html = r#"<a href="https://example.com/blog/?page=2">Blog</a>
+<a href="//example.com">Home</a> <a HREF="http://example.com#top">Top</a>
+<a href="https://example.com.evil/">Evil</a> <a href="https://other.org/">Other</a>
+<meta property="og:url" content="https://example.com/">
+<img src="https://example.com/a.png" srcset="https://example.com/a.png 1x, /b.png 2x">"#;
+        assert_eq!(
+            rewrite_links(html, "https://example.com/", LinkStyle::Relative),
+            r#"<a href="/blog/?page=2">Blog</a>
+<a href="/">Home</a> <a HREF="/#top">Top</a>
+<a href="https://example.com.evil/">Evil</a> <a href="https://other.org/">Other</a>
+<meta property="og:url" content="https://example.com/">
+<img src="/a.png" srcset="/a.png 1x, /b.png 2x">"#
+        );
+        assert_eq!(
+            rewrite_links(
+                r#"<a href="/about/">About</a> <a href="//cdn.net/x.js">CDN</a> <a href="x/">X</a>"#,
+                "https://example.com",
+                LinkStyle::Absolute
+            ),
+            r#"<a href="https://example.com/about/">About</a> <a href="//cdn.net/x.js">CDN</a> <a href="x/">X</a>"#
+        );
+    }
+}
@@ -0,0 +1,198 @@
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+use crate::{
+    clock::rfc3339,
+    html::{elements, unescape},
+    Error, Parsed,
+};
+
+/// A bookmark from a browser or Pinboard export, see [`parse_bookmarks`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bookmark {
+    /// The title of the bookmarked page.
+    pub title: String,
+    /// The URL of the bookmarked page.
+    pub url: String,
+    /// The tags of the bookmark.
+    pub tags: Vec<String>,
+    /// When the bookmark was added, as an RFC 3339 datetime in UTC.
+    pub date: Option<String>,
+    /// A note on the bookmark.
+    pub note: Option<String>,
+}
+
+/// A bookmark in a Pinboard JSON export.
+#[derive(Deserialize)]
+struct PinboardBookmark {
+    href: String,
+    #[serde(default)]
+    description: String,
+    #[serde(default)]
+    extended: String,
+    #[serde(default)]
+    tags: String,
+    time: Option<String>,
+}
+
+/// Parse a bookmarks export into bookmarks, in the order of the file, to
+/// render a links page or linklog. Reads the Netscape bookmark HTML format
+/// browsers export to and Pinboard's JSON export.
+///
+/// ```no_run
+/// pichu::parse_bookmarks("data/bookmarks.html")?
+///     .render_all(
+///         |bookmarks| {
+///             bookmarks
+///                 .iter()
+///                 .map(|bookmark| format!("<li><a href=\"{}\">{}</a></li>", bookmark.url, bookmark.title))
+///                 .collect::<String>()
+///         },
+///         "dist/links/index.html",
+///     )?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or isn't valid JSON.
+pub fn parse_bookmarks(path: impl AsRef<Path>) -> Result<Parsed<Bookmark>, Error> {
+    let contents = fs::read_to_string(path)?;
+    let bookmarks = if contents.trim_start().starts_with('[') {
+        serde_json::from_str::<Vec<PinboardBookmark>>(&contents)
+            .map_err(|e| Error::Parse(Box::new(e)))?
+            .into_iter()
+            .map(|bookmark| Bookmark {
+                title: bookmark.description,
+                url: bookmark.href,
+                tags: bookmark.tags.split_whitespace().map(String::from).collect(),
+                date: bookmark.time,
+                note: Some(bookmark.extended).filter(|note| !note.is_empty()),
+            })
+            .collect()
+    } else {
+        parse_netscape(&contents)
+    };
+    Ok(Parsed::from(bookmarks))
+}
+
+/// Parse the Netscape bookmark file format, where every bookmark is a link
+/// with `ADD_DATE` and `TAGS` attributes, optionally followed by a `<DD>`
+/// with a note.
+fn parse_netscape(html: &str) -> Vec<Bookmark> {
+    let mut bookmarks = Vec::<Bookmark>::new();
+    // Whether the last tag was a bookmark's link, so a `<DD>` is its note.
+    let mut in_bookmark = false;
+    for (name, attributes, text) in elements(html) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| unescape(value))
+        };
+        match name.as_str() {
+            "a" => {
+                let Some(url) = attribute("href") else {
+                    in_bookmark = false;
+                    continue;
+                };
+                bookmarks.push(Bookmark {
+                    title: unescape(text.trim()),
+                    url,
+                    tags: attribute("tags")
+                        .map(|tags| {
+                            tags.split(',')
+                                .map(str::trim)
+                                .filter(|tag| !tag.is_empty())
+                                .map(String::from)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    date: attribute("add_date")
+                        .and_then(|secs| secs.parse().ok())
+                        .map(rfc3339),
+                    note: None,
+                });
+                in_bookmark = true;
+            }
+            "dd" if in_bookmark => {
+                let note = unescape(text.trim());
+                if let Some(bookmark) = bookmarks.last_mut() {
+                    bookmark.note = Some(note).filter(|note| !note.is_empty());
+                }
+                in_bookmark = false;
+            }
+            _ => in_bookmark = false,
+        }
+    }
+    bookmarks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_parse_bookmarks() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_parse_bookmarks");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(
+            dir.join("bookmarks.html"),
+            r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<META HTTP-EQUIV="Content-Type" CONTENT="text/html; charset=UTF-8">
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1714550400">Reading</H3>
+    <DL><p>
+        <DT><A HREF="https://example.com/?a=1&amp;b=2" ADD_DATE="1714557600" TAGS="rust, web">Rust &amp; the Web</A>
+        <DD>Worth a read.
+        <DT><A HREF="https://example.org/">Example</A>
+    </DL><p>
+</DL><p>
+"#,
+        )?;
+        let bookmarks = parse_bookmarks(dir.join("bookmarks.html"))?;
+        assert_eq!(
+            bookmarks.items,
+            [
+                Bookmark {
+                    title: "Rust & the Web".to_string(),
+                    url: "https://example.com/?a=1&b=2".to_string(),
+                    tags: vec!["rust".to_string(), "web".to_string()],
+                    date: Some("2024-05-01T10:00:00Z".to_string()),
+                    note: Some("Worth a read.".to_string()),
+                },
+                Bookmark {
+                    title: "Example".to_string(),
+                    url: "https://example.org/".to_string(),
+                    tags: Vec::new(),
+                    date: None,
+                    note: None,
+                },
+            ]
+        );
+
+        crate::write(
+            dir.join("pinboard.json"),
+            r#"[{"href":"https://example.com/","description":"Example","extended":"","meta":"x","hash":"y","time":"2024-05-01T10:00:00Z","shared":"yes","toread":"no","tags":"rust web"}]"#,
+        )?;
+        let bookmarks = parse_bookmarks(dir.join("pinboard.json"))?;
+        assert_eq!(
+            bookmarks.items,
+            [Bookmark {
+                title: "Example".to_string(),
+                url: "https://example.com/".to_string(),
+                tags: vec!["rust".to_string(), "web".to_string()],
+                date: Some("2024-05-01T10:00:00Z".to_string()),
+                note: None,
+            }]
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
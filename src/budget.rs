@@ -0,0 +1,313 @@
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{html::tags, Error, UrlPath};
+
+/// A part of a page's weight, see [`PageWeight`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum WeightKind {
+    /// The HTML document itself.
+    Html,
+    /// Stylesheets linked with `<link rel="stylesheet">`.
+    Css,
+    /// Scripts loaded with `<script src>`.
+    Js,
+    /// Images loaded with `<img src>`.
+    Images,
+    /// All of the above.
+    Total,
+}
+
+impl fmt::Display for WeightKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Html => "html",
+            Self::Css => "css",
+            Self::Js => "js",
+            Self::Images => "images",
+            Self::Total => "total",
+        })
+    }
+}
+
+/// The size in bytes of a generated page and the local assets it
+/// references. Assets referenced several times are counted once, external
+/// and missing ones aren't counted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageWeight {
+    /// The URL path of the page.
+    pub url: UrlPath,
+    /// The size of the HTML document.
+    pub html: u64,
+    /// The size of the stylesheets.
+    pub css: u64,
+    /// The size of the scripts.
+    pub js: u64,
+    /// The size of the images.
+    pub images: u64,
+}
+
+impl PageWeight {
+    /// The weight of a part of the page.
+    #[must_use]
+    pub fn get(&self, kind: WeightKind) -> u64 {
+        match kind {
+            WeightKind::Html => self.html,
+            WeightKind::Css => self.css,
+            WeightKind::Js => self.js,
+            WeightKind::Images => self.images,
+            WeightKind::Total => self.html + self.css + self.js + self.images,
+        }
+    }
+}
+
+/// Weigh every HTML page in the output directory, sorted by URL path.
+///
+/// # Errors
+///
+/// Returns an error if the directory or a page cannot be read.
+pub fn page_weights(out_dir: impl AsRef<Path>) -> Result<Vec<PageWeight>, Error> {
+    let out_dir = out_dir.as_ref();
+    let pattern = out_dir.join("**").join("*.html");
+    let pages = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+    let mut weights = pages
+        .par_iter()
+        .map(|page| weigh(out_dir, page))
+        .collect::<Result<Vec<_>, Error>>()?;
+    weights.sort_by(|a, b| a.url.cmp(&b.url));
+    Ok(weights)
+}
+
+fn weigh(out_dir: &Path, page: &Path) -> Result<PageWeight, Error> {
+    let html = fs::read_to_string(page)?;
+    let mut weight = PageWeight {
+        url: UrlPath::from_output(out_dir, page)?,
+        html: html.len() as u64,
+        css: 0,
+        js: 0,
+        images: 0,
+    };
+    let mut seen = BTreeSet::new();
+    for (tag, attributes) in tags(&html) {
+        let attribute = |name: &str| {
+            attributes
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, value)| *value)
+        };
+        let (kind, url) = match tag.as_str() {
+            "link"
+                if attribute("rel").is_some_and(|rel| {
+                    rel.split_whitespace()
+                        .any(|rel| rel.eq_ignore_ascii_case("stylesheet"))
+                }) =>
+            {
+                (WeightKind::Css, attribute("href"))
+            }
+            "script" => (WeightKind::Js, attribute("src")),
+            "img" => (WeightKind::Images, attribute("src")),
+            _ => continue,
+        };
+        let Some(path) = url.and_then(|url| resolve(out_dir, page, url)) else {
+            continue;
+        };
+        if !seen.insert(path.clone()) {
+            continue;
+        }
+        let Ok(metadata) = fs::metadata(&path) else {
+            continue;
+        };
+        match kind {
+            WeightKind::Css => weight.css += metadata.len(),
+            WeightKind::Js => weight.js += metadata.len(),
+            _ => weight.images += metadata.len(),
+        }
+    }
+    Ok(weight)
+}
+
+/// The file a local URL on `page` points to, or `None` for external URLs.
+fn resolve(out_dir: &Path, page: &Path, url: &str) -> Option<PathBuf> {
+    let path = url.split(['?', '#']).next().unwrap_or_default();
+    let is_external = path.starts_with("//")
+        || path
+            .split('/')
+            .next()
+            .is_some_and(|first| first.contains(':'));
+    if path.is_empty() || is_external {
+        return None;
+    }
+    match path.strip_prefix('/') {
+        Some(path) => Some(out_dir.join(path)),
+        None => Some(page.parent()?.join(path)),
+    }
+}
+
+/// A page over its weight budget, see [`WeightBudget`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BudgetViolation {
+    /// The URL path of the page.
+    pub url: UrlPath,
+    /// The part of the page over budget.
+    pub kind: WeightKind,
+    /// The weight in bytes.
+    pub weight: u64,
+    /// The budget in bytes.
+    pub budget: u64,
+}
+
+impl fmt::Display for BudgetViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} weighs {} bytes of {}, over the budget of {} bytes",
+            self.url, self.weight, self.kind, self.budget
+        )
+    }
+}
+
+/// Limits on the weight of generated pages, to catch performance
+/// regressions at build time.
+///
+/// ```no_run
+/// use pichu::{WeightBudget, WeightKind};
+///
+/// let warnings = WeightBudget::new()
+///     .limit(WeightKind::Total, 500 * 1024)
+///     .limit(WeightKind::Js, 50 * 1024)
+///     .check("dist")?;
+/// for warning in warnings {
+///     eprintln!("warning: {warning}");
+/// }
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct WeightBudget {
+    limits: BTreeMap<WeightKind, u64>,
+    fail: bool,
+}
+
+impl WeightBudget {
+    /// Create a budget without limits.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit a part of every page to the given number of bytes.
+    #[must_use]
+    pub fn limit(mut self, kind: WeightKind, bytes: u64) -> Self {
+        self.limits.insert(kind, bytes);
+        self
+    }
+
+    /// Whether [`WeightBudget::check`] fails if a page is over budget,
+    /// instead of returning the violations as warnings. Defaults to false.
+    #[must_use]
+    pub fn fail(mut self, fail: bool) -> Self {
+        self.fail = fail;
+        self
+    }
+
+    /// The violations of the budget by the given weights.
+    #[must_use]
+    pub fn violations(&self, weights: &[PageWeight]) -> Vec<BudgetViolation> {
+        weights
+            .iter()
+            .flat_map(|weight| {
+                self.limits.iter().filter_map(|(kind, budget)| {
+                    let page_weight = weight.get(*kind);
+                    (page_weight > *budget).then(|| BudgetViolation {
+                        url: weight.url.clone(),
+                        kind: *kind,
+                        weight: page_weight,
+                        budget: *budget,
+                    })
+                })
+            })
+            .collect()
+    }
+
+    /// Weigh the pages in the output directory and return the violations of
+    /// the budget.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be read, or
+    /// [`Error::OverBudget`] if a page is over budget and the budget is set
+    /// to [`WeightBudget::fail`].
+    pub fn check(&self, out_dir: impl AsRef<Path>) -> Result<Vec<BudgetViolation>, Error> {
+        let violations = self.violations(&page_weights(out_dir)?);
+        if self.fail && !violations.is_empty() {
+            return Err(Error::OverBudget(violations));
+        }
+        Ok(violations)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_weight_budget() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_weight_budget");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let html = r#"<link rel="stylesheet" href="/style.css?v=1"><script src="app.js"></script><img src="/cover.png"><img src="/cover.png"><img src="https://example.com/x.png"><img src="/missing.png">"#;
+        crate::write(dir.join("index.html"), html)?;
+        crate::write(dir.join("blog/index.html"), "<p>Hi</p>")?;
+        crate::write(dir.join("style.css"), "a".repeat(100))?;
+        crate::write(dir.join("app.js"), "a".repeat(200))?;
+        crate::write(dir.join("cover.png"), "a".repeat(1000))?;
+
+        let weights = page_weights(&dir)?;
+        assert_eq!(
+            weights,
+            [
+                PageWeight {
+                    url: UrlPath::new("/")?,
+                    html: html.len() as u64,
+                    css: 100,
+                    js: 200,
+                    images: 1000,
+                },
+                PageWeight {
+                    url: UrlPath::new("/blog/")?,
+                    html: 9,
+                    css: 0,
+                    js: 0,
+                    images: 0,
+                }
+            ]
+        );
+
+        let budget = WeightBudget::new()
+            .limit(WeightKind::Js, 200)
+            .limit(WeightKind::Total, 1000);
+        let violations = budget.check(&dir)?;
+        assert_eq!(
+            violations,
+            [BudgetViolation {
+                url: UrlPath::new("/")?,
+                kind: WeightKind::Total,
+                weight: weights[0].get(WeightKind::Total),
+                budget: 1000,
+            }]
+        );
+        assert!(violations[0].to_string().starts_with("/ weighs 1"));
+        assert!(matches!(
+            budget.fail(true).check(&dir),
+            Err(Error::OverBudget(v)) if v == violations
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,579 @@
+use rayon::prelude::*;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crate::{AssetManifest, BuildProfile, DepGraph, Error, OutputRegistry, RenderCache, UrlStyle};
+
+/// A boxed step for [`par_steps`].
+pub type BoxedStep<'a> =
+    Box<dyn FnOnce() -> Result<(), Box<dyn fmt::Debug + Send + Sync>> + Send + 'a>;
+
+type StepFn = dyn Fn(&BuildContext) -> Result<(), Box<dyn fmt::Debug + Send + Sync>> + Send + Sync;
+
+/// Configuration for a [`Build`].
+#[derive(Debug, Clone)]
+pub struct BuildConfig {
+    /// The directory the site is built into.
+    pub out_dir: PathBuf,
+    /// Where to persist the [`RenderCache`] between runs, if anywhere.
+    pub cache_path: Option<PathBuf>,
+    /// Where to write the [`AssetManifest`] after a run, if anywhere.
+    pub manifest_path: Option<PathBuf>,
+    /// The environment the site is built for.
+    pub profile: BuildProfile,
+    /// How the site's pages are addressed, for steps to pass to
+    /// [`Parsed::permalinks`](crate::Parsed::permalinks),
+    /// [`restyle_links`](crate::restyle_links) and
+    /// [`Sitemap::url_style`](crate::Sitemap::url_style).
+    pub url_style: UrlStyle,
+    /// Write an integrity manifest of the output after a run, see
+    /// [`write_integrity`](crate::write_integrity).
+    pub integrity: bool,
+}
+
+impl BuildConfig {
+    /// The config of a site in a multi-site build, see
+    /// [`Sites`](crate::Sites): the output directory and the directories
+    /// of the cache and manifest paths get a subdirectory named after the
+    /// site.
+    #[must_use]
+    pub fn for_site(&self, name: &str) -> Self {
+        let namespace = |path: &PathBuf| match (path.parent(), path.file_name()) {
+            (Some(parent), Some(file_name)) => parent.join(name).join(file_name),
+            _ => path.join(name),
+        };
+        Self {
+            out_dir: self.out_dir.join(name),
+            cache_path: self.cache_path.as_ref().map(namespace),
+            manifest_path: self.manifest_path.as_ref().map(namespace),
+            profile: self.profile,
+            url_style: self.url_style,
+            integrity: self.integrity,
+        }
+    }
+}
+
+impl Default for BuildConfig {
+    fn default() -> Self {
+        Self {
+            out_dir: PathBuf::from("dist"),
+            cache_path: None,
+            manifest_path: None,
+            profile: BuildProfile::from_env(),
+            url_style: UrlStyle::default(),
+            integrity: false,
+        }
+    }
+}
+
+/// State shared between the steps of a [`Build`].
+#[derive(Debug)]
+pub struct BuildContext {
+    /// The build configuration.
+    pub config: BuildConfig,
+    /// Asset names to output URLs, shared by all steps.
+    pub manifest: AssetManifest,
+    /// Render cache, shared by all steps.
+    pub cache: RenderCache,
+    /// Source to output dependencies, shared by all steps.
+    pub deps: DepGraph,
+    /// Output paths claimed by source files, shared by all steps, e.g. with
+    /// [`Parsed::claim_outputs`](crate::Parsed::claim_outputs). Cleared at
+    /// the start of every run, so sources can move between collections.
+    pub outputs: OutputRegistry,
+}
+
+/// How a step of a [`Build`] ended.
+#[derive(Debug)]
+pub enum StepOutcome {
+    /// The step succeeded.
+    Succeeded,
+    /// The step returned an error.
+    Failed(Box<dyn fmt::Debug + Send + Sync>),
+    /// The step didn't run because a step it depends on didn't succeed.
+    Skipped,
+}
+
+/// The result of a single step.
+#[derive(Debug)]
+pub struct StepReport {
+    /// The step's name.
+    pub name: String,
+    /// How the step ended.
+    pub outcome: StepOutcome,
+    /// How long the step took.
+    pub duration: Duration,
+}
+
+/// The consolidated result of [`Build::run`].
+#[derive(Debug)]
+pub struct BuildReport {
+    /// The result of each step, in the order they were added.
+    pub steps: Vec<StepReport>,
+    /// How long the whole build took.
+    pub duration: Duration,
+}
+
+impl BuildReport {
+    /// Returns true if all steps succeeded.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.steps
+            .iter()
+            .all(|step| matches!(step.outcome, StepOutcome::Succeeded))
+    }
+
+    /// Turn failed steps into an [`Error::Steps`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any step failed.
+    pub fn into_result(self) -> Result<Self, Error> {
+        if self.is_success() {
+            return Ok(self);
+        }
+        let failed = self
+            .steps
+            .into_iter()
+            .filter_map(|step| match step.outcome {
+                StepOutcome::Failed(e) => Some((step.name, e)),
+                _ => None,
+            })
+            .collect();
+        Err(Error::Steps(failed))
+    }
+}
+
+struct Step {
+    name: String,
+    after: Vec<String>,
+    run: Box<StepFn>,
+}
+
+/// A build session running named steps (collections, stylesheets, static
+/// files, feeds, …) with shared state, and reporting on all of them.
+/// Steps run in parallel unless they declare dependencies on each other.
+///
+/// ```no_run
+/// use pichu::{Build, BuildConfig};
+///
+/// # fn main() -> Result<(), pichu::Error> {
+/// let report = Build::new(BuildConfig::default())?
+///     .step("static", |ctx| pichu::copy_dir("static", &ctx.config.out_dir))
+///     .step("blog", |_| -> Result<(), pichu::Error> { Ok(()) })
+///     .step_after("feed", ["blog"], |_| -> Result<(), pichu::Error> { Ok(()) })
+///     .run()?
+///     .into_result()?;
+/// println!("Built in {:?}", report.duration);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Build {
+    context: BuildContext,
+    steps: Vec<Step>,
+}
+
+impl Build {
+    /// Create a build session, loading the render cache if configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the render cache exists but cannot be read.
+    pub fn new(config: BuildConfig) -> Result<Self, Error> {
+        let cache = match &config.cache_path {
+            Some(path) => RenderCache::load(path)?,
+            None => RenderCache::new(),
+        };
+        Ok(Self {
+            context: BuildContext {
+                config,
+                manifest: AssetManifest::new(),
+                cache,
+                deps: DepGraph::new(),
+                outputs: OutputRegistry::new(),
+            },
+            steps: Vec::new(),
+        })
+    }
+
+    /// Add a step without dependencies.
+    #[must_use]
+    pub fn step<E: fmt::Debug + Send + Sync + 'static>(
+        self,
+        name: impl Into<String>,
+        step_fn: impl Fn(&BuildContext) -> Result<(), E> + Send + Sync + 'static,
+    ) -> Self {
+        self.step_after(name, [] as [&str; 0], step_fn)
+    }
+
+    /// Add a step that only runs once the named steps have succeeded.
+    #[must_use]
+    pub fn step_after<E: fmt::Debug + Send + Sync + 'static>(
+        mut self,
+        name: impl Into<String>,
+        after: impl IntoIterator<Item = impl Into<String>>,
+        step_fn: impl Fn(&BuildContext) -> Result<(), E> + Send + Sync + 'static,
+    ) -> Self {
+        self.steps.push(Step {
+            name: name.into(),
+            after: after.into_iter().map(Into::into).collect(),
+            run: Box::new(move |ctx| {
+                step_fn(ctx).map_err(|e| Box::new(e) as Box<dyn fmt::Debug + Send + Sync>)
+            }),
+        });
+        self
+    }
+
+    /// The state shared between steps.
+    #[must_use]
+    pub fn context(&self) -> &BuildContext {
+        &self.context
+    }
+
+    /// Mark the output directory for [`clean`](crate::clean), run all
+    /// steps, in parallel where their dependencies allow, then persist the
+    /// render cache and asset manifest if configured.
+    /// A failing step doesn't stop independent steps, check the report.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if step names are duplicated, a step depends on an
+    /// unknown step, the dependencies form a cycle, if the output directory
+    /// cannot be marked, see [`mark_build`](crate::mark_build), or if the
+    /// cache or manifest cannot be written.
+    pub fn run(&self) -> Result<BuildReport, Error> {
+        let waves = self.validate()?;
+        crate::mark_build(&self.context.config.out_dir)?;
+        self.context.outputs.clear();
+        let start = Instant::now();
+
+        let mut outcomes: HashMap<&str, (StepOutcome, Duration)> = HashMap::new();
+        for ready in waves {
+            let results = ready
+                .into_par_iter()
+                .map(|step| {
+                    let dependencies_succeeded = step.after.iter().all(|a| {
+                        matches!(outcomes.get(a.as_str()), Some((StepOutcome::Succeeded, _)))
+                    });
+                    if !dependencies_succeeded {
+                        return (step.name.as_str(), (StepOutcome::Skipped, Duration::ZERO));
+                    }
+                    let start = Instant::now();
+                    let outcome = match (step.run)(&self.context) {
+                        Ok(()) => StepOutcome::Succeeded,
+                        Err(e) => StepOutcome::Failed(e),
+                    };
+                    (step.name.as_str(), (outcome, start.elapsed()))
+                })
+                .collect::<Vec<_>>();
+            outcomes.extend(results);
+        }
+
+        if let Some(path) = &self.context.config.cache_path {
+            self.context.cache.save(path)?;
+        }
+        if let Some(path) = &self.context.config.manifest_path {
+            self.context.manifest.save(path)?;
+        }
+        if self.context.config.integrity {
+            crate::write_integrity(&self.context.config.out_dir)?;
+        }
+
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| {
+                let (outcome, duration) = outcomes
+                    .remove(step.name.as_str())
+                    .unwrap_or((StepOutcome::Skipped, Duration::ZERO));
+                StepReport {
+                    name: step.name.clone(),
+                    outcome,
+                    duration,
+                }
+            })
+            .collect();
+        Ok(BuildReport {
+            steps,
+            duration: start.elapsed(),
+        })
+    }
+
+    /// Check the steps and group them into waves whose dependencies all
+    /// run in earlier waves, so nothing is touched if they can't run.
+    fn validate(&self) -> Result<Vec<Vec<&Step>>, Error> {
+        let mut names = HashSet::new();
+        for step in &self.steps {
+            if !names.insert(step.name.as_str()) {
+                return Err(Error::InvalidSteps(format!("duplicate step {}", step.name)));
+            }
+        }
+        for step in &self.steps {
+            if let Some(unknown) = step.after.iter().find(|a| !names.contains(a.as_str())) {
+                return Err(Error::InvalidSteps(format!(
+                    "step {} depends on unknown step {unknown}",
+                    step.name
+                )));
+            }
+        }
+
+        let mut scheduled = HashSet::new();
+        let mut waves = Vec::new();
+        while scheduled.len() < self.steps.len() {
+            let ready = self
+                .steps
+                .iter()
+                .filter(|step| !scheduled.contains(step.name.as_str()))
+                .filter(|step| step.after.iter().all(|a| scheduled.contains(a.as_str())))
+                .collect::<Vec<_>>();
+            if ready.is_empty() {
+                let mut blocked = self
+                    .steps
+                    .iter()
+                    .filter(|step| !scheduled.contains(step.name.as_str()))
+                    .map(|step| step.name.as_str())
+                    .collect::<Vec<_>>();
+                blocked.sort_unstable();
+                return Err(Error::InvalidSteps(format!(
+                    "dependency cycle between {}",
+                    blocked.join(", ")
+                )));
+            }
+            scheduled.extend(ready.iter().map(|step| step.name.as_str()));
+            waves.push(ready);
+        }
+        Ok(waves)
+    }
+}
+
+impl fmt::Debug for Build {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Build")
+            .field("context", &self.context)
+            .field(
+                "steps",
+                &self.steps.iter().map(|s| &s.name).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
+
+/// Run independent named steps concurrently on the rayon pool.
+/// All steps run to completion, even if some fail. Usually called through
+/// [`join!`](crate::join).
+///
+/// # Errors
+///
+/// Returns [`Error::Steps`] with the error of every step that failed.
+pub fn par_steps(steps: Vec<(&str, BoxedStep<'_>)>) -> Result<(), Error> {
+    let failed = steps
+        .into_par_iter()
+        .filter_map(|(name, step)| step().err().map(|e| (name.to_string(), e)))
+        .collect::<Vec<_>>();
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Steps(failed))
+    }
+}
+
+/// Run independent named steps concurrently, aggregating their errors into
+/// [`Error::Steps`]. Each step is a closure returning `Result<(), E>` for
+/// any `E: Debug + Send + Sync`.
+///
+/// ```no_run
+/// # fn main() -> Result<(), pichu::Error> {
+/// pichu::join!(
+///     "static" => || pichu::copy_dir("static", "dist"),
+///     "blog" => || -> Result<(), pichu::Error> { Ok(()) },
+/// )?;
+/// # Ok(())
+/// # }
+/// ```
+#[macro_export]
+macro_rules! join {
+    ($($name:expr => $step:expr),+ $(,)?) => {
+        $crate::par_steps(vec![$((
+            $name,
+            Box::new(|| {
+                ($step)().map_err(|e| {
+                    Box::new(e) as Box<dyn ::std::fmt::Debug + Send + Sync>
+                })
+            }) as $crate::BoxedStep<'_>,
+        )),+])
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_run() -> Result<(), Error> {
+        let log = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let (log_a, log_b, log_c) = (log.clone(), log.clone(), log.clone());
+        let out_dir = std::env::temp_dir().join("pichu_test_build");
+        let config = BuildConfig {
+            out_dir: out_dir.clone(),
+            integrity: true,
+            ..Default::default()
+        };
+        let report = Build::new(config)?
+            .step_after("feed", ["blog"], move |_| -> Result<(), ()> {
+                log_a.lock().map_err(|_| ())?.push("feed");
+                Ok(())
+            })
+            .step("blog", move |ctx| -> Result<(), ()> {
+                ctx.manifest.register("blog", "/blog/");
+                log_b.lock().map_err(|_| ())?.push("blog");
+                Ok(())
+            })
+            .step("sass", |_| Err("invalid syntax"))
+            .step_after("css", ["sass"], move |_| -> Result<(), ()> {
+                log_c.lock().map_err(|_| ())?.push("css");
+                Ok(())
+            })
+            .run()?;
+
+        assert_eq!(
+            *log.lock().map_err(|_| Error::InvalidSteps(String::new()))?,
+            ["blog", "feed"]
+        );
+        let outcomes = report
+            .steps
+            .iter()
+            .map(|step| (step.name.as_str(), format!("{:?}", step.outcome)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            [
+                ("feed", "Succeeded".to_string()),
+                ("blog", "Succeeded".to_string()),
+                ("sass", "Failed(\"invalid syntax\")".to_string()),
+                ("css", "Skipped".to_string()),
+            ]
+        );
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::Steps(failed)) if failed.len() == 1 && failed[0].0 == "sass"
+        ));
+        crate::verify(&out_dir)?;
+
+        crate::clean(out_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_outputs() -> Result<(), Box<dyn std::error::Error>> {
+        struct Page(PathBuf);
+
+        impl crate::HasSource for Page {
+            fn source(&self) -> &std::path::Path {
+                &self.0
+            }
+        }
+
+        let dir = std::env::temp_dir().join("pichu_test_build_outputs");
+        if dir.exists() {
+            std::fs::remove_dir_all(&dir)?;
+        }
+        let out_dir = dir.join("dist");
+        let section = |name: &'static str| {
+            let pattern = dir.join(name).join("*.md");
+            move |ctx: &BuildContext| -> Result<(), Error> {
+                let path_fn = |page: &Page| {
+                    let stem = page.0.file_stem().unwrap_or_default();
+                    ctx.config.out_dir.join(stem).join("index.html")
+                };
+                crate::glob(pattern.to_string_lossy())?
+                    .parse(Page)
+                    .claim_outputs(&ctx.outputs, path_fn)?
+                    .render_each(|_| name, path_fn)?;
+                Ok(())
+            }
+        };
+        let build = Build::new(BuildConfig {
+            out_dir: out_dir.clone(),
+            ..Default::default()
+        })?
+        .step("blog", section("blog"))
+        .step_after("pages", ["blog"], section("pages"));
+
+        crate::write(dir.join("blog/about.md"), "")?;
+        std::fs::create_dir_all(dir.join("pages"))?;
+        build.run()?.into_result()?;
+        // Moving the page claims its output for the new source on rebuild.
+        std::fs::rename(dir.join("blog/about.md"), dir.join("pages/about.md"))?;
+        build.run()?.into_result()?;
+        assert_eq!(
+            build
+                .context()
+                .outputs
+                .owner(out_dir.join("about/index.html")),
+            Some(dir.join("pages/about.md"))
+        );
+        assert_eq!(
+            std::fs::read_to_string(out_dir.join("about/index.html"))?,
+            "pages"
+        );
+
+        crate::write(dir.join("blog/about.md"), "")?;
+        let report = build.run()?;
+        let Err(Error::Steps(failed)) = report.into_result() else {
+            panic!("expected a conflict");
+        };
+        assert_eq!(failed[0].0, "pages");
+        let conflict = format!("{:?}", failed[0].1);
+        assert!(conflict.contains("OutputConflict"), "{conflict}");
+        assert!(conflict.contains(&format!("{:?}", dir.join("blog/about.md"))));
+        assert!(conflict.contains(&format!("{:?}", dir.join("pages/about.md"))));
+
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_join() {
+        let blog = Mutex::new(0);
+        let result = crate::join!(
+            "blog" => || -> Result<(), ()> {
+                *blog.lock().map_err(|_| ())? += 1;
+                Ok(())
+            },
+            "sass" => || Err("invalid syntax"),
+            "static" => || Err(Error::FileExists(PathBuf::from("dist/index.html"))),
+        );
+
+        assert_eq!(blog.into_inner().ok(), Some(1));
+        let Err(Error::Steps(failed)) = result else {
+            panic!("expected failed steps");
+        };
+        let names = failed
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["sass", "static"]);
+        assert!(crate::join!("noop" => || -> Result<(), ()> { Ok(()) }).is_ok());
+    }
+
+    #[test]
+    fn test_invalid_steps() -> Result<(), Error> {
+        let ok = |_: &BuildContext| -> Result<(), ()> { Ok(()) };
+        let build = Build::new(BuildConfig::default())?
+            .step_after("a", ["b"], ok)
+            .step_after("b", ["a"], ok);
+        assert!(
+            matches!(build.run(), Err(Error::InvalidSteps(e)) if e == "dependency cycle between a, b")
+        );
+
+        let build = Build::new(BuildConfig::default())?.step_after("a", ["c"], ok);
+        assert!(matches!(build.run(), Err(Error::InvalidSteps(_))));
+        Ok(())
+    }
+}
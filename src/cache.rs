@@ -0,0 +1,124 @@
+use std::{
+    collections::HashMap,
+    fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{manifest::content_hash, output::write_atomic, sync::lock};
+
+/// Remembers the inputs each output was last rendered from, so that outputs
+/// whose inputs didn't change can be skipped.
+/// Used by [`Parsed::render_each_cached`](crate::Parsed::render_each_cached).
+///
+/// Keep one around between rebuilds (e.g. in a [`watch`](crate::watch)
+/// callback), or persist it with [`RenderCache::save`] and
+/// [`RenderCache::load`].
+#[derive(Debug, Default)]
+pub struct RenderCache {
+    entries: Mutex<HashMap<PathBuf, String>>,
+}
+
+impl RenderCache {
+    /// Create an empty cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a cache previously written with [`RenderCache::save`].
+    /// Returns an empty cache if the file doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+
+        // Lines that fail to parse are dropped, which only causes a re-render.
+        let entries = contents
+            .lines()
+            .filter_map(|line| {
+                let (key, path) = line.split_once(' ')?;
+                Some((PathBuf::from(path), key.to_string()))
+            })
+            .collect();
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Write the cache to disk. The file is replaced in one step, so an
+    /// interrupted save keeps the previous cache intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let mut lines = lock(&self.entries)
+            .iter()
+            .map(|(path, key)| format!("{key} {}\n", path.display()))
+            .collect::<Vec<_>>();
+        lines.sort_unstable();
+        write_atomic(path.as_ref(), lines.concat().as_bytes())
+    }
+
+    /// Forget the given outputs, forcing them to be rendered again.
+    /// Combine with [`DepGraph::affected`](crate::DepGraph::affected) to
+    /// invalidate everything depending on a changed template or data file.
+    pub fn invalidate<P: AsRef<Path>>(&self, outputs: impl IntoIterator<Item = P>) {
+        let mut entries = lock(&self.entries);
+        for output in outputs {
+            entries.remove(output.as_ref());
+        }
+    }
+
+    /// Forget all entries, forcing the next render to write everything.
+    pub fn clear(&self) {
+        lock(&self.entries).clear();
+    }
+
+    /// Returns true if `path` was rendered from `key` and still exists.
+    pub(crate) fn is_fresh(&self, path: &Path, key: &str) -> bool {
+        lock(&self.entries)
+            .get(path)
+            .is_some_and(|entry| entry == key)
+            && path.exists()
+    }
+
+    pub(crate) fn insert(&self, entries: impl IntoIterator<Item = (PathBuf, String)>) {
+        lock(&self.entries).extend(entries);
+    }
+}
+
+/// Hash a render key into the fingerprint stored in a [`RenderCache`].
+/// Unlike `DefaultHasher`, blake3 hashes the same everywhere and in every
+/// Rust release, so a saved cache stays valid.
+pub(crate) fn fingerprint(key: &impl Hash) -> String {
+    let mut bytes = KeyBytes::default();
+    key.hash(&mut bytes);
+    content_hash(&bytes.0)
+}
+
+/// Collects what a key feeds into its hasher.
+#[derive(Default)]
+struct KeyBytes(Vec<u8>);
+
+impl Hasher for KeyBytes {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.extend_from_slice(bytes);
+    }
+
+    fn finish(&self) -> u64 {
+        let hash = blake3::hash(&self.0);
+        let mut first = [0; 8];
+        first.copy_from_slice(&hash.as_bytes()[..8]);
+        u64::from_le_bytes(first)
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::Error;
+
+/// The file marking a directory as pichu output, see [`mark_build`].
+pub const BUILD_MARKER: &str = ".pichu-build";
+
+/// Mark the directory as pichu output, allowing [`clean`] to delete it.
+/// Only missing or empty directories are marked, so mark before building.
+/// [`Build::run`](crate::Build::run) and the `cli` do this automatically.
+///
+/// # Errors
+///
+/// Returns [`Error::ProtectedDir`] for the filesystem root, the home
+/// directory and the current directory or its parents,
+/// [`Error::NotBuildDir`] if the directory has other files but no marker,
+/// or an error if the marker cannot be written.
+pub fn mark_build(dir: impl AsRef<Path>) -> Result<(), Error> {
+    let dir = dir.as_ref();
+    check_protected(dir)?;
+    let marker = dir.join(BUILD_MARKER);
+    if marker.is_file() {
+        return Ok(());
+    }
+    if dir.exists() && fs::read_dir(dir)?.next().is_some() {
+        return Err(Error::NotBuildDir(dir.to_owned()));
+    }
+    crate::write(marker, "")?;
+    Ok(())
+}
+
+/// Refuse directories whose deletion would take more than a build with it.
+fn check_protected(dir: &Path) -> Result<(), Error> {
+    // Missing directories can't contain anything, and the parents of the
+    // current directory all exist.
+    let Ok(canonical) = dir.canonicalize() else {
+        return Ok(());
+    };
+    let home = env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .and_then(|home| Path::new(&home).canonicalize().ok());
+    let cwd = env::current_dir().and_then(|cwd| cwd.canonicalize());
+    if canonical.parent().is_none()
+        || home.is_some_and(|home| home == canonical)
+        || cwd.is_ok_and(|cwd| cwd.starts_with(&canonical))
+    {
+        return Err(Error::ProtectedDir(dir.to_owned()));
+    }
+    Ok(())
+}
+
+/// Delete a build output directory, refusing to unless it contains the
+/// [`BUILD_MARKER`]. Use [`Clean`] to preserve some paths.
+///
+/// # Errors
+///
+/// Returns [`Error::NotBuildDir`] if the directory isn't marked as pichu
+/// output, [`Error::ProtectedDir`] if it must never be deleted, see
+/// [`mark_build`], or an error if it cannot be deleted.
+pub fn clean(dir: impl AsRef<Path>) -> Result<(), Error> {
+    Clean::new(dir).run()
+}
+
+/// Deletes a build output directory like [`clean`], except for protected
+/// paths.
+#[derive(Debug, Clone)]
+pub struct Clean {
+    dir: PathBuf,
+    protected: Vec<PathBuf>,
+}
+
+impl Clean {
+    /// Prepare to clean the directory.
+    #[must_use]
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+            protected: Vec::new(),
+        }
+    }
+
+    /// Keep a file or directory, given relative to the output directory.
+    #[must_use]
+    pub fn protect(mut self, path: impl AsRef<Path>) -> Self {
+        self.protected.push(self.dir.join(path));
+        self
+    }
+
+    /// Delete everything but the protected paths. If anything is protected,
+    /// the directory and its marker are kept. Missing directories are fine.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::NotBuildDir`] if the directory isn't marked as pichu
+    /// output, [`Error::ProtectedDir`] if it must never be deleted, see
+    /// [`mark_build`], or an error if it cannot be deleted.
+    pub fn run(&self) -> Result<(), Error> {
+        if !self.dir.exists() {
+            return Ok(());
+        }
+        check_protected(&self.dir)?;
+        if !self.dir.join(BUILD_MARKER).is_file() {
+            return Err(Error::NotBuildDir(self.dir.clone()));
+        }
+        if self.protected.is_empty() {
+            fs::remove_dir_all(&self.dir)?;
+            return Ok(());
+        }
+        self.remove_unprotected(&self.dir)
+    }
+
+    fn remove_unprotected(&self, dir: &Path) -> Result<(), Error> {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if self.protected.contains(&path) || path == self.dir.join(BUILD_MARKER) {
+                continue;
+            }
+            if self.protected.iter().any(|p| p.starts_with(&path)) {
+                self.remove_unprotected(&path)?;
+            } else if path.is_dir() && !path.is_symlink() {
+                fs::remove_dir_all(&path)?;
+            } else {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_clean() -> Result<(), Error> {
+        let dir = env::temp_dir().join("pichu_test_clean");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("index.html"), "")?;
+        assert!(matches!(clean(&dir), Err(Error::NotBuildDir(_))));
+        assert!(matches!(mark_build(&dir), Err(Error::NotBuildDir(_))));
+        assert!(dir.join("index.html").exists());
+
+        fs::remove_file(dir.join("index.html"))?;
+        mark_build(&dir)?;
+        crate::write(dir.join("index.html"), "")?;
+        crate::write(dir.join("blog/hello/index.html"), "")?;
+        crate::write(dir.join("blog/keep.txt"), "")?;
+        crate::write(dir.join("CNAME"), "")?;
+        mark_build(&dir)?;
+        Clean::new(&dir)
+            .protect("CNAME")
+            .protect("blog/keep.txt")
+            .run()?;
+        assert!(!dir.join("index.html").exists());
+        assert!(!dir.join("blog/hello").exists());
+        assert!(dir.join("blog/keep.txt").exists());
+        assert!(dir.join("CNAME").exists());
+
+        clean(&dir)?;
+        assert!(!dir.exists());
+        clean(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_protected() -> Result<(), Error> {
+        let cwd = env::current_dir()?;
+        for dir in [Path::new("/"), Path::new("."), Path::new(".."), &cwd] {
+            assert!(matches!(mark_build(dir), Err(Error::ProtectedDir(_))));
+            assert!(matches!(clean(dir), Err(Error::ProtectedDir(_))));
+        }
+        assert!(!cwd.join(BUILD_MARKER).exists());
+        Ok(())
+    }
+}
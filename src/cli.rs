@@ -0,0 +1,402 @@
+use clap::{Parser, Subcommand};
+use std::{
+    ffi::OsString,
+    fmt, io,
+    path::{Path, PathBuf},
+    thread,
+};
+
+use crate::{
+    serve::ServeError, watch::WatchError, BuildProfile, LiveReload, Overlay, Server, Shutdown,
+    WatchOptions,
+};
+
+/// Errors that can occur while running the command line interface.
+#[derive(thiserror::Error, Debug)]
+pub enum CliError {
+    /// The arguments could not be parsed.
+    #[error("{0}")]
+    Args(#[from] clap::Error),
+    /// The build function failed.
+    #[error("build failed: {0:?}")]
+    Build(Box<dyn fmt::Debug + Send + Sync>),
+    /// Watching for changes failed.
+    #[error("{0}")]
+    Watch(#[from] WatchError),
+    /// Serving the output directory failed.
+    #[error("{0}")]
+    Serve(#[from] ServeError),
+    /// Cleaning the output directory failed.
+    #[error("{0}")]
+    Clean(#[from] crate::Error),
+    /// Creating new content failed.
+    #[error("{0}")]
+    Scaffold(#[from] crate::ScaffoldError),
+    /// An IO error occurred.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// Options passed to the build function by [`Cli`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliOptions {
+    /// The base URL passed with `--base-url`, if any.
+    pub base_url: Option<String>,
+    /// Whether drafts should be rendered into the [`CliOptions::overlay`],
+    /// set with `--drafts` or by the development profile.
+    pub drafts: bool,
+    /// The build profile: `build` and `clean` default to production,
+    /// `watch` and `serve` to development. Set with `--profile`.
+    pub profile: BuildProfile,
+    /// The directory the site is built into.
+    pub out_dir: PathBuf,
+    /// Where drafts are rendered instead of the output directory, see
+    /// [`Parsed::render_drafts`](crate::Parsed::render_drafts), so they
+    /// never end up in it. `serve` serves its pages, other commands discard
+    /// them. Each build renders into a new overlay, which replaces the
+    /// served one once the build succeeded.
+    pub overlay: Overlay,
+}
+
+#[derive(Parser, Debug)]
+struct Args {
+    #[command(subcommand)]
+    command: Command,
+    /// Override the base URL of the site.
+    #[arg(long, global = true)]
+    base_url: Option<String>,
+    /// Include drafts.
+    #[arg(long, global = true)]
+    drafts: bool,
+    /// The build profile, `dev` or `prod`.
+    #[arg(long, global = true)]
+    profile: Option<BuildProfile>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Build the site.
+    Build,
+    /// Build the site and rebuild on change.
+    Watch,
+    /// Build the site, rebuild on change and serve the output directory,
+    /// reloading open pages.
+    Serve {
+        /// The address to listen on. If the port is in use, the next free
+        /// one is used.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// Open the site in the browser.
+        #[arg(long)]
+        open: bool,
+        /// Forward requests below a path to a backend, e.g.
+        /// `/api=http://localhost:3000`. Can be repeated.
+        #[arg(long, value_name = "PREFIX=URL")]
+        proxy: Vec<String>,
+        /// Serve over HTTPS with a self-signed certificate.
+        #[cfg(feature = "https")]
+        #[arg(long)]
+        https: bool,
+    },
+    /// Remove the output directory, if it was built by pichu.
+    Clean,
+    /// Create a file for new content from its registered archetype, see
+    /// `pichu::new_content`.
+    New {
+        /// The kind of content, e.g. `blog`.
+        kind: String,
+        /// The title or slug of the content.
+        name: String,
+    },
+}
+
+/// A command line interface around a build function, providing the
+/// `build`, `watch`, `serve` and `clean` subcommands, and `new` to create
+/// content from an [`Archetype`](crate::Archetype).
+/// Create one with [`cli`].
+///
+/// `watch` and `serve` stop on Ctrl-C once the running build has finished,
+/// so caches and manifests saved by the build function are never cut off
+/// mid-write, and then call the functions registered on
+/// [`Shutdown::ctrl_c`] with [`Shutdown::on_stop`].
+///
+/// ```no_run
+/// fn main() -> Result<(), pichu::CliError> {
+///     pichu::cli(|options| -> Result<(), pichu::Error> {
+///         pichu::copy_dir("static", &options.out_dir)
+///     })
+///     .watch_paths(["content", "static"])
+///     .run()
+/// }
+/// ```
+pub struct Cli<F> {
+    build_fn: F,
+    out_dir: PathBuf,
+    watch_paths: Vec<PathBuf>,
+    watch_options: WatchOptions,
+}
+
+/// Create a command line interface calling the build function.
+pub fn cli<F, E>(build_fn: F) -> Cli<F>
+where
+    F: Fn(&CliOptions) -> Result<(), E> + Sync,
+    E: fmt::Debug + Send + Sync + 'static,
+{
+    Cli {
+        build_fn,
+        out_dir: PathBuf::from("dist"),
+        watch_paths: Vec::new(),
+        watch_options: WatchOptions::new(),
+    }
+}
+
+impl<F, E> Cli<F>
+where
+    F: Fn(&CliOptions) -> Result<(), E> + Sync,
+    E: fmt::Debug + Send + Sync + 'static,
+{
+    /// Set the output directory, defaults to `dist`.
+    #[must_use]
+    pub fn out_dir(mut self, out_dir: impl Into<PathBuf>) -> Self {
+        self.out_dir = out_dir.into();
+        self
+    }
+
+    /// Set the paths watched by `watch` and `serve`, defaults to the
+    /// current directory excluding the output directory.
+    #[must_use]
+    pub fn watch_paths<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.watch_paths = paths.into_iter().map(|p| p.as_ref().to_owned()).collect();
+        self
+    }
+
+    /// Set the options used by `watch` and `serve`, e.g. to poll for
+    /// changes on a network filesystem.
+    #[must_use]
+    pub fn watch_options(mut self, options: WatchOptions) -> Self {
+        self.watch_options = options;
+        self
+    }
+
+    /// Parse the process arguments and run the command. Prints help and
+    /// exits if the arguments are invalid.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the command failed.
+    pub fn run(self) -> Result<(), CliError> {
+        let args = Args::try_parse().unwrap_or_else(|e| e.exit());
+        self.run_args(args)
+    }
+
+    /// Parse the given arguments, including the binary name, and run the
+    /// command.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the arguments are invalid or the command failed.
+    pub fn run_from<I, T>(self, args: I) -> Result<(), CliError>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        self.run_args(Args::try_parse_from(args)?)
+    }
+
+    fn run_args(self, args: Args) -> Result<(), CliError> {
+        let profile = args.profile.unwrap_or(match args.command {
+            Command::Build | Command::Clean | Command::New { .. } => BuildProfile::Prod,
+            Command::Watch | Command::Serve { .. } => BuildProfile::Dev,
+        });
+        let options = CliOptions {
+            base_url: args.base_url,
+            drafts: args.drafts || profile.include_drafts(),
+            profile,
+            out_dir: self.out_dir.clone(),
+            overlay: Overlay::new(),
+        };
+        match args.command {
+            Command::Build => self.build(&options),
+            Command::Watch => {
+                self.build(&options)?;
+                let shutdown = Shutdown::ctrl_c()?;
+                self.watch(&options, &shutdown, || {})?;
+                println!("Stopped watching");
+                Ok(())
+            }
+            Command::Serve {
+                addr,
+                open,
+                proxy,
+                #[cfg(feature = "https")]
+                https,
+            } => {
+                self.build(&options)?;
+                let live_reload = LiveReload::new();
+                live_reload.snapshot(&options.out_dir)?;
+                let mut server = Server::new(&options.out_dir)
+                    .overlay(&options.overlay)
+                    .live_reload(&live_reload)
+                    .port_attempts(10)
+                    .open(open);
+                #[cfg(feature = "https")]
+                if https {
+                    server = server.https(crate::Tls::SelfSigned);
+                }
+                for rule in proxy {
+                    let (prefix, target) = rule.split_once('=').ok_or_else(|| {
+                        ServeError::InvalidProxy(format!("{rule}: expected PREFIX=URL"))
+                    })?;
+                    server = server.proxy(prefix, target);
+                }
+                let listener = server.bind(&addr)?;
+                println!(
+                    "Serving {} on {}",
+                    options.out_dir.display(),
+                    listener.url()
+                );
+                let shutdown = Shutdown::ctrl_c()?;
+                thread::scope(|scope| {
+                    let watcher = scope.spawn(|| {
+                        self.watch(&options, &shutdown, || {
+                            match live_reload.changed(&options.out_dir) {
+                                // Drafts only change the overlay, not the output directory.
+                                Ok(false) => live_reload.reload(),
+                                Ok(_) => {}
+                                Err(e) => eprintln!("{e}"),
+                            }
+                        })
+                    });
+                    // The watcher calls the `on_stop` functions after its last build.
+                    let serve_result = listener.accept_until(&shutdown);
+                    // Stop the watcher as well if the server failed.
+                    shutdown.request();
+                    let watch_result = watcher
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+                    serve_result?;
+                    watch_result
+                })?;
+                println!("Stopped serving");
+                Ok(())
+            }
+            Command::Clean => Ok(crate::clean(&options.out_dir)?),
+            Command::New { kind, name } => {
+                let path = crate::new_content(&kind, &name)?;
+                println!("Created {}", path.display());
+                Ok(())
+            }
+        }
+    }
+
+    fn build(&self, options: &CliOptions) -> Result<(), CliError> {
+        crate::mark_build(&options.out_dir)?;
+        let build_options = CliOptions {
+            overlay: Overlay::new(),
+            ..options.clone()
+        };
+        (self.build_fn)(&build_options).map_err(|e| CliError::Build(Box::new(e)))?;
+        options.overlay.replace_with(&build_options.overlay);
+        Ok(())
+    }
+
+    fn watch(
+        &self,
+        options: &CliOptions,
+        shutdown: &Shutdown,
+        after_build: impl Fn(),
+    ) -> Result<(), CliError> {
+        let out_dir = crate::collection::absolute(&options.out_dir);
+        let paths = if self.watch_paths.is_empty() {
+            vec![PathBuf::from(".")]
+        } else {
+            self.watch_paths.clone()
+        };
+        let watch_options = self.watch_options.clone().shutdown(shutdown);
+        crate::watch_with(paths, &watch_options, |changed| {
+            // Ignore our own output, otherwise every build triggers the next.
+            if changed
+                .iter()
+                .all(|path| crate::collection::absolute(path).starts_with(&out_dir))
+            {
+                return;
+            }
+            match self.build(options) {
+                Ok(()) => after_build(),
+                Err(e) => eprintln!("{e}"),
+            }
+        })?;
+        Ok(())
+    }
+}
+
+impl<F> fmt::Debug for Cli<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Cli")
+            .field("out_dir", &self.out_dir)
+            .field("watch_paths", &self.watch_paths)
+            .field("watch_options", &self.watch_options)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, sync::Mutex};
+
+    #[test]
+    fn test_run_from() -> Result<(), CliError> {
+        let out_dir = env::temp_dir().join("pichu_test_cli");
+        let calls = Mutex::new(Vec::new());
+        let build = |options: &CliOptions| -> Result<(), io::Error> {
+            if options.drafts {
+                let url = crate::UrlPath::new("/wip/").map_err(io::Error::other)?;
+                options.overlay.insert(url, "draft");
+            }
+            calls
+                .lock()
+                .map_err(|_| io::Error::other("poisoned"))?
+                .push(options.clone());
+            crate::write(options.out_dir.join("index.html"), "")
+        };
+        let last_call = || {
+            calls
+                .lock()
+                .ok()
+                .and_then(|calls| calls.last().cloned())
+                .ok_or_else(|| io::Error::other("not called"))
+        };
+
+        cli(build).out_dir(&out_dir).run_from([
+            "site",
+            "build",
+            "--drafts",
+            "--base-url",
+            "http://localhost:8080",
+        ])?;
+        assert!(out_dir.join("index.html").exists());
+        assert!(!out_dir.join("wip").exists());
+        let options = last_call()?;
+        assert_eq!(options.base_url.as_deref(), Some("http://localhost:8080"));
+        assert!(options.drafts);
+        assert_eq!(options.profile, BuildProfile::Prod);
+        assert_eq!(options.out_dir, out_dir);
+
+        cli(build)
+            .out_dir(&out_dir)
+            .run_from(["site", "build", "--profile", "dev"])?;
+        let options = last_call()?;
+        assert_eq!(options.base_url, None);
+        assert!(options.drafts);
+        assert_eq!(options.profile, BuildProfile::Dev);
+
+        cli(build).out_dir(&out_dir).run_from(["site", "clean"])?;
+        assert!(!out_dir.exists());
+        assert!(matches!(
+            cli(build).run_from(["site", "deploy"]),
+            Err(CliError::Args(_))
+        ));
+        Ok(())
+    }
+}
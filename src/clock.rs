@@ -0,0 +1,153 @@
+use std::{
+    env,
+    fmt::Debug,
+    time::{Duration, SystemTime},
+};
+
+use crate::Parsed;
+
+/// The source of the current time for date-based features like scheduled
+/// posts or "new" badges. Builds use [`SystemClock`], tests and
+/// reproducible builds a [`FixedClock`].
+pub trait Clock: Debug + Send + Sync {
+    /// The current time.
+    fn now(&self) -> SystemTime;
+
+    /// Whether `time` is less than `within` ago, e.g. to mark recent posts.
+    /// Times in the future count as recent.
+    fn is_recent(&self, time: SystemTime, within: Duration) -> bool {
+        self.now()
+            .duration_since(time)
+            .map_or(true, |elapsed| elapsed < within)
+    }
+}
+
+/// The time of the system.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that always returns the same time.
+///
+/// ```
+/// use pichu::{Clock, FixedClock};
+/// use std::time::{Duration, SystemTime};
+///
+/// let clock = FixedClock::from_unix(1_700_000_000);
+/// let published = SystemTime::UNIX_EPOCH + Duration::from_secs(1_699_900_000);
+/// assert!(clock.is_recent(published, Duration::from_secs(7 * 24 * 60 * 60)));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FixedClock(pub SystemTime);
+
+impl FixedClock {
+    /// A clock fixed at the given seconds since the Unix epoch.
+    #[must_use]
+    pub fn from_unix(secs: u64) -> Self {
+        Self(SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// A clock fixed at the time in the `SOURCE_DATE_EPOCH` environment
+    /// variable, which reproducible build tools set to the time of the last
+    /// commit. Returns `None` if it's unset or invalid.
+    #[must_use]
+    pub fn from_env() -> Option<Self> {
+        env::var("SOURCE_DATE_EPOCH")
+            .ok()
+            .and_then(|secs| secs.trim().parse().ok())
+            .map(Self::from_unix)
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> SystemTime {
+        self.0
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Remove items scheduled to be published after the current time of the
+    /// clock. Items without a publish time are kept.
+    #[must_use]
+    pub fn scheduled(
+        mut self,
+        clock: &impl Clock,
+        publish_at: impl Fn(&T) -> Option<SystemTime>,
+    ) -> Self {
+        let now = clock.now();
+        self.items
+            .retain(|item| publish_at(item).is_none_or(|time| time <= now));
+        self
+    }
+}
+
+/// Days since the Unix epoch of a date, see
+/// <https://howardhinnant.github.io/date_algorithms.html>.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The date of a number of days since the Unix epoch.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month + 2) / 5 + 1;
+    let month = if month < 10 { month + 3 } else { month - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Format seconds since the Unix epoch as an RFC 3339 datetime in UTC.
+pub(crate) fn rfc3339(secs: i64) -> String {
+    let (year, month, day) = civil_from_days(secs.div_euclid(86_400));
+    let time = secs.rem_euclid(86_400);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        time / 3600,
+        time % 3600 / 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        let clock = FixedClock::from_unix(1_000);
+        assert_eq!(
+            clock.now(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(1_000)
+        );
+        assert!(clock.is_recent(FixedClock::from_unix(990).0, Duration::from_secs(30)));
+        assert!(!clock.is_recent(FixedClock::from_unix(900).0, Duration::from_secs(30)));
+        assert!(clock.is_recent(FixedClock::from_unix(2_000).0, Duration::from_secs(30)));
+        assert!(SystemClock.now() > clock.now());
+
+        let posts = Parsed::from(vec![
+            ("past", Some(FixedClock::from_unix(500).0)),
+            ("undated", None),
+            ("now", Some(clock.now())),
+            ("future", Some(FixedClock::from_unix(1_500).0)),
+        ]);
+        let posts = posts.scheduled(&clock, |post| post.1);
+        let titles = posts.items.iter().map(|post| post.0).collect::<Vec<_>>();
+        assert_eq!(titles, ["past", "undated", "now"]);
+    }
+}
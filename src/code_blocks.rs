@@ -0,0 +1,173 @@
+use std::{
+    fmt::{self, Write},
+    sync::Arc,
+};
+
+use crate::html::escape;
+
+type CopyButtonFn = dyn Fn(&str) -> String + Send + Sync;
+
+/// A container around highlighted code blocks, see
+/// [`MarkdownOptions::code_blocks`](crate::MarkdownOptions::code_blocks).
+///
+/// A fence like ```` ```rust,filename=main.rs ```` is rendered as:
+///
+/// ```html
+/// <div class="code-block">
+/// <div class="code-block-header"><span class="code-block-filename">main.rs</span></div>
+/// <pre>…</pre>
+/// </div>
+/// ```
+#[derive(Clone)]
+pub struct CodeBlocks {
+    class: String,
+    header_class: String,
+    filename_class: String,
+    copy_button: Option<Arc<CopyButtonFn>>,
+}
+
+impl Default for CodeBlocks {
+    fn default() -> Self {
+        Self {
+            class: "code-block".to_string(),
+            header_class: "code-block-header".to_string(),
+            filename_class: "code-block-filename".to_string(),
+            copy_button: None,
+        }
+    }
+}
+
+impl CodeBlocks {
+    /// Create the default container, with classes prefixed `code-block`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the class of the container, defaults to `code-block`.
+    #[must_use]
+    pub fn class(mut self, class: impl Into<String>) -> Self {
+        self.class = class.into();
+        self
+    }
+
+    /// Set the class of the header, defaults to `code-block-header`.
+    #[must_use]
+    pub fn header_class(mut self, header_class: impl Into<String>) -> Self {
+        self.header_class = header_class.into();
+        self
+    }
+
+    /// Set the class of the filename in the header, defaults to
+    /// `code-block-filename`.
+    #[must_use]
+    pub fn filename_class(mut self, filename_class: impl Into<String>) -> Self {
+        self.filename_class = filename_class.into();
+        self
+    }
+
+    /// Add markup for a copy button to the header of every block. The
+    /// function is called with the code of the block.
+    #[must_use]
+    pub fn copy_button(
+        mut self,
+        copy_button: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.copy_button = Some(Arc::new(copy_button));
+        self
+    }
+
+    /// The markup opening and closing the container of a block.
+    pub(crate) fn wrap(&self, code: &str, filename: Option<&str>) -> (String, String) {
+        let mut open = format!("<div class=\"{}\">\n", escape(&self.class));
+        if filename.is_some() || self.copy_button.is_some() {
+            let _ = write!(open, "<div class=\"{}\">", escape(&self.header_class));
+            if let Some(filename) = filename {
+                let _ = write!(
+                    open,
+                    "<span class=\"{}\">{}</span>",
+                    escape(&self.filename_class),
+                    escape(filename)
+                );
+            }
+            if let Some(copy_button) = &self.copy_button {
+                open.push_str(&copy_button(code));
+            }
+            open.push_str("</div>\n");
+        }
+        (open, "</div>\n".to_string())
+    }
+}
+
+impl fmt::Debug for CodeBlocks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CodeBlocks")
+            .field("class", &self.class)
+            .field("header_class", &self.header_class)
+            .field("filename_class", &self.filename_class)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The info string of a code fence, like `rust,filename=main.rs`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct FenceInfo<'a> {
+    /// The language, the first word.
+    pub(crate) lang: &'a str,
+    /// The value of a `filename=` attribute, without quotes.
+    pub(crate) filename: Option<&'a str>,
+    /// Any other attributes.
+    pub(crate) rest: Vec<&'a str>,
+}
+
+impl<'a> FenceInfo<'a> {
+    pub(crate) fn parse(info: &'a str) -> Self {
+        let mut words = info
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty());
+        let lang = words.next().unwrap_or_default();
+        let mut filename = None;
+        let mut rest = Vec::new();
+        for word in words {
+            match word.strip_prefix("filename=") {
+                Some(value) => filename = Some(value.trim_matches(['"', '\''])),
+                None => rest.push(word),
+            }
+        }
+        Self {
+            lang,
+            filename,
+            rest,
+        }
+    }
+
+    /// The info string without the attributes pichu handles, so the
+    /// highlighter sees the bare language.
+    pub(crate) fn to_info_string(&self) -> String {
+        [self.lang]
+            .into_iter()
+            .chain(self.rest.iter().copied())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fence_info() {
+        let info = FenceInfo::parse("rust,filename=\"src/main.rs\" ignore");
+        assert_eq!(
+            info,
+            FenceInfo {
+                lang: "rust",
+                filename: Some("src/main.rs"),
+                rest: vec!["ignore"],
+            }
+        );
+        assert_eq!(info.to_info_string(), "rust ignore");
+        assert_eq!(FenceInfo::parse("").to_info_string(), "");
+    }
+}
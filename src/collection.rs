@@ -0,0 +1,267 @@
+use rayon::prelude::*;
+use std::{
+    collections::BTreeSet,
+    env, fmt,
+    path::{self, Path, PathBuf},
+};
+
+use crate::{Error, Parsed};
+
+type ParseFn<T> = dyn Fn(&PathBuf) -> Result<T, Error> + Send + Sync;
+type InputsFn<T> = dyn Fn(&T) -> Vec<PathBuf> + Send + Sync;
+
+/// A parsed collection of files that can be updated in place when some of
+/// them change, instead of reparsing everything.
+/// Meant to be kept alive across [`watch`](crate::watch) callbacks.
+///
+/// ```no_run
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut pages = pichu::Collection::new("content/*.md", |path| std::fs::read_to_string(path))?;
+/// pichu::watch(["content"], |paths| match pages.update(&paths) {
+///     Ok(true) => {
+///         let result = pages
+///             .parsed()
+///             .render_all(|pages| pages.concat(), "dist/index.html");
+///         if let Err(e) = result {
+///             eprintln!("Render error: {e}");
+///         }
+///     }
+///     Ok(false) => {}
+///     Err(e) => eprintln!("Parse error: {e}"),
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Collection<T> {
+    pattern: glob::Pattern,
+    /// Whether the pattern was relative, and so are the paths of the items.
+    relative: bool,
+    items: Vec<(PathBuf, T)>,
+    parse_fn: Box<ParseFn<T>>,
+    /// Other files an item was parsed from, like included snippets.
+    inputs_fn: Option<Box<InputsFn<T>>>,
+}
+
+impl<T: Send + Sync> Collection<T> {
+    /// Glob and parse all files matching `pattern`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or if any of the files fail to parse.
+    pub fn new<E: fmt::Debug + Send + Sync + 'static>(
+        pattern: impl AsRef<str>,
+        parse_fn: impl Fn(&PathBuf) -> Result<T, E> + Send + Sync + 'static,
+    ) -> Result<Self, Error> {
+        let pattern = pattern.as_ref();
+        let parse_fn: Box<ParseFn<T>> =
+            Box::new(move |path| parse_fn(path).map_err(|e| Error::Parse(Box::new(e))));
+        let mut items = crate::glob(pattern)?
+            .paths
+            .into_par_iter()
+            .map(|path| {
+                let item = parse_fn(&path)?;
+                Ok((path, item))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        items.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        Ok(Self {
+            pattern: glob::Pattern::new(&absolute(Path::new(pattern)).to_string_lossy())?,
+            relative: Path::new(pattern).is_relative(),
+            items,
+            parse_fn,
+            inputs_fn: None,
+        })
+    }
+
+    /// Also reparse items when one of the files returned by the function
+    /// changes.
+    #[cfg(feature = "markdown")]
+    #[must_use]
+    pub(crate) fn with_inputs(
+        mut self,
+        inputs_fn: impl Fn(&T) -> Vec<PathBuf> + Send + Sync + 'static,
+    ) -> Self {
+        self.inputs_fn = Some(Box::new(inputs_fn));
+        self
+    }
+
+    /// Reparse the given paths if they belong to this collection, adding new
+    /// files and dropping deleted ones.
+    /// Paths not matching the collection's pattern are ignored, so it's fine to
+    /// pass everything reported by [`watch`](crate::watch). Markdown files
+    /// including a changed file are reparsed as well.
+    /// Returns whether the collection changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any of the changed files fail to parse. The
+    /// collection is left unchanged in that case.
+    pub fn update(&mut self, changed: &[PathBuf]) -> Result<bool, Error> {
+        let changed = changed
+            .iter()
+            .map(|path| absolute(path))
+            .collect::<BTreeSet<_>>();
+        let mut reparse = changed
+            .iter()
+            .filter(|path| matches(&self.pattern, path))
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if let Some(inputs_fn) = &self.inputs_fn {
+            reparse.extend(
+                self.items
+                    .iter()
+                    .filter(|(_, item)| {
+                        inputs_fn(item)
+                            .iter()
+                            .any(|input| changed.contains(&absolute(input)))
+                    })
+                    .map(|(path, _)| absolute(path)),
+            );
+        }
+        let changed = reparse.into_iter().collect::<Vec<_>>();
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        let reparsed = changed
+            .into_par_iter()
+            .map(|path| {
+                let path = if self.relative {
+                    relative_to_cwd(path)
+                } else {
+                    path
+                };
+                if path.is_file() {
+                    let item = (self.parse_fn)(&path)?;
+                    Ok((path, Some(item)))
+                } else {
+                    Ok((path, None))
+                }
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for (path, item) in reparsed {
+            let position = self.items.binary_search_by(|(p, _)| p.cmp(&path));
+            match (position, item) {
+                (Ok(i), Some(item)) => self.items[i].1 = item,
+                (Ok(i), None) => {
+                    self.items.remove(i);
+                }
+                (Err(i), Some(item)) => self.items.insert(i, (path, item)),
+                (Err(_), None) => {}
+            }
+        }
+        Ok(true)
+    }
+
+    /// The paths of the files in this collection, sorted.
+    pub fn paths(&self) -> impl Iterator<Item = &Path> {
+        self.items.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Returns the number of items in the collection.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    /// Returns true if the collection has no items.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+impl<T: Clone + Send + Sync> Collection<T> {
+    /// Clone the current items into a [`Parsed`], ready to be sorted and rendered.
+    /// Combine with [`Parsed::render_each_cached`] to only re-render the
+    /// items that changed.
+    #[must_use]
+    pub fn parsed(&self) -> Parsed<T> {
+        Parsed::from(
+            self.items
+                .iter()
+                .map(|(_, item)| item.clone())
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+impl<T> fmt::Debug for Collection<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Collection")
+            .field("pattern", &self.pattern.as_str())
+            .field(
+                "paths",
+                &self.items.iter().map(|(p, _)| p).collect::<Vec<_>>(),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+/// Make `path` absolute without touching the filesystem, so deleted files can
+/// still be matched.
+pub(crate) fn absolute(path: &Path) -> PathBuf {
+    path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Whether `path` matches `pattern` the way [`glob`](crate::glob) would
+/// have found it, with `*` not crossing directories.
+pub(crate) fn matches(pattern: &glob::Pattern, path: &Path) -> bool {
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+    pattern.matches_path_with(path, options)
+}
+
+/// Make `path` relative to the current directory if it's inside it, so
+/// reparsed items look the same as the ones created by the initial glob.
+pub(crate) fn relative_to_cwd(path: PathBuf) -> PathBuf {
+    env::current_dir()
+        .ok()
+        .and_then(|cwd| path.strip_prefix(cwd).ok().map(Path::to_path_buf))
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs};
+
+    #[test]
+    fn test_update() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_collection_update");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(dir.join("a.txt"), "a")?;
+        fs::write(dir.join("b.txt"), "b")?;
+
+        let pattern = dir.join("*.txt");
+        let mut collection =
+            Collection::new(pattern.to_string_lossy(), |path| fs::read_to_string(path))?;
+        assert_eq!(collection.parsed().into_vec(), vec!["a", "b"]);
+
+        // Unrelated paths are ignored
+        fs::write(dir.join("c.md"), "c")?;
+        assert!(!collection.update(&[dir.join("c.md")])?);
+        // `*` doesn't match across directories, like the initial glob
+        fs::create_dir_all(dir.join("nested"))?;
+        fs::write(dir.join("nested/d.txt"), "d")?;
+        assert!(!collection.update(&[dir.join("nested/d.txt")])?);
+
+        fs::write(dir.join("a.txt"), "a2")?;
+        fs::write(dir.join("0.txt"), "0")?;
+        fs::remove_file(dir.join("b.txt"))?;
+        let changed = [dir.join("a.txt"), dir.join("0.txt"), dir.join("b.txt")];
+        assert!(collection.update(&changed)?);
+        assert_eq!(collection.parsed().into_vec(), vec!["0", "a2"]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use gray_matter::engine::{Engine, YAML};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{markdown::render_at, Error, Glob, MarkdownError, MarkdownOptions, Parsed};
+
+/// A comment on a post, stored as a YAML file in a directory named after the
+/// post's slug, e.g. `data/comments/hello-world/2024-05-01-alice.yaml`:
+///
+/// ```yaml
+/// author: Alice
+/// url: https://alice.example
+/// date: 2024-05-01
+/// body: Great post, *thanks*!
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Comment {
+    /// The slug of the post, the name of the directory of the file.
+    pub slug: String,
+    /// The path of the source file.
+    pub path: PathBuf,
+    /// The name of the author.
+    pub author: String,
+    /// The website of the author.
+    pub url: Option<String>,
+    /// The date the comment was written, as written in the file. Comments
+    /// are sorted by it, so it should be an ISO 8601 date.
+    pub date: Option<String>,
+    /// The comment in markdown.
+    pub body: String,
+    /// The sanitized HTML of the comment.
+    pub html: String,
+}
+
+/// The fields of a comment file.
+#[derive(Deserialize)]
+struct CommentFile {
+    author: String,
+    url: Option<String>,
+    date: Option<String>,
+    body: String,
+}
+
+/// Parse a comment file, rendering its body with the given options.
+/// The HTML is always sanitized, see [`MarkdownOptions::sanitize`].
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or isn't a valid comment.
+pub fn parse_comment(path: &Path, options: &MarkdownOptions) -> Result<Comment, MarkdownError> {
+    let contents = fs::read_to_string(path)?;
+    let file: CommentFile = YAML::parse(&contents)
+        .deserialize()
+        .map_err(|e| MarkdownError::Comment(path.to_path_buf(), e))?;
+    let options = options.clone().sanitize(true);
+    let html = render_at(&file.body, path.parent().unwrap_or(Path::new("")), &options)?;
+    let slug = path
+        .parent()
+        .and_then(Path::file_name)
+        .map(|slug| slug.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(Comment {
+        slug,
+        path: path.to_path_buf(),
+        author: file.author,
+        url: file.url,
+        date: file.date,
+        body: file.body,
+        html,
+    })
+}
+
+impl Glob {
+    /// Parse the paths as comment files, usually globbed with
+    /// `data/comments/*/*.yaml`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any comment file cannot be parsed.
+    pub fn parse_comments(self) -> Result<Parsed<Comment>, Error> {
+        self.parse_comments_with(&MarkdownOptions::default())
+    }
+
+    /// Parse the paths as comment files, rendering with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any comment file cannot be parsed.
+    pub fn parse_comments_with(self, options: &MarkdownOptions) -> Result<Parsed<Comment>, Error> {
+        self.try_parse::<Comment, MarkdownError>(|path| parse_comment(path, options))
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Pair each item with the comments whose slug matches the item's,
+    /// sorted by date, so render functions can show them. Comments on
+    /// unknown slugs are dropped.
+    ///
+    /// ```no_run
+    /// # #[derive(serde::Deserialize, Debug)]
+    /// # struct Post {}
+    /// let comments = pichu::glob("data/comments/*/*.yaml")?.parse_comments()?;
+    /// pichu::glob("content/blog/*.md")?
+    ///     .parse_markdown::<Post>()?
+    ///     .join_comments(comments, |post| &post.basename)
+    ///     .render_each(
+    ///         |(post, comments)| format!("{}<p>{} comments</p>", post.html, comments.len()),
+    ///         |(post, _)| format!("dist/blog/{}/index.html", post.basename),
+    ///     )?;
+    /// # Ok::<(), pichu::Error>(())
+    /// ```
+    #[must_use]
+    pub fn join_comments(
+        self,
+        comments: Parsed<Comment>,
+        slug_fn: impl Fn(&T) -> &str,
+    ) -> Parsed<(T, Vec<Comment>)> {
+        let mut by_slug = HashMap::<String, Vec<Comment>>::new();
+        for comment in comments.items {
+            by_slug
+                .entry(comment.slug.clone())
+                .or_default()
+                .push(comment);
+        }
+        let unordered = self.unordered;
+        let items = self
+            .items
+            .into_iter()
+            .map(|item| {
+                let mut comments = by_slug.remove(slug_fn(&item)).unwrap_or_default();
+                comments.sort_by(|a, b| (&a.date, &a.path).cmp(&(&b.date, &b.path)));
+                (item, comments)
+            })
+            .collect::<Vec<_>>();
+        Parsed { items, unordered }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_comments() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_comments");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(
+            dir.join("hello/b.yaml"),
+            "author: Bob\ndate: 2024-05-02\nbody: Thanks!\n",
+        )?;
+        crate::write(
+            dir.join("hello/a.yaml"),
+            "author: Alice\nurl: https://alice.example\ndate: 2024-05-01\nbody: |\n  *Nice* <script>alert(1)</script>\n",
+        )?;
+        crate::write(dir.join("gone/c.yaml"), "author: Carol\nbody: Hi\n")?;
+
+        let comments = crate::glob(dir.join("*/*.yaml").to_string_lossy())?.parse_comments()?;
+        let posts = Parsed::from(vec!["hello", "quiet"])
+            .unordered()
+            .join_comments(comments, |post| post);
+        assert!(posts.unordered);
+
+        let (post, comments) = &posts.items[0];
+        assert_eq!(*post, "hello");
+        let authors = comments
+            .iter()
+            .map(|c| c.author.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(authors, ["Alice", "Bob"]);
+        assert_eq!(comments[0].slug, "hello");
+        assert_eq!(comments[0].url.as_deref(), Some("https://alice.example"));
+        assert!(comments[0].html.contains("<em>Nice</em>"));
+        assert!(!comments[0].html.contains("<script"));
+        assert!(posts.items[1].1.is_empty());
+
+        crate::write(dir.join("hello/invalid.yaml"), "body: No author\n")?;
+        assert!(crate::glob(dir.join("*/*.yaml").to_string_lossy())?
+            .parse_comments()
+            .is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
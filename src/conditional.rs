@@ -0,0 +1,114 @@
+use std::{fs, io, path::Path};
+
+use crate::{output::write_optimistic, Error, Parsed};
+
+/// What [`Parsed::render_all_or`] does when there are no items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OnEmpty {
+    /// Render and write the page anyway, like [`Parsed::render_all`].
+    Write,
+    /// Don't write anything.
+    #[default]
+    Skip,
+    /// Return [`Error::Empty`], e.g. because a glob matching nothing is a
+    /// typo rather than an empty section.
+    Fail,
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Like [`Parsed::render_all`], but only renders and writes if the
+    /// condition holds for the items. The file isn't rewritten if its
+    /// contents are unchanged, keeping its modification time.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written to the filesystem.
+    pub fn render_all_if<S: Into<String>>(
+        self,
+        condition: impl Fn(&[T]) -> bool,
+        render_fn: impl Fn(&Vec<T>) -> S,
+        dest_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        if condition(&self.items) {
+            write_if_changed(dest_path.as_ref(), render_fn(&self.items).into().as_bytes())?;
+        }
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_all_if`], rendering if there are items and
+    /// doing what `on_empty` says otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Empty`] if there are no items and `on_empty` is
+    /// [`OnEmpty::Fail`], or an error if the file cannot be written.
+    pub fn render_all_or<S: Into<String>>(
+        self,
+        on_empty: OnEmpty,
+        render_fn: impl Fn(&Vec<T>) -> S,
+        dest_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        if self.items.is_empty() && on_empty == OnEmpty::Fail {
+            return Err(Error::Empty(dest_path.as_ref().to_path_buf()));
+        }
+        self.render_all_if(
+            |items| !items.is_empty() || on_empty == OnEmpty::Write,
+            render_fn,
+            dest_path,
+        )
+    }
+}
+
+/// Write `contents` to `path`, unless the file already has these contents.
+fn write_if_changed(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    match fs::read(path) {
+        Ok(existing) if existing == contents => Ok(()),
+        _ => write_optimistic(path, contents),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_render_all_if() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_all_if");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let empty = Parsed::<&str>::from(vec![]);
+        let posts = Parsed::from(vec!["hello"]);
+
+        let empty = empty
+            .render_all_if(|items| items.len() > 1, |_| "", dir.join("if.html"))?
+            .render_all_or(OnEmpty::Skip, |_| "", dir.join("skip.html"))?
+            .render_all_or(OnEmpty::Write, |_| "No posts", dir.join("write.html"))?;
+        assert!(!dir.join("if.html").exists());
+        assert!(!dir.join("skip.html").exists());
+        assert_eq!(fs::read_to_string(dir.join("write.html"))?, "No posts");
+        assert!(matches!(
+            empty.render_all_or(OnEmpty::Fail, |_| "", dir.join("fail.html")),
+            Err(Error::Empty(path)) if path == dir.join("fail.html")
+        ));
+
+        let posts = posts.render_all_or(
+            OnEmpty::Fail,
+            |items| items.join(""),
+            dir.join("index.html"),
+        )?;
+        let modified = fs::metadata(dir.join("index.html"))?.modified()?;
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        posts.render_all_or(
+            OnEmpty::Fail,
+            |items| items.join(""),
+            dir.join("index.html"),
+        )?;
+        assert_eq!(fs::metadata(dir.join("index.html"))?.modified()?, modified);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
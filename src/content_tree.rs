@@ -0,0 +1,184 @@
+use rayon::prelude::*;
+use serde::de::DeserializeOwned;
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    markdown::parse_markdown_with, CopyOptions, Error, Markdown, MarkdownError, MarkdownOptions,
+    Parsed,
+};
+
+/// A content directory mixing markdown files with images, downloads and
+/// other files. Parses the markdown files and copies everything else to the
+/// same place below the output directory in one pass, so the two can't
+/// drift apart like a separate [`glob`](crate::glob) and
+/// [`copy_dir`](crate::copy_dir) can.
+/// Skips files starting with a `.`, except `.well-known`.
+///
+/// ```no_run
+/// # #[derive(serde::Deserialize, Debug)]
+/// # struct Doc {}
+/// use pichu::{ContentTree, UrlStyle};
+///
+/// ContentTree::new("content/docs")
+///     .build::<Doc>("dist/docs")?
+///     .render_each_at("dist/docs", |doc| doc.html.clone(), |doc| doc.url(UrlStyle::Html))?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ContentTree {
+    dir: PathBuf,
+    markdown_options: MarkdownOptions,
+    copy_options: CopyOptions,
+}
+
+impl ContentTree {
+    /// Read the content directory `dir`.
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            markdown_options: MarkdownOptions::default(),
+            copy_options: CopyOptions::default(),
+        }
+    }
+
+    /// Render markdown files with the given options.
+    #[must_use]
+    pub fn markdown_options(mut self, options: MarkdownOptions) -> Self {
+        self.markdown_options = options;
+        self
+    }
+
+    /// Copy other files with the given options.
+    #[must_use]
+    pub fn copy_options(mut self, options: CopyOptions) -> Self {
+        self.copy_options = options;
+        self
+    }
+
+    /// Parse the markdown files, with [`Markdown::relative_path`] relative
+    /// to the content directory, and copy the other files to the output
+    /// directory, keeping their relative paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read, a markdown file
+    /// cannot be parsed, a file cannot be copied, or
+    /// [`Error::FileExists`] if it already exists at the destination.
+    pub fn build<T: DeserializeOwned + fmt::Debug + Send + Sync>(
+        &self,
+        out_dir: impl AsRef<Path>,
+    ) -> Result<Parsed<Markdown<T>>, Error> {
+        let mut markdown = Vec::new();
+        let mut files = Vec::new();
+        walk(&self.dir, &mut markdown, &mut files)?;
+        markdown.sort();
+
+        let out_dir = out_dir.as_ref();
+        files
+            .par_iter()
+            .map(|path| {
+                let to = out_dir.join(path.strip_prefix(&self.dir).unwrap_or(path));
+                if to.exists() {
+                    return Err(Error::FileExists(to));
+                }
+                if let Some(parent) = to.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Ok(self.copy_options.copy(path, &to)?)
+            })
+            .collect::<Result<Vec<()>, Error>>()?;
+
+        let items = markdown
+            .par_iter()
+            .map(|path| {
+                let mut markdown = parse_markdown_with(path, &self.markdown_options)?;
+                markdown.relative_path = path.strip_prefix(&self.dir).unwrap_or(path).to_path_buf();
+                Ok(markdown)
+            })
+            .collect::<Result<Vec<_>, MarkdownError>>()
+            .map_err(|e| Error::Parse(Box::new(e)))?;
+        Ok(Parsed::from(items))
+    }
+}
+
+/// Collect the markdown and other files below `dir`.
+fn walk(dir: &Path, markdown: &mut Vec<PathBuf>, files: &mut Vec<PathBuf>) -> Result<(), Error> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with('.') && name != ".well-known" {
+            continue;
+        }
+        let path = entry.path();
+        if path.is_dir() {
+            walk(&path, markdown, files)?;
+        } else if path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("md"))
+        {
+            markdown.push(path);
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UrlStyle;
+    use std::env;
+
+    #[derive(serde::Deserialize, Debug)]
+    struct Doc {
+        title: String,
+    }
+
+    #[test]
+    fn test_content_tree() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_content_tree");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let content = dir.join("content");
+        crate::write(content.join("index.md"), "---\ntitle: Docs\n---\n")?;
+        crate::write(content.join("guide/setup.md"), "---\ntitle: Setup\n---\n")?;
+        crate::write(content.join("guide/diagram.png"), "png")?;
+        crate::write(content.join("files/manual.pdf"), "pdf")?;
+        crate::write(content.join(".DS_Store"), "")?;
+
+        let out_dir = dir.join("dist");
+        let docs = ContentTree::new(&content).build::<Doc>(&out_dir)?;
+        let titles = docs
+            .items
+            .iter()
+            .map(|doc| doc.frontmatter.title.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(titles, ["Setup", "Docs"]);
+        assert_eq!(
+            docs.items[0].url(UrlStyle::Pretty)?.as_str(),
+            "/guide/setup/"
+        );
+        assert_eq!(
+            fs::read_to_string(out_dir.join("guide/diagram.png"))?,
+            "png"
+        );
+        assert_eq!(fs::read_to_string(out_dir.join("files/manual.pdf"))?, "pdf");
+        assert!(!out_dir.join(".DS_Store").exists());
+        assert!(!out_dir.join("index.md").exists());
+
+        assert!(matches!(
+            ContentTree::new(&content).build::<Doc>(&out_dir),
+            Err(Error::FileExists(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
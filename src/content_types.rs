@@ -0,0 +1,286 @@
+use std::{collections::BTreeMap, fmt::Write, io, path::Path};
+
+use serde::Serialize;
+
+use crate::{output::write_atomic, Error, UrlPath};
+
+/// Cache-control for fingerprinted files, which never change under the same
+/// name.
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+/// Cache-control for HTML, which keeps its URL when the content changes.
+const REVALIDATE: &str = "public, max-age=0, must-revalidate";
+/// Cache-control for everything else.
+const DEFAULT_CACHE: &str = "public, max-age=3600";
+
+/// MIME types by lowercase file extension. Types missing from common tables
+/// (`.webmanifest`, `.wasm`, `.avif`) are included on purpose.
+const TYPES: &[(&str, &str)] = &[
+    ("html", "text/html"),
+    ("htm", "text/html"),
+    ("css", "text/css"),
+    ("js", "text/javascript"),
+    ("mjs", "text/javascript"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("jsonld", "application/ld+json"),
+    ("webmanifest", "application/manifest+json"),
+    ("xml", "application/xml"),
+    ("rss", "application/rss+xml"),
+    ("atom", "application/atom+xml"),
+    ("txt", "text/plain"),
+    ("md", "text/markdown"),
+    ("csv", "text/csv"),
+    ("ics", "text/calendar"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("wasm", "application/wasm"),
+    ("pdf", "application/pdf"),
+    ("zip", "application/zip"),
+    ("gz", "application/gzip"),
+    ("mp3", "audio/mpeg"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// How a file should be served.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ContentType {
+    /// The MIME type, e.g. `text/html`.
+    pub mime: String,
+    /// The charset for text types, always `utf-8`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub charset: Option<String>,
+    /// A suggested `Cache-Control` header: immutable for fingerprinted files,
+    /// revalidated for HTML.
+    pub cache_control: String,
+}
+
+impl ContentType {
+    /// The value of the `Content-Type` header, e.g.
+    /// `text/html; charset=utf-8`.
+    #[must_use]
+    pub fn header(&self) -> String {
+        match &self.charset {
+            Some(charset) => format!("{}; charset={charset}", self.mime),
+            None => self.mime.clone(),
+        }
+    }
+}
+
+/// Maps the files of the output directory to their content types, for deploy
+/// tools and hosts that need them spelled out.
+///
+/// ```
+/// use pichu::ContentTypes;
+///
+/// let types = ContentTypes::new().with_type("gmi", "text/gemini");
+/// assert_eq!(types.get("site.webmanifest").mime, "application/manifest+json");
+/// assert_eq!(types.get("index.gmi").header(), "text/gemini; charset=utf-8");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ContentTypes {
+    overrides: BTreeMap<String, String>,
+}
+
+impl ContentTypes {
+    /// Create a map with the built-in types.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve files with the extension (without the dot) as `mime`,
+    /// replacing the built-in type.
+    #[must_use]
+    pub fn with_type(mut self, extension: impl AsRef<str>, mime: impl Into<String>) -> Self {
+        self.overrides
+            .insert(extension.as_ref().to_lowercase(), mime.into());
+        self
+    }
+
+    /// The content type of the file at `path`, falling back to
+    /// `application/octet-stream` for unknown extensions.
+    #[must_use]
+    pub fn get(&self, path: impl AsRef<Path>) -> ContentType {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .map(|extension| extension.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let mime = self.overrides.get(&extension).map_or_else(
+            || {
+                TYPES
+                    .iter()
+                    .find(|(known, _)| *known == extension)
+                    .map_or("application/octet-stream", |(_, mime)| mime)
+            },
+            String::as_str,
+        );
+        let text = mime.starts_with("text/")
+            || mime.ends_with("+json")
+            || mime.ends_with("+xml")
+            || matches!(mime, "application/json" | "application/xml");
+        let cache_control = if is_fingerprinted(path) {
+            IMMUTABLE
+        } else if mime == "text/html" {
+            REVALIDATE
+        } else {
+            DEFAULT_CACHE
+        };
+        ContentType {
+            mime: mime.to_string(),
+            charset: text.then(|| "utf-8".to_string()),
+            cache_control: cache_control.to_string(),
+        }
+    }
+
+    /// Collect the content types of every file in the output directory,
+    /// keyed by URL path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn scan(&self, out_dir: impl AsRef<Path>) -> Result<ContentTypeManifest, Error> {
+        let out_dir = out_dir.as_ref();
+        let pattern = Path::new(&glob::Pattern::escape(&out_dir.to_string_lossy())).join("**/*");
+        let mut entries = BTreeMap::new();
+        for path in glob::glob(&pattern.to_string_lossy())? {
+            let path = path?;
+            if !path.is_file() {
+                continue;
+            }
+            let url = UrlPath::from_output(out_dir, &path)?;
+            entries.insert(url.as_str().to_string(), self.get(&path));
+        }
+        Ok(ContentTypeManifest { entries })
+    }
+}
+
+/// Whether a file name contains a hash as written by
+/// [`copy_file`](crate::copy_file), e.g. `main.4f2a9c1e0b3d5a7f.css`.
+fn is_fingerprinted(path: &Path) -> bool {
+    path.file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .is_some_and(|stem| {
+            stem.split(['.', '-'])
+                .skip(1)
+                .any(|part| part.len() == 16 && part.bytes().all(|b| b.is_ascii_hexdigit()))
+        })
+}
+
+/// The content types of the files of a build, from [`ContentTypes::scan`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct ContentTypeManifest {
+    entries: BTreeMap<String, ContentType>,
+}
+
+impl ContentTypeManifest {
+    /// The content type of the file at the URL path.
+    #[must_use]
+    pub fn get(&self, url: &UrlPath) -> Option<&ContentType> {
+        self.entries.get(url.as_str())
+    }
+
+    /// All entries, sorted by URL path.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ContentType)> {
+        self.entries.iter().map(|(url, ty)| (url.as_str(), ty))
+    }
+
+    /// Write the manifest to disk as a JSON object of URL paths to
+    /// `mime`, `charset` and `cache_control`, for deploy scripts uploading
+    /// to object storage.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(path.as_ref(), json.as_bytes())
+    }
+
+    /// The manifest as a `_headers` file for Netlify and Cloudflare Pages.
+    #[must_use]
+    pub fn to_headers_file(&self) -> String {
+        let mut file = String::new();
+        for (url, ty) in &self.entries {
+            let _ = writeln!(
+                file,
+                "{url}\n  Content-Type: {}\n  Cache-Control: {}",
+                ty.header(),
+                ty.cache_control
+            );
+        }
+        file
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn test_content_types() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_content_types[1]");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("index.html"), "<h1>Hi</h1>")?;
+        crate::write(dir.join("site.webmanifest"), "{}")?;
+        crate::write(dir.join("app.wasm"), "")?;
+        crate::write(dir.join("img/cover.AVIF"), "")?;
+        crate::write(dir.join("main.4f2a9c1e0b3d5a7f.css"), "")?;
+
+        let manifest = ContentTypes::new().scan(&dir)?;
+        let urls = manifest.iter().map(|(url, _)| url).collect::<Vec<_>>();
+        assert_eq!(
+            urls,
+            [
+                "/",
+                "/app.wasm",
+                "/img/cover.AVIF",
+                "/main.4f2a9c1e0b3d5a7f.css",
+                "/site.webmanifest"
+            ]
+        );
+        let get = |url: &str| manifest.get(&UrlPath::new(url).ok()?).cloned();
+        let index = get("/").ok_or("missing index")?;
+        assert_eq!(index.header(), "text/html; charset=utf-8");
+        assert_eq!(index.cache_control, REVALIDATE);
+        let wasm = get("/app.wasm").ok_or("missing wasm")?;
+        assert_eq!(wasm.header(), "application/wasm");
+        assert_eq!(wasm.cache_control, DEFAULT_CACHE);
+        assert_eq!(
+            get("/img/cover.AVIF").map(|ty| ty.mime),
+            Some("image/avif".to_string())
+        );
+        assert_eq!(
+            get("/main.4f2a9c1e0b3d5a7f.css").map(|ty| ty.cache_control),
+            Some(IMMUTABLE.to_string())
+        );
+
+        assert!(manifest.to_headers_file().contains(
+            "/site.webmanifest\n  Content-Type: application/manifest+json; charset=utf-8\n"
+        ));
+        manifest.save(dir.join("content-types.json"))?;
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("content-types.json"))?)?;
+        assert_eq!(json["/app.wasm"]["mime"], "application/wasm");
+        assert!(json["/app.wasm"].get("charset").is_none());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+use std::{
+    fs::{self, File},
+    io,
+    path::Path,
+    time::SystemTime,
+};
+
+use crate::Error;
+
+/// How [`copy_dir_with`] creates files.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CopyMode {
+    /// Copy the contents.
+    #[default]
+    Copy,
+    /// Clone the file copy-on-write, on filesystems that support it (APFS,
+    /// btrfs, XFS and Windows Dev Drives), copying otherwise. Clones share disk space until
+    /// either file is modified.
+    #[cfg(feature = "reflink")]
+    Reflink,
+    /// Hard link the file, copying if that fails, e.g. because the
+    /// destination is on another filesystem. Modifying either file modifies
+    /// both, so the output must not be changed in place.
+    Hardlink,
+}
+
+/// What to do with symlinks when copying or globbing, see
+/// [`CopyOptions::symlinks`] and [`Glob::symlinks`](crate::Glob::symlinks).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SymlinkPolicy {
+    /// Treat symlinks like the file or directory they point to.
+    #[default]
+    Follow,
+    /// Create a symlink with the same target. The same as
+    /// [`SymlinkPolicy::Follow`] for globs.
+    Preserve,
+    /// Leave symlinks out.
+    Skip,
+    /// Return [`Error::Symlink`].
+    Error,
+}
+
+/// Options for [`copy_dir_with`].
+#[derive(Debug, Clone)]
+pub struct CopyOptions {
+    mode: CopyMode,
+    min_size: u64,
+    symlinks: SymlinkPolicy,
+    preserve_permissions: bool,
+    preserve_mtime: bool,
+}
+
+impl Default for CopyOptions {
+    fn default() -> Self {
+        Self {
+            mode: CopyMode::default(),
+            min_size: 0,
+            symlinks: SymlinkPolicy::default(),
+            preserve_permissions: true,
+            preserve_mtime: false,
+        }
+    }
+}
+
+impl CopyOptions {
+    /// Create the default options, copying every file like [`copy_dir`](crate::copy_dir).
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how files are created, defaults to [`CopyMode::Copy`].
+    #[must_use]
+    pub fn mode(mut self, mode: CopyMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Only use the mode for files at least this many bytes large, e.g.
+    /// videos and archives, and copy smaller ones.
+    #[must_use]
+    pub fn min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Set what to do with symlinks, defaults to [`SymlinkPolicy::Follow`].
+    #[must_use]
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Whether copies keep the permissions of the original, e.g. the
+    /// executable bit of a download, defaults to true. Otherwise they get
+    /// the permissions of new files. Hard links always share them.
+    #[must_use]
+    pub fn preserve_permissions(mut self, preserve_permissions: bool) -> Self {
+        self.preserve_permissions = preserve_permissions;
+        self
+    }
+
+    /// Whether copies keep the modification time of the original, so tools
+    /// syncing by modification time only see changed files as changed,
+    /// defaults to false. Hard links always share it.
+    #[must_use]
+    pub fn preserve_mtime(mut self, preserve_mtime: bool) -> Self {
+        self.preserve_mtime = preserve_mtime;
+        self
+    }
+
+    pub(crate) fn copy(&self, from: &Path, to: &Path) -> Result<(), io::Error> {
+        let metadata = fs::metadata(from)?;
+        let mode = if metadata.len() < self.min_size {
+            CopyMode::Copy
+        } else {
+            self.mode
+        };
+        match mode {
+            CopyMode::Copy if !self.preserve_permissions => {
+                io::copy(&mut File::open(from)?, &mut File::create(to)?)?;
+            }
+            CopyMode::Copy => {
+                fs::copy(from, to)?;
+            }
+            #[cfg(feature = "reflink")]
+            CopyMode::Reflink => {
+                reflink_copy::reflink_or_copy(from, to)?;
+            }
+            CopyMode::Hardlink => {
+                if fs::hard_link(from, to).is_ok() {
+                    return Ok(());
+                }
+                fs::copy(from, to)?;
+            }
+        }
+
+        if self.preserve_mtime {
+            set_modified(to, metadata.modified()?)?;
+        }
+        if self.preserve_permissions {
+            fs::set_permissions(to, metadata.permissions())?;
+        }
+        Ok(())
+    }
+}
+
+/// Like [`copy_dir`](crate::copy_dir), with the given options.
+///
+/// # Errors
+///
+/// Returns an error if directories cannot be created, files cannot be copied, or if a file already exists at the destination.
+pub fn copy_dir_with(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    options: &CopyOptions,
+) -> Result<(), Error> {
+    fs::create_dir_all(to.as_ref())?;
+    fs::read_dir(from.as_ref())?
+        .map(|entry| {
+            let entry = entry?;
+            let file_name = entry.file_name();
+
+            let file_name_str = file_name.to_string_lossy();
+            if file_name_str.starts_with('.') && file_name_str != ".well-known" {
+                return Ok(());
+            }
+
+            let new_path = to.as_ref().join(file_name);
+            if entry.file_type()?.is_symlink() {
+                match options.symlinks {
+                    SymlinkPolicy::Follow => {}
+                    SymlinkPolicy::Preserve => {
+                        if new_path.symlink_metadata().is_ok() {
+                            return Err(Error::FileExists(new_path));
+                        }
+                        return Ok(symlink(
+                            &fs::read_link(entry.path())?,
+                            &entry.path(),
+                            &new_path,
+                        )?);
+                    }
+                    SymlinkPolicy::Skip => return Ok(()),
+                    SymlinkPolicy::Error => return Err(Error::Symlink(entry.path())),
+                }
+            }
+            if entry.path().is_dir() {
+                fs::create_dir(&new_path)?;
+                copy_dir_with(entry.path(), &new_path, options)?;
+            } else {
+                if new_path.exists() {
+                    return Err(Error::FileExists(new_path));
+                }
+
+                options.copy(&entry.path(), &new_path)?;
+            }
+
+            Ok(())
+        })
+        .collect::<Result<Vec<()>, Error>>()?;
+    Ok(())
+}
+
+/// Set the modification time of the file at `path`.
+fn set_modified(path: &Path, modified: SystemTime) -> Result<(), io::Error> {
+    // Windows needs write access to change file times, Unix only ownership,
+    // which also works for read-only files.
+    let file = if cfg!(windows) {
+        File::options().write(true).open(path)?
+    } else {
+        File::open(path)?
+    };
+    file.set_modified(modified)
+}
+
+/// Create a symlink at `path` to `target`, like the one at `original`.
+#[cfg(unix)]
+fn symlink(target: &Path, _original: &Path, path: &Path) -> Result<(), io::Error> {
+    std::os::unix::fs::symlink(target, path)
+}
+
+/// Create a symlink at `path` to `target`, like the one at `original`.
+#[cfg(windows)]
+fn symlink(target: &Path, original: &Path, path: &Path) -> Result<(), io::Error> {
+    if original.is_dir() {
+        std::os::windows::fs::symlink_dir(target, path)
+    } else {
+        std::os::windows::fs::symlink_file(target, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_copy_dir_with() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_copy_dir_with");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("static/video.mp4"), "large video")?;
+        crate::write(dir.join("static/css/main.css"), "body {}")?;
+
+        let options = CopyOptions::new().mode(CopyMode::Hardlink).min_size(10);
+        copy_dir_with(dir.join("static"), dir.join("dist"), &options)?;
+        assert_eq!(
+            fs::read_to_string(dir.join("dist/video.mp4"))?,
+            "large video"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("dist/css/main.css"))?,
+            "body {}"
+        );
+
+        // Writing through the hard link changes the source, the copy doesn't.
+        fs::write(dir.join("dist/video.mp4"), "edited")?;
+        fs::write(dir.join("dist/css/main.css"), "edited")?;
+        assert_eq!(fs::read_to_string(dir.join("static/video.mp4"))?, "edited");
+        assert_eq!(
+            fs::read_to_string(dir.join("static/css/main.css"))?,
+            "body {}"
+        );
+
+        #[cfg(feature = "reflink")]
+        {
+            let options = CopyOptions::new().mode(CopyMode::Reflink);
+            copy_dir_with(dir.join("static"), dir.join("reflinked"), &options)?;
+            fs::write(dir.join("reflinked/video.mp4"), "cloned")?;
+            assert_eq!(fs::read_to_string(dir.join("static/video.mp4"))?, "edited");
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_preserve() -> Result<(), Box<dyn std::error::Error>> {
+        use std::{os::unix::fs::PermissionsExt, time::Duration};
+
+        let dir = env::temp_dir().join("pichu_test_copy_preserve");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("static/install.sh"), "#!/bin/sh")?;
+        fs::set_permissions(
+            dir.join("static/install.sh"),
+            fs::Permissions::from_mode(0o755),
+        )?;
+        let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        File::open(dir.join("static/install.sh"))?.set_modified(modified)?;
+
+        let options = CopyOptions::new().preserve_mtime(true);
+        copy_dir_with(dir.join("static"), dir.join("preserved"), &options)?;
+        let metadata = fs::metadata(dir.join("preserved/install.sh"))?;
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o755);
+        assert_eq!(metadata.modified()?, modified);
+
+        let options = CopyOptions::new().preserve_permissions(false);
+        copy_dir_with(dir.join("static"), dir.join("plain"), &options)?;
+        let metadata = fs::metadata(dir.join("plain/install.sh"))?;
+        assert_eq!(metadata.permissions().mode() & 0o111, 0);
+        assert_ne!(metadata.modified()?, modified);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_copy_symlinks");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("shared/logo.svg"), "<svg/>")?;
+        crate::write(dir.join("static/main.css"), "body {}")?;
+        std::os::unix::fs::symlink(dir.join("shared"), dir.join("static/shared"))?;
+
+        let copy = |name: &str, symlinks| {
+            let options = CopyOptions::new().symlinks(symlinks);
+            copy_dir_with(dir.join("static"), dir.join(name), &options)
+        };
+        copy("follow", SymlinkPolicy::Follow)?;
+        assert!(!dir.join("follow/shared").is_symlink());
+        assert!(dir.join("follow/shared/logo.svg").is_file());
+        copy("preserve", SymlinkPolicy::Preserve)?;
+        assert_eq!(
+            fs::read_link(dir.join("preserve/shared"))?,
+            dir.join("shared")
+        );
+        copy("skip", SymlinkPolicy::Skip)?;
+        assert!(!dir.join("skip/shared").exists());
+        assert!(dir.join("skip/main.css").exists());
+        assert!(matches!(
+            copy("error", SymlinkPolicy::Error),
+            Err(Error::Symlink(path)) if path == dir.join("static/shared")
+        ));
+
+        let glob =
+            |symlinks| crate::glob(dir.join("static/**/*.*").to_string_lossy())?.symlinks(symlinks);
+        assert_eq!(glob(SymlinkPolicy::Follow)?.paths.len(), 2);
+        assert_eq!(
+            glob(SymlinkPolicy::Skip)?.paths,
+            [dir.join("static/main.css")]
+        );
+        assert!(matches!(
+            glob(SymlinkPolicy::Error),
+            Err(Error::Symlink(path)) if path == dir.join("static/shared")
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
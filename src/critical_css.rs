@@ -0,0 +1,384 @@
+use std::collections::HashSet;
+
+use crate::html::{escape, tags};
+
+/// Extract the subset of `css` whose selectors can match elements in `html`.
+///
+/// This is a static approximation: a rule is kept if every tag, class and
+/// ID in one of its selectors appears somewhere in the document. Layout isn't
+/// computed, so rules for content below the fold are kept too.
+/// `@media` and `@supports` blocks are filtered recursively, `@font-face` is
+/// kept, `@keyframes` are kept if a kept rule animates with them and other
+/// at-rules are dropped.
+#[must_use]
+pub fn critical_css(html: &str, css: &str) -> String {
+    let used = UsedSelectors::from_html(html);
+    let mut critical = String::new();
+    let mut keyframes = Vec::new();
+    filter_rules(css, &used, &mut critical, &mut keyframes);
+    let animations = animation_names(&critical);
+    let used_keyframes = keyframes
+        .into_iter()
+        .filter(|(name, _)| animations.contains(name))
+        .map(|(_, rule)| rule)
+        .collect::<String>();
+    critical + &used_keyframes
+}
+
+/// Inline the critical CSS for `html` into its `<head>` and load the
+/// stylesheet at `stylesheet_href` without blocking rendering.
+/// The `<link rel="stylesheet">` referencing the stylesheet is replaced by a
+/// preload that applies it once loaded, with a `<noscript>` fallback.
+#[must_use]
+pub fn inline_critical_css(html: &str, css: &str, stylesheet_href: &str) -> String {
+    let critical = critical_css(html, css);
+    let href = escape(stylesheet_href);
+    let deferred = format!(
+        r#"<link rel="preload" href="{href}" as="style" onload="this.onload=null;this.rel='stylesheet'"><noscript><link rel="stylesheet" href="{href}"></noscript>"#
+    );
+
+    let mut html = html.to_string();
+    let link =
+        find_stylesheet_link(&html, &href).or_else(|| find_stylesheet_link(&html, stylesheet_href));
+    if let Some((start, end)) = link {
+        html.replace_range(start..end, &deferred);
+    } else if let Some(head_end) = html.find("</head>") {
+        html.insert_str(head_end, &deferred);
+    }
+    let style = format!("<style>{critical}</style>");
+    match html.find("</head>") {
+        Some(head_end) => html.insert_str(head_end, &style),
+        None => html.insert_str(0, &style),
+    }
+    html
+}
+
+/// Find the byte range of the `<link>` tag loading `href` as a stylesheet.
+fn find_stylesheet_link(html: &str, href: &str) -> Option<(usize, usize)> {
+    let mut offset = 0;
+    while let Some(start) = html[offset..].find("<link").map(|i| i + offset) {
+        let end = html[start..].find('>').map(|i| start + i + 1)?;
+        let tag = &html[start..end];
+        if tag.contains("stylesheet")
+            && (tag.contains(&format!("href=\"{href}\""))
+                || tag.contains(&format!("href='{href}'")))
+        {
+            return Some((start, end));
+        }
+        offset = end;
+    }
+    None
+}
+
+/// The tags, classes and IDs used in a document.
+#[derive(Debug, Default)]
+struct UsedSelectors {
+    tags: HashSet<String>,
+    classes: HashSet<String>,
+    ids: HashSet<String>,
+}
+
+impl UsedSelectors {
+    fn from_html(html: &str) -> Self {
+        let mut used = Self::default();
+        for (tag, attributes) in tags(html) {
+            for (name, value) in attributes {
+                match name.to_ascii_lowercase().as_str() {
+                    "class" => used
+                        .classes
+                        .extend(value.split_whitespace().map(str::to_string)),
+                    "id" => {
+                        used.ids.insert(value.trim().to_string());
+                    }
+                    _ => {}
+                }
+            }
+            used.tags.insert(tag);
+        }
+        used
+    }
+
+    /// Returns true if any selector in the comma-separated list can match.
+    fn matches_any(&self, selectors: &str) -> bool {
+        split_top_level(selectors, ',')
+            .iter()
+            .any(|selector| self.matches(selector))
+    }
+
+    fn matches(&self, selector: &str) -> bool {
+        let mut chars = selector.chars().peekable();
+        let mut at_compound_start = true;
+        while let Some(c) = chars.next() {
+            match c {
+                '.' | '#' => {
+                    let name = take_ident(&mut chars);
+                    let set = if c == '.' { &self.classes } else { &self.ids };
+                    if !set.contains(&name) {
+                        return false;
+                    }
+                    at_compound_start = false;
+                }
+                ':' => {
+                    // Pseudo-classes and pseudo-elements depend on state, assume they match.
+                    if chars.peek() == Some(&':') {
+                        chars.next();
+                    }
+                    take_ident(&mut chars);
+                    skip_group(&mut chars, '(', ')');
+                    at_compound_start = false;
+                }
+                '[' => {
+                    skip_until(&mut chars, ']');
+                    at_compound_start = false;
+                }
+                '*' => at_compound_start = false,
+                ' ' | '\t' | '\n' | '\r' | '>' | '+' | '~' => at_compound_start = true,
+                c if at_compound_start && (c.is_ascii_alphabetic() || c == '-' || c == '_') => {
+                    let mut name = c.to_ascii_lowercase().to_string();
+                    name.push_str(&take_ident(&mut chars).to_ascii_lowercase());
+                    if !self.tags.contains(&name) {
+                        return false;
+                    }
+                    at_compound_start = false;
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+}
+
+/// Append the rules of `css` matching `used` to `out`, and collect the
+/// `@keyframes` rules by name in `keyframes`.
+fn filter_rules<'a>(
+    css: &'a str,
+    used: &UsedSelectors,
+    out: &mut String,
+    keyframes: &mut Vec<(&'a str, String)>,
+) {
+    for (prelude, block) in parse_rules(css) {
+        let prelude = prelude.trim();
+        let Some(block) = block else {
+            // Statements like `@import` and `@charset` aren't needed inline.
+            continue;
+        };
+
+        if let Some(at_rule) = prelude.strip_prefix('@') {
+            let name = at_rule
+                .split(|c: char| c.is_whitespace() || c == '(')
+                .next()
+                .unwrap_or_default()
+                .to_ascii_lowercase();
+            match name.as_str() {
+                "media" | "supports" | "layer" => {
+                    let mut nested = String::new();
+                    filter_rules(block, used, &mut nested, keyframes);
+                    if !nested.is_empty() {
+                        out.push_str(prelude);
+                        out.push('{');
+                        out.push_str(&nested);
+                        out.push('}');
+                    }
+                }
+                "font-face" => {
+                    out.push_str(prelude);
+                    out.push('{');
+                    out.push_str(block.trim());
+                    out.push('}');
+                }
+                "keyframes" | "-webkit-keyframes" => {
+                    let name = at_rule[name.len()..].trim().trim_matches(['"', '\'']);
+                    keyframes.push((name, format!("{prelude}{{{}}}", block.trim())));
+                }
+                _ => {}
+            }
+        } else if used.matches_any(prelude) {
+            out.push_str(prelude);
+            out.push('{');
+            out.push_str(block.trim());
+            out.push('}');
+        }
+    }
+}
+
+/// The keyframes names used by `animation` and `animation-name`
+/// declarations of a stylesheet.
+fn animation_names(css: &str) -> HashSet<&str> {
+    css.split(['{', '}', ';'])
+        .filter_map(|declaration| declaration.split_once(':'))
+        .filter(|(property, _)| {
+            let property = property.trim().to_ascii_lowercase();
+            property == "animation" || property == "animation-name"
+        })
+        .flat_map(|(_, value)| {
+            value.split(|c: char| !(c.is_alphanumeric() || c == '-' || c == '_'))
+        })
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// Split a stylesheet into top-level `(prelude, block)` pairs. Statements
+/// without a block, like `@import`, have a block of `None`.
+fn parse_rules(css: &str) -> Vec<(&str, Option<&str>)> {
+    let bytes = css.as_bytes();
+    let mut rules = Vec::new();
+    let mut start = 0;
+    let mut block_start = None;
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                let end = css[i + 2..]
+                    .find("*/")
+                    .map_or(bytes.len(), |e| i + 2 + e + 2);
+                if depth == 0 && block_start.is_none() && css[start..i].trim().is_empty() {
+                    start = end;
+                }
+                i = end;
+                continue;
+            }
+            quote @ (b'"' | b'\'') => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    if bytes[i] == b'\\' {
+                        i += 1;
+                    }
+                    i += 1;
+                }
+            }
+            b'{' => {
+                if depth == 0 {
+                    block_start = Some(i);
+                }
+                depth += 1;
+            }
+            b'}' => {
+                depth = depth.saturating_sub(1);
+                if depth == 0 {
+                    if let Some(block_start) = block_start.take() {
+                        rules.push((&css[start..block_start], Some(&css[block_start + 1..i])));
+                    }
+                    start = i + 1;
+                }
+            }
+            b';' if depth == 0 => {
+                rules.push((&css[start..i], None));
+                start = i + 1;
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    rules
+}
+
+/// Split `input` on `separator`, ignoring separators inside parentheses or brackets.
+fn split_top_level(input: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '(' | '[' => depth += 1,
+            ')' | ']' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                parts.push(&input[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&input[start..]);
+    parts
+}
+
+fn take_ident(chars: &mut std::iter::Peekable<std::str::Chars<'_>>) -> String {
+    let mut ident = String::new();
+    while let Some(&c) = chars.peek() {
+        if c.is_alphanumeric() || c == '-' || c == '_' {
+            ident.push(c);
+            chars.next();
+        } else if c == '\\' {
+            chars.next();
+            ident.extend(chars.next());
+        } else {
+            break;
+        }
+    }
+    ident
+}
+
+fn skip_group(chars: &mut std::iter::Peekable<std::str::Chars<'_>>, open: char, close: char) {
+    if chars.peek() != Some(&open) {
+        return;
+    }
+    let mut depth = 0usize;
+    for c in chars.by_ref() {
+        if c == open {
+            depth += 1;
+        } else if c == close {
+            depth -= 1;
+            if depth == 0 {
+                return;
+            }
+        }
+    }
+}
+
+fn skip_until(chars: &mut std::iter::Peekable<std::str::Chars<'_>>, end: char) {
+    for c in chars.by_ref() {
+        if c == end {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HTML: &str = r#"<!doctype html><html><head><link rel="stylesheet" href="/main.css"></head><body><!-- <aside class="unused"> --><nav id="top" class="nav dark"><a href="/">Home</a></nav><p>Hi</p></body></html>"#;
+
+    #[test]
+    fn test_critical_css() {
+        let css = r#"
+            /* reset */
+            @charset "utf-8";
+            @import url("fonts.css");
+            body { margin: 0 }
+            .nav a:hover, .footer { color: red }
+            #top.dark > a::before { content: "{" }
+            .unused, table td { display: none }
+            @media (min-width: 40em) { .nav { display: flex } .footer { display: none } }
+            @media print { .footer { display: none } }
+            @font-face { font-family: "X"; src: url(x.woff2) }
+            @keyframes spin { from { color: red } }
+            input[type="text"] { border: 0 }
+        "#;
+        assert_eq!(
+            critical_css(HTML, css),
+            r#"body{margin: 0}.nav a:hover, .footer{color: red}#top.dark > a::before{content: "{"}@media (min-width: 40em){.nav{display: flex}}@font-face{font-family: "X"; src: url(x.woff2)}"#
+        );
+
+        let css = "p { animation: 1s ease-in fade } .unused { animation-name: spin } @keyframes fade { from { opacity: 0 } } @keyframes spin { to { rotate: 1turn } }";
+        assert_eq!(
+            critical_css(HTML, css),
+            "p{animation: 1s ease-in fade}@keyframes fade{from { opacity: 0 }}"
+        );
+    }
+
+    #[test]
+    fn test_inline_critical_css() {
+        let html = inline_critical_css(HTML, "p{color:red}li{color:blue}", "/main.css");
+        assert!(html.contains(
+            r#"<link rel="preload" href="/main.css" as="style" onload="this.onload=null;this.rel='stylesheet'"><noscript><link rel="stylesheet" href="/main.css"></noscript><style>p{color:red}</style></head>"#
+        ));
+        assert!(!html.contains(r#"<link rel="stylesheet" href="/main.css"></head>"#));
+
+        let html = inline_critical_css(HTML, "", r#"/x.css?a=1&b="2""#);
+        assert!(html.contains(
+            r#"<noscript><link rel="stylesheet" href="/x.css?a=1&amp;b=&quot;2&quot;"></noscript>"#
+        ));
+    }
+}
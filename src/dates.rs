@@ -0,0 +1,337 @@
+//! Locale-aware date formatting for use in render functions.
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use pichu::dates::{self, Locale};
+//!
+//! let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+//! assert_eq!(dates::format(&date, "%-d. %B %Y", Locale::De), "1. Mai 2024");
+//! ```
+
+use chrono::{DateTime, Datelike, NaiveDate, NaiveDateTime, TimeZone};
+use std::{fmt, str::FromStr};
+
+/// A locale for month and weekday names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Locale {
+    /// English.
+    #[default]
+    En,
+    /// German.
+    De,
+    /// French.
+    Fr,
+    /// Spanish.
+    Es,
+    /// Italian.
+    It,
+    /// Dutch.
+    Nl,
+}
+
+struct Names {
+    months: [&'static str; 12],
+    months_short: [&'static str; 12],
+    weekdays: [&'static str; 7],
+    weekdays_short: [&'static str; 7],
+}
+
+const EN: Names = Names {
+    months: [
+        "January",
+        "February",
+        "March",
+        "April",
+        "May",
+        "June",
+        "July",
+        "August",
+        "September",
+        "October",
+        "November",
+        "December",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ],
+    weekdays: [
+        "Monday",
+        "Tuesday",
+        "Wednesday",
+        "Thursday",
+        "Friday",
+        "Saturday",
+        "Sunday",
+    ],
+    weekdays_short: ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"],
+};
+
+const DE: Names = Names {
+    months: [
+        "Januar",
+        "Februar",
+        "März",
+        "April",
+        "Mai",
+        "Juni",
+        "Juli",
+        "August",
+        "September",
+        "Oktober",
+        "November",
+        "Dezember",
+    ],
+    months_short: [
+        "Jan", "Feb", "Mär", "Apr", "Mai", "Jun", "Jul", "Aug", "Sep", "Okt", "Nov", "Dez",
+    ],
+    weekdays: [
+        "Montag",
+        "Dienstag",
+        "Mittwoch",
+        "Donnerstag",
+        "Freitag",
+        "Samstag",
+        "Sonntag",
+    ],
+    weekdays_short: ["Mo", "Di", "Mi", "Do", "Fr", "Sa", "So"],
+};
+
+const FR: Names = Names {
+    months: [
+        "janvier",
+        "février",
+        "mars",
+        "avril",
+        "mai",
+        "juin",
+        "juillet",
+        "août",
+        "septembre",
+        "octobre",
+        "novembre",
+        "décembre",
+    ],
+    months_short: [
+        "janv.", "févr.", "mars", "avr.", "mai", "juin", "juil.", "août", "sept.", "oct.", "nov.",
+        "déc.",
+    ],
+    weekdays: [
+        "lundi", "mardi", "mercredi", "jeudi", "vendredi", "samedi", "dimanche",
+    ],
+    weekdays_short: ["lun.", "mar.", "mer.", "jeu.", "ven.", "sam.", "dim."],
+};
+
+const ES: Names = Names {
+    months: [
+        "enero",
+        "febrero",
+        "marzo",
+        "abril",
+        "mayo",
+        "junio",
+        "julio",
+        "agosto",
+        "septiembre",
+        "octubre",
+        "noviembre",
+        "diciembre",
+    ],
+    months_short: [
+        "ene", "feb", "mar", "abr", "may", "jun", "jul", "ago", "sept", "oct", "nov", "dic",
+    ],
+    weekdays: [
+        "lunes",
+        "martes",
+        "miércoles",
+        "jueves",
+        "viernes",
+        "sábado",
+        "domingo",
+    ],
+    weekdays_short: ["lun", "mar", "mié", "jue", "vie", "sáb", "dom"],
+};
+
+const IT: Names = Names {
+    months: [
+        "gennaio",
+        "febbraio",
+        "marzo",
+        "aprile",
+        "maggio",
+        "giugno",
+        "luglio",
+        "agosto",
+        "settembre",
+        "ottobre",
+        "novembre",
+        "dicembre",
+    ],
+    months_short: [
+        "gen", "feb", "mar", "apr", "mag", "giu", "lug", "ago", "set", "ott", "nov", "dic",
+    ],
+    weekdays: [
+        "lunedì",
+        "martedì",
+        "mercoledì",
+        "giovedì",
+        "venerdì",
+        "sabato",
+        "domenica",
+    ],
+    weekdays_short: ["lun", "mar", "mer", "gio", "ven", "sab", "dom"],
+};
+
+const NL: Names = Names {
+    months: [
+        "januari",
+        "februari",
+        "maart",
+        "april",
+        "mei",
+        "juni",
+        "juli",
+        "augustus",
+        "september",
+        "oktober",
+        "november",
+        "december",
+    ],
+    months_short: [
+        "jan", "feb", "mrt", "apr", "mei", "jun", "jul", "aug", "sep", "okt", "nov", "dec",
+    ],
+    weekdays: [
+        "maandag",
+        "dinsdag",
+        "woensdag",
+        "donderdag",
+        "vrijdag",
+        "zaterdag",
+        "zondag",
+    ],
+    weekdays_short: ["ma", "di", "wo", "do", "vr", "za", "zo"],
+};
+
+impl Locale {
+    fn names(self) -> &'static Names {
+        match self {
+            Self::En => &EN,
+            Self::De => &DE,
+            Self::Fr => &FR,
+            Self::Es => &ES,
+            Self::It => &IT,
+            Self::Nl => &NL,
+        }
+    }
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parse a language tag like `de` or `de-AT`, ignoring the region.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let language = s.split(['-', '_']).next().unwrap_or_default();
+        match language.to_ascii_lowercase().as_str() {
+            "en" => Ok(Self::En),
+            "de" => Ok(Self::De),
+            "fr" => Ok(Self::Fr),
+            "es" => Ok(Self::Es),
+            "it" => Ok(Self::It),
+            "nl" => Ok(Self::Nl),
+            _ => Err(format!("unsupported locale: {s}")),
+        }
+    }
+}
+
+/// Dates and datetimes that can be formatted with [`format`].
+pub trait FormatLocalized: Datelike {
+    /// Format with `strftime`-style specifiers, with `%B`, `%b`, `%A` and
+    /// `%a` using the locale's month and weekday names.
+    fn format_localized(&self, fmt: &str, locale: Locale) -> String;
+}
+
+impl FormatLocalized for NaiveDate {
+    fn format_localized(&self, fmt: &str, locale: Locale) -> String {
+        self.format(&localize(self, fmt, locale)).to_string()
+    }
+}
+
+impl FormatLocalized for NaiveDateTime {
+    fn format_localized(&self, fmt: &str, locale: Locale) -> String {
+        self.format(&localize(self, fmt, locale)).to_string()
+    }
+}
+
+impl<Tz: TimeZone> FormatLocalized for DateTime<Tz>
+where
+    Tz::Offset: fmt::Display,
+{
+    fn format_localized(&self, fmt: &str, locale: Locale) -> String {
+        self.format(&localize(self, fmt, locale)).to_string()
+    }
+}
+
+/// Format a date with `strftime`-style specifiers, using the locale's month
+/// and weekday names for `%B`, `%b`, `%A` and `%a`.
+#[must_use]
+pub fn format(date: &impl FormatLocalized, fmt: &str, locale: Locale) -> String {
+    date.format_localized(fmt, locale)
+}
+
+/// Replace name specifiers with the localized names, leaving the rest for
+/// chrono.
+fn localize(date: &impl Datelike, fmt: &str, locale: Locale) -> String {
+    let names = locale.names();
+    let month = date.month0() as usize;
+    let weekday = date.weekday().num_days_from_monday() as usize;
+
+    let mut out = String::with_capacity(fmt.len());
+    let mut chars = fmt.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let mut spec = String::from('%');
+        while let Some(&flag @ ('-' | '_' | '0' | '^' | '#')) = chars.peek() {
+            spec.push(flag);
+            chars.next();
+        }
+        match chars.next() {
+            Some('B') => out.push_str(names.months[month]),
+            Some('b' | 'h') => out.push_str(names.months_short[month]),
+            Some('A') => out.push_str(names.weekdays[weekday]),
+            Some('a') => out.push_str(names.weekdays_short[weekday]),
+            Some(other) => {
+                out.push_str(&spec);
+                out.push(other);
+            }
+            None => out.push_str(&spec),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let date = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap_or_default();
+        assert_eq!(format(&date, "%-d. %B %Y", Locale::De), "1. März 2024");
+        assert_eq!(format(&date, "%A %-d %B", Locale::Fr), "vendredi 1 mars");
+        assert_eq!(format(&date, "%a, %b %d", Locale::En), "Fri, Mar 01");
+        assert_eq!(
+            format(&date, "100%% %Y-%m-%d", Locale::Nl),
+            "100% 2024-03-01"
+        );
+
+        let datetime = date.and_hms_opt(9, 5, 0).unwrap_or_default().and_utc();
+        assert_eq!(
+            format(&datetime, "%e %B, %H:%M", Locale::Es),
+            " 1 marzo, 09:05"
+        );
+        assert_eq!("de-AT".parse(), Ok(Locale::De));
+        assert!("tlh".parse::<Locale>().is_err());
+    }
+}
@@ -0,0 +1,175 @@
+//! Serde helpers for frontmatter dates, for use with `deserialize_with`.
+//!
+//! ```
+//! use chrono::NaiveDate;
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Blogpost {
+//!     #[serde(deserialize_with = "pichu::de::date")]
+//!     published: NaiveDate,
+//!     #[serde(default, deserialize_with = "pichu::de::option_date")]
+//!     updated: Option<NaiveDate>,
+//! }
+//! ```
+//!
+//! Dates implement [`Ord`], so collections can be sorted with
+//! [`Parsed::sort_by_key_reverse`](crate::Parsed::sort_by_key_reverse) to
+//! show the newest first.
+
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use serde::{de::Error as _, Deserialize, Deserializer};
+
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%B %d, %Y", "%d %B %Y", "%d. %B %Y"];
+const DATETIME_FORMATS: &[&str] = &["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"];
+
+/// Parse a date like `2024-05-01`, `May 1, 2024`, `1 May 2024` or an
+/// RFC 3339 datetime, of which only the date is kept.
+#[must_use]
+pub fn parse_date(s: &str) -> Option<NaiveDate> {
+    let s = s.trim();
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+        .or_else(|| parse_datetime(s).map(|datetime| datetime.date_naive()))
+}
+
+/// Parse an RFC 3339 datetime, a datetime without offset like
+/// `2024-05-01 12:00` or any date accepted by [`parse_date`].
+/// Missing offsets are assumed to be UTC, missing times midnight.
+#[must_use]
+pub fn parse_datetime(s: &str) -> Option<DateTime<FixedOffset>> {
+    let s = s.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Some(datetime);
+    }
+    DATETIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(s, format).ok())
+        .or_else(|| {
+            DATE_FORMATS
+                .iter()
+                .find_map(|format| NaiveDate::parse_from_str(s, format).ok())
+                .map(|date| date.and_time(NaiveTime::MIN))
+        })
+        .map(|datetime| datetime.and_utc().fixed_offset())
+}
+
+/// Deserialize a date with [`parse_date`].
+///
+/// # Errors
+///
+/// Returns an error if the value isn't a string or not a supported date.
+pub fn date<'de, D: Deserializer<'de>>(deserializer: D) -> Result<NaiveDate, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_date(&s).ok_or_else(|| D::Error::custom(format!("invalid date: {s}")))
+}
+
+/// Like [`date`], for optional fields. Combine with `#[serde(default)]`.
+///
+/// # Errors
+///
+/// Returns an error if the value is set but not a supported date.
+pub fn option_date<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<NaiveDate>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| parse_date(&s).ok_or_else(|| D::Error::custom(format!("invalid date: {s}"))))
+        .transpose()
+}
+
+/// Deserialize a datetime with [`parse_datetime`].
+///
+/// # Errors
+///
+/// Returns an error if the value isn't a string or not a supported datetime.
+pub fn datetime_flexible<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<FixedOffset>, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    parse_datetime(&s).ok_or_else(|| D::Error::custom(format!("invalid datetime: {s}")))
+}
+
+/// Like [`datetime_flexible`], for optional fields. Combine with
+/// `#[serde(default)]`.
+///
+/// # Errors
+///
+/// Returns an error if the value is set but not a supported datetime.
+pub fn option_datetime_flexible<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<FixedOffset>>, D::Error> {
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| {
+            parse_datetime(&s).ok_or_else(|| D::Error::custom(format!("invalid datetime: {s}")))
+        })
+        .transpose()
+}
+
+/// Format a date as an RFC 3339 datetime at midnight UTC, e.g. for Atom
+/// feeds and sitemaps.
+#[must_use]
+pub fn to_rfc3339(date: NaiveDate) -> String {
+    date.and_time(NaiveTime::MIN).and_utc().to_rfc3339()
+}
+
+/// Format a date as an RFC 2822 datetime at midnight UTC, e.g. for RSS
+/// feeds.
+#[must_use]
+pub fn to_rfc2822(date: NaiveDate) -> String {
+    DateTime::<Utc>::from_naive_utc_and_offset(date.and_time(NaiveTime::MIN), Utc).to_rfc2822()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    struct Frontmatter {
+        #[serde(deserialize_with = "date")]
+        published: NaiveDate,
+        #[serde(default, deserialize_with = "option_datetime_flexible")]
+        updated: Option<DateTime<FixedOffset>>,
+    }
+
+    #[test]
+    fn test_parse_date() {
+        let expected = NaiveDate::from_ymd_opt(2024, 5, 1);
+        for s in [
+            "2024-05-01",
+            "May 1, 2024",
+            "1 May 2024",
+            "2024-05-01T23:00:00+02:00",
+            "2024-05-01 08:30",
+        ] {
+            assert_eq!(parse_date(s), expected, "{s}");
+        }
+        assert_eq!(parse_date("yesterday"), None);
+    }
+
+    #[test]
+    fn test_deserialize() -> Result<(), serde_json::Error> {
+        let frontmatter: Frontmatter =
+            serde_json::from_str(r#"{"published": "May 1, 2024", "updated": "2024-05-02 12:00"}"#)?;
+        assert_eq!(
+            frontmatter.published,
+            NaiveDate::from_ymd_opt(2024, 5, 1).unwrap_or_default()
+        );
+        assert_eq!(
+            frontmatter.updated.map(|updated| updated.to_rfc3339()),
+            Some("2024-05-02T12:00:00+00:00".to_string())
+        );
+
+        let frontmatter: Frontmatter = serde_json::from_str(r#"{"published": "2024-05-01"}"#)?;
+        assert!(frontmatter.updated.is_none());
+        assert!(serde_json::from_str::<Frontmatter>(r#"{"published": "soon"}"#).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_format() {
+        let date = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap_or_default();
+        assert_eq!(to_rfc3339(date), "2024-05-01T00:00:00+00:00");
+        assert_eq!(to_rfc2822(date), "Wed, 1 May 2024 00:00:00 +0000");
+    }
+}
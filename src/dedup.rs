@@ -0,0 +1,100 @@
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    fmt,
+    hash::Hash,
+};
+
+use crate::{Error, HasSource, Parsed};
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Remove items with a key seen before, keeping the first of each.
+    #[must_use]
+    pub fn dedup_by_key<K, F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> K,
+        K: Hash + Eq,
+    {
+        let mut seen = HashSet::new();
+        self.items.retain(|item| seen.insert(f(item)));
+        self
+    }
+
+    /// Make sure no two items have the same key, e.g. the same slug.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Duplicate`] with the key and the sources of the first
+    /// two items sharing it.
+    pub fn assert_unique_by<K, F>(self, f: F) -> Result<Self, Error>
+    where
+        T: HasSource,
+        F: Fn(&T) -> K,
+        K: Hash + Eq + fmt::Debug,
+    {
+        let mut seen = HashMap::<K, usize>::new();
+        for (index, item) in self.items.iter().enumerate() {
+            match seen.entry(f(item)) {
+                Entry::Occupied(entry) => {
+                    return Err(Error::Duplicate {
+                        key: format!("{:?}", entry.key()),
+                        first: self.items[*entry.get()].source().to_path_buf(),
+                        second: item.source().to_path_buf(),
+                    });
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(index);
+                }
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn test_dedup_by_key() {
+        let parsed = Parsed::from(vec![("hello", 1), ("world", 2), ("hello", 3)])
+            .dedup_by_key(|(slug, _)| *slug);
+        assert_eq!(parsed.items, [("hello", 1), ("world", 2)]);
+    }
+
+    #[test]
+    fn test_assert_unique_by() {
+        #[derive(Clone)]
+        struct Post {
+            source: PathBuf,
+            slug: &'static str,
+        }
+
+        impl HasSource for Post {
+            fn source(&self) -> &Path {
+                &self.source
+            }
+        }
+
+        let post = |source: &str, slug| Post {
+            source: PathBuf::from(source),
+            slug,
+        };
+        let parsed = Parsed::from(vec![
+            post("blog/hello.md", "hello"),
+            post("blog/world.md", "world"),
+            post("pages/hello.md", "hello"),
+        ]);
+        assert!(parsed
+            .clone()
+            .assert_unique_by(|post| post.source.clone())
+            .is_ok());
+        assert!(matches!(
+            parsed.assert_unique_by(|post| post.slug),
+            Err(Error::Duplicate { key, first, second })
+                if key == "\"hello\""
+                    && first == Path::new("blog/hello.md")
+                    && second == Path::new("pages/hello.md")
+        ));
+    }
+}
@@ -0,0 +1,160 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{collection::absolute, sync::lock};
+
+/// The kind of input an output depends on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DepKind {
+    /// The source file the output was rendered from.
+    Source,
+    /// A template used during rendering.
+    Template,
+    /// A data file read during rendering.
+    Data,
+    /// A file included into the source, see
+    /// [`MarkdownOptions::includes`](crate::MarkdownOptions::includes).
+    Include,
+}
+
+/// The inputs declared by a single render, see
+/// [`Parsed::render_each_with_deps`](crate::Parsed::render_each_with_deps).
+#[derive(Debug, Clone, Default)]
+pub struct Deps {
+    inputs: BTreeSet<(DepKind, PathBuf)>,
+}
+
+impl Deps {
+    /// Create an empty set of dependencies.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declare the source file the output is rendered from.
+    pub fn source(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.add(DepKind::Source, path)
+    }
+
+    /// Declare a template used to render the output.
+    pub fn template(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.add(DepKind::Template, path)
+    }
+
+    /// Declare a data file read to render the output.
+    pub fn data(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.add(DepKind::Data, path)
+    }
+
+    /// Declare a file included into the source.
+    pub fn include(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        self.add(DepKind::Include, path)
+    }
+
+    /// Iterate over the declared inputs.
+    pub fn iter(&self) -> impl Iterator<Item = (DepKind, &Path)> {
+        self.inputs
+            .iter()
+            .map(|(kind, path)| (*kind, path.as_path()))
+    }
+
+    fn add(&mut self, kind: DepKind, path: impl AsRef<Path>) -> &mut Self {
+        self.inputs.insert((kind, absolute(path.as_ref())));
+        self
+    }
+}
+
+/// Records which inputs each output was rendered from, so a rebuild knows
+/// exactly which outputs a changed file invalidates.
+#[derive(Debug, Default)]
+pub struct DepGraph {
+    outputs: Mutex<HashMap<PathBuf, Deps>>,
+}
+
+impl DepGraph {
+    /// Create an empty graph.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the inputs of `output`, replacing the ones recorded by a previous render.
+    pub fn record(&self, output: impl Into<PathBuf>, deps: Deps) {
+        lock(&self.outputs).insert(output.into(), deps);
+    }
+
+    /// Forget about `output`, e.g. because its source was deleted.
+    pub fn remove(&self, output: impl AsRef<Path>) {
+        lock(&self.outputs).remove(output.as_ref());
+    }
+
+    /// The inputs recorded for `output`.
+    #[must_use]
+    pub fn dependencies(&self, output: impl AsRef<Path>) -> Deps {
+        lock(&self.outputs)
+            .get(output.as_ref())
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// The outputs depending on any of the `changed` paths, sorted.
+    /// Pass the paths reported by [`watch`](crate::watch) to find out what
+    /// needs to be rebuilt.
+    #[must_use]
+    pub fn affected(&self, changed: &[PathBuf]) -> Vec<PathBuf> {
+        let changed = changed
+            .iter()
+            .map(|path| absolute(path))
+            .collect::<BTreeSet<_>>();
+        let mut affected = lock(&self.outputs)
+            .iter()
+            .filter(|(_, deps)| deps.inputs.iter().any(|(_, path)| changed.contains(path)))
+            .map(|(output, _)| output.clone())
+            .collect::<Vec<_>>();
+        affected.sort();
+        affected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_affected() {
+        let graph = DepGraph::new();
+        let mut deps = Deps::new();
+        deps.source("content/blog/a.md")
+            .template("templates/post.html");
+        graph.record("dist/blog/a/index.html", deps);
+        let mut deps = Deps::new();
+        deps.template("templates/index.html")
+            .data("data/authors.yaml");
+        graph.record("dist/index.html", deps);
+
+        assert_eq!(
+            graph.affected(&[PathBuf::from("templates/post.html")]),
+            vec![PathBuf::from("dist/blog/a/index.html")]
+        );
+        assert_eq!(
+            graph.affected(&[
+                absolute(Path::new("data/authors.yaml")),
+                PathBuf::from("content/blog/a.md")
+            ]),
+            vec![
+                PathBuf::from("dist/blog/a/index.html"),
+                PathBuf::from("dist/index.html")
+            ]
+        );
+        assert!(graph.affected(&[PathBuf::from("static/x.png")]).is_empty());
+
+        // Re-recording replaces the previous inputs
+        graph.record("dist/index.html", Deps::new());
+        assert!(graph
+            .affected(&[PathBuf::from("data/authors.yaml")])
+            .is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+use encoding_rs::Encoding;
+use std::{borrow::Cow, path::Path, sync::RwLock};
+
+use crate::{collection::matches, sync, Error};
+
+/// The rules installed with [`OutputEncodings::install`].
+static RULES: RwLock<Vec<(glob::Pattern, OutputEncoding)>> = RwLock::new(Vec::new());
+
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// How a written file is encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputEncoding {
+    /// UTF-8 without a byte order mark, removing one if present.
+    Utf8,
+    /// UTF-8 with a byte order mark, e.g. for CSV files opened in Excel.
+    Utf8Bom,
+    /// A legacy encoding like [`encoding_rs::WINDOWS_1252`]. Characters it
+    /// can't represent are written as HTML numeric character references.
+    /// UTF-16 isn't supported and is written as UTF-8.
+    Legacy(&'static Encoding),
+}
+
+impl OutputEncoding {
+    /// The encoding for a label like `windows-1252` or `latin1`, see the
+    /// [Encoding Standard](https://encoding.spec.whatwg.org/#names-and-labels).
+    #[must_use]
+    pub fn for_label(label: &str) -> Option<Self> {
+        Encoding::for_label(label.as_bytes()).map(|encoding| {
+            if encoding == encoding_rs::UTF_8 {
+                Self::Utf8
+            } else {
+                Self::Legacy(encoding)
+            }
+        })
+    }
+
+    /// Encode UTF-8 contents. Contents that aren't valid UTF-8, e.g.
+    /// images, are returned unchanged.
+    fn encode(self, contents: &[u8]) -> Cow<'_, [u8]> {
+        let text = contents.strip_prefix(BOM).unwrap_or(contents);
+        let Ok(text) = std::str::from_utf8(text) else {
+            return Cow::Borrowed(contents);
+        };
+        match self {
+            Self::Utf8 => Cow::Borrowed(text.as_bytes()),
+            Self::Utf8Bom => Cow::Owned([BOM, text.as_bytes()].concat()),
+            Self::Legacy(encoding) => Cow::Owned(encoding.encode(text).0.into_owned()),
+        }
+    }
+}
+
+/// Output encodings by path pattern, for legacy consumers that need e.g.
+/// Windows-1252 or a byte order mark instead of plain UTF-8.
+///
+/// Once installed, every file pichu writes is encoded with the first rule
+/// whose pattern matches its path, e.g. by [`write`](crate::write) and the
+/// render functions. Copied files are left as they are. Remember to update
+/// the `<meta charset>` of HTML pages.
+///
+/// ```no_run
+/// use pichu::{OutputEncoding, OutputEncodings};
+///
+/// OutputEncodings::new()
+///     .rule("dist/legacy/**/*.html", OutputEncoding::Legacy(pichu::encoding_rs::WINDOWS_1252))?
+///     .rule("dist/**/*.csv", OutputEncoding::Utf8Bom)?
+///     .install();
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct OutputEncodings {
+    rules: Vec<(glob::Pattern, OutputEncoding)>,
+}
+
+impl OutputEncodings {
+    /// Create an empty set of rules, writing UTF-8 as is.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Encode files whose path matches the glob pattern with the encoding,
+    /// unless an earlier rule matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn rule(mut self, pattern: &str, encoding: OutputEncoding) -> Result<Self, Error> {
+        self.rules.push((glob::Pattern::new(pattern)?, encoding));
+        Ok(self)
+    }
+
+    /// Use the rules for all following writes, replacing any installed
+    /// before.
+    pub fn install(self) {
+        *sync::write(&RULES) = self.rules;
+    }
+}
+
+/// Encode the contents of the file at the path with the first matching
+/// installed rule.
+pub(crate) fn encode<'a>(path: &Path, contents: &'a [u8]) -> Cow<'a, [u8]> {
+    let rules = sync::read(&RULES);
+    match rules.iter().find(|(pattern, _)| matches(pattern, path)) {
+        Some((_, encoding)) => encoding.encode(contents),
+        None => Cow::Borrowed(contents),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn test_output_encodings() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_output_encodings");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let pattern = glob::Pattern::escape(&dir.to_string_lossy());
+        OutputEncodings::new()
+            .rule(
+                &format!("{pattern}/legacy/*"),
+                OutputEncoding::for_label("latin1").ok_or("unknown label")?,
+            )?
+            .rule(&format!("{pattern}/**/*.csv"), OutputEncoding::Utf8Bom)?
+            .rule(&format!("{pattern}/**/*.txt"), OutputEncoding::Utf8)?
+            .install();
+
+        crate::write(dir.join("legacy/index.html"), "Café €5 ✓")?;
+        crate::write(dir.join("legacy/data.csv"), "é")?;
+        crate::write(dir.join("legacy/v2/index.html"), "é")?;
+        crate::write(dir.join("export/data.csv"), "é")?;
+        crate::write(dir.join("bom.txt"), "\u{FEFF}é")?;
+        crate::write(dir.join("image.png"), [0xFF, 0xFE])?;
+
+        assert_eq!(
+            fs::read(dir.join("legacy/index.html"))?,
+            b"Caf\xE9 \x805 &#10003;"
+        );
+        assert_eq!(fs::read(dir.join("legacy/data.csv"))?, b"\xE9");
+        // `*` doesn't match across directories.
+        assert_eq!(fs::read(dir.join("legacy/v2/index.html"))?, "é".as_bytes());
+        assert_eq!(
+            fs::read(dir.join("export/data.csv"))?,
+            "\u{FEFF}é".as_bytes()
+        );
+        assert_eq!(fs::read(dir.join("bom.txt"))?, "é".as_bytes());
+        assert_eq!(fs::read(dir.join("image.png"))?, [0xFF, 0xFE]);
+
+        OutputEncodings::new().install();
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
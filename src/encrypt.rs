@@ -0,0 +1,264 @@
+use aes_gcm::{
+    aead::{rand_core::RngCore, Aead, AeadCore, KeyInit, OsRng},
+    Aes256Gcm,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use rayon::prelude::*;
+use sha2::Sha256;
+use std::path::Path;
+
+use crate::{html, output, Error, Parsed};
+
+const SALT_LEN: usize = 16;
+
+/// The page shown instead of an encrypted page. The passphrase is checked
+/// in the browser with the Web Crypto API, which replaces the document with
+/// the decrypted page.
+const WRAPPER: &str = r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<meta name="robots" content="noindex">
+<title>{title}</title>
+</head>
+<body>
+<form id="pichu-unlock" data-payload="{payload}" data-iterations="{iterations}">
+<label>Passphrase <input type="password" name="passphrase" autocomplete="current-password" required autofocus></label>
+<button>Unlock</button>
+<output></output>
+</form>
+<script>
+const form = document.getElementById("pichu-unlock");
+form.addEventListener("submit", async (event) => {
+  event.preventDefault();
+  const bytes = Uint8Array.from(atob(form.dataset.payload), (c) => c.charCodeAt(0));
+  const passphrase = new TextEncoder().encode(form.elements.passphrase.value);
+  const material = await crypto.subtle.importKey("raw", passphrase, "PBKDF2", false, ["deriveKey"]);
+  const key = await crypto.subtle.deriveKey(
+    { name: "PBKDF2", hash: "SHA-256", salt: bytes.slice(0, 16), iterations: Number(form.dataset.iterations) },
+    material,
+    { name: "AES-GCM", length: 256 },
+    false,
+    ["decrypt"],
+  );
+  try {
+    const page = await crypto.subtle.decrypt({ name: "AES-GCM", iv: bytes.slice(16, 28) }, key, bytes.slice(28));
+    document.open();
+    document.write(new TextDecoder().decode(page));
+    document.close();
+  } catch {
+    form.querySelector("output").textContent = "Wrong passphrase";
+  }
+});
+</script>
+</body>
+</html>
+"#;
+
+/// Error type for page encryption.
+#[derive(thiserror::Error, Debug)]
+pub enum EncryptError {
+    /// The system's random number generator failed.
+    #[error("failed to generate random bytes: {0}")]
+    Random(String),
+    /// The page couldn't be encrypted.
+    #[error("failed to encrypt page")]
+    Encrypt,
+}
+
+/// Items that may only be read with a passphrase, usually through a
+/// `private` frontmatter key.
+pub trait IsPrivate {
+    /// Whether the rendered page should be encrypted.
+    fn is_private(&self) -> bool;
+}
+
+/// Encrypts rendered pages with AES-GCM, using a key derived from a
+/// passphrase with PBKDF2, and wraps them in a page that asks for the
+/// passphrase and decrypts them in the browser.
+///
+/// This keeps pages private on any static host, as long as the passphrase
+/// is strong: anyone can download the page and try passphrases offline.
+///
+/// ```
+/// let encryption = pichu::Encryption::new("correct horse battery staple");
+/// let page = encryption.encrypt("<h1>Members only</h1>")?;
+/// assert!(!page.contains("Members only"));
+/// # Ok::<(), pichu::EncryptError>(())
+/// ```
+#[derive(Clone)]
+pub struct Encryption {
+    passphrase: String,
+    iterations: u32,
+    title: String,
+}
+
+impl Encryption {
+    /// Encrypt pages with the passphrase.
+    #[must_use]
+    pub fn new(passphrase: impl Into<String>) -> Self {
+        Self {
+            passphrase: passphrase.into(),
+            iterations: 600_000,
+            title: "Private page".to_string(),
+        }
+    }
+
+    /// Set the number of PBKDF2 iterations, making guessing passphrases
+    /// slower at the cost of a slower unlock, defaults to 600,000.
+    #[must_use]
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Set the title of the passphrase page, defaults to `Private page`.
+    #[must_use]
+    pub fn title(mut self, title: impl Into<String>) -> Self {
+        self.title = title.into();
+        self
+    }
+
+    /// Encrypt a page, returning the page to publish in its place.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no random salt or nonce can be generated.
+    pub fn encrypt(&self, page: &str) -> Result<String, EncryptError> {
+        let mut salt = [0; SALT_LEN];
+        OsRng
+            .try_fill_bytes(&mut salt)
+            .map_err(|e| EncryptError::Random(e.to_string()))?;
+        let mut key = [0; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(self.passphrase.as_bytes(), &salt, self.iterations, &mut key);
+        let cipher = Aes256Gcm::new(&key.into());
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = cipher
+            .encrypt(&nonce, page.as_bytes())
+            .map_err(|_| EncryptError::Encrypt)?;
+
+        let payload = [&salt[..], &nonce, &ciphertext].concat();
+        Ok(WRAPPER
+            .replace("{title}", &html::escape(&self.title))
+            .replace("{payload}", &STANDARD.encode(payload))
+            .replace("{iterations}", &self.iterations.to_string()))
+    }
+}
+
+impl std::fmt::Debug for Encryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Encryption")
+            .field("iterations", &self.iterations)
+            .field("title", &self.title)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<T: IsPrivate + Send + Sync> Parsed<T> {
+    /// Like [`Parsed::render_each`], but encrypts the pages of private items
+    /// with `encryption`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be encrypted, in which case nothing
+    /// is written, or if any file cannot be written.
+    pub fn render_each_encrypted<P: AsRef<Path>, S: Into<String> + Send>(
+        self,
+        encryption: &Encryption,
+        render_fn: impl Fn(&T) -> S + Send + Sync,
+        build_path_fn: impl Fn(&T) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let files = self
+            .items
+            .par_iter()
+            .map(|item| {
+                let mut content = render_fn(item).into();
+                if item.is_private() {
+                    content = encryption.encrypt(&content)?;
+                }
+                Ok((
+                    build_path_fn(item).as_ref().to_path_buf(),
+                    content.into_bytes(),
+                ))
+            })
+            .collect::<Result<Vec<_>, EncryptError>>()
+            .map_err(|e| Error::Render(Box::new(e)))?;
+        output::write_batch(files)?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    struct Page {
+        name: &'static str,
+        private: bool,
+    }
+
+    impl IsPrivate for Page {
+        fn is_private(&self) -> bool {
+            self.private
+        }
+    }
+
+    fn decrypt(page: &str, passphrase: &str, iterations: u32) -> Option<String> {
+        let payload = page.split("data-payload=\"").nth(1)?.split('"').next()?;
+        let payload = STANDARD.decode(payload).ok()?;
+        let (salt, rest) = payload.split_at(SALT_LEN);
+        let (nonce, ciphertext) = rest.split_at(12);
+        let mut key = [0; 32];
+        pbkdf2::pbkdf2_hmac::<Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+        let plaintext = Aes256Gcm::new(&key.into())
+            .decrypt(nonce.into(), ciphertext)
+            .ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    #[test]
+    fn test_render_each_encrypted() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_each_encrypted");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let encryption = Encryption::new("hunter2")
+            .iterations(1_000)
+            .title("Notes & Drafts");
+        let pages = Parsed::from(vec![
+            Page {
+                name: "public",
+                private: false,
+            },
+            Page {
+                name: "private",
+                private: true,
+            },
+        ]);
+        pages.render_each_encrypted(
+            &encryption,
+            |page| format!("<h1>{}</h1>", page.name),
+            |page| dir.join(page.name).join("index.html"),
+        )?;
+
+        assert_eq!(
+            fs::read_to_string(dir.join("public/index.html"))?,
+            "<h1>public</h1>"
+        );
+        let private = fs::read_to_string(dir.join("private/index.html"))?;
+        assert!(!private.contains("<h1>private</h1>"));
+        assert!(private.contains("<title>Notes &amp; Drafts</title>"));
+        assert!(private.contains("data-iterations=\"1000\""));
+        assert_eq!(
+            decrypt(&private, "hunter2", 1_000).as_deref(),
+            Some("<h1>private</h1>")
+        );
+        assert_eq!(decrypt(&private, "hunter3", 1_000), None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
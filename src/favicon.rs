@@ -0,0 +1,323 @@
+use image::{imageops, DynamicImage, ImageFormat, RgbaImage};
+use resvg::{tiny_skia, usvg};
+use serde_json::json;
+use std::{
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::{html::escape, write, AssetManifest};
+
+/// The sizes embedded in `favicon.ico`.
+const ICO_SIZES: [u32; 3] = [16, 32, 48];
+
+/// Error type for favicon generation.
+#[derive(thiserror::Error, Debug)]
+pub enum FaviconError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// Failed to read or write a raster image.
+    #[error("image error: {0}")]
+    Image(#[from] image::ImageError),
+    /// Failed to parse the SVG source.
+    #[error("failed to parse svg: {0}")]
+    Svg(#[from] usvg::Error),
+    /// The source image has an empty size.
+    #[error("source image is empty: {0}")]
+    EmptySource(PathBuf),
+}
+
+/// Options for [`render_favicons`].
+#[derive(Debug, Clone)]
+pub struct FaviconOptions {
+    /// The site name used in the web app manifest.
+    pub name: String,
+    /// The theme color, used in the manifest and the `theme-color` meta tag.
+    pub theme_color: String,
+    /// The background color used in the manifest.
+    pub background_color: String,
+    /// The URL the output directory is served at, e.g. `/`.
+    pub url_prefix: String,
+}
+
+impl Default for FaviconOptions {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            theme_color: "#ffffff".to_string(),
+            background_color: "#ffffff".to_string(),
+            url_prefix: "/".to_string(),
+        }
+    }
+}
+
+/// The icon set generated by [`render_favicons`].
+#[derive(Debug, Clone)]
+pub struct Favicons {
+    /// The generated files as `(name, url)` pairs.
+    pub files: Vec<(String, String)>,
+    html: String,
+}
+
+impl Favicons {
+    /// The `<link>` and `<meta>` tags referencing the icon set, ready to be
+    /// placed in `<head>`.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        self.html.clone()
+    }
+
+    /// Register the generated files in the asset manifest.
+    pub fn register(&self, manifest: &AssetManifest) {
+        for (name, url) in &self.files {
+            manifest.register(name, url);
+        }
+    }
+}
+
+/// Generate the standard icon set from a single SVG or PNG source:
+/// `favicon.ico`, `apple-touch-icon.png`, `icon-192.png`, `icon-512.png`
+/// and a `site.webmanifest`, plus `icon.svg` for SVG sources.
+/// Non-square sources are centered on a transparent background.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be read or decoded, or if the icons cannot be written.
+pub fn render_favicons(
+    source: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    options: &FaviconOptions,
+) -> Result<Favicons, FaviconError> {
+    let source = source.as_ref();
+    let out_dir = out_dir.as_ref();
+    fs::create_dir_all(out_dir)?;
+
+    let image = Source::open(source)?;
+    let prefix = options.url_prefix.trim_end_matches('/');
+    let url = |name: &str| format!("{prefix}/{name}");
+    let mut files = Vec::new();
+
+    let mut ico = ico::IconDir::new(ico::ResourceType::Icon);
+    for size in ICO_SIZES {
+        let icon = image.render(size)?;
+        let icon = ico::IconImage::from_rgba_data(size, size, icon.into_raw());
+        ico.add_entry(ico::IconDirEntry::encode(&icon)?);
+    }
+    ico.write(fs::File::create(out_dir.join("favicon.ico"))?)?;
+    files.push(("favicon.ico".to_string(), url("favicon.ico")));
+
+    for (name, size) in [
+        ("apple-touch-icon.png", 180),
+        ("icon-192.png", 192),
+        ("icon-512.png", 512),
+    ] {
+        image
+            .render(size)?
+            .save_with_format(out_dir.join(name), ImageFormat::Png)?;
+        files.push((name.to_string(), url(name)));
+    }
+
+    if matches!(image, Source::Svg(_)) {
+        fs::copy(source, out_dir.join("icon.svg"))?;
+        files.push(("icon.svg".to_string(), url("icon.svg")));
+    }
+
+    let webmanifest = json!({
+        "name": options.name,
+        "icons": [
+            { "src": url("icon-192.png"), "sizes": "192x192", "type": "image/png" },
+            { "src": url("icon-512.png"), "sizes": "512x512", "type": "image/png" },
+        ],
+        "theme_color": options.theme_color,
+        "background_color": options.background_color,
+        "display": "standalone",
+    });
+    write(
+        out_dir.join("site.webmanifest"),
+        serde_json::to_string_pretty(&webmanifest).map_err(io::Error::from)?,
+    )?;
+    files.push(("site.webmanifest".to_string(), url("site.webmanifest")));
+
+    let mut html = String::new();
+    let _ = writeln!(
+        html,
+        r#"<link rel="icon" href="{}" sizes="32x32">"#,
+        escape(&url("favicon.ico"))
+    );
+    if matches!(image, Source::Svg(_)) {
+        let _ = writeln!(
+            html,
+            r#"<link rel="icon" href="{}" type="image/svg+xml">"#,
+            escape(&url("icon.svg"))
+        );
+    }
+    let _ = writeln!(
+        html,
+        r#"<link rel="apple-touch-icon" href="{}">"#,
+        escape(&url("apple-touch-icon.png"))
+    );
+    let _ = writeln!(
+        html,
+        r#"<link rel="manifest" href="{}">"#,
+        escape(&url("site.webmanifest"))
+    );
+    let _ = writeln!(
+        html,
+        r#"<meta name="theme-color" content="{}">"#,
+        escape(&options.theme_color)
+    );
+
+    Ok(Favicons { files, html })
+}
+
+enum Source {
+    Svg(Box<usvg::Tree>),
+    Raster(DynamicImage),
+}
+
+impl Source {
+    fn open(path: &Path) -> Result<Self, FaviconError> {
+        let is_svg = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"));
+        let source = if is_svg {
+            let data = fs::read(path)?;
+            Self::Svg(Box::new(usvg::Tree::from_data(
+                &data,
+                &usvg::Options::default(),
+            )?))
+        } else {
+            Self::Raster(image::open(path)?)
+        };
+
+        let (width, height) = source.size();
+        if width <= 0.0 || height <= 0.0 {
+            return Err(FaviconError::EmptySource(path.to_path_buf()));
+        }
+        Ok(source)
+    }
+
+    #[allow(clippy::cast_precision_loss)] // Image sizes are far below f32's precision limit.
+    fn size(&self) -> (f32, f32) {
+        match self {
+            Self::Svg(tree) => (tree.size().width(), tree.size().height()),
+            Self::Raster(image) => (image.width() as f32, image.height() as f32),
+        }
+    }
+
+    /// Render the source into a `size`×`size` square, preserving the aspect ratio.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )] // Image sizes are far below f32's precision limit.
+    fn render(&self, size: u32) -> Result<RgbaImage, FaviconError> {
+        let (width, height) = self.size();
+        let scale = size as f32 / width.max(height);
+        let (scaled_width, scaled_height) = (width * scale, height * scale);
+        let (x, y) = (
+            (size as f32 - scaled_width) / 2.0,
+            (size as f32 - scaled_height) / 2.0,
+        );
+
+        match self {
+            Self::Svg(tree) => {
+                let mut pixmap = tiny_skia::Pixmap::new(size, size)
+                    .ok_or_else(|| io::Error::other("invalid icon size"))?;
+                let transform = tiny_skia::Transform::from_scale(scale, scale).post_translate(x, y);
+                resvg::render(tree, transform, &mut pixmap.as_mut());
+                let data = pixmap
+                    .pixels()
+                    .iter()
+                    .flat_map(|pixel| {
+                        let color = pixel.demultiply();
+                        [color.red(), color.green(), color.blue(), color.alpha()]
+                    })
+                    .collect();
+                RgbaImage::from_raw(size, size, data)
+                    .ok_or_else(|| io::Error::other("invalid icon size").into())
+            }
+            Self::Raster(image) => {
+                let resized = image
+                    .resize(
+                        (scaled_width.round() as u32).max(1),
+                        (scaled_height.round() as u32).max(1),
+                        imageops::FilterType::Lanczos3,
+                    )
+                    .into_rgba8();
+                let mut canvas = RgbaImage::new(size, size);
+                imageops::overlay(
+                    &mut canvas,
+                    &resized,
+                    i64::from(x as u32),
+                    i64::from(y as u32),
+                );
+                Ok(canvas)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_render_favicons() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_favicons");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+
+        let svg = dir.join("logo.svg");
+        fs::write(
+            &svg,
+            r##"<svg xmlns="http://www.w3.org/2000/svg" width="20" height="10"><rect width="20" height="10" fill="#f00"/></svg>"##,
+        )?;
+        let favicons = render_favicons(
+            &svg,
+            dir.join("dist"),
+            &FaviconOptions {
+                name: "Pichu".to_string(),
+                ..Default::default()
+            },
+        )?;
+
+        let icon = image::open(dir.join("dist/icon-192.png"))?.into_rgba8();
+        assert_eq!(icon.dimensions(), (192, 192));
+        // Centered vertically on a transparent background
+        assert_eq!(icon.get_pixel(96, 0).0[3], 0);
+        assert_eq!(icon.get_pixel(96, 96).0, [255, 0, 0, 255]);
+        assert!(dir.join("dist/favicon.ico").exists());
+        assert!(dir.join("dist/icon.svg").exists());
+        assert!(fs::read_to_string(dir.join("dist/site.webmanifest"))?.contains("Pichu"));
+        assert!(favicons
+            .to_html()
+            .contains(r#"<link rel="apple-touch-icon" href="/apple-touch-icon.png">"#));
+
+        let manifest = AssetManifest::new();
+        favicons.register(&manifest);
+        assert_eq!(
+            manifest.get("icon-512.png").as_deref(),
+            Some("/icon-512.png")
+        );
+
+        // Raster sources work too
+        icon.save(dir.join("logo.png"))?;
+        render_favicons(
+            dir.join("logo.png"),
+            dir.join("dist-png"),
+            &FaviconOptions::default(),
+        )?;
+        assert!(dir.join("dist-png/apple-touch-icon.png").exists());
+        assert!(!dir.join("dist-png/icon.svg").exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,539 @@
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt, fs,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::PathBuf,
+    sync::Arc,
+    time::Duration,
+};
+
+use crate::{
+    clock::{days_from_civil, rfc3339},
+    BuildProfile, Clock, Error, Parsed, SystemClock,
+};
+
+type FetchFn = dyn Fn(&str, Duration) -> Result<Vec<u8>, String> + Send + Sync;
+
+/// Error type for reading feeds.
+#[derive(thiserror::Error, Debug)]
+pub enum FeedError {
+    /// An I/O error occurred while caching a feed.
+    #[error("io error: {0}")]
+    Io(#[from] io::Error),
+    /// A feed couldn't be fetched and isn't cached.
+    #[error("failed to fetch {url}: {message}")]
+    Fetch {
+        /// The URL of the feed.
+        url: String,
+        /// What went wrong.
+        message: String,
+    },
+    /// A feed isn't valid RSS or Atom.
+    #[error("invalid feed {url}: {message}")]
+    Parse {
+        /// The URL of the feed.
+        url: String,
+        /// What went wrong.
+        message: String,
+    },
+}
+
+/// An entry of an RSS or Atom feed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedEntry {
+    /// The title of the feed.
+    pub feed_title: String,
+    /// The URL the feed was read from.
+    pub feed_url: String,
+    /// The title of the entry.
+    pub title: String,
+    /// The link to the entry.
+    pub url: String,
+    /// When the entry was published, or else updated, as an RFC 3339
+    /// datetime in UTC.
+    pub date: Option<String>,
+    /// The summary or content of the entry, usually HTML.
+    pub summary: Option<String>,
+    /// The name of the author.
+    pub author: Option<String>,
+}
+
+/// Reads external RSS and Atom feeds at build time, e.g. for a "planet"
+/// page aggregating the blogs of a community.
+///
+/// Only `http://` feeds can be fetched out of the box. Use
+/// [`FeedReader::fetch_with`] to fetch `https://` ones with the HTTP client
+/// of your choice.
+///
+/// ```no_run
+/// use pichu::FeedReader;
+///
+/// FeedReader::new()
+///     .cache("target/feeds")
+///     .fetch_with(|url, timeout| {
+///         // Fetch the URL with an HTTP client here.
+///         Err(format!("can't fetch {url} within {timeout:?}"))
+///     })
+///     .read(&["https://blog.rust-lang.org/feed.xml", "https://arne.me/feed.xml"])?
+///     .render_all(
+///         |entries| {
+///             entries
+///                 .iter()
+///                 .map(|entry| format!("<li><a href=\"{}\">{}</a></li>", entry.url, entry.title))
+///                 .collect::<String>()
+///         },
+///         "dist/planet/index.html",
+///     )?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Clone)]
+pub struct FeedReader {
+    cache_dir: Option<PathBuf>,
+    max_age: Duration,
+    timeout: Duration,
+    fetch: Arc<FetchFn>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for FeedReader {
+    fn default() -> Self {
+        Self {
+            cache_dir: None,
+            max_age: Duration::from_hours(1),
+            timeout: Duration::from_secs(10),
+            fetch: Arc::new(http_get),
+            clock: Arc::new(SystemClock),
+        }
+    }
+}
+
+impl FeedReader {
+    /// Create a reader without a cache.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Cache fetched feeds in the directory, so builds don't fetch them
+    /// again within [`FeedReader::max_age`] and still work when a feed is
+    /// unreachable.
+    #[must_use]
+    pub fn cache(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = Some(dir.into());
+        self
+    }
+
+    /// How long a cached feed is used before fetching it again, defaults
+    /// to an hour.
+    #[must_use]
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Configure the reader for the profile: development builds use cached
+    /// feeds however old they are, so rebuilds don't wait on the network.
+    #[must_use]
+    pub fn profile(self, profile: BuildProfile) -> Self {
+        match profile {
+            BuildProfile::Dev => self.max_age(Duration::MAX),
+            BuildProfile::Prod => self,
+        }
+    }
+
+    /// How long fetching a feed may take, defaults to 10 seconds.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Tell the age of cached feeds with the clock instead of the
+    /// [`SystemClock`].
+    #[must_use]
+    pub fn clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Arc::new(clock);
+        self
+    }
+
+    /// Fetch feeds with the given function, which is called with the URL
+    /// and the timeout and returns the body of the response.
+    #[must_use]
+    pub fn fetch_with(
+        mut self,
+        fetch_fn: impl Fn(&str, Duration) -> Result<Vec<u8>, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.fetch = Arc::new(fetch_fn);
+        self
+    }
+
+    /// Fetch and parse the feeds in parallel, returning their entries
+    /// newest first.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a feed can't be fetched and isn't cached, or
+    /// isn't valid RSS or Atom.
+    pub fn read(&self, urls: &[&str]) -> Result<Parsed<FeedEntry>, Error> {
+        let mut entries = urls
+            .par_iter()
+            .map(|url| parse_feed(url, &self.fetch_cached(url)?))
+            .collect::<Result<Vec<_>, FeedError>>()
+            .map_err(|e| Error::Parse(Box::new(e)))?
+            .into_iter()
+            .flatten()
+            .collect::<Vec<_>>();
+        entries.sort_by(|a, b| b.date.cmp(&a.date));
+        Ok(Parsed::from(entries))
+    }
+
+    /// The body of the feed, from the cache if it's fresh or the feed can't
+    /// be fetched.
+    fn fetch_cached(&self, url: &str) -> Result<Vec<u8>, FeedError> {
+        let Some(cache_dir) = &self.cache_dir else {
+            return (self.fetch)(url, self.timeout).map_err(|message| FeedError::Fetch {
+                url: url.to_string(),
+                message,
+            });
+        };
+        let path = cache_dir.join(format!("{}.xml", blake3::hash(url.as_bytes()).to_hex()));
+        let is_fresh = fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| self.clock.now().duration_since(modified).ok())
+            .is_some_and(|age| age < self.max_age);
+        if is_fresh {
+            return Ok(fs::read(&path)?);
+        }
+        match (self.fetch)(url, self.timeout) {
+            Ok(body) => {
+                crate::write(&path, &body)?;
+                Ok(body)
+            }
+            Err(message) => fs::read(&path).map_err(|_| FeedError::Fetch {
+                url: url.to_string(),
+                message,
+            }),
+        }
+    }
+}
+
+impl fmt::Debug for FeedReader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FeedReader")
+            .field("cache_dir", &self.cache_dir)
+            .field("max_age", &self.max_age)
+            .field("timeout", &self.timeout)
+            .field("clock", &self.clock)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Fetch an `http://` URL.
+fn http_get(url: &str, timeout: Duration) -> Result<Vec<u8>, String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| "only http:// is supported, see FeedReader::fetch_with".to_string())?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    let host = authority.split(':').next().unwrap_or(authority);
+    let address = if authority.contains(':') {
+        authority.to_string()
+    } else {
+        format!("{authority}:80")
+    };
+    let send = || -> Result<Vec<u8>, io::Error> {
+        let address = address
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "host not found"))?;
+        let mut stream = TcpStream::connect_timeout(&address, timeout)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        write!(
+            stream,
+            "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: pichu\r\nAccept: application/rss+xml, application/atom+xml, application/xml, text/xml\r\n\r\n"
+        )?;
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response)?;
+        Ok(response)
+    };
+    let response = send().map_err(|e| e.to_string())?;
+    let split = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| "invalid response".to_string())?;
+    let head = String::from_utf8_lossy(&response[..split]);
+    let status = head.split_whitespace().nth(1).unwrap_or_default();
+    if status != "200" {
+        return Err(format!("status {status}"));
+    }
+    Ok(response[split + 4..].to_vec())
+}
+
+/// Parse an RSS or Atom feed into its entries.
+fn parse_feed(url: &str, body: &[u8]) -> Result<Vec<FeedEntry>, FeedError> {
+    let parse_error = |message: String| FeedError::Parse {
+        url: url.to_string(),
+        message,
+    };
+    let text = std::str::from_utf8(body).map_err(|e| parse_error(e.to_string()))?;
+    let document = roxmltree::Document::parse(text).map_err(|e| parse_error(e.to_string()))?;
+    let root = document.root_element();
+    let child_text = |node: roxmltree::Node, name: &str| {
+        node.children()
+            .find(|child| child.tag_name().name() == name)
+            .and_then(|child| child.text())
+            .map(|text| text.trim().to_string())
+    };
+
+    let entries = match root.tag_name().name() {
+        "feed" => {
+            let feed_title = child_text(root, "title").unwrap_or_default();
+            root.children()
+                .filter(|node| node.tag_name().name() == "entry")
+                .map(|entry| {
+                    let link = entry
+                        .children()
+                        .filter(|node| node.tag_name().name() == "link")
+                        .find(|link| link.attribute("rel").is_none_or(|rel| rel == "alternate"))
+                        .and_then(|link| link.attribute("href"));
+                    let author = entry
+                        .children()
+                        .find(|node| node.tag_name().name() == "author")
+                        .and_then(|author| child_text(author, "name"));
+                    FeedEntry {
+                        feed_title: feed_title.clone(),
+                        feed_url: url.to_string(),
+                        title: child_text(entry, "title").unwrap_or_default(),
+                        url: link.unwrap_or_default().to_string(),
+                        date: child_text(entry, "published")
+                            .or_else(|| child_text(entry, "updated"))
+                            .and_then(|date| normalize_date(&date)),
+                        summary: child_text(entry, "summary")
+                            .or_else(|| child_text(entry, "content")),
+                        author,
+                    }
+                })
+                .collect()
+        }
+        "rss" | "RDF" => {
+            let feed_title = root
+                .descendants()
+                .find(|node| node.tag_name().name() == "channel")
+                .and_then(|channel| child_text(channel, "title"))
+                .unwrap_or_default();
+            root.descendants()
+                .filter(|node| node.tag_name().name() == "item")
+                .map(|item| FeedEntry {
+                    feed_title: feed_title.clone(),
+                    feed_url: url.to_string(),
+                    title: child_text(item, "title").unwrap_or_default(),
+                    url: child_text(item, "link").unwrap_or_default(),
+                    // `dc:date` in RSS 1.0.
+                    date: child_text(item, "pubDate")
+                        .or_else(|| child_text(item, "date"))
+                        .and_then(|date| normalize_date(&date)),
+                    summary: child_text(item, "description"),
+                    author: child_text(item, "creator").or_else(|| child_text(item, "author")),
+                })
+                .collect()
+        }
+        name => return Err(parse_error(format!("unknown root element {name}"))),
+    };
+    Ok(entries)
+}
+
+/// Convert an RFC 3339 or RFC 2822 date to RFC 3339 in UTC, so entries of
+/// different feeds can be sorted.
+fn normalize_date(date: &str) -> Option<String> {
+    parse_rfc3339(date)
+        .or_else(|| parse_rfc2822(date))
+        .map(rfc3339)
+}
+
+/// Seconds since the Unix epoch of e.g. `2024-05-01T10:00:00+02:00` or
+/// `2024-05-01`.
+fn parse_rfc3339(date: &str) -> Option<i64> {
+    let number = |range: std::ops::Range<usize>| date.get(range)?.parse::<i64>().ok();
+    let (year, month, day) = (number(0..4)?, number(5..7)?, number(8..10)?);
+    if date.len() == 10 {
+        return Some(days_from_civil(year, month, day) * 86_400);
+    }
+    let (hour, minute, second) = (number(11..13)?, number(14..16)?, number(17..19)?);
+    let zone = date[19..].trim_start_matches(|c: char| c == '.' || c.is_ascii_digit());
+    Some(
+        days_from_civil(year, month, day) * 86_400 + hour * 3600 + minute * 60 + second
+            - parse_offset(zone)?,
+    )
+}
+
+/// Seconds since the Unix epoch of e.g. `Wed, 01 May 2024 10:00:00 +0200`.
+fn parse_rfc2822(date: &str) -> Option<i64> {
+    let date = date.split_once(',').map_or(date, |(_, date)| date);
+    let mut parts = date.split_whitespace();
+    let day = parts.next()?.parse::<i64>().ok()?;
+    let month = parts.next()?.get(..3)?.to_ascii_lowercase();
+    let month = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ]
+    .iter()
+    .position(|name| *name == month)?;
+    let year = parts.next()?.parse::<i64>().ok()?;
+    let mut time = parts.next()?.split(':').map(|n| n.parse::<i64>().ok());
+    let hour = time.next()??;
+    let minute = time.next()??;
+    let second = time.next().unwrap_or(Some(0))?;
+    let offset = parse_offset(parts.next().unwrap_or("Z"))?;
+    Some(
+        days_from_civil(year, i64::try_from(month).ok()? + 1, day) * 86_400
+            + hour * 3600
+            + minute * 60
+            + second
+            - offset,
+    )
+}
+
+/// The offset from UTC in seconds of e.g. `Z`, `GMT`, `+02:00` or `-0500`.
+fn parse_offset(zone: &str) -> Option<i64> {
+    match zone {
+        "" | "Z" | "z" | "GMT" | "UT" | "UTC" => return Some(0),
+        _ => {}
+    }
+    let sign = match zone.get(..1)? {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let digits = zone[1..].replace(':', "");
+    let hours = digits.get(..2)?.parse::<i64>().ok()?;
+    let minutes = digits.get(2..4)?.parse::<i64>().ok()?;
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env,
+        sync::atomic::{AtomicUsize, Ordering},
+        time::SystemTime,
+    };
+
+    const RSS: &str = r#"<?xml version="1.0"?>
+<rss version="2.0" xmlns:dc="http://purl.org/dc/elements/1.1/">
+<channel>
+<title>Alice's Blog</title>
+<item>
+<title>Older</title>
+<link>https://alice.example/older</link>
+<pubDate>Wed, 01 May 2024 10:00:00 +0200</pubDate>
+<dc:creator>Alice</dc:creator>
+<description>&lt;p&gt;Hi&lt;/p&gt;</description>
+</item>
+</channel>
+</rss>"#;
+
+    const ATOM: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+<title>Bob's Blog</title>
+<entry>
+<title>Newer</title>
+<link rel="self" href="https://bob.example/newer.xml"/>
+<link href="https://bob.example/newer"/>
+<updated>2024-05-02T00:00:00-05:00</updated>
+<author><name>Bob</name></author>
+</entry>
+</feed>"#;
+
+    #[test]
+    fn test_feed_reader() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_feed_reader");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&fetches);
+        let reader = FeedReader::new().cache(&dir).fetch_with(move |url, _| {
+            counter.fetch_add(1, Ordering::Relaxed);
+            match url {
+                "http://alice.example/feed.xml" => Ok(RSS.as_bytes().to_vec()),
+                "http://bob.example/feed.xml" => Ok(ATOM.as_bytes().to_vec()),
+                _ => Err("not found".to_string()),
+            }
+        });
+        let urls = [
+            "http://alice.example/feed.xml",
+            "http://bob.example/feed.xml",
+        ];
+        let entries = reader.read(&urls)?;
+        assert_eq!(
+            entries.items,
+            [
+                FeedEntry {
+                    feed_title: "Bob's Blog".to_string(),
+                    feed_url: urls[1].to_string(),
+                    title: "Newer".to_string(),
+                    url: "https://bob.example/newer".to_string(),
+                    date: Some("2024-05-02T05:00:00Z".to_string()),
+                    summary: None,
+                    author: Some("Bob".to_string()),
+                },
+                FeedEntry {
+                    feed_title: "Alice's Blog".to_string(),
+                    feed_url: urls[0].to_string(),
+                    title: "Older".to_string(),
+                    url: "https://alice.example/older".to_string(),
+                    date: Some("2024-05-01T08:00:00Z".to_string()),
+                    summary: Some("<p>Hi</p>".to_string()),
+                    author: Some("Alice".to_string()),
+                },
+            ]
+        );
+
+        // Fresh feeds come from the cache, stale ones too if unreachable.
+        reader.read(&urls)?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+        // Development builds don't refetch stale feeds.
+        let dev = reader
+            .clone()
+            .max_age(Duration::ZERO)
+            .profile(BuildProfile::Dev);
+        dev.read(&urls)?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 2);
+        // The cache expires by the clock.
+        let later = crate::FixedClock(SystemTime::now() + Duration::from_hours(2));
+        reader.clone().clock(later).read(&urls)?;
+        assert_eq!(fetches.load(Ordering::Relaxed), 4);
+        let offline = reader
+            .clone()
+            .max_age(Duration::ZERO)
+            .fetch_with(|_, _| Err("offline".to_string()));
+        assert_eq!(offline.read(&urls)?.items.len(), 2);
+        assert!(offline.read(&["http://carol.example/feed.xml"]).is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize_date() {
+        assert_eq!(
+            normalize_date("2024-02-29T23:30:00.5+01:00").as_deref(),
+            Some("2024-02-29T22:30:00Z")
+        );
+        assert_eq!(
+            normalize_date("Thu, 01 Jan 1970 00:00 GMT").as_deref(),
+            Some("1970-01-01T00:00:00Z")
+        );
+        assert_eq!(
+            normalize_date("2024-05-01").as_deref(),
+            Some("2024-05-01T00:00:00Z")
+        );
+        assert_eq!(normalize_date("yesterday"), None);
+    }
+}
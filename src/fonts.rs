@@ -0,0 +1,892 @@
+use brotli::enc::backward_references::{BrotliEncoderMode, BrotliEncoderParams};
+use rayon::prelude::*;
+use std::{
+    collections::{BTreeSet, HashSet},
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+};
+use ttf_parser::{
+    gsub::{SingleSubstitution, SubstitutionSubtable},
+    Face, GlyphId, RawFace,
+};
+
+use crate::{
+    html::{attributes, tag_end, unescape},
+    manifest::{content_hash, fill_hash},
+    write, AssetManifest, Error,
+};
+
+/// Attributes whose values are shown to readers, so their characters count
+/// as used.
+const TEXT_ATTRIBUTES: [&str; 4] = ["alt", "title", "placeholder", "aria-label"];
+
+/// Tables dropped from subsets because they are invalidated by subsetting.
+/// `GSUB` is kept, along with the glyphs it can substitute, but AAT layout
+/// tables aren't followed and could reference removed glyphs.
+const DROPPED_TABLES: [&[u8; 4]; 3] = [b"morx", b"mort", b"DSIG"];
+
+/// The tags WOFF2 encodes as a single byte, by index.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// An OpenType table with its tag.
+type Table = ([u8; 4], Vec<u8>);
+
+/// A character and the glyph it maps to.
+type Mapping = (char, u16);
+
+/// Error type for font subsetting operations.
+#[derive(thiserror::Error, Debug)]
+pub enum FontError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// The font failed to parse.
+    #[error("invalid font {0}: {1}")]
+    Invalid(PathBuf, String),
+    /// The font has CFF outlines, only TrueType outlines can be subset.
+    #[error("only fonts with TrueType outlines are supported: {0}")]
+    Unsupported(PathBuf),
+}
+
+/// A font file to serve as a web font.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebFont {
+    /// The `font-family` it is used as.
+    pub family: String,
+    /// The TrueType or OpenType font file.
+    pub source: PathBuf,
+    /// The `font-weight`, e.g. `700`, or a range like `100 900` for variable
+    /// fonts. Defaults to `400`.
+    pub weight: String,
+    /// The `font-style`. Defaults to `normal`.
+    pub style: String,
+}
+
+impl WebFont {
+    /// A regular font of the family.
+    #[must_use]
+    pub fn new(family: impl Into<String>, source: impl Into<PathBuf>) -> Self {
+        Self {
+            family: family.into(),
+            source: source.into(),
+            weight: "400".to_string(),
+            style: "normal".to_string(),
+        }
+    }
+
+    /// Set the `font-weight`.
+    #[must_use]
+    pub fn weight(mut self, weight: impl Into<String>) -> Self {
+        self.weight = weight.into();
+        self
+    }
+
+    /// Set the `font-style`, e.g. `italic`.
+    #[must_use]
+    pub fn style(mut self, style: impl Into<String>) -> Self {
+        self.style = style.into();
+        self
+    }
+}
+
+/// Options for [`subset_fonts`].
+#[derive(Debug, Clone)]
+pub struct FontOptions {
+    /// The directory subsets are written to.
+    pub out_dir: PathBuf,
+    /// The URL `out_dir` is served at, e.g. `/fonts`.
+    pub url_prefix: String,
+    /// Add a hash of the subset to file names, for cache busting. Defaults
+    /// to true.
+    pub fingerprint: bool,
+}
+
+impl Default for FontOptions {
+    fn default() -> Self {
+        Self {
+            out_dir: PathBuf::from("dist/fonts"),
+            url_prefix: "/fonts".to_string(),
+            fingerprint: true,
+        }
+    }
+}
+
+/// A font reduced to the characters a site uses, written as WOFF2.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubsetFont {
+    /// The font it was subset from.
+    pub font: WebFont,
+    /// The URL of the WOFF2 file.
+    pub url: String,
+    /// The `unicode-range` of the characters the subset covers, e.g.
+    /// `U+20-7E,U+E9`.
+    pub unicode_range: String,
+    /// The size of the source font in bytes.
+    pub original_size: u64,
+    /// The size of the WOFF2 file in bytes.
+    pub size: u64,
+}
+
+impl SubsetFont {
+    /// The `@font-face` rule for the subset.
+    #[must_use]
+    pub fn font_face(&self) -> String {
+        format!(
+            "@font-face{{font-family:\"{}\";font-style:{};font-weight:{};font-display:swap;src:url({}) format(\"woff2\");unicode-range:{}}}",
+            self.font.family, self.font.style, self.font.weight, self.url, self.unicode_range
+        )
+    }
+
+    /// The `<link rel="preload">` tag for the subset, for fonts used above
+    /// the fold.
+    #[must_use]
+    pub fn preload(&self) -> String {
+        format!(
+            r#"<link rel="preload" href="{}" as="font" type="font/woff2" crossorigin>"#,
+            self.url
+        )
+    }
+}
+
+/// The characters used in the text of the HTML pages in the output
+/// directory, including `alt`, `title`, `placeholder` and `aria-label`
+/// attributes, but not scripts and styles.
+///
+/// # Errors
+///
+/// Returns an error if a page cannot be read.
+pub fn used_chars(out_dir: impl AsRef<Path>) -> Result<BTreeSet<char>, Error> {
+    let pattern =
+        Path::new(&glob::Pattern::escape(&out_dir.as_ref().to_string_lossy())).join("**/*.html");
+    let pages = glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?;
+    pages
+        .par_iter()
+        .map(|page| Ok(text_chars(&fs::read_to_string(page)?)))
+        .try_reduce(BTreeSet::new, |mut a, b| {
+            a.extend(b);
+            Ok(a)
+        })
+}
+
+/// Subset the fonts to the glyphs needed for `chars`, e.g. from
+/// [`used_chars`], and write them as WOFF2 files registered in the manifest
+/// by their source file name, e.g. `Inter.woff2` for `Inter.ttf`.
+///
+/// Glyph IDs are kept, unused glyphs are emptied and the character map only
+/// covers `chars`, so browsers fall back to another font for the rest.
+/// Substitutions like ligatures are dropped, kerning is kept.
+///
+/// ```no_run
+/// use pichu::{AssetManifest, FontOptions, WebFont};
+///
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+/// let manifest = AssetManifest::new();
+/// let chars = pichu::used_chars("dist")?;
+/// let fonts = pichu::subset_fonts(
+///     &[WebFont::new("Noto Sans JP", "fonts/NotoSansJP-Regular.ttf")],
+///     &chars,
+///     &FontOptions::default(),
+///     &manifest,
+/// )?;
+/// let css = fonts.iter().map(|font| font.font_face()).collect::<String>();
+/// let preloads = fonts.iter().map(|font| font.preload()).collect::<String>();
+/// # drop((css, preloads));
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if a font cannot be read, parsed or written, or if it
+/// has CFF outlines.
+pub fn subset_fonts(
+    fonts: &[WebFont],
+    chars: &BTreeSet<char>,
+    options: &FontOptions,
+    manifest: &AssetManifest,
+) -> Result<Vec<SubsetFont>, FontError> {
+    fonts
+        .par_iter()
+        .map(|font| {
+            let data = fs::read(&font.source)?;
+            let (mut tables, mapped) = subset(&font.source, &data, chars)?;
+            let sfnt = sfnt(&mut tables);
+            let woff2 = woff2(&tables, sfnt.len())?;
+
+            let stem = font
+                .source
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy();
+            let hash = content_hash(&woff2);
+            let hash = options.fingerprint.then_some(hash.as_str());
+            let file_name = fill_hash(Path::new(&format!("{stem}.[hash].woff2")), hash);
+            write(options.out_dir.join(&file_name), &woff2)?;
+            let url = format!(
+                "{}/{}",
+                options.url_prefix.trim_end_matches('/'),
+                file_name.display()
+            );
+            manifest.register(format!("{stem}.woff2"), &url);
+
+            Ok(SubsetFont {
+                font: font.clone(),
+                url,
+                unicode_range: unicode_range(&mapped),
+                original_size: data.len() as u64,
+                size: woff2.len() as u64,
+            })
+        })
+        .collect()
+}
+
+/// The characters in the text and [`TEXT_ATTRIBUTES`] of a page.
+fn text_chars(html: &str) -> BTreeSet<char> {
+    let mut chars = BTreeSet::new();
+    let mut pieces = html.split('<');
+    chars.extend(decode(pieces.next().unwrap_or_default()).chars());
+    let mut raw_text: Option<String> = None;
+    for piece in pieces {
+        let name = piece
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '/')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match &raw_text {
+            // Only the end tag closes a script or style, anything else is code.
+            Some(element) if name.strip_prefix('/') == Some(element) => raw_text = None,
+            Some(_) => continue,
+            None if name == "script" || name == "style" => raw_text = Some(name.clone()),
+            None => {}
+        }
+
+        let end = tag_end(piece);
+        for (attribute, value) in attributes(&piece[name.len()..end]) {
+            if TEXT_ATTRIBUTES
+                .iter()
+                .any(|text| attribute.eq_ignore_ascii_case(text))
+            {
+                chars.extend(decode(value).chars());
+            }
+        }
+        if raw_text.is_none() {
+            chars.extend(decode(&piece[end..]).chars());
+        }
+    }
+    chars.retain(|c| !c.is_control());
+    chars
+}
+
+/// Unescape text, including numeric character references.
+fn decode(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("&#") {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let reference = rest.find(';').and_then(|end| {
+            let number = &rest[2..end];
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => number.parse().ok(),
+            };
+            Some((char::from_u32(code?)?, end))
+        });
+        if let Some((c, end)) = reference {
+            decoded.push(c);
+            rest = &rest[end + 1..];
+        } else {
+            decoded.push_str("&#");
+            rest = &rest[2..];
+        }
+    }
+    decoded.push_str(rest);
+    unescape(&decoded)
+}
+
+/// Subset a TrueType font, returning its tables sorted by tag and the
+/// characters it maps.
+fn subset(
+    path: &Path,
+    data: &[u8],
+    chars: &BTreeSet<char>,
+) -> Result<(Vec<Table>, Vec<Mapping>), FontError> {
+    let invalid = |message: &str| FontError::Invalid(path.to_path_buf(), message.to_string());
+    let raw = RawFace::parse(data, 0).map_err(|e| invalid(&e.to_string()))?;
+    let face = Face::parse(data, 0).map_err(|e| invalid(&e.to_string()))?;
+    let table = |tag: &[u8; 4]| raw.table(ttf_parser::Tag::from_bytes(tag));
+    let (Some(glyf), Some(loca), Some(head)) = (table(b"glyf"), table(b"loca"), table(b"head"))
+    else {
+        return Err(FontError::Unsupported(path.to_path_buf()));
+    };
+    if head.len() < 54 {
+        return Err(invalid("head table is too short"));
+    }
+
+    let long_offsets = head[50..52] != [0, 0];
+    let offset = |id: usize| -> Option<usize> {
+        if long_offsets {
+            let bytes = loca.get(4 * id..4 * id + 4)?;
+            usize::try_from(u32::from_be_bytes(bytes.try_into().ok()?)).ok()
+        } else {
+            Some(usize::from(u16_at(loca, 2 * id)?) * 2)
+        }
+    };
+    let glyph = |id: u16| glyf.get(offset(id.into())?..offset(usize::from(id) + 1)?);
+
+    let mapped = chars
+        .iter()
+        .filter_map(|&c| Some((c, face.glyph_index(c)?.0)))
+        .collect::<Vec<_>>();
+    let num_glyphs = face.number_of_glyphs();
+    let mut keep = HashSet::new();
+    let mut work = vec![0];
+    work.extend(mapped.iter().map(|(_, id)| id));
+    while !work.is_empty() {
+        while let Some(id) = work.pop() {
+            if id < num_glyphs && keep.insert(id) {
+                work.extend(components(glyph(id).unwrap_or_default()));
+            }
+        }
+        work = substitutes(&face, &keep)
+            .into_iter()
+            .filter(|id| *id < num_glyphs && !keep.contains(id))
+            .collect();
+    }
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::with_capacity(4 * (usize::from(num_glyphs) + 1));
+    for id in 0..=num_glyphs {
+        let offset = u32::try_from(new_glyf.len()).map_err(|_| invalid("font is too large"))?;
+        new_loca.extend(offset.to_be_bytes());
+        if id < num_glyphs && keep.contains(&id) {
+            new_glyf.extend(glyph(id).ok_or_else(|| invalid("glyph offset out of bounds"))?);
+            new_glyf.resize(new_glyf.len().next_multiple_of(4), 0);
+        }
+    }
+    let mut new_head = head.to_vec();
+    new_head[8..12].fill(0);
+    new_head[50..52].copy_from_slice(&1_i16.to_be_bytes());
+
+    let mut tables = Vec::new();
+    for record in raw.table_records {
+        let tag = record.tag.to_bytes();
+        let data = match &tag {
+            b"glyf" => new_glyf.clone(),
+            b"loca" => new_loca.clone(),
+            b"head" => new_head.clone(),
+            b"cmap" => cmap(&mapped),
+            tag if DROPPED_TABLES.contains(&tag) => continue,
+            _ => table(&tag)
+                .ok_or_else(|| invalid("table out of bounds"))?
+                .to_vec(),
+        };
+        tables.push((tag, data));
+    }
+    tables.sort_by_key(|(tag, _)| *tag);
+
+    let size = tables
+        .iter()
+        .map(|(_, data)| 16 + data.len().next_multiple_of(4))
+        .sum::<usize>();
+    if u32::try_from(12 + size).is_err() {
+        return Err(invalid("font is too large"));
+    }
+    Ok((tables, mapped))
+}
+
+/// The glyphs `GSUB` can substitute for the kept ones, e.g. ligatures or
+/// alternates. Contextual lookups only apply other lookups, which are
+/// followed for every glyph anyway.
+fn substitutes(face: &Face, keep: &HashSet<u16>) -> Vec<u16> {
+    let Some(gsub) = face.tables().gsub else {
+        return Vec::new();
+    };
+    let mut found = Vec::new();
+    for lookup in gsub.lookups {
+        for subtable in lookup.subtables.into_iter::<SubstitutionSubtable>() {
+            let coverage = subtable.coverage();
+            for &id in keep {
+                let Some(index) = coverage.get(GlyphId(id)) else {
+                    continue;
+                };
+                match subtable {
+                    SubstitutionSubtable::Single(SingleSubstitution::Format1 { delta, .. }) => {
+                        found.push(id.wrapping_add_signed(delta));
+                    }
+                    SubstitutionSubtable::Single(SingleSubstitution::Format2 {
+                        substitutes,
+                        ..
+                    }) => found.extend(substitutes.get(index).map(|glyph| glyph.0)),
+                    SubstitutionSubtable::Multiple(multiple) => found.extend(
+                        multiple
+                            .sequences
+                            .get(index)
+                            .into_iter()
+                            .flat_map(|sequence| sequence.substitutes)
+                            .map(|glyph| glyph.0),
+                    ),
+                    SubstitutionSubtable::Alternate(alternate) => found.extend(
+                        alternate
+                            .alternate_sets
+                            .get(index)
+                            .into_iter()
+                            .flat_map(|set| set.alternates)
+                            .map(|glyph| glyph.0),
+                    ),
+                    SubstitutionSubtable::Ligature(ligature) => found.extend(
+                        ligature
+                            .ligature_sets
+                            .get(index)
+                            .into_iter()
+                            .flatten()
+                            .filter(|ligature| {
+                                ligature
+                                    .components
+                                    .into_iter()
+                                    .all(|glyph| keep.contains(&glyph.0))
+                            })
+                            .map(|ligature| ligature.glyph.0),
+                    ),
+                    SubstitutionSubtable::ReverseChainSingle(reverse) => {
+                        found.extend(reverse.substitutes.get(index).map(|glyph| glyph.0));
+                    }
+                    SubstitutionSubtable::Context(_) | SubstitutionSubtable::ChainContext(_) => {}
+                }
+            }
+        }
+    }
+    found
+}
+
+/// The glyphs a composite glyph is made of.
+fn components(glyph: &[u8]) -> Vec<u16> {
+    const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+    const WE_HAVE_A_SCALE: u16 = 0x0008;
+    const MORE_COMPONENTS: u16 = 0x0020;
+    const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+    const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+    let mut ids = Vec::new();
+    // A negative number of contours marks a composite glyph.
+    if u16_at(glyph, 0).is_none_or(|contours| contours & 0x8000 == 0) {
+        return ids;
+    }
+    let mut offset = 10;
+    while let (Some(flags), Some(id)) = (u16_at(glyph, offset), u16_at(glyph, offset + 2)) {
+        ids.push(id);
+        offset += if flags & ARG_1_AND_2_ARE_WORDS == 0 {
+            6
+        } else {
+            8
+        };
+        if flags & WE_HAVE_A_SCALE != 0 {
+            offset += 2;
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            offset += 4;
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            offset += 8;
+        }
+        if flags & MORE_COMPONENTS == 0 {
+            break;
+        }
+    }
+    ids
+}
+
+/// A `cmap` table with a single format 12 subtable mapping the characters.
+fn cmap(mapped: &[Mapping]) -> Vec<u8> {
+    let mut groups: Vec<(u32, u32, u32)> = Vec::new();
+    for &(c, id) in mapped {
+        let (c, id) = (u32::from(c), u32::from(id));
+        match groups.last_mut() {
+            Some((start, end, start_id)) if *end + 1 == c && *start_id + (c - *start) == id => {
+                *end = c;
+            }
+            _ => groups.push((c, c, id)),
+        }
+    }
+
+    let mut cmap = Vec::with_capacity(36 + 12 * groups.len());
+    cmap.extend(0_u16.to_be_bytes());
+    cmap.extend(2_u16.to_be_bytes());
+    // Unicode full repertoire and Windows UCS-4, sharing the subtable.
+    for (platform, encoding) in [(0_u16, 4_u16), (3, 10)] {
+        cmap.extend(platform.to_be_bytes());
+        cmap.extend(encoding.to_be_bytes());
+        cmap.extend(20_u32.to_be_bytes());
+    }
+    cmap.extend(12_u16.to_be_bytes());
+    cmap.extend(0_u16.to_be_bytes());
+    cmap.extend(u32_of(16 + 12 * groups.len()).to_be_bytes());
+    cmap.extend(0_u32.to_be_bytes());
+    cmap.extend(u32_of(groups.len()).to_be_bytes());
+    for (start, end, start_id) in groups {
+        cmap.extend(start.to_be_bytes());
+        cmap.extend(end.to_be_bytes());
+        cmap.extend(start_id.to_be_bytes());
+    }
+    cmap
+}
+
+/// Assemble the tables into a TrueType font, updating the checksum
+/// adjustment in `head`.
+fn sfnt(tables: &mut [Table]) -> Vec<u8> {
+    let count = u16::try_from(tables.len()).unwrap_or(u16::MAX);
+    let entry_selector = count.checked_ilog2().unwrap_or_default();
+    let search_range = 16_u16 << entry_selector;
+    let mut font = Vec::new();
+    font.extend(0x0001_0000_u32.to_be_bytes());
+    font.extend(count.to_be_bytes());
+    font.extend(search_range.to_be_bytes());
+    font.extend(
+        u16::try_from(entry_selector)
+            .unwrap_or_default()
+            .to_be_bytes(),
+    );
+    font.extend(
+        count
+            .saturating_mul(16)
+            .saturating_sub(search_range)
+            .to_be_bytes(),
+    );
+
+    let mut offset = 12 + 16 * tables.len();
+    let mut head_offset = None;
+    for (tag, data) in tables.iter() {
+        if tag == b"head" {
+            head_offset = Some(offset);
+        }
+        font.extend(tag);
+        font.extend(checksum(data).to_be_bytes());
+        font.extend(u32_of(offset).to_be_bytes());
+        font.extend(u32_of(data.len()).to_be_bytes());
+        offset += data.len().next_multiple_of(4);
+    }
+    for (_, data) in tables.iter() {
+        font.extend(data);
+        font.resize(font.len().next_multiple_of(4), 0);
+    }
+
+    if let Some(head_offset) = head_offset {
+        let adjustment = 0xB1B0_AFBA_u32.wrapping_sub(checksum(&font)).to_be_bytes();
+        font[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment);
+        if let Some((_, head)) = tables.iter_mut().find(|(tag, _)| tag == b"head") {
+            head[8..12].copy_from_slice(&adjustment);
+        }
+    }
+    font
+}
+
+/// Encode the tables as WOFF2, without transforming `glyf` and `loca`.
+fn woff2(tables: &[Table], sfnt_size: usize) -> Result<Vec<u8>, io::Error> {
+    let mut directory = Vec::new();
+    let mut data = Vec::new();
+    for (tag, table) in tables {
+        // Version 3 is the null transform for glyf and loca, 0 for the rest.
+        let transform = if tag == b"glyf" || tag == b"loca" {
+            0xC0
+        } else {
+            0
+        };
+        if let Some((_, index)) = KNOWN_TAGS
+            .iter()
+            .zip(0_u8..)
+            .find(|(known, _)| **known == tag)
+        {
+            directory.push(transform | index);
+        } else {
+            directory.push(transform | 63);
+            directory.extend(tag);
+        }
+        base128(&mut directory, u32_of(table.len()));
+        data.extend(table);
+    }
+
+    let mut compressed = Vec::new();
+    let params = BrotliEncoderParams {
+        quality: 11,
+        mode: BrotliEncoderMode::BROTLI_MODE_FONT,
+        size_hint: data.len(),
+        ..Default::default()
+    };
+    brotli::BrotliCompress(&mut data.as_slice(), &mut compressed, &params)?;
+
+    let length = (48 + directory.len() + compressed.len()).next_multiple_of(4);
+    let mut font = Vec::with_capacity(length);
+    font.extend(b"wOF2");
+    font.extend(0x0001_0000_u32.to_be_bytes());
+    font.extend(u32_of(length).to_be_bytes());
+    font.extend(
+        u16::try_from(tables.len())
+            .unwrap_or(u16::MAX)
+            .to_be_bytes(),
+    );
+    font.extend(0_u16.to_be_bytes());
+    font.extend(u32_of(sfnt_size).to_be_bytes());
+    font.extend(u32_of(compressed.len()).to_be_bytes());
+    font.extend(1_u16.to_be_bytes());
+    font.extend(0_u16.to_be_bytes());
+    // No metadata or private data.
+    font.extend([0; 20]);
+    font.extend(directory);
+    font.extend(compressed);
+    font.resize(length, 0);
+    Ok(font)
+}
+
+/// The `unicode-range` descriptor for the characters, merging runs.
+fn unicode_range(mapped: &[Mapping]) -> String {
+    let mut ranges: Vec<(u32, u32)> = Vec::new();
+    for &(c, _) in mapped {
+        let c = u32::from(c);
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == c => *end = c,
+            _ => ranges.push((c, c)),
+        }
+    }
+    let mut range = String::new();
+    for (start, end) in ranges {
+        if !range.is_empty() {
+            range.push(',');
+        }
+        let _ = write!(range, "U+{start:X}");
+        if end > start {
+            let _ = write!(range, "-{end:X}");
+        }
+    }
+    range
+}
+
+/// Append a WOFF2 `UIntBase128`: big-endian groups of 7 bits, with the high
+/// bit set on all but the last byte.
+fn base128(out: &mut Vec<u8>, value: u32) {
+    let mut shift = value.max(1).ilog2() / 7 * 7;
+    loop {
+        let byte = u8::try_from((value >> shift) & 0x7F).unwrap_or_default();
+        if shift == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+        shift -= 7;
+    }
+}
+
+/// The OpenType checksum: the sum of big-endian `u32`s, zero-padded.
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0, |sum, chunk| {
+        let mut bytes = [0; 4];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(bytes))
+    })
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+/// Convert a size to `u32`, which [`subset`] checked the font fits.
+fn u32_of(size: usize) -> u32 {
+    u32::try_from(size).unwrap_or(u32::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    /// A simple glyph with one on-curve point.
+    fn point(x: i16) -> Vec<u8> {
+        let mut glyph = Vec::new();
+        for value in [1, x, 0, x, 0, 0, 0] {
+            glyph.extend(value.to_be_bytes());
+        }
+        glyph.push(0x01);
+        glyph.extend(x.to_be_bytes());
+        glyph.extend(0_i16.to_be_bytes());
+        glyph
+    }
+
+    /// A font mapping `a`, `b` and `é`, a composite of glyph 2, with the
+    /// ligatures `aa` and `ab`.
+    fn font() -> Vec<u8> {
+        let mut composite = Vec::new();
+        for value in [-1_i16, 0, 0, 9, 0, 0x0003, 2, 0, 0] {
+            composite.extend(value.to_be_bytes());
+        }
+        let glyphs = [
+            Vec::new(),
+            point(1),
+            point(2),
+            composite,
+            point(4),
+            point(5),
+            point(6),
+        ];
+        let mut glyf = Vec::new();
+        let mut loca = Vec::new();
+        for glyph in &glyphs {
+            loca.extend(u32_of(glyf.len()).to_be_bytes());
+            glyf.extend(glyph);
+        }
+        loca.extend(u32_of(glyf.len()).to_be_bytes());
+
+        let mut head = vec![0; 54];
+        head[1] = 1;
+        head[12..16].copy_from_slice(&0x5F0F_3CF5_u32.to_be_bytes());
+        head[18..20].copy_from_slice(&1000_u16.to_be_bytes());
+        head[50..52].copy_from_slice(&1_i16.to_be_bytes());
+        let mut hhea = vec![0; 36];
+        hhea[1] = 1;
+        hhea[34..36].copy_from_slice(&7_u16.to_be_bytes());
+        let mut maxp = vec![0, 0, 0x50, 0];
+        maxp.extend(7_u16.to_be_bytes());
+        // One ligature lookup substituting glyph 5 for `aa` and 6 for `ab`.
+        let mut gsub = Vec::new();
+        for value in [
+            1_u16, 0, 10, 12, 14, 0, 0, 1, 4, 4, 0, 1, 8, 1, 8, 1, 14, 1, 1, 1, 2, 6, 12, 5, 2, 1,
+            6, 2, 4,
+        ] {
+            gsub.extend(value.to_be_bytes());
+        }
+        let mut tables = vec![
+            (*b"cmap", cmap(&[('a', 1), ('b', 4), ('é', 3)])),
+            (*b"GSUB", gsub),
+            (*b"glyf", glyf),
+            (*b"head", head),
+            (*b"hhea", hhea),
+            (*b"hmtx", [0, 100, 0, 0].repeat(7)),
+            (*b"loca", loca),
+            (*b"maxp", maxp),
+        ];
+        sfnt(&mut tables)
+    }
+
+    /// Decode WOFF2 tables encoded with the null transform, following the
+    /// spec rather than [`woff2`].
+    fn decode_woff2(font: &[u8]) -> Result<Vec<Table>, Box<dyn std::error::Error>> {
+        let num_tables = u16_at(font, 12).ok_or("truncated header")?;
+        let compressed_size = usize::try_from(u32::from_be_bytes(font[20..24].try_into()?))?;
+        let mut offset = 48;
+        let mut directory = Vec::new();
+        for _ in 0..num_tables {
+            let flags = font[offset];
+            offset += 1;
+            let tag: [u8; 4] = if flags & 63 == 63 {
+                offset += 4;
+                font[offset - 4..offset].try_into()?
+            } else {
+                *KNOWN_TAGS[usize::from(flags & 63)]
+            };
+            let is_glyf = tag == *b"glyf" || tag == *b"loca";
+            // Only the null transform, which has no transformed length.
+            assert_eq!(flags >> 6, if is_glyf { 3 } else { 0 });
+            let mut length = 0_usize;
+            loop {
+                let byte = font[offset];
+                offset += 1;
+                length = length << 7 | usize::from(byte & 0x7F);
+                if byte & 0x80 == 0 {
+                    break;
+                }
+            }
+            directory.push((tag, length));
+        }
+
+        let mut data = Vec::new();
+        brotli::BrotliDecompress(&mut &font[offset..offset + compressed_size], &mut data)?;
+        let mut tables = Vec::new();
+        let mut start = 0;
+        for (tag, length) in directory {
+            tables.push((tag, data[start..start + length].to_vec()));
+            start += length;
+        }
+        assert_eq!(start, data.len());
+        Ok(tables)
+    }
+
+    #[test]
+    fn test_subset_fonts() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_subset_fonts");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        write(
+            dir.join("dist/index.html"),
+            "<p title=\"&#xE9;\">a &amp; <b>a</b>a<script>let b;</script></p>",
+        )?;
+        write(dir.join("Test.ttf"), font())?;
+
+        let chars = used_chars(dir.join("dist"))?;
+        assert_eq!(chars, BTreeSet::from(['a', '&', ' ', 'é']));
+
+        let (mut tables, _) = subset(Path::new("Test.ttf"), &font(), &chars)?;
+        let subset = sfnt(&mut tables);
+        let face = Face::parse(&subset, 0)?;
+        assert!(face.glyph_index('a').is_some());
+        assert!(face.glyph_index('b').is_none());
+        // The component of `é` is kept, the unused `b` is emptied.
+        let loca = face
+            .raw_face()
+            .table(ttf_parser::Tag::from_bytes(b"loca"))
+            .unwrap_or_default();
+        assert_ne!(loca[8..12], loca[12..16]);
+        assert_eq!(loca[16..20], loca[20..24]);
+        // So is the `aa` ligature, but not `ab`.
+        assert_ne!(loca[20..24], loca[24..28]);
+        assert_eq!(loca[24..28], loca[28..32]);
+        assert!(tables.iter().any(|(tag, _)| tag == b"GSUB"));
+        assert_eq!(checksum(&subset), 0xB1B0_AFBA);
+
+        let manifest = AssetManifest::new();
+        let options = FontOptions {
+            out_dir: dir.join("dist/fonts"),
+            ..Default::default()
+        };
+        let fonts = subset_fonts(
+            &[WebFont::new("Test", dir.join("Test.ttf")).weight("700")],
+            &chars,
+            &options,
+            &manifest,
+        )?;
+        assert_eq!(manifest.get("Test.woff2"), Some(fonts[0].url.clone()));
+        assert!(fonts[0].font_face().contains("font-weight:700;"));
+        assert!(fonts[0].font_face().ends_with("unicode-range:U+61,U+E9}"));
+        let woff2 = fs::read(dir.join("dist").join(fonts[0].url.trim_start_matches('/')))?;
+        assert_eq!(&woff2[..4], b"wOF2");
+        assert_eq!(
+            u64::from(u32::from_be_bytes(woff2[8..12].try_into()?)),
+            fonts[0].size
+        );
+        let mut decoded = decode_woff2(&woff2)?;
+        assert_eq!(decoded, tables);
+        // Reassemble the font, recomputing the checksum adjustment.
+        for (tag, data) in &mut decoded {
+            if tag == b"head" {
+                data[8..12].fill(0);
+            }
+        }
+        let decoded = sfnt(&mut decoded);
+        assert_eq!(decoded, subset);
+        assert_eq!(
+            u32::from_be_bytes(woff2[16..20].try_into()?),
+            u32_of(decoded.len())
+        );
+        assert!(Face::parse(&decoded, 0)?.glyph_index('é').is_some());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,387 @@
+use rayon::prelude::*;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    clock::{civil_from_days, days_from_civil},
+    html::escape,
+    output::write_atomic,
+    sync::lock,
+    Clock, HasSource, Page, Parsed, UrlPath,
+};
+
+/// Where [`Freshness`] takes the date a page last changed from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FreshnessSource {
+    /// The date of the last commit changing the page's source file, if it's
+    /// tracked in git.
+    Git,
+    /// The page's [`Page::updated`] or else [`Page::date`].
+    Date,
+}
+
+/// Collects when the pages of a site last changed, e.g. from several
+/// collections, to report the ones not reviewed in a number of months.
+/// Can be shared between threads.
+///
+/// ```no_run
+/// use pichu::{Freshness, SystemClock};
+///
+/// # #[derive(Debug, serde::Deserialize)]
+/// # struct Doc { title: String }
+/// # impl pichu::Page for Doc {
+/// #     fn title(&self) -> &str { &self.title }
+/// #     fn url(&self) -> pichu::UrlPath { pichu::UrlPath::root() }
+/// # }
+/// # fn main() -> Result<(), pichu::Error> {
+/// let freshness = Freshness::new(12);
+/// let docs = pichu::glob("content/docs/**/*.md")?
+///     .parse_markdown::<Doc>()?
+///     .track_freshness(&freshness);
+/// // Render the docs ...
+/// # drop(docs);
+/// let report = freshness.report(&SystemClock);
+/// report.save("freshness.json")?;
+/// report.save_html("dist/internal/freshness/index.html")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Freshness {
+    months: u32,
+    sources: Vec<FreshnessSource>,
+    pages: Mutex<BTreeMap<UrlPath, Tracked>>,
+}
+
+/// A tracked page, with the days since the Unix epoch it last changed.
+#[derive(Debug)]
+struct Tracked {
+    title: String,
+    source: PathBuf,
+    changed: Option<i64>,
+}
+
+impl Freshness {
+    /// Report pages that haven't changed in `months` months, looking up
+    /// when they changed from git and else from their date.
+    #[must_use]
+    pub fn new(months: u32) -> Self {
+        Self {
+            months,
+            sources: vec![FreshnessSource::Git, FreshnessSource::Date],
+            pages: Mutex::default(),
+        }
+    }
+
+    /// Set where to look up when a page last changed, in order. The first
+    /// source that knows wins.
+    #[must_use]
+    pub fn sources(mut self, sources: impl IntoIterator<Item = FreshnessSource>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// Add pages, looking up when they last changed from the configured
+    /// sources.
+    pub fn extend<'a, T: Page + HasSource + Sync + 'a>(
+        &self,
+        pages: impl IntoParallelIterator<Item = &'a T>,
+    ) {
+        let pages = pages
+            .into_par_iter()
+            .map(|page| {
+                let changed = self.sources.iter().find_map(|source| match source {
+                    FreshnessSource::Git => git_lastmod(page.source()).as_deref().and_then(days),
+                    FreshnessSource::Date => page.updated().or(page.date()).and_then(days),
+                });
+                let tracked = Tracked {
+                    title: page.title().to_string(),
+                    source: page.source().to_path_buf(),
+                    changed,
+                };
+                (page.url(), tracked)
+            })
+            .collect::<Vec<_>>();
+        lock(&self.pages).extend(pages);
+    }
+
+    /// The pages that haven't changed in the configured number of months
+    /// at the time of the clock. Pages without a known date are reported
+    /// too.
+    #[must_use]
+    pub fn report(&self, clock: &impl Clock) -> FreshnessReport {
+        let secs = clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let today = i64::try_from(secs / 86_400).unwrap_or(i64::MAX);
+        let (year, month, day) = civil_from_days(today);
+        let months = year * 12 + month - 1 - i64::from(self.months);
+        let (year, month) = (months.div_euclid(12), months.rem_euclid(12) + 1);
+        let first = days_from_civil(year, month, 1);
+        let days_in_month =
+            days_from_civil(year + i64::from(month == 12), month % 12 + 1, 1) - first;
+        let cutoff = first + day.min(days_in_month) - 1;
+
+        let mut sections = BTreeMap::<String, Vec<StalePage>>::new();
+        for (url, tracked) in lock(&self.pages).iter() {
+            if tracked.changed.is_some_and(|changed| changed >= cutoff) {
+                continue;
+            }
+            sections.entry(section(url)).or_default().push(StalePage {
+                url: url.as_str().to_string(),
+                title: tracked.title.clone(),
+                source: tracked.source.clone(),
+                last_changed: tracked.changed.map(|changed| {
+                    let (year, month, day) = civil_from_days(changed);
+                    format!("{year:04}-{month:02}-{day:02}")
+                }),
+                age_days: tracked.changed.map(|changed| today - changed),
+            });
+        }
+        for pages in sections.values_mut() {
+            // Oldest first, pages without a date before all others.
+            pages.sort_by_key(|page| (page.last_changed.clone(), page.url.clone()));
+        }
+        FreshnessReport {
+            months: self.months,
+            sections,
+        }
+    }
+}
+
+impl<T: Page + HasSource + Send + Sync> Parsed<T> {
+    /// Add the pages to the freshness report, see [`Freshness::extend`].
+    #[must_use]
+    pub fn track_freshness(self, freshness: &Freshness) -> Self {
+        freshness.extend(&self.items);
+        self
+    }
+}
+
+/// A page that hasn't changed recently, see [`Freshness::report`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StalePage {
+    /// The URL path of the page.
+    pub url: String,
+    /// The title of the page.
+    pub title: String,
+    /// The source file of the page.
+    pub source: PathBuf,
+    /// When the page last changed as `YYYY-MM-DD`, if known.
+    pub last_changed: Option<String>,
+    /// How many days ago the page last changed, if known.
+    pub age_days: Option<i64>,
+}
+
+/// The stale pages of a site grouped by section, the first segment of
+/// their URL path, from [`Freshness::report`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct FreshnessReport {
+    months: u32,
+    sections: BTreeMap<String, Vec<StalePage>>,
+}
+
+impl FreshnessReport {
+    /// Whether no page is stale.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// The sections like `/docs/` with their stale pages, oldest first.
+    /// Pages at the top level are in the `/` section.
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &[StalePage])> {
+        self.sections
+            .iter()
+            .map(|(section, pages)| (section.as_str(), pages.as_slice()))
+    }
+
+    /// Write the report to disk as JSON, for review tools and scripts.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(self)?;
+        write_atomic(path.as_ref(), json.as_bytes())
+    }
+
+    /// The report as a standalone HTML page with a table per section.
+    #[must_use]
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+             <title>Stale pages</title>\n</head>\n<body>\n<h1>Stale pages</h1>\n\
+             <p>Pages that haven't changed in {} months.</p>\n",
+            self.months
+        );
+        for (section, pages) in &self.sections {
+            let _ = write!(
+                html,
+                "<h2>{}</h2>\n<table>\n<tr><th>Page</th><th>Last changed</th><th>Days</th></tr>\n",
+                escape(section)
+            );
+            for page in pages {
+                let _ = writeln!(
+                    html,
+                    "<tr><td><a href=\"{}\">{}</a></td><td>{}</td><td>{}</td></tr>",
+                    escape(&page.url),
+                    escape(&page.title),
+                    page.last_changed.as_deref().unwrap_or("unknown"),
+                    page.age_days
+                        .map(|days| days.to_string())
+                        .unwrap_or_default()
+                );
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write the report to disk as an HTML page, see
+    /// [`FreshnessReport::to_html`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save_html(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        write_atomic(path.as_ref(), self.to_html().as_bytes())
+    }
+}
+
+/// The section of a page, e.g. `/docs/` for `/docs/install/`.
+fn section(url: &UrlPath) -> String {
+    let mut segments = url.segments();
+    match (segments.next(), segments.next()) {
+        (Some(first), Some(_)) => format!("/{first}/"),
+        (Some(first), None) if url.is_dir() => format!("/{first}/"),
+        _ => "/".to_string(),
+    }
+}
+
+/// The days since the Unix epoch of an ISO 8601 date or datetime.
+fn days(date: &str) -> Option<i64> {
+    let mut parts = date.get(..10)?.splitn(3, '-');
+    let year = parts.next()?.parse().ok()?;
+    let month = parts.next()?.parse().ok()?;
+    let day = parts.next()?.parse().ok()?;
+    ((1..=12).contains(&month) && (1..=31).contains(&day))
+        .then(|| days_from_civil(year, month, day))
+}
+
+/// The committer date of the last commit changing the file, if git is
+/// installed and the file is tracked.
+pub(crate) fn git_lastmod(path: &Path) -> Option<String> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%cI", "--"])
+        .arg(path.file_name()?)
+        .current_dir(
+            path.parent()
+                .filter(|parent| !parent.as_os_str().is_empty())?,
+        )
+        .output()
+        .ok()?;
+    let date = String::from_utf8(output.stdout).ok()?;
+    let date = date.trim();
+    (output.status.success() && !date.is_empty()).then(|| date.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedClock;
+    use std::{env, fs};
+
+    struct Doc {
+        source: PathBuf,
+        url: &'static str,
+        updated: Option<&'static str>,
+    }
+
+    impl Page for Doc {
+        fn title(&self) -> &str {
+            self.url
+        }
+
+        fn url(&self) -> UrlPath {
+            UrlPath::new(self.url).unwrap_or_else(|_| UrlPath::root())
+        }
+
+        fn updated(&self) -> Option<&str> {
+            self.updated
+        }
+    }
+
+    impl HasSource for Doc {
+        fn source(&self) -> &Path {
+            &self.source
+        }
+    }
+
+    #[test]
+    fn test_freshness() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_freshness");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let doc = |url, updated| Doc {
+            source: dir.join("doc.md"),
+            url,
+            updated,
+        };
+        let freshness = Freshness::new(6);
+        let _ = Parsed::from(vec![
+            doc("/", Some("2024-06-01")),
+            doc("/about/", Some("2023-01-15T10:00:00Z")),
+            doc("/docs/", Some("2024-03-31")),
+            doc("/docs/install/", Some("2024-03-30")),
+            doc("/docs/usage/", None),
+            doc("/docs/faq/", Some("2022-12-01")),
+        ])
+        .track_freshness(&freshness);
+
+        // 2024-09-30, so the cutoff is 2024-03-30.
+        let report = freshness.report(&FixedClock::from_unix(1_727_654_400));
+        let sections = report
+            .sections()
+            .map(|(section, pages)| {
+                let urls = pages.iter().map(|page| page.url.as_str()).collect();
+                (section, urls)
+            })
+            .collect::<Vec<(_, Vec<_>)>>();
+        assert_eq!(
+            sections,
+            [
+                ("/about/", vec!["/about/"]),
+                ("/docs/", vec!["/docs/usage/", "/docs/faq/"]),
+            ]
+        );
+        let about = &report.sections().next().ok_or("no section")?.1[0];
+        assert_eq!(about.last_changed.as_deref(), Some("2023-01-15"));
+        assert_eq!(about.age_days, Some(624));
+
+        report.save(dir.join("freshness.json"))?;
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(dir.join("freshness.json"))?)?;
+        assert_eq!(
+            json["sections"]["/docs/"][0]["last_changed"],
+            serde_json::Value::Null
+        );
+        report.save_html(dir.join("freshness.html"))?;
+        let html = fs::read_to_string(dir.join("freshness.html"))?;
+        assert!(html.contains("<a href=\"/docs/faq/\">/docs/faq/</a></td><td>2022-12-01</td>"));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,247 @@
+use exif::{In, Rational, Tag, Value};
+use image::{imageops::FilterType, metadata::Orientation};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader},
+    path::{Path, PathBuf},
+};
+
+use crate::{Error, Glob, HasSource, Parsed};
+
+/// Error type for photo parsing operations.
+#[derive(thiserror::Error, Debug)]
+pub enum GalleryError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// Failed to decode the photo or encode its thumbnail.
+    #[error("image error for {0}: {1}")]
+    Image(PathBuf, image::ImageError),
+    /// The file path has no file stem (filename without extension).
+    #[error("no file stem for: {0}")]
+    NoFileStem(PathBuf),
+}
+
+/// Options for [`Glob::parse_photos`].
+#[derive(Debug, Clone)]
+pub struct GalleryOptions {
+    /// Where to write thumbnails, named after the photo. No thumbnails are
+    /// generated if `None`.
+    pub thumbnail_dir: Option<PathBuf>,
+    /// The width of generated thumbnails in pixels, the height is scaled to
+    /// keep the aspect ratio.
+    pub thumbnail_width: u32,
+    /// Whether to read the GPS position. Off by default, so photos don't
+    /// accidentally reveal where they were taken.
+    pub include_gps: bool,
+}
+
+impl Default for GalleryOptions {
+    fn default() -> Self {
+        Self {
+            thumbnail_dir: None,
+            thumbnail_width: 400,
+            include_gps: false,
+        }
+    }
+}
+
+/// A parsed photo.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Photo {
+    /// The path of the photo.
+    pub path: PathBuf,
+    /// Filename without extension.
+    pub basename: String,
+    /// Width in pixels, after applying the EXIF orientation.
+    pub width: u32,
+    /// Height in pixels, after applying the EXIF orientation.
+    pub height: u32,
+    /// When the photo was taken, as ISO 8601 datetime without time zone.
+    pub taken: Option<String>,
+    /// The camera make and model.
+    pub camera: Option<String>,
+    /// Latitude and longitude in degrees, only read if
+    /// [`GalleryOptions::include_gps`] is set.
+    pub gps: Option<(f64, f64)>,
+    /// The path of the generated thumbnail.
+    pub thumbnail: Option<PathBuf>,
+}
+
+impl Glob {
+    /// Parse the paths as photos, reading their EXIF data and generating
+    /// thumbnails as configured.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any photo cannot be read or decoded, or if a thumbnail cannot be written.
+    pub fn parse_photos(self, options: &GalleryOptions) -> Result<Parsed<Photo>, Error> {
+        self.try_parse(|path| parse_photo(path, options))
+    }
+}
+
+/// Parse a photo at the given path.
+///
+/// # Errors
+///
+/// Returns an error if the photo cannot be read or decoded, or if the thumbnail cannot be written.
+pub fn parse_photo(path: &Path, options: &GalleryOptions) -> Result<Photo, GalleryError> {
+    let basename = path
+        .file_stem()
+        .ok_or_else(|| GalleryError::NoFileStem(path.to_path_buf()))?
+        .to_string_lossy()
+        .to_string();
+
+    let exif = match exif::Reader::new().read_from_container(&mut BufReader::new(File::open(path)?))
+    {
+        Ok(exif) => Some(exif),
+        Err(exif::Error::Io(e)) => return Err(e.into()),
+        // Photos without (valid) EXIF data are fine, they just lack the metadata.
+        Err(_) => None,
+    };
+    let field = |tag| exif.as_ref()?.get_field(tag, In::PRIMARY);
+    let ascii = |tag| match &field(tag)?.value {
+        Value::Ascii(values) => values
+            .first()
+            .map(|value| String::from_utf8_lossy(value).trim().to_string())
+            .filter(|value| !value.is_empty()),
+        _ => None,
+    };
+
+    let taken = field(Tag::DateTimeOriginal)
+        .or_else(|| field(Tag::DateTime))
+        .and_then(|field| match &field.value {
+            Value::Ascii(values) => exif::DateTime::from_ascii(values.first()?).ok(),
+            _ => None,
+        })
+        .map(|dt| {
+            format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                dt.year, dt.month, dt.day, dt.hour, dt.minute, dt.second
+            )
+        });
+    let camera = match (ascii(Tag::Make), ascii(Tag::Model)) {
+        (Some(make), Some(model)) if model.starts_with(&make) => Some(model),
+        (Some(make), Some(model)) => Some(format!("{make} {model}")),
+        (make, model) => make.or(model),
+    };
+    let gps = if options.include_gps {
+        let coordinate = |tag, ref_tag, negative: &str| {
+            let Value::Rational(dms) = &field(tag)?.value else {
+                return None;
+            };
+            let degrees = dms_to_degrees(dms)?;
+            Some(if ascii(ref_tag)? == negative {
+                -degrees
+            } else {
+                degrees
+            })
+        };
+        coordinate(Tag::GPSLatitude, Tag::GPSLatitudeRef, "S").zip(coordinate(
+            Tag::GPSLongitude,
+            Tag::GPSLongitudeRef,
+            "W",
+        ))
+    } else {
+        None
+    };
+    let orientation = field(Tag::Orientation)
+        .and_then(|field| field.value.get_uint(0))
+        .and_then(|value| u8::try_from(value).ok())
+        .and_then(Orientation::from_exif);
+
+    let mut image = image::open(path).map_err(|e| GalleryError::Image(path.to_path_buf(), e))?;
+    if let Some(orientation) = orientation {
+        image.apply_orientation(orientation);
+    }
+
+    let thumbnail = match &options.thumbnail_dir {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            let thumbnail = dir.join(path.file_name().unwrap_or_default());
+            image
+                .resize(options.thumbnail_width, u32::MAX, FilterType::Lanczos3)
+                .save(&thumbnail)
+                .map_err(|e| GalleryError::Image(thumbnail.clone(), e))?;
+            Some(thumbnail)
+        }
+        None => None,
+    };
+
+    Ok(Photo {
+        path: path.to_path_buf(),
+        basename,
+        width: image.width(),
+        height: image.height(),
+        taken,
+        camera,
+        gps,
+        thumbnail,
+    })
+}
+
+/// Convert degrees, minutes and seconds to decimal degrees.
+fn dms_to_degrees(dms: &[Rational]) -> Option<f64> {
+    match dms {
+        [degrees, minutes, seconds, ..] => {
+            Some(degrees.to_f64() + minutes.to_f64() / 60.0 + seconds.to_f64() / 3600.0)
+        }
+        _ => None,
+    }
+}
+
+impl HasSource for Photo {
+    fn source(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_parse_photos() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_parse_photos");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("photos"))?;
+        image::RgbImage::new(800, 600).save(dir.join("photos/beach.png"))?;
+
+        let options = GalleryOptions {
+            thumbnail_dir: Some(dir.join("thumbnails")),
+            thumbnail_width: 200,
+            ..Default::default()
+        };
+        let photos = crate::glob(dir.join("photos/*.png").to_string_lossy())?
+            .parse_photos(&options)?
+            .into_vec();
+
+        let photo = &photos[0];
+        assert_eq!(photo.basename, "beach");
+        assert_eq!((photo.width, photo.height), (800, 600));
+        assert_eq!(photo.taken, None);
+        assert_eq!(photo.thumbnail, Some(dir.join("thumbnails/beach.png")));
+        assert_eq!(
+            image::image_dimensions(dir.join("thumbnails/beach.png"))?,
+            (200, 150)
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_dms_to_degrees() {
+        let dms = [
+            Rational::from((52, 1)),
+            Rational::from((30, 1)),
+            Rational::from((36, 1)),
+        ];
+        assert_eq!(dms_to_degrees(&dms), Some(52.51));
+        assert_eq!(dms_to_degrees(&dms[..2]), None);
+    }
+}
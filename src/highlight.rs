@@ -0,0 +1,220 @@
+use comrak::{
+    adapters::SyntaxHighlighterAdapter,
+    plugins::syntect::{SyntectAdapter, SyntectAdapterBuilder},
+};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    io,
+    sync::{LazyLock, OnceLock},
+};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle},
+    parsing::SyntaxSet,
+};
+
+use crate::MarkdownError;
+
+static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
+
+/// The highlighting used by markdown options that don't set their own, set
+/// with [`configure_highlighting`] or on first use.
+static DEFAULT_HIGHLIGHTING: OnceLock<(Highlighting, SyntectAdapter)> = OnceLock::new();
+
+/// How code fences in markdown are highlighted, see
+/// [`configure_highlighting`] and
+/// [`MarkdownOptions::highlighting`](crate::MarkdownOptions::highlighting).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Highlighting {
+    /// With CSS classes, to be styled with a stylesheet like the one from
+    /// [`highlight_css`].
+    #[default]
+    Css,
+    /// With inline styles from a theme, see [`highlight_css`] for the
+    /// available themes.
+    Theme(String),
+}
+
+impl Highlighting {
+    /// An adapter highlighting like this, with the syntaxes if given or
+    /// else the default ones.
+    pub(crate) fn adapter(
+        &self,
+        syntax_set: Option<SyntaxSet>,
+    ) -> Result<SyntectAdapter, MarkdownError> {
+        let mut builder = SyntectAdapterBuilder::new();
+        builder = match self {
+            Self::Css => builder.css(),
+            Self::Theme(name) if THEME_SET.themes.contains_key(name) => builder.theme(name),
+            Self::Theme(name) => return Err(MarkdownError::UnknownTheme(name.clone())),
+        };
+        if let Some(syntax_set) = syntax_set {
+            builder = builder.syntax_set(syntax_set);
+        }
+        Ok(builder.build())
+    }
+}
+
+/// Set how code fences are highlighted by markdown options that don't set
+/// their own highlighting, e.g. per build profile. Must be called before
+/// the first markdown file is rendered, which fixes the highlighting to
+/// [`Highlighting::Css`] otherwise.
+///
+/// ```no_run
+/// use pichu::Highlighting;
+///
+/// pichu::configure_highlighting(Highlighting::Theme("base16-ocean.dark".to_string()))?;
+/// # Ok::<(), pichu::MarkdownError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the theme doesn't exist or the highlighting was
+/// already fixed.
+pub fn configure_highlighting(highlighting: Highlighting) -> Result<(), MarkdownError> {
+    let adapter = highlighting.adapter(None)?;
+    DEFAULT_HIGHLIGHTING
+        .set((highlighting, adapter))
+        .map_err(|_| MarkdownError::HighlightingConfigured)
+}
+
+/// The highlighting configured with [`configure_highlighting`], if any,
+/// without fixing it.
+pub(crate) fn configured_highlighting() -> Option<&'static Highlighting> {
+    DEFAULT_HIGHLIGHTING
+        .get()
+        .map(|(highlighting, _)| highlighting)
+}
+
+/// The adapter of markdown options without their own highlighting.
+pub(crate) fn default_adapter() -> &'static SyntectAdapter {
+    &DEFAULT_HIGHLIGHTING
+        .get_or_init(|| (Highlighting::Css, SyntectAdapter::new(None)))
+        .1
+}
+
+/// Generate a stylesheet for highlighted code blocks, using the `light`
+/// theme by default and the `dark` theme if the viewer prefers a dark color
+/// scheme. Code blocks are highlighted with CSS classes unless configured
+/// otherwise, so the stylesheet can be written once and linked from the
+/// layout.
+///
+/// Available themes are `InspiredGitHub`, `Solarized (light)`,
+/// `Solarized (dark)`, `base16-ocean.light`, `base16-ocean.dark`,
+/// `base16-eighties.dark` and `base16-mocha.dark`.
+///
+/// ```
+/// let css = pichu::highlight_css("InspiredGitHub", "base16-ocean.dark")?;
+/// assert!(css.contains("@media (prefers-color-scheme: dark)"));
+/// # Ok::<(), pichu::MarkdownError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if either theme doesn't exist.
+pub fn highlight_css(light: &str, dark: &str) -> Result<String, MarkdownError> {
+    let mut css = theme_css(light)?;
+    let _ = write!(
+        css,
+        "\n@media (prefers-color-scheme: dark) {{\n{}}}\n",
+        theme_css(dark)?
+    );
+    Ok(css)
+}
+
+/// The rules of a single theme, with the root rule applying to the `<pre>`
+/// of code blocks.
+fn theme_css(name: &str) -> Result<String, MarkdownError> {
+    let theme = THEME_SET
+        .themes
+        .get(name)
+        .ok_or_else(|| MarkdownError::UnknownTheme(name.to_string()))?;
+    let css = css_for_theme_with_class_style(theme, ClassStyle::Spaced)
+        .map_err(|e| MarkdownError::IO(io::Error::other(e)))?;
+    Ok(css.replacen(".code {", ".syntax-highlighting {", 1))
+}
+
+/// Highlights code fences with syntect, looking up languages registered with
+/// [`MarkdownOptions::syntax_alias`](crate::MarkdownOptions::syntax_alias)
+/// under the language they stand for.
+pub(crate) struct Highlighter<'a> {
+    pub(crate) adapter: &'a SyntectAdapter,
+    pub(crate) aliases: &'a [(String, String)],
+}
+
+impl Highlighter<'_> {
+    fn resolve<'b>(&'b self, lang: Option<&'b str>) -> Option<&'b str> {
+        let lang = lang?;
+        Some(
+            self.aliases
+                .iter()
+                .find(|(alias, _)| alias.eq_ignore_ascii_case(lang))
+                .map_or(lang, |(_, target)| target.as_str()),
+        )
+    }
+}
+
+impl SyntaxHighlighterAdapter for Highlighter<'_> {
+    fn write_highlighted(
+        &self,
+        output: &mut dyn io::Write,
+        lang: Option<&str>,
+        code: &str,
+    ) -> io::Result<()> {
+        self.adapter
+            .write_highlighted(output, self.resolve(lang), code)
+    }
+
+    fn write_pre_tag(
+        &self,
+        output: &mut dyn io::Write,
+        attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        self.adapter.write_pre_tag(output, attributes)
+    }
+
+    fn write_code_tag(
+        &self,
+        output: &mut dyn io::Write,
+        attributes: HashMap<String, String>,
+    ) -> io::Result<()> {
+        self.adapter.write_code_tag(output, attributes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_css() -> Result<(), MarkdownError> {
+        let css = highlight_css("InspiredGitHub", "base16-ocean.dark")?;
+        let (light, dark) = css
+            .split_once("@media (prefers-color-scheme: dark) {")
+            .expect("dark rules");
+        assert!(light
+            .contains(".syntax-highlighting {\n color: #323232;\n background-color: #ffffff;\n}"));
+        assert!(dark
+            .contains(".syntax-highlighting {\n color: #c0c5ce;\n background-color: #2b303b;\n}"));
+        assert!(!css.contains(".code {"));
+        assert!(css.ends_with("}\n}\n"));
+
+        // Markdown rendered by other tests may have fixed it already.
+        let _ = default_adapter();
+        assert!(matches!(
+            configure_highlighting(Highlighting::Css),
+            Err(MarkdownError::HighlightingConfigured)
+        ));
+        assert!(matches!(
+            configure_highlighting(Highlighting::Theme("missing".to_string())),
+            Err(MarkdownError::UnknownTheme(_))
+        ));
+
+        assert!(matches!(
+            highlight_css("InspiredGitHub", "missing"),
+            Err(MarkdownError::UnknownTheme(theme)) if theme == "missing"
+        ));
+        Ok(())
+    }
+}
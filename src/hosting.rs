@@ -0,0 +1,398 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+use crate::{clock::rfc3339, output, Clock, Error, OutputRegistry, Redirects};
+
+/// An error page rendered by [`Host::render_error_pages`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorPage {
+    /// Shown for missing pages, written to `404.html`.
+    NotFound,
+    /// Shown when the server fails, written to `50x.html`.
+    ServerError,
+}
+
+impl ErrorPage {
+    /// The HTTP status code of the page.
+    #[must_use]
+    pub fn status(self) -> u16 {
+        match self {
+            Self::NotFound => 404,
+            Self::ServerError => 500,
+        }
+    }
+
+    /// The file name of the page, relative to the output directory.
+    #[must_use]
+    pub fn file_name(self) -> &'static str {
+        match self {
+            Self::NotFound => "404.html",
+            Self::ServerError => "50x.html",
+        }
+    }
+}
+
+/// Where the site is deployed, deciding how error pages and redirects are
+/// written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Host {
+    /// Netlify, reading `404.html` and `_redirects` from the root.
+    Netlify,
+    /// Cloudflare Pages, reading `404.html` and `_redirects` from the root.
+    CloudflarePages,
+    /// GitHub Pages, reading `404.html` from the root and without redirect
+    /// rules.
+    GitHubPages,
+    /// A self-managed nginx, configured with [`Host::nginx_config`].
+    Nginx,
+}
+
+impl Host {
+    /// The error pages the host can serve.
+    #[must_use]
+    pub fn error_pages(self) -> &'static [ErrorPage] {
+        match self {
+            Self::Netlify | Self::CloudflarePages | Self::GitHubPages => &[ErrorPage::NotFound],
+            Self::Nginx => &[ErrorPage::NotFound, ErrorPage::ServerError],
+        }
+    }
+
+    /// Render every error page the host supports into the output directory,
+    /// e.g. with the same layout as regular pages. Returns the written paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a page cannot be written.
+    pub fn render_error_pages<S: Into<String>>(
+        self,
+        out_dir: impl AsRef<Path>,
+        render_fn: impl Fn(ErrorPage) -> S,
+    ) -> Result<Vec<PathBuf>, Error> {
+        let files = self
+            .error_pages()
+            .iter()
+            .map(|page| {
+                let path = out_dir.as_ref().join(page.file_name());
+                (path, render_fn(*page).into().into_bytes())
+            })
+            .collect::<Vec<_>>();
+        let paths = files.iter().map(|(path, _)| path.clone()).collect();
+        output::write_batch(files)?;
+        Ok(paths)
+    }
+
+    /// Write the redirects the way the host supports them: a `_redirects`
+    /// file for Netlify and Cloudflare Pages, HTML stubs for GitHub Pages.
+    /// Nothing is written for nginx, see [`Host::nginx_config`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a redirect collides with a page or cannot be
+    /// written.
+    pub fn write_redirects(
+        self,
+        redirects: &Redirects,
+        out_dir: impl AsRef<Path>,
+        registry: &OutputRegistry,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Netlify | Self::CloudflarePages => {
+                let path = out_dir.as_ref().join("_redirects");
+                registry.claim(&path, "_redirects")?;
+                crate::write(path, redirects.to_redirects_file())?;
+            }
+            Self::GitHubPages => redirects.write_stubs(out_dir, registry)?,
+            Self::Nginx => {}
+        }
+        Ok(())
+    }
+
+    /// An nginx configuration snippet with `error_page` directives and
+    /// redirects, to be included in the site's `server` block. Returns
+    /// `None` for other hosts.
+    #[must_use]
+    pub fn nginx_config(self, redirects: &Redirects) -> Option<String> {
+        if self != Self::Nginx {
+            return None;
+        }
+        let mut config =
+            String::from("error_page 404 /404.html;\nerror_page 500 502 503 504 /50x.html;\n");
+        for (from, redirect) in redirects.entries() {
+            let _ = writeln!(
+                config,
+                "location = {from} {{ return 301 {}; }}",
+                redirect.to
+            );
+        }
+        Some(config)
+    }
+}
+
+/// Facts about how the site was built, for "built with" pages and
+/// [`Humans`]. Serialize it into template contexts, so a colophon stays
+/// accurate without manual updates.
+///
+/// ```no_run
+/// use pichu::{Colophon, SystemClock};
+///
+/// let colophon = Colophon::new(&SystemClock).lockfile("Cargo.lock")?;
+/// println!("Built with pichu {}", colophon.pichu_version);
+/// println!("and maud {:?}", colophon.dependencies.get("maud"));
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Colophon {
+    /// When the site was built, as an RFC 3339 datetime in UTC.
+    pub built_at: String,
+    /// The version of pichu the site was built with.
+    pub pichu_version: String,
+    /// The versions of the site's dependencies by crate name, see
+    /// [`Colophon::lockfile`].
+    pub dependencies: BTreeMap<String, String>,
+}
+
+impl Colophon {
+    /// A colophon for a build at the clock's current time, without
+    /// dependencies.
+    #[must_use]
+    pub fn new(clock: &impl Clock) -> Self {
+        let secs = clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        Self {
+            built_at: rfc3339(i64::try_from(secs).unwrap_or(i64::MAX)),
+            pichu_version: env!("CARGO_PKG_VERSION").to_string(),
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    /// Add a dependency's version, e.g. of a template engine.
+    #[must_use]
+    pub fn dependency(mut self, name: impl Into<String>, version: impl Into<String>) -> Self {
+        self.dependencies.insert(name.into(), version.into());
+        self
+    }
+
+    /// Add the versions of all packages in the site's `Cargo.lock`. If a
+    /// package is locked in several versions, the last listed wins, which
+    /// is the highest in lockfiles written by Cargo.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the lockfile cannot be read.
+    pub fn lockfile(mut self, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let lockfile = fs::read_to_string(path)?;
+        for package in lockfile.split("[[package]]").skip(1) {
+            let field = |key: &str| {
+                package.lines().find_map(|line| {
+                    let value = line.strip_prefix(key)?.trim_start().strip_prefix('=')?;
+                    Some(value.trim().trim_matches('"').to_string())
+                })
+            };
+            if let (Some(name), Some(version)) = (field("name"), field("version")) {
+                self.dependencies.insert(name, version);
+            }
+        }
+        Ok(self)
+    }
+}
+
+/// A person in [`Humans`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Human {
+    /// The person's name.
+    pub name: String,
+    /// What the person did, e.g. `Developer`.
+    pub role: Option<String>,
+    /// How to reach the person, e.g. an email address or website.
+    pub contact: Option<String>,
+    /// Where the person is, e.g. `Berlin, Germany`.
+    pub location: Option<String>,
+}
+
+/// The people behind the site and the technology it uses, written as
+/// `humans.txt`, see <https://humanstxt.org>. Deserialize it from the site's
+/// config, or build it in code.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Humans {
+    /// The people who made the site.
+    pub team: Vec<Human>,
+    /// People to thank.
+    pub thanks: Vec<String>,
+    /// The languages of the site, e.g. `English`.
+    pub language: Vec<String>,
+    /// The standards the site uses, e.g. `HTML5`.
+    pub standards: Vec<String>,
+    /// Crates to list with their version from the [`Colophon`], after
+    /// pichu itself.
+    pub components: Vec<String>,
+    /// Other software used to make the site, e.g. an editor.
+    pub software: Vec<String>,
+}
+
+impl Humans {
+    /// The contents of `humans.txt`, with the last update, pichu's version
+    /// and the components' versions taken from the colophon.
+    #[must_use]
+    pub fn render(&self, colophon: &Colophon) -> String {
+        let mut txt = String::new();
+        if !self.team.is_empty() {
+            txt.push_str("/* TEAM */\n");
+            for human in &self.team {
+                let _ = writeln!(txt, "Name: {}", human.name);
+                for (label, value) in [
+                    ("Role", &human.role),
+                    ("Contact", &human.contact),
+                    ("Location", &human.location),
+                ] {
+                    if let Some(value) = value {
+                        let _ = writeln!(txt, "{label}: {value}");
+                    }
+                }
+                txt.push('\n');
+            }
+        }
+        if !self.thanks.is_empty() {
+            txt.push_str("/* THANKS */\n");
+            for name in &self.thanks {
+                let _ = writeln!(txt, "Name: {name}");
+            }
+            txt.push('\n');
+        }
+
+        txt.push_str("/* SITE */\n");
+        let date = colophon.built_at.get(..10).unwrap_or_default();
+        let _ = writeln!(txt, "Last update: {}", date.replace('-', "/"));
+        let components = std::iter::once(format!("pichu {}", colophon.pichu_version))
+            .chain(
+                self.components
+                    .iter()
+                    .map(|name| match colophon.dependencies.get(name) {
+                        Some(version) => format!("{name} {version}"),
+                        None => name.clone(),
+                    }),
+            )
+            .collect::<Vec<_>>();
+        for (label, values) in [
+            ("Language", &self.language),
+            ("Standards", &self.standards),
+            ("Components", &components),
+            ("Software", &self.software),
+        ] {
+            if !values.is_empty() {
+                let _ = writeln!(txt, "{label}: {}", values.join(", "));
+            }
+        }
+        txt
+    }
+
+    /// Write `humans.txt` to the output directory, claiming it in the
+    /// registry. Returns the written path.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file collides with another output or cannot
+    /// be written.
+    pub fn write(
+        &self,
+        colophon: &Colophon,
+        out_dir: impl AsRef<Path>,
+        registry: &OutputRegistry,
+    ) -> Result<PathBuf, Error> {
+        let path = out_dir.as_ref().join("humans.txt");
+        registry.claim(&path, "humans.txt")?;
+        crate::write(&path, self.render(colophon))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Redirect, UrlPath};
+    use std::{env, fs};
+
+    #[test]
+    fn test_hosts() -> Result<(), Error> {
+        let out_dir = env::temp_dir().join("pichu_test_hosting");
+        let render = |page: ErrorPage| format!("<h1>{}</h1>", page.status());
+
+        let paths = Host::GitHubPages.render_error_pages(&out_dir, render)?;
+        assert_eq!(paths, [out_dir.join("404.html")]);
+        Host::Nginx.render_error_pages(&out_dir, render)?;
+        assert_eq!(
+            fs::read_to_string(out_dir.join("50x.html"))?,
+            "<h1>500</h1>"
+        );
+
+        let redirects = Redirects::new();
+        redirects.add(
+            UrlPath::new("/old/")?,
+            Redirect {
+                to: UrlPath::new("/new/")?,
+                source: None,
+            },
+        )?;
+        let registry = OutputRegistry::new();
+        Host::Netlify.write_redirects(&redirects, &out_dir, &registry)?;
+        assert_eq!(
+            fs::read_to_string(out_dir.join("_redirects"))?,
+            "/old/ /new/ 301\n"
+        );
+        Host::GitHubPages.write_redirects(&redirects, &out_dir, &registry)?;
+        assert!(out_dir.join("old/index.html").exists());
+
+        let config = Host::Nginx.nginx_config(&redirects).unwrap_or_default();
+        assert!(config.contains("error_page 500 502 503 504 /50x.html;"));
+        assert!(config.contains("location = /old/ { return 301 /new/; }"));
+        assert_eq!(Host::Netlify.nginx_config(&redirects), None);
+
+        fs::remove_dir_all(out_dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_humans() -> Result<(), Error> {
+        let dir = env::temp_dir().join("pichu_test_humans");
+        crate::write(
+            dir.join("Cargo.lock"),
+            "version = 4\n\n[[package]]\nname = \"maud\"\nversion = \"0.27.0\"\nsource = \"registry+https://github.com/rust-lang/crates.io-index\"\n",
+        )?;
+        let colophon = Colophon::new(&crate::FixedClock::from_unix(1_714_557_600))
+            .lockfile(dir.join("Cargo.lock"))?;
+        assert_eq!(colophon.built_at, "2024-05-01T10:00:00Z");
+        assert_eq!(colophon.dependencies["maud"], "0.27.0");
+
+        let humans = Humans {
+            team: vec![Human {
+                name: "Arne".to_string(),
+                role: Some("Developer".to_string()),
+                ..Default::default()
+            }],
+            language: vec!["English".to_string()],
+            components: vec!["maud".to_string()],
+            ..Default::default()
+        };
+        humans.write(&colophon, &dir, &OutputRegistry::new())?;
+        assert_eq!(
+            fs::read_to_string(dir.join("humans.txt"))?,
+            format!(
+                "/* TEAM */\nName: Arne\nRole: Developer\n\n/* SITE */\nLast update: 2024/05/01\n\
+                 Language: English\nComponents: pichu {}, maud 0.27.0\n",
+                env!("CARGO_PKG_VERSION")
+            )
+        );
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,131 @@
+/// Escape text for use in HTML element content and quoted attribute values.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Unescape the entities [`escape`] produces, plus `&#x27;`.
+pub(crate) fn unescape(text: &str) -> String {
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The start tags in a document, with their lowercase names and attributes.
+/// Comments are skipped.
+pub(crate) fn tags(html: &str) -> Vec<(String, Vec<(&str, &str)>)> {
+    elements(html)
+        .into_iter()
+        .map(|(name, attributes, _)| (name, attributes))
+        .collect()
+}
+
+/// A start tag with its lowercase name, its attributes and the raw text
+/// following it up to the next tag.
+pub(crate) type Element<'a> = (String, Vec<(&'a str, &'a str)>, &'a str);
+
+/// Like [`tags`], with the raw text following each tag up to the next one.
+pub(crate) fn elements(html: &str) -> Vec<Element<'_>> {
+    let mut tags = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(comment) = rest.strip_prefix("!--") {
+            rest = comment.find("-->").map_or("", |end| &comment[end + 3..]);
+            continue;
+        }
+
+        let name_len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '-'))
+            .unwrap_or(rest.len());
+        if name_len == 0 || !rest.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            continue;
+        }
+        let name = rest[..name_len].to_ascii_lowercase();
+        rest = &rest[name_len..];
+
+        let tag_end = tag_end(rest);
+        let attributes = attributes(&rest[..tag_end]);
+        rest = &rest[tag_end..];
+        let text = &rest[..rest.find('<').unwrap_or(rest.len())];
+        tags.push((name, attributes, text));
+    }
+    tags
+}
+
+/// The length of the tag's attribute section up to and including `>`.
+pub(crate) fn tag_end(tag: &str) -> usize {
+    let mut quote = None;
+    for (i, c) in tag.char_indices() {
+        match (quote, c) {
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), c) if q == c => quote = None,
+            (None, '>') => return i + 1,
+            _ => {}
+        }
+    }
+    tag.len()
+}
+
+/// Parse `name="value"` pairs from the attribute section of a tag.
+pub(crate) fn attributes(mut attrs: &str) -> Vec<(&str, &str)> {
+    let mut result = Vec::new();
+    loop {
+        attrs = attrs.trim_start_matches(|c: char| c.is_whitespace() || c == '/' || c == '>');
+        let name_len = attrs
+            .find(|c: char| c.is_whitespace() || c == '=' || c == '>' || c == '/')
+            .unwrap_or(attrs.len());
+        if name_len == 0 {
+            return result;
+        }
+        let name = &attrs[..name_len];
+        attrs = attrs[name_len..].trim_start();
+        let Some(value) = attrs.strip_prefix('=') else {
+            result.push((name, ""));
+            continue;
+        };
+        let value = value.trim_start();
+        let (value, rest) = if let Some(quote @ ('"' | '\'')) = value.chars().next() {
+            let inner = &value[1..];
+            let end = inner.find(quote).unwrap_or(inner.len());
+            (&inner[..end], inner.get(end + 1..).unwrap_or_default())
+        } else {
+            let end = value
+                .find(|c: char| c.is_whitespace() || c == '>')
+                .unwrap_or(value.len());
+            (&value[..end], &value[end..])
+        };
+        result.push((name, value));
+        attrs = rest;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(
+            escape(r#"<a href="x">Tom & Jerry's</a>"#),
+            "&lt;a href=&quot;x&quot;&gt;Tom &amp; Jerry&#39;s&lt;/a&gt;"
+        );
+        assert_eq!(
+            unescape(&escape(r#"<a href="x">Tom & Jerry's</a> &amp;"#)),
+            r#"<a href="x">Tom & Jerry's</a> &amp;"#
+        );
+    }
+}
@@ -0,0 +1,97 @@
+use comrak::nodes::{AstNode, NodeValue};
+use std::{
+    fmt::Write,
+    path::{Path, PathBuf},
+};
+
+use crate::{html::escape, url_path::percent_decode};
+
+/// Where local images referenced from markdown are looked up, see
+/// [`MarkdownOptions::probe_images`](crate::MarkdownOptions::probe_images).
+#[derive(Debug, Clone)]
+pub(crate) struct ImageProbe {
+    /// The directory URLs starting with `/` are resolved against.
+    pub(crate) root: PathBuf,
+    /// Whether to add the average color as a placeholder background.
+    #[cfg(feature = "images")]
+    pub(crate) placeholder: bool,
+}
+
+impl ImageProbe {
+    /// Replace the image with an `<img>` tag including its dimensions, if
+    /// it's a local file that can be read. Relative URLs are resolved
+    /// against `dir`, the directory of the markdown file.
+    pub(crate) fn apply<'a>(&self, node: &'a AstNode<'a>, dir: &Path) {
+        let tag = {
+            let ast = node.data.borrow();
+            let NodeValue::Image(link) = &ast.value else {
+                return;
+            };
+            let Some(path) = self.resolve(&link.url, dir) else {
+                return;
+            };
+            let Ok(size) = imagesize::size(&path) else {
+                return;
+            };
+
+            let mut alt = String::new();
+            for descendant in node.descendants().skip(1) {
+                match &descendant.data.borrow().value {
+                    NodeValue::Text(text)
+                    | NodeValue::Code(comrak::nodes::NodeCode { literal: text, .. }) => {
+                        alt.push_str(text);
+                    }
+                    _ => {}
+                }
+            }
+
+            let mut tag = format!(
+                "<img src=\"{}\" alt=\"{}\"",
+                escape(&link.url),
+                escape(&alt)
+            );
+            if !link.title.is_empty() {
+                let _ = write!(tag, " title=\"{}\"", escape(&link.title));
+            }
+            let _ = write!(tag, " width=\"{}\" height=\"{}\"", size.width, size.height);
+            #[cfg(feature = "images")]
+            if self.placeholder {
+                if let Some(color) = average_color(&path) {
+                    let _ = write!(tag, " style=\"background-color:{color}\"");
+                }
+            }
+            tag.push_str(" />");
+            tag
+        };
+
+        node.data.borrow_mut().value = NodeValue::HtmlInline(tag);
+        while let Some(child) = node.first_child() {
+            child.detach();
+        }
+    }
+
+    /// The file a local image URL points to.
+    fn resolve(&self, url: &str, dir: &Path) -> Option<PathBuf> {
+        if url.contains("://") || url.starts_with("//") || url.starts_with("data:") {
+            return None;
+        }
+        let path = url.split(['?', '#']).next()?;
+        let path = percent_decode(path)?;
+        match path.strip_prefix('/') {
+            Some(path) => Some(self.root.join(path)),
+            None => Some(dir.join(path)),
+        }
+    }
+}
+
+/// The average color of the image as `#rrggbb`, a cheap stand-in for the
+/// dominant color while the image loads.
+#[cfg(feature = "images")]
+fn average_color(path: &Path) -> Option<String> {
+    let image = image::open(path).ok()?;
+    let pixel = image
+        .resize_exact(1, 1, image::imageops::FilterType::Triangle)
+        .to_rgb8();
+    let [r, g, b] = pixel.get_pixel(0, 0).0;
+    Some(format!("#{r:02x}{g:02x}{b:02x}"))
+}
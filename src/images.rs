@@ -0,0 +1,467 @@
+use image::{imageops::FilterType, DynamicImage};
+use std::{
+    collections::HashMap,
+    fmt::Write,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::{
+    html::{attributes, escape, tag_end, unescape},
+    sync::lock,
+    workspace::persist,
+};
+
+/// Error type for image processing operations.
+#[derive(thiserror::Error, Debug)]
+pub enum ImageError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// Failed to decode or encode an image.
+    #[error("image error for {0}: {1}")]
+    Image(PathBuf, image::ImageError),
+}
+
+/// A modern format [`Images`] can generate variants in, next to the format
+/// of the source image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ImageFormat {
+    /// WebP, encoded losslessly.
+    WebP,
+    /// AVIF, smaller than WebP but much slower to encode.
+    #[cfg(feature = "avif")]
+    Avif,
+}
+
+impl ImageFormat {
+    /// The file extension of the format.
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::WebP => "webp",
+            #[cfg(feature = "avif")]
+            Self::Avif => "avif",
+        }
+    }
+
+    /// The MIME type of the format, for the `type` of `<source>` elements.
+    #[must_use]
+    pub fn mime(self) -> &'static str {
+        match self {
+            Self::WebP => "image/webp",
+            #[cfg(feature = "avif")]
+            Self::Avif => "image/avif",
+        }
+    }
+}
+
+/// Options for [`Images`].
+#[derive(Debug, Clone)]
+pub struct ImageOptions {
+    /// The directory source images are looked up in.
+    pub source_dir: PathBuf,
+    /// The directory variants are written to.
+    pub out_dir: PathBuf,
+    /// The URL `out_dir` is served at, e.g. `/images`.
+    pub url_prefix: String,
+    /// The widths to generate variants for. Widths larger than the source
+    /// are skipped, the source width is always included.
+    pub widths: Vec<u32>,
+    /// Modern formats to also generate every variant in, most preferred
+    /// first. [`Images::picture`] offers them before the source format, so
+    /// browsers without support fall back to it.
+    pub formats: Vec<ImageFormat>,
+}
+
+impl Default for ImageOptions {
+    fn default() -> Self {
+        Self {
+            source_dir: PathBuf::from("images"),
+            out_dir: PathBuf::from("dist/images"),
+            url_prefix: "/images".to_string(),
+            widths: vec![480, 960, 1440, 1920],
+            formats: Vec::new(),
+        }
+    }
+}
+
+/// An image resized to multiple widths.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessedImage {
+    /// The width of the source image in pixels.
+    pub width: u32,
+    /// The height of the source image in pixels.
+    pub height: u32,
+    /// The generated variants as `(width, url)` pairs, ascending by width.
+    pub variants: Vec<(u32, String)>,
+    /// The variants in each of [`ImageOptions::formats`], like `variants`.
+    pub formats: Vec<(ImageFormat, Vec<(u32, String)>)>,
+}
+
+impl ProcessedImage {
+    /// The `srcset` attribute value listing all variants.
+    #[must_use]
+    pub fn srcset(&self) -> String {
+        srcset(&self.variants)
+    }
+
+    /// The `srcset` attribute value listing all variants in a modern
+    /// format, if they were generated.
+    #[must_use]
+    pub fn format_srcset(&self, format: ImageFormat) -> Option<String> {
+        self.formats
+            .iter()
+            .find(|(f, _)| *f == format)
+            .map(|(_, variants)| srcset(variants))
+    }
+
+    /// The URL of the largest variant.
+    #[must_use]
+    pub fn src(&self) -> &str {
+        self.variants.last().map_or("", |(_, url)| url)
+    }
+}
+
+/// Resizes images into responsive variants and renders the HTML referencing
+/// them. Processed images are cached, so it's cheap to call
+/// [`Images::picture`] for the same image from many render functions.
+#[derive(Debug)]
+pub struct Images {
+    options: ImageOptions,
+    processed: Mutex<HashMap<String, Arc<ProcessedImage>>>,
+}
+
+impl Images {
+    /// Create an image pipeline with the given options.
+    #[must_use]
+    pub fn new(options: ImageOptions) -> Self {
+        Self {
+            options,
+            processed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resize the image `name`, relative to the source directory, into the
+    /// configured widths. Variants newer than the source are reused.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be read, decoded or written.
+    pub fn process(&self, name: &str) -> Result<Arc<ProcessedImage>, ImageError> {
+        if let Some(processed) = lock(&self.processed).get(name) {
+            return Ok(Arc::clone(processed));
+        }
+
+        let source = self.options.source_dir.join(name);
+        let (width, height) =
+            image::image_dimensions(&source).map_err(|e| ImageError::Image(source.clone(), e))?;
+        let source_modified = fs::metadata(&source)?.modified()?;
+
+        let mut widths = self
+            .options
+            .widths
+            .iter()
+            .copied()
+            .filter(|w| *w < width)
+            .collect::<Vec<_>>();
+        widths.push(width);
+        widths.sort_unstable();
+        widths.dedup();
+
+        let (dir, stem, extension) = split_name(name);
+        let out_dir = self.options.out_dir.join(dir);
+        fs::create_dir_all(&out_dir)?;
+
+        let mut image = None;
+        // Variants are encoded in a workspace and moved into place, so an
+        // interrupted build never leaves a truncated variant that looks fresh.
+        let mut workspace = None;
+        let mut variants = Vec::with_capacity(widths.len());
+        let mut formats = self
+            .options
+            .formats
+            .iter()
+            .map(|format| (*format, Vec::with_capacity(widths.len())))
+            .collect::<Vec<_>>();
+        for variant_width in widths {
+            let files = std::iter::once((None, extension))
+                .chain(
+                    self.options
+                        .formats
+                        .iter()
+                        .map(|format| (Some(*format), format.extension())),
+                )
+                .map(|(format, extension)| (format, format!("{stem}-{variant_width}.{extension}")))
+                .collect::<Vec<_>>();
+            let stale = files
+                .iter()
+                .filter(|(_, file_name)| !is_fresh(&out_dir.join(file_name), source_modified))
+                .collect::<Vec<_>>();
+            if !stale.is_empty() {
+                if image.is_none() {
+                    image = Some(
+                        image::open(&source).map_err(|e| ImageError::Image(source.clone(), e))?,
+                    );
+                }
+                if workspace.is_none() {
+                    workspace = Some(crate::workspace()?);
+                }
+                if let (Some(image), Some(workspace)) = (&image, &workspace) {
+                    let resized = image.resize(variant_width, u32::MAX, FilterType::Lanczos3);
+                    for (format, file_name) in stale {
+                        let tmp = workspace.path().join(file_name);
+                        save(&resized, *format, &tmp)
+                            .map_err(|e| ImageError::Image(out_dir.join(file_name), e))?;
+                        persist(&tmp, &out_dir.join(file_name))?;
+                    }
+                }
+            }
+
+            for (format, file_name) in files {
+                let variant = (variant_width, self.url(dir, &file_name));
+                match formats.iter_mut().find(|(f, _)| Some(*f) == format) {
+                    Some((_, variants)) => variants.push(variant),
+                    None => variants.push(variant),
+                }
+            }
+        }
+
+        let processed = Arc::new(ProcessedImage {
+            width,
+            height,
+            variants,
+            formats,
+        });
+        lock(&self.processed).insert(name.to_string(), Arc::clone(&processed));
+        Ok(processed)
+    }
+
+    /// Process the image `name` and render a `<picture>` element with a
+    /// `srcset` of all variants and `width`/`height` attributes, so browsers
+    /// can reserve space before the image loads.
+    /// `sizes` is the value of the `sizes` attribute, e.g.
+    /// `(min-width: 60rem) 60rem, 100vw`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the image cannot be processed.
+    pub fn picture(&self, name: &str, alt: &str, sizes: &str) -> Result<String, ImageError> {
+        let image = self.process(name)?;
+        let mut html = String::from("<picture>");
+        for (format, variants) in &image.formats {
+            let _ = write!(
+                html,
+                r#"<source type="{}" srcset="{}" sizes="{}">"#,
+                format.mime(),
+                escape(&srcset(variants)),
+                escape(sizes),
+            );
+        }
+        let _ = write!(
+            html,
+            r#"<img src="{}" srcset="{}" sizes="{}" width="{}" height="{}" alt="{}" loading="lazy" decoding="async">"#,
+            escape(image.src()),
+            escape(&image.srcset()),
+            escape(sizes),
+            image.width,
+            image.height,
+            escape(alt),
+        );
+        html.push_str("</picture>");
+        Ok(html)
+    }
+
+    /// Rewrite plain `<img>` tags, e.g. in rendered markdown, into
+    /// [`Images::picture`] elements with the same `alt` text, so existing
+    /// content gets responsive variants and modern formats without edits.
+    /// The `src` is looked up in the source directory after removing the
+    /// URL prefix, so `/images/posts/hero.jpg` and `posts/hero.jpg` both
+    /// refer to `posts/hero.jpg`. Images that aren't found, are external or
+    /// already are in a `<picture>` are left alone.
+    ///
+    /// ```no_run
+    /// use pichu::{ImageFormat, ImageOptions, Images};
+    ///
+    /// let images = Images::new(ImageOptions {
+    ///     formats: vec![ImageFormat::WebP],
+    ///     ..Default::default()
+    /// });
+    /// let html = images.rewrite_img_tags(r#"<p><img src="/images/hero.jpg" alt="Hero"></p>"#, "100vw")?;
+    /// # Ok::<(), pichu::ImageError>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an image cannot be processed.
+    pub fn rewrite_img_tags(&self, html: &str, sizes: &str) -> Result<String, ImageError> {
+        let mut rewritten = String::with_capacity(html.len());
+        let mut copied = 0;
+        let mut search = 0;
+        while let Some(offset) = html[search..].find("<img") {
+            let attrs_start = search + offset + "<img".len();
+            let attrs = &html[attrs_start..];
+            if !attrs.starts_with(char::is_whitespace) {
+                search = attrs_start;
+                continue;
+            }
+            let end = attrs_start + tag_end(attrs);
+            search = end;
+
+            let before = &html[..attrs_start];
+            let in_picture = before
+                .rfind("<picture")
+                .is_some_and(|open| before.rfind("</picture>").is_none_or(|close| close < open));
+            if in_picture {
+                continue;
+            }
+            let attributes = attributes(&html[attrs_start..end]);
+            let attribute = |name: &str| {
+                attributes
+                    .iter()
+                    .find(|(n, _)| n.eq_ignore_ascii_case(name))
+                    .map(|(_, value)| unescape(value))
+            };
+            let Some(name) = attribute("src").and_then(|src| self.source_name(&src)) else {
+                continue;
+            };
+            let alt = attribute("alt").unwrap_or_default();
+
+            rewritten.push_str(&html[copied..attrs_start - "<img".len()]);
+            rewritten.push_str(&self.picture(&name, &alt, sizes)?);
+            copied = end;
+        }
+        rewritten.push_str(&html[copied..]);
+        Ok(rewritten)
+    }
+
+    /// The name of the source image an `<img>` `src` refers to, if it exists.
+    fn source_name(&self, src: &str) -> Option<String> {
+        if src.starts_with("//") || src.contains(':') {
+            return None;
+        }
+        let path = src.split(['?', '#']).next().unwrap_or_default();
+        let prefix = format!("{}/", self.options.url_prefix.trim_end_matches('/'));
+        let name = path
+            .strip_prefix(&prefix)
+            .unwrap_or_else(|| path.trim_start_matches('/'));
+        self.options
+            .source_dir
+            .join(name)
+            .is_file()
+            .then(|| name.to_string())
+    }
+
+    fn url(&self, dir: &str, file_name: &str) -> String {
+        let prefix = self.options.url_prefix.trim_end_matches('/');
+        if dir.is_empty() {
+            format!("{prefix}/{file_name}")
+        } else {
+            format!("{prefix}/{dir}/{file_name}")
+        }
+    }
+}
+
+fn srcset(variants: &[(u32, String)]) -> String {
+    variants
+        .iter()
+        .map(|(width, url)| format!("{url} {width}w"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Whether the variant at `path` was written after the source changed.
+fn is_fresh(path: &Path, source_modified: SystemTime) -> bool {
+    fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .is_ok_and(|modified| modified >= source_modified)
+}
+
+/// Save a variant in its format, or the source format for `None`.
+fn save(
+    image: &DynamicImage,
+    format: Option<ImageFormat>,
+    path: &Path,
+) -> Result<(), image::ImageError> {
+    match format {
+        None => image.save(path),
+        // Modern encoders only take 8-bit images.
+        Some(_) if image.color().has_alpha() => DynamicImage::from(image.to_rgba8()).save(path),
+        Some(_) => DynamicImage::from(image.to_rgb8()).save(path),
+    }
+}
+
+/// Split an image name like `posts/hero.jpg` into directory, stem and extension.
+fn split_name(name: &str) -> (&str, &str, &str) {
+    let (dir, file_name) = name.rsplit_once('/').unwrap_or(("", name));
+    let (stem, extension) = Path::new(file_name)
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .zip(
+            Path::new(file_name)
+                .extension()
+                .and_then(|ext| ext.to_str()),
+        )
+        .unwrap_or((file_name, ""));
+    (dir, stem, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_picture() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_images_picture");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("images/posts"))?;
+        image::RgbImage::new(1000, 500).save(dir.join("images/posts/hero.png"))?;
+
+        let images = Images::new(ImageOptions {
+            source_dir: dir.join("images"),
+            out_dir: dir.join("dist/images"),
+            widths: vec![480, 960, 1920],
+            ..Default::default()
+        });
+        let html = images.picture("posts/hero.png", "A \"hero\"", "100vw")?;
+
+        assert_eq!(
+            html,
+            r#"<picture><img src="/images/posts/hero-1000.png" srcset="/images/posts/hero-480.png 480w, /images/posts/hero-960.png 960w, /images/posts/hero-1000.png 1000w" sizes="100vw" width="1000" height="500" alt="A &quot;hero&quot;" loading="lazy" decoding="async"></picture>"#
+        );
+        assert_eq!(
+            image::image_dimensions(dir.join("dist/images/posts/hero-480.png"))?,
+            (480, 240)
+        );
+        assert!(!dir.join("dist/images/posts/hero-1920.png").exists());
+
+        let images = Images::new(ImageOptions {
+            source_dir: dir.join("images"),
+            out_dir: dir.join("dist/images"),
+            widths: vec![480],
+            formats: vec![ImageFormat::WebP],
+            ..Default::default()
+        });
+        let html = images.rewrite_img_tags(
+            r#"<p><img src="/images/posts/hero.png" alt="Hero"> <img src="/missing.png"></p><picture><img src="posts/hero.png"></picture>"#,
+            "100vw",
+        )?;
+        assert_eq!(
+            html,
+            r#"<p><picture><source type="image/webp" srcset="/images/posts/hero-480.webp 480w, /images/posts/hero-1000.webp 1000w" sizes="100vw"><img src="/images/posts/hero-1000.png" srcset="/images/posts/hero-480.png 480w, /images/posts/hero-1000.png 1000w" sizes="100vw" width="1000" height="500" alt="Hero" loading="lazy" decoding="async"></picture> <img src="/missing.png"></p><picture><img src="posts/hero.png"></picture>"#
+        );
+        assert_eq!(
+            image::image_dimensions(dir.join("dist/images/posts/hero-480.webp"))?,
+            (480, 240)
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
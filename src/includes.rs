@@ -0,0 +1,126 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{collection::absolute, markdown::split_frontmatter, MarkdownError};
+
+/// Replace `{{ include "path" }}` lines with the contents of the file at
+/// `path`, relative to `root`, recursively. Lines in fenced code blocks are
+/// left alone, so the directive can be documented.
+/// Returns the expanded markdown and the included files, in the order they
+/// were first included.
+pub(crate) fn expand_includes(
+    content: &str,
+    root: &Path,
+    source: &Path,
+) -> Result<(String, Vec<PathBuf>), MarkdownError> {
+    let mut included = Vec::new();
+    let expanded = expand(content, root, &mut vec![absolute(source)], &mut included)?;
+    Ok((expanded, included))
+}
+
+fn expand(
+    content: &str,
+    root: &Path,
+    stack: &mut Vec<PathBuf>,
+    included: &mut Vec<PathBuf>,
+) -> Result<String, MarkdownError> {
+    let mut expanded = String::with_capacity(content.len());
+    let mut fence: Option<&str> = None;
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) && trimmed.trim_start_matches(marker).is_empty() {
+                fence = None;
+            }
+        } else if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            let marker = &trimmed[..trimmed.len() - trimmed.trim_start_matches(['`', '~']).len()];
+            fence = Some(marker);
+        } else if let Some(include) = directive(trimmed) {
+            let path = root.join(include);
+            let key = absolute(&path);
+            if stack.contains(&key) {
+                let mut cycle = stack.clone();
+                cycle.push(key);
+                return Err(MarkdownError::IncludeCycle(cycle));
+            }
+            let contents =
+                fs::read_to_string(&path).map_err(|e| MarkdownError::Include(path.clone(), e))?;
+            let body = split_frontmatter(&contents).map_or(contents.as_str(), |(_, body)| body);
+
+            if !included.contains(&path) {
+                included.push(path);
+            }
+            stack.push(key);
+            let body = expand(body, root, stack, included)?;
+            stack.pop();
+
+            expanded.push_str(&body);
+            if !body.ends_with('\n') {
+                expanded.push('\n');
+            }
+            continue;
+        }
+        expanded.push_str(line);
+    }
+    Ok(expanded)
+}
+
+/// The path of an `{{ include "path" }}` directive.
+fn directive(line: &str) -> Option<&str> {
+    let inner = line.strip_prefix("{{")?.strip_suffix("}}")?.trim();
+    let path = inner.strip_prefix("include")?.trim_start();
+    path.strip_prefix('"')?.strip_suffix('"')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_expand_includes() -> Result<(), Box<dyn std::error::Error>> {
+        let root = env::temp_dir().join("pichu_test_expand_includes");
+        if root.exists() {
+            fs::remove_dir_all(&root)?;
+        }
+        fs::create_dir_all(root.join("shared"))?;
+        fs::write(
+            root.join("shared/warning.md"),
+            "---\ntitle: ignored\n---\n> **Warning**\n{{ include \"shared/footer.md\" }}\n",
+        )?;
+        fs::write(root.join("shared/footer.md"), "Footer")?;
+
+        let content = "# Post\n\n{{include \"shared/warning.md\"}}\n\n```\n{{ include \"shared/footer.md\" }}\n```\n";
+        let (expanded, included) = expand_includes(content, &root, &root.join("post.md"))?;
+        assert_eq!(
+            expanded,
+            "# Post\n\n> **Warning**\nFooter\n\n```\n{{ include \"shared/footer.md\" }}\n```\n"
+        );
+        assert_eq!(
+            included,
+            vec![
+                root.join("shared/warning.md"),
+                root.join("shared/footer.md")
+            ]
+        );
+
+        fs::write(
+            root.join("shared/footer.md"),
+            "{{ include \"shared/warning.md\" }}",
+        )?;
+        assert!(matches!(
+            expand_includes(content, &root, &root.join("post.md")),
+            Err(MarkdownError::IncludeCycle(cycle)) if cycle.len() == 4
+        ));
+        assert!(matches!(
+            expand_includes("{{ include \"missing.md\" }}", &root, &root.join("post.md")),
+            Err(MarkdownError::Include(..))
+        ));
+
+        fs::remove_dir_all(&root)?;
+        Ok(())
+    }
+}
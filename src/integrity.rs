@@ -0,0 +1,170 @@
+use rayon::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{output::write_atomic, Error};
+
+/// The file [`write_integrity`] writes to the output directory.
+pub const INTEGRITY_MANIFEST: &str = ".pichu-manifest.json";
+
+/// Why a file failed [`verify`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum IntegrityIssue {
+    /// The file is in the manifest but missing, e.g. after a partial upload.
+    Missing,
+    /// The file's contents don't match its hash in the manifest.
+    Modified,
+    /// The file isn't in the manifest.
+    Unexpected,
+}
+
+/// A file that failed [`verify`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntegrityViolation {
+    /// The path of the file, relative to the output directory.
+    pub path: PathBuf,
+    /// What is wrong with the file.
+    pub issue: IntegrityIssue,
+}
+
+impl fmt::Display for IntegrityViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let issue = match self.issue {
+            IntegrityIssue::Missing => "missing",
+            IntegrityIssue::Modified => "modified",
+            IntegrityIssue::Unexpected => "unexpected",
+        };
+        write!(f, "{} is {issue}", self.path.display())
+    }
+}
+
+/// Write [`INTEGRITY_MANIFEST`] to the output directory, a JSON object of
+/// every file's path, with `/` separators, to its blake3 hash. Run it last,
+/// or set [`BuildConfig::integrity`](crate::BuildConfig::integrity).
+///
+/// # Errors
+///
+/// Returns an error if a file cannot be read or the manifest cannot be
+/// written.
+pub fn write_integrity(out_dir: impl AsRef<Path>) -> Result<(), Error> {
+    let out_dir = out_dir.as_ref();
+    let hashes = hash_files(out_dir)?;
+    let json = serde_json::to_string_pretty(&hashes).map_err(std::io::Error::from)?;
+    write_atomic(&out_dir.join(INTEGRITY_MANIFEST), json.as_bytes())?;
+    Ok(())
+}
+
+/// Check the output directory against its [`INTEGRITY_MANIFEST`], e.g.
+/// after a deploy or CDN sync, to detect tampering or partial uploads.
+///
+/// ```no_run
+/// pichu::write_integrity("dist")?;
+/// // Upload dist/ ...
+/// pichu::verify("/var/www/site")?;
+/// # Ok::<(), pichu::Error>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns [`Error::Integrity`] with every missing, modified or unexpected
+/// file, or an error if the manifest or a file cannot be read.
+pub fn verify(out_dir: impl AsRef<Path>) -> Result<(), Error> {
+    let out_dir = out_dir.as_ref();
+    let manifest = fs::read_to_string(out_dir.join(INTEGRITY_MANIFEST))?;
+    let expected: BTreeMap<String, String> =
+        serde_json::from_str(&manifest).map_err(|e| Error::Parse(Box::new(e)))?;
+    let actual = hash_files(out_dir)?;
+
+    let mut violations = Vec::new();
+    for (path, hash) in &expected {
+        let issue = match actual.get(path) {
+            Some(actual) if actual == hash => continue,
+            Some(_) => IntegrityIssue::Modified,
+            None => IntegrityIssue::Missing,
+        };
+        violations.push(IntegrityViolation {
+            path: PathBuf::from(path),
+            issue,
+        });
+    }
+    violations.extend(
+        actual
+            .keys()
+            .filter(|path| !expected.contains_key(*path))
+            .map(|path| IntegrityViolation {
+                path: PathBuf::from(path),
+                issue: IntegrityIssue::Unexpected,
+            }),
+    );
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        violations.sort_by(|a, b| a.path.cmp(&b.path));
+        Err(Error::Integrity(violations))
+    }
+}
+
+/// The blake3 hashes of all files in the directory except the manifest, by
+/// their relative path with `/` separators.
+fn hash_files(dir: &Path) -> Result<BTreeMap<String, String>, Error> {
+    let pattern = Path::new(&glob::Pattern::escape(&dir.to_string_lossy())).join("**/*");
+    let files = glob::glob(&pattern.to_string_lossy())?
+        .filter(|path| path.as_ref().map_or(true, |path| path.is_file()))
+        .collect::<Result<Vec<_>, _>>()?;
+    files
+        .par_iter()
+        .filter_map(|file| {
+            let relative = file.strip_prefix(dir).ok()?;
+            let key = relative
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            (key != INTEGRITY_MANIFEST).then_some((key, file))
+        })
+        .map(|(key, file)| Ok((key, blake3::hash(&fs::read(file)?).to_hex().to_string())))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_verify() -> Result<(), Error> {
+        let dir = env::temp_dir().join("pichu_test_verify");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("index.html"), "home")?;
+        crate::write(dir.join("blog/index.html"), "blog")?;
+        crate::write(dir.join(".well-known/security.txt"), "")?;
+        write_integrity(&dir)?;
+        verify(&dir)?;
+
+        crate::write(dir.join("index.html"), "tampered")?;
+        fs::remove_file(dir.join("blog/index.html"))?;
+        crate::write(dir.join("extra.js"), "")?;
+        match verify(&dir) {
+            Err(Error::Integrity(violations)) => assert_eq!(
+                violations
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect::<Vec<_>>(),
+                [
+                    "blog/index.html is missing",
+                    "extra.js is unexpected",
+                    "index.html is modified"
+                ]
+            ),
+            result => panic!("expected integrity error, got {result:?}"),
+        }
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,778 @@
+use minify_js::{minify, Session, TopLevelMode};
+use parse_js::ast::{ClassOrObjectMemberKey, ExportNames, NodeData, Syntax};
+use std::{
+    collections::HashMap,
+    fmt::Write as _,
+    fs, io,
+    ops::Range,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::{
+    manifest::{content_hash, fill_hash},
+    write, AssetManifest, BuildProfile, Deps, UrlPath,
+};
+
+/// Error type for JavaScript processing operations.
+#[derive(thiserror::Error, Debug)]
+pub enum JsError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// The source failed to parse.
+    #[error("failed to parse {0}: {1}")]
+    Syntax(PathBuf, String),
+    /// TypeScript sources can't be compiled yet.
+    #[error("typescript is not supported: {0}")]
+    TypeScriptUnsupported(PathBuf),
+    /// An import isn't a relative path to an existing file.
+    #[error("failed to resolve import {1} in {0}")]
+    Unresolved(PathBuf, String),
+    /// Modules import each other, which can't be bundled.
+    #[error("import cycle through {0}")]
+    ImportCycle(PathBuf),
+    /// The destination isn't a relative path within the output directory.
+    #[error("destination isn't within the output directory: {0}")]
+    InvalidDest(PathBuf),
+}
+
+/// Options for [`render_js`] and [`bundle_js`].
+#[derive(Debug, Clone)]
+pub struct JsOptions {
+    /// Minify the output.
+    pub minify: bool,
+    /// Treat the source as an ES module instead of a classic script. For
+    /// [`bundle_js`], output an ES module with the exports of the entry
+    /// instead of a classic script.
+    pub module: bool,
+    /// Replace a `[hash]` placeholder in the destination with a hash of the
+    /// output. If disabled, the placeholder is removed. Defaults to true.
+    pub fingerprint: bool,
+}
+
+impl Default for JsOptions {
+    fn default() -> Self {
+        Self {
+            minify: false,
+            module: false,
+            fingerprint: true,
+        }
+    }
+}
+
+impl JsOptions {
+    /// Options for the profile, minifying and fingerprinting for production.
+    #[must_use]
+    pub fn for_profile(profile: BuildProfile) -> Self {
+        Self {
+            minify: profile.minify(),
+            module: false,
+            fingerprint: profile.fingerprint(),
+        }
+    }
+}
+
+/// A bundle written by [`bundle_js`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsBundle {
+    /// The file the bundle was written to.
+    pub path: PathBuf,
+    /// The URL of the bundle, as registered in the manifest.
+    pub url: UrlPath,
+    /// The bundled modules, imported ones first and the entry last.
+    pub inputs: Vec<PathBuf>,
+}
+
+impl JsBundle {
+    /// The bundled modules as the dependencies of the bundle, to record in a
+    /// [`DepGraph`](crate::DepGraph) so changing any of them rebuilds it.
+    #[must_use]
+    pub fn deps(&self) -> Deps {
+        let mut deps = Deps::new();
+        if let Some((entry, imports)) = self.inputs.split_last() {
+            deps.source(entry);
+            for import in imports {
+                deps.include(import);
+            }
+        }
+        deps
+    }
+}
+
+/// Render a JavaScript file to the destination, minifying it if configured.
+/// A `[hash]` placeholder in the destination is replaced with a hash of the
+/// output, for cache busting, unless fingerprinting is disabled. Returns the
+/// path the file was written to, to be registered in an
+/// [`AssetManifest`](crate::AssetManifest).
+///
+/// Imports are left as they are, use [`bundle_js`] to bundle them.
+///
+/// # Errors
+///
+/// Returns an error if the source cannot be read or parsed, if it's
+/// TypeScript, or if the output cannot be written.
+pub fn render_js(
+    source: impl AsRef<Path>,
+    dest: impl AsRef<Path>,
+    options: &JsOptions,
+) -> Result<PathBuf, JsError> {
+    let source = source.as_ref();
+    if is_typescript(source) {
+        return Err(JsError::TypeScriptUnsupported(source.to_path_buf()));
+    }
+
+    let code = fs::read(source)?;
+    let js = if options.minify {
+        minify_code(source, &code, options.module)?
+    } else {
+        code
+    };
+
+    let hash = content_hash(&js);
+    let hash = options.fingerprint.then_some(hash.as_str());
+    let dest = fill_hash(dest.as_ref(), hash);
+    write(&dest, js)?;
+    Ok(dest)
+}
+
+/// Bundle an ES module and the modules it imports into a single script,
+/// written to `to` within the output directory and minified if configured.
+/// A `[hash]` placeholder in the file name is replaced with a hash of the
+/// output unless fingerprinting is disabled. The bundle is registered in
+/// the manifest under the destination without the placeholder, e.g.
+/// `js/main.js`.
+///
+/// Only relative imports of JavaScript files, like `./nav.js`, are bundled.
+/// Each module keeps its own scope, and its exports are read once it ran. Record [`JsBundle::deps`] to rebuild the bundle
+/// when any of its modules change:
+///
+/// ```no_run
+/// use pichu::{AssetManifest, DepGraph, JsOptions};
+/// use std::path::PathBuf;
+///
+/// # fn main() -> Result<(), pichu::JsError> {
+/// let (manifest, graph) = (AssetManifest::new(), DepGraph::new());
+/// let options = JsOptions::default();
+/// let bundle = pichu::bundle_js(&manifest, "assets/main.js", "dist", "js/main.[hash].js", &options)?;
+/// graph.record(&bundle.path, bundle.deps());
+///
+/// // Later, e.g. in the function passed to `pichu::watch`:
+/// let changed = vec![PathBuf::from("assets/nav.js")];
+/// if !graph.affected(&changed).is_empty() {
+///     let bundle = pichu::bundle_js(&manifest, "assets/main.js", "dist", "js/main.[hash].js", &options)?;
+///     graph.record(&bundle.path, bundle.deps());
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if a module cannot be read or parsed, is TypeScript or
+/// can't be resolved, if modules import each other, or if the output cannot
+/// be written within the output directory.
+pub fn bundle_js(
+    manifest: &AssetManifest,
+    source: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    options: &JsOptions,
+) -> Result<JsBundle, JsError> {
+    let (source, out_dir, to) = (source.as_ref(), out_dir.as_ref(), to.as_ref());
+    if !to
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(JsError::InvalidDest(to.to_path_buf()));
+    }
+    let mut bundler = Bundler::default();
+    bundler.add(source)?;
+    let code = bundler.finish(options.module);
+    let js = if options.minify {
+        minify_code(source, code.as_bytes(), options.module)?
+    } else {
+        code.into_bytes()
+    };
+
+    let hash = content_hash(&js);
+    let dest = out_dir.join(fill_hash(to, options.fingerprint.then_some(hash.as_str())));
+    let url =
+        UrlPath::from_output(out_dir, &dest).map_err(|_| JsError::InvalidDest(dest.clone()))?;
+    write(&dest, js)?;
+    let name = fill_hash(to, None).to_string_lossy().replace('\\', "/");
+    manifest.register(name, url.as_str());
+    Ok(JsBundle {
+        path: dest,
+        url,
+        inputs: bundler
+            .modules
+            .into_iter()
+            .map(|module| module.path)
+            .collect(),
+    })
+}
+
+fn is_typescript(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ["ts", "tsx", "mts", "cts"].iter().any(|ts| ext == *ts))
+}
+
+fn minify_code(path: &Path, code: &[u8], module: bool) -> Result<Vec<u8>, JsError> {
+    let session = Session::new();
+    let mode = if module {
+        TopLevelMode::Module
+    } else {
+        TopLevelMode::Global
+    };
+    let mut out = Vec::with_capacity(code.len());
+    minify(&session, mode, code, &mut out)
+        .map_err(|e| JsError::Syntax(path.to_path_buf(), format!("{e:?}")))?;
+    Ok(out)
+}
+
+/// The modules of a bundle, in the order they run.
+#[derive(Default)]
+struct Bundler {
+    modules: Vec<Module>,
+    /// The index of each module by canonical path, `None` while its imports
+    /// are being added.
+    ids: HashMap<PathBuf, Option<usize>>,
+}
+
+/// The edits to a module's source, and what it imports and exports.
+#[derive(Default)]
+struct Rewrite {
+    imports: String,
+    edits: Vec<(Range<usize>, String)>,
+    /// The exported names and the expressions they are read from.
+    exports: Vec<(String, String)>,
+    /// The modules whose exports are all exported too.
+    stars: Vec<usize>,
+}
+
+impl Rewrite {
+    fn remove(&mut self, range: Range<usize>) {
+        self.edits.push((range, String::new()));
+    }
+
+    /// Bind an imported name to its value at the start of the module.
+    fn bind(&mut self, pattern: &NodeData<'_>, value: &str) {
+        let _ = writeln!(self.imports, "const {} = {value};", name(pattern));
+    }
+
+    /// Replace the `export default` keywords in the range with a `const`
+    /// holding the default export, terminated at `end` if given.
+    fn export_default(&mut self, keywords: Range<usize>, end: Option<usize>) {
+        let value = "__pichu_default".to_string();
+        self.edits.push((keywords, format!("const {value} = ")));
+        if let Some(end) = end {
+            self.edits.push((end..end, ";".to_string()));
+        }
+        self.exports.push(("default".to_string(), value));
+    }
+
+    /// Wrap the edited source in a function returning its exports.
+    fn finish(mut self, bundler: &Bundler, id: usize, path: &Path, source: &[u8]) -> Module {
+        for star in &self.stars {
+            for export in &bundler.modules[*star].exports {
+                if export != "default" && self.exports.iter().all(|(name, _)| name != export) {
+                    self.exports
+                        .push((export.clone(), format!("__pichu_{star}.{export}")));
+                }
+            }
+        }
+
+        let mut code = format!(
+            "// {}\nconst __pichu_{id} = (function () {{\n{}",
+            path.display(),
+            self.imports
+        );
+        self.edits.sort_by_key(|(range, _)| range.start);
+        let mut last = 0;
+        for (range, replacement) in self.edits {
+            code.push_str(&String::from_utf8_lossy(&source[last..range.start]));
+            code.push_str(&replacement);
+            last = range.end;
+        }
+        code.push_str(&String::from_utf8_lossy(&source[last..]));
+        code.push_str("\nreturn {\n");
+        for (name, value) in &self.exports {
+            let _ = writeln!(code, "{name}: {value},");
+        }
+        code.push_str("};\n})();\n");
+
+        Module {
+            path: path.to_path_buf(),
+            code,
+            exports: self.exports.into_iter().map(|(name, _)| name).collect(),
+        }
+    }
+}
+
+struct Module {
+    path: PathBuf,
+    /// A `const` holding an object with the module's exports.
+    code: String,
+    exports: Vec<String>,
+}
+
+impl Bundler {
+    /// Add a module after the modules it imports, returning its index.
+    fn add(&mut self, path: &Path) -> Result<usize, JsError> {
+        if is_typescript(path) {
+            return Err(JsError::TypeScriptUnsupported(path.to_path_buf()));
+        }
+        let key = fs::canonicalize(path)?;
+        match self.ids.get(&key) {
+            Some(Some(id)) => return Ok(*id),
+            Some(None) => return Err(JsError::ImportCycle(path.to_path_buf())),
+            None => self.ids.insert(key.clone(), None),
+        };
+
+        let source = fs::read(path)?;
+        let session = Session::new();
+        let node = parse_js::parse(&session, &source, TopLevelMode::Module)
+            .map_err(|e| JsError::Syntax(path.to_path_buf(), format!("{e:?}")))?;
+        let body = match &node.stx {
+            Syntax::TopLevel { body } => body.as_slice(),
+            _ => &[],
+        };
+
+        let mut rewrite = Rewrite::default();
+        for statement in body {
+            self.rewrite(path, &source, statement, &mut rewrite)?;
+        }
+        let id = self.modules.len();
+        let module = rewrite.finish(self, id, path, &source);
+        self.modules.push(module);
+        self.ids.insert(key, Some(id));
+        Ok(id)
+    }
+
+    /// Rewrite a top-level statement if it imports or exports.
+    fn rewrite(
+        &mut self,
+        path: &Path,
+        source: &[u8],
+        statement: &NodeData<'_>,
+        rewrite: &mut Rewrite,
+    ) -> Result<(), JsError> {
+        let start = statement.loc.start();
+        match &statement.stx {
+            Syntax::ImportStmt {
+                default,
+                names,
+                module,
+            } => {
+                let id = self.import(path, module)?;
+                rewrite.remove(start..statement_end(source, start, true));
+                if let Some(default) = default {
+                    rewrite.bind(default, &format!("__pichu_{id}.default"));
+                }
+                match names {
+                    Some(ExportNames::All(Some(alias))) => {
+                        rewrite.bind(alias, &format!("__pichu_{id}"));
+                    }
+                    Some(ExportNames::Specific(names)) => {
+                        for import in names {
+                            let target = import.target.as_str();
+                            rewrite.bind(import.alias, &format!("__pichu_{id}.{target}"));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Syntax::ExportListStmt { names, from } => {
+                let from = match from {
+                    Some(module) => Some(self.import(path, module)?),
+                    None => None,
+                };
+                rewrite.remove(start..statement_end(source, start, from.is_some()));
+                match (names, from) {
+                    (ExportNames::Specific(names), from) => {
+                        for export in names {
+                            let target = export.target.as_str();
+                            let value = match from {
+                                Some(id) => format!("__pichu_{id}.{target}"),
+                                None => target.to_string(),
+                            };
+                            rewrite.exports.push((name(export.alias), value));
+                        }
+                    }
+                    (ExportNames::All(Some(alias)), Some(id)) => {
+                        rewrite.exports.push((name(alias), format!("__pichu_{id}")));
+                    }
+                    (ExportNames::All(None), Some(id)) => rewrite.stars.push(id),
+                    (ExportNames::All(_), None) => {}
+                }
+            }
+            Syntax::ExportDefaultExprStmt { expression } => {
+                rewrite.export_default(start..expression.loc.start(), None);
+            }
+            Syntax::VarDecl {
+                export: true,
+                declarators,
+                ..
+            } => {
+                rewrite.remove(export_start(source, start)..start);
+                let mut names = Vec::new();
+                for declarator in declarators {
+                    pattern_names(declarator.pattern, &mut names);
+                }
+                rewrite
+                    .exports
+                    .extend(names.into_iter().map(|name| (name.clone(), name)));
+            }
+            Syntax::FunctionDecl {
+                export: true,
+                export_default,
+                name: declared,
+                ..
+            }
+            | Syntax::ClassDecl {
+                export: true,
+                export_default,
+                name: declared,
+                ..
+            } => {
+                let keywords = export_start(source, start)..start;
+                if let Some(declared) = declared {
+                    rewrite.remove(keywords);
+                    let exported = if *export_default {
+                        "default".to_string()
+                    } else {
+                        name(declared)
+                    };
+                    rewrite.exports.push((exported, name(declared)));
+                } else {
+                    // An anonymous default function or class.
+                    rewrite.export_default(keywords, Some(statement.loc.end()));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Add the module imported by `importer` as `specifier`.
+    fn import(&mut self, importer: &Path, specifier: &str) -> Result<usize, JsError> {
+        let unresolved = || JsError::Unresolved(importer.to_path_buf(), specifier.to_string());
+        if !specifier.starts_with("./") && !specifier.starts_with("../") {
+            return Err(unresolved());
+        }
+        let path = importer.parent().unwrap_or(Path::new("")).join(specifier);
+        let path = if path.is_file() {
+            path
+        } else {
+            let mut with_extension = path.into_os_string();
+            with_extension.push(".js");
+            PathBuf::from(with_extension)
+        };
+        if !path.is_file() {
+            return Err(unresolved());
+        }
+        self.add(&path)
+    }
+
+    /// Concatenate the modules, exporting what the entry, the last one,
+    /// exports if the output is a module.
+    fn finish(&self, module: bool) -> String {
+        let mut code = String::new();
+        if !module {
+            code.push_str("(function () {\n\"use strict\";\n");
+        }
+        for module in &self.modules {
+            code.push_str(&module.code);
+        }
+        let entry = self.modules.len().saturating_sub(1);
+        if module {
+            for export in self.modules.iter().last().map_or(&[][..], |m| &m.exports) {
+                if export == "default" {
+                    let _ = writeln!(code, "export default __pichu_{entry}.default;");
+                } else {
+                    let _ = writeln!(code, "export const {export} = __pichu_{entry}.{export};");
+                }
+            }
+        } else {
+            code.push_str("})();\n");
+        }
+        code
+    }
+}
+
+/// The end of an import or export statement starting at `start`, after its
+/// module specifier or closing brace and an optional semicolon.
+fn statement_end(source: &[u8], start: usize, from: bool) -> usize {
+    let rest = &source[start..];
+    let end = if from {
+        rest.iter()
+            .position(|&b| b == b'"' || b == b'\'')
+            .and_then(|open| {
+                let quote = rest[open];
+                let close = rest[open + 1..].iter().position(|&b| b == quote)?;
+                Some(open + close + 2)
+            })
+    } else {
+        rest.iter().position(|&b| b == b'}').map(|close| close + 1)
+    }
+    .unwrap_or(rest.len());
+    let semicolon = rest[end..]
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .filter(|&i| rest[end + i] == b';')
+        .map_or(0, |i| i + 1);
+    start + end + semicolon
+}
+
+/// The start of the `export` keyword of a declaration, which isn't part of
+/// its location.
+fn export_start(source: &[u8], start: usize) -> usize {
+    source[..start]
+        .windows(6)
+        .rposition(|window| window == b"export")
+        .unwrap_or(start)
+}
+
+/// The name of an identifier pattern or a class or function name.
+fn name(node: &NodeData<'_>) -> String {
+    match &node.stx {
+        Syntax::IdentifierPattern { name } | Syntax::ClassOrFunctionName { name } => {
+            name.as_str().to_string()
+        }
+        _ => String::new(),
+    }
+}
+
+/// The names a declaration pattern binds, e.g. `a` and `b` for `{ a, c: [b] }`.
+fn pattern_names(pattern: &NodeData<'_>, names: &mut Vec<String>) {
+    match &pattern.stx {
+        Syntax::IdentifierPattern { name } => names.push(name.as_str().to_string()),
+        Syntax::ArrayPattern { elements, rest } => {
+            for element in elements.iter().flatten() {
+                pattern_names(element.target, names);
+            }
+            if let Some(rest) = rest {
+                pattern_names(rest, names);
+            }
+        }
+        Syntax::ObjectPattern { properties, rest } => {
+            for property in properties {
+                if let Syntax::ObjectPatternProperty { key, target, .. } = &property.stx {
+                    match (target, key) {
+                        (Some(target), _) => pattern_names(target, names),
+                        (None, ClassOrObjectMemberKey::Direct(key)) => {
+                            names.push(key.as_str().to_string());
+                        }
+                        (None, ClassOrObjectMemberKey::Computed(_)) => {}
+                    }
+                }
+            }
+            if let Some(rest) = rest {
+                pattern_names(rest, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_render_js() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_js");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("main.js"),
+            "const main = () => {\n  let a = 1;\n};\n",
+        )?;
+
+        let options = JsOptions {
+            minify: true,
+            ..Default::default()
+        };
+        let path = render_js(
+            dir.join("main.js"),
+            dir.join("dist/main.[hash].js"),
+            &options,
+        )?;
+        assert!(!path.to_string_lossy().contains("[hash]"));
+        assert_eq!(fs::read_to_string(&path)?, "const main=()=>{let a=1}");
+
+        let path = render_js(
+            dir.join("main.js"),
+            dir.join("dist/main.[hash].js"),
+            &JsOptions::for_profile(BuildProfile::Dev),
+        )?;
+        assert_eq!(path, dir.join("dist/main.js"));
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "const main = () => {\n  let a = 1;\n};\n"
+        );
+
+        fs::write(dir.join("broken.js"), "const = ;")?;
+        assert!(matches!(
+            render_js(dir.join("broken.js"), dir.join("dist/broken.js"), &options),
+            Err(JsError::Syntax(..))
+        ));
+        fs::write(dir.join("main.ts"), "const a: number = 1;")?;
+        assert!(matches!(
+            render_js(dir.join("main.ts"), dir.join("dist/main.js"), &options),
+            Err(JsError::TypeScriptUnsupported(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_js() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_bundle_js");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(
+            dir.join("assets/main.js"),
+            "import greet, { name as who } from \"./lib/greet.js\";\n\
+             import * as util from './lib/util';\n\
+             console.log(greet(who), util.twice(2), util.name);\n\
+             export const answer = util.twice(21);\n\
+             export default function () {}\n",
+        )?;
+        crate::write(
+            dir.join("assets/lib/greet.js"),
+            "export const name = \"pichu\", { length } = name;\n\
+             export default (who) => `hi ${who}`;\n",
+        )?;
+        crate::write(
+            dir.join("assets/lib/util.js"),
+            "function twice(n) { return 2 * n; }\n\
+             export { twice };\n\
+             export * from \"./greet.js\";\n",
+        )?;
+
+        let manifest = AssetManifest::new();
+        let options = JsOptions {
+            fingerprint: false,
+            ..Default::default()
+        };
+        let out_dir = dir.join("dist");
+        let bundle = bundle_js(
+            &manifest,
+            dir.join("assets/main.js"),
+            &out_dir,
+            "js/main.[hash].js",
+            &options,
+        )?;
+        assert_eq!(bundle.path, out_dir.join("js/main.js"));
+        assert_eq!(manifest.get("js/main.js"), Some("/js/main.js".to_string()));
+        assert_eq!(
+            bundle.inputs,
+            [
+                dir.join("assets/./lib/greet.js"),
+                dir.join("assets/./lib/util.js"),
+                dir.join("assets/main.js"),
+            ]
+        );
+        assert_eq!(bundle.deps().iter().count(), 3);
+        let js = fs::read_to_string(&bundle.path)?;
+        assert!(js.starts_with("(function () {\n\"use strict\";\n"));
+        assert!(js.contains("const who = __pichu_0.name;\n"));
+        assert!(js.contains("const util = __pichu_1;\n"));
+        assert!(js.contains("length: length,"));
+        assert!(js.contains("name: __pichu_0.name,"));
+        assert!(!js.contains("import") && !js.contains("export"));
+
+        let module = JsOptions {
+            minify: true,
+            module: true,
+            ..options
+        };
+        let bundle = bundle_js(
+            &manifest,
+            dir.join("assets/main.js"),
+            &out_dir,
+            "js/main.mjs",
+            &module,
+        )?;
+        let js = fs::read_to_string(&bundle.path)?;
+        assert!(js.contains(" as answer}") && js.contains("export default "));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_bundle_js_errors() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_bundle_js_errors");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let manifest = AssetManifest::new();
+        let options = JsOptions::default();
+        let out_dir = dir.join("dist");
+        crate::write(
+            dir.join("assets/main.js"),
+            "import { twice } from './lib/util.js';\nexport const answer = twice(21);\n",
+        )?;
+
+        crate::write(
+            dir.join("assets/lib/util.js"),
+            "import { missing } from './missing.js';",
+        )?;
+        assert!(matches!(
+            bundle_js(
+                &manifest,
+                dir.join("assets/main.js"),
+                &out_dir,
+                "main.js",
+                &options
+            ),
+            Err(JsError::Unresolved(..))
+        ));
+        crate::write(
+            dir.join("assets/lib/util.js"),
+            "import { answer } from '../main.js';",
+        )?;
+        assert!(matches!(
+            bundle_js(
+                &manifest,
+                dir.join("assets/main.js"),
+                &out_dir,
+                "main.js",
+                &options
+            ),
+            Err(JsError::ImportCycle(..))
+        ));
+        assert!(matches!(
+            bundle_js(
+                &manifest,
+                dir.join("assets/main.ts"),
+                &out_dir,
+                "main.js",
+                &options
+            ),
+            Err(JsError::TypeScriptUnsupported(_))
+        ));
+        crate::write(dir.join("assets/solo.js"), "export const a = 1;")?;
+        assert!(matches!(
+            bundle_js(
+                &manifest,
+                dir.join("assets/solo.js"),
+                &out_dir,
+                "../main.js",
+                &options
+            ),
+            Err(JsError::InvalidDest(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
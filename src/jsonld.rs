@@ -0,0 +1,422 @@
+use serde_json::{json, Map, Value};
+use std::fmt;
+
+/// Error returned when building structured data with missing required fields.
+#[derive(thiserror::Error, Debug, PartialEq, Eq)]
+pub enum JsonLdError {
+    /// A required field is missing or empty.
+    #[error("{schema_type} is missing required field `{field}`")]
+    MissingField {
+        /// The schema.org type, e.g. `Article`.
+        schema_type: &'static str,
+        /// The missing field.
+        field: &'static str,
+    },
+}
+
+/// Validated schema.org structured data, ready to be embedded in a page.
+/// Its [`fmt::Display`] implementation renders a
+/// `<script type="application/ld+json">` tag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonLd(Value);
+
+impl JsonLd {
+    /// The underlying JSON value.
+    #[must_use]
+    pub fn value(&self) -> &Value {
+        &self.0
+    }
+}
+
+impl fmt::Display for JsonLd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Escape `</` so the content can't close the script tag early.
+        let json = self.0.to_string().replace("</", r"<\/");
+        write!(f, r#"<script type="application/ld+json">{json}</script>"#)
+    }
+}
+
+/// A schema.org `Person`.
+#[derive(Debug, Clone, Default)]
+pub struct Person {
+    name: String,
+    url: Option<String>,
+    image: Option<String>,
+}
+
+impl Person {
+    /// Create a person with the given name.
+    #[must_use]
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Set the URL of the person's website or profile.
+    #[must_use]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Set the URL of an image of the person.
+    #[must_use]
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.image = Some(image.into());
+        self
+    }
+
+    /// Validate and build the structured data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the name is empty.
+    pub fn build(&self) -> Result<JsonLd, JsonLdError> {
+        let mut value = self.to_value()?;
+        value.insert("@context".to_string(), json!("https://schema.org"));
+        Ok(JsonLd(Value::Object(value)))
+    }
+
+    fn to_value(&self) -> Result<Map<String, Value>, JsonLdError> {
+        let mut value = object("Person");
+        value.insert(
+            "name".to_string(),
+            json!(required("Person", "name", Some(&self.name))?),
+        );
+        insert_opt(&mut value, "url", self.url.as_ref());
+        insert_opt(&mut value, "image", self.image.as_ref());
+        Ok(value)
+    }
+}
+
+/// A schema.org `Article` or `BlogPosting`.
+#[derive(Debug, Clone)]
+pub struct Article {
+    schema_type: &'static str,
+    headline: Option<String>,
+    description: Option<String>,
+    url: Option<String>,
+    images: Vec<String>,
+    authors: Vec<Person>,
+    date_published: Option<String>,
+    date_modified: Option<String>,
+    publisher: Option<Person>,
+}
+
+impl Default for Article {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Article {
+    /// Create an `Article`.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_type("Article")
+    }
+
+    /// Create a `BlogPosting`, the more specific type for blog posts.
+    #[must_use]
+    pub fn blog_posting() -> Self {
+        Self::with_type("BlogPosting")
+    }
+
+    fn with_type(schema_type: &'static str) -> Self {
+        Self {
+            schema_type,
+            headline: None,
+            description: None,
+            url: None,
+            images: Vec::new(),
+            authors: Vec::new(),
+            date_published: None,
+            date_modified: None,
+            publisher: None,
+        }
+    }
+
+    /// Set the headline (required).
+    #[must_use]
+    pub fn headline(mut self, headline: impl Into<String>) -> Self {
+        self.headline = Some(headline.into());
+        self
+    }
+
+    /// Set the description.
+    #[must_use]
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    /// Set the canonical URL of the article.
+    #[must_use]
+    pub fn url(mut self, url: impl Into<String>) -> Self {
+        self.url = Some(url.into());
+        self
+    }
+
+    /// Add an image URL.
+    #[must_use]
+    pub fn image(mut self, image: impl Into<String>) -> Self {
+        self.images.push(image.into());
+        self
+    }
+
+    /// Add an author.
+    #[must_use]
+    pub fn author(mut self, author: Person) -> Self {
+        self.authors.push(author);
+        self
+    }
+
+    /// Set the publication date as ISO 8601 date or datetime (required).
+    #[must_use]
+    pub fn date_published(mut self, date: impl Into<String>) -> Self {
+        self.date_published = Some(date.into());
+        self
+    }
+
+    /// Set the modification date as ISO 8601 date or datetime.
+    #[must_use]
+    pub fn date_modified(mut self, date: impl Into<String>) -> Self {
+        self.date_modified = Some(date.into());
+        self
+    }
+
+    /// Set the publisher.
+    #[must_use]
+    pub fn publisher(mut self, publisher: Person) -> Self {
+        self.publisher = Some(publisher);
+        self
+    }
+
+    /// Validate and build the structured data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the headline or publication date is missing, or
+    /// if any person involved has no name.
+    pub fn build(&self) -> Result<JsonLd, JsonLdError> {
+        let schema_type = self.schema_type;
+        let mut value = object(schema_type);
+        value.insert("@context".to_string(), json!("https://schema.org"));
+        value.insert(
+            "headline".to_string(),
+            json!(required(schema_type, "headline", self.headline.as_ref())?),
+        );
+        value.insert(
+            "datePublished".to_string(),
+            json!(required(
+                schema_type,
+                "datePublished",
+                self.date_published.as_ref()
+            )?),
+        );
+        insert_opt(&mut value, "dateModified", self.date_modified.as_ref());
+        insert_opt(&mut value, "description", self.description.as_ref());
+        if let Some(url) = &self.url {
+            value.insert("url".to_string(), json!(url));
+            value.insert("mainEntityOfPage".to_string(), json!(url));
+        }
+        if !self.images.is_empty() {
+            value.insert("image".to_string(), json!(self.images));
+        }
+        if !self.authors.is_empty() {
+            let authors = self
+                .authors
+                .iter()
+                .map(|author| author.to_value().map(Value::Object))
+                .collect::<Result<Vec<_>, _>>()?;
+            value.insert("author".to_string(), Value::Array(authors));
+        }
+        if let Some(publisher) = &self.publisher {
+            value.insert(
+                "publisher".to_string(),
+                Value::Object(publisher.to_value()?),
+            );
+        }
+        Ok(JsonLd(Value::Object(value)))
+    }
+}
+
+/// A schema.org `BreadcrumbList`.
+#[derive(Debug, Clone, Default)]
+pub struct BreadcrumbList {
+    items: Vec<(String, String)>,
+}
+
+impl BreadcrumbList {
+    /// Create an empty breadcrumb list.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a breadcrumb, from the root of the site towards the current page.
+    #[must_use]
+    pub fn item(mut self, name: impl Into<String>, url: impl Into<String>) -> Self {
+        self.items.push((name.into(), url.into()));
+        self
+    }
+
+    /// Validate and build the structured data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no items or an item has no name or URL.
+    pub fn build(&self) -> Result<JsonLd, JsonLdError> {
+        if self.items.is_empty() {
+            return Err(missing("BreadcrumbList", "itemListElement"));
+        }
+        let items = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, (name, url))| {
+                Ok(json!({
+                    "@type": "ListItem",
+                    "position": i + 1,
+                    "name": required("ListItem", "name", Some(name))?,
+                    "item": required("ListItem", "item", Some(url))?,
+                }))
+            })
+            .collect::<Result<Vec<_>, JsonLdError>>()?;
+        Ok(JsonLd(json!({
+            "@context": "https://schema.org",
+            "@type": "BreadcrumbList",
+            "itemListElement": items,
+        })))
+    }
+}
+
+/// A schema.org `FAQPage`.
+#[derive(Debug, Clone, Default)]
+pub struct Faq {
+    questions: Vec<(String, String)>,
+}
+
+impl Faq {
+    /// Create an empty FAQ.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a question and its answer. The answer may contain HTML.
+    #[must_use]
+    pub fn question(mut self, question: impl Into<String>, answer: impl Into<String>) -> Self {
+        self.questions.push((question.into(), answer.into()));
+        self
+    }
+
+    /// Validate and build the structured data.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if there are no questions or a question or answer is empty.
+    pub fn build(&self) -> Result<JsonLd, JsonLdError> {
+        if self.questions.is_empty() {
+            return Err(missing("FAQPage", "mainEntity"));
+        }
+        let questions = self
+            .questions
+            .iter()
+            .map(|(question, answer)| {
+                Ok(json!({
+                    "@type": "Question",
+                    "name": required("Question", "name", Some(question))?,
+                    "acceptedAnswer": {
+                        "@type": "Answer",
+                        "text": required("Answer", "text", Some(answer))?,
+                    },
+                }))
+            })
+            .collect::<Result<Vec<_>, JsonLdError>>()?;
+        Ok(JsonLd(json!({
+            "@context": "https://schema.org",
+            "@type": "FAQPage",
+            "mainEntity": questions,
+        })))
+    }
+}
+
+fn object(schema_type: &str) -> Map<String, Value> {
+    let mut value = Map::new();
+    value.insert("@type".to_string(), json!(schema_type));
+    value
+}
+
+fn insert_opt(value: &mut Map<String, Value>, key: &str, field: Option<&String>) {
+    if let Some(field) = field {
+        value.insert(key.to_string(), json!(field));
+    }
+}
+
+fn required<'a>(
+    schema_type: &'static str,
+    field: &'static str,
+    value: Option<&'a String>,
+) -> Result<&'a str, JsonLdError> {
+    value
+        .map(String::as_str)
+        .filter(|value| !value.trim().is_empty())
+        .ok_or_else(|| missing(schema_type, field))
+}
+
+fn missing(schema_type: &'static str, field: &'static str) -> JsonLdError {
+    JsonLdError::MissingField { schema_type, field }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_article() -> Result<(), JsonLdError> {
+        let article = Article::blog_posting()
+            .headline("Hello")
+            .date_published("2024-05-01")
+            .author(Person::new("Arne").url("https://arne.me"))
+            .build()?;
+        assert_eq!(article.value()["@type"], "BlogPosting");
+        assert_eq!(article.value()["author"][0]["name"], "Arne");
+
+        assert_eq!(
+            Article::new().headline("Hello").build(),
+            Err(JsonLdError::MissingField {
+                schema_type: "Article",
+                field: "datePublished"
+            })
+        );
+        assert_eq!(
+            Article::new()
+                .headline("Hello")
+                .date_published("2024-05-01")
+                .author(Person::new(" "))
+                .build(),
+            Err(JsonLdError::MissingField {
+                schema_type: "Person",
+                field: "name"
+            })
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_faq_and_breadcrumbs() -> Result<(), JsonLdError> {
+        let faq = Faq::new().question("Why?", "Because </script>").build()?;
+        assert!(faq.to_string().contains(r"Because <\/script>"));
+        assert!(Faq::new().build().is_err());
+
+        let breadcrumbs = BreadcrumbList::new()
+            .item("Home", "https://example.com/")
+            .item("Blog", "https://example.com/blog/")
+            .build()?;
+        assert_eq!(breadcrumbs.value()["itemListElement"][1]["position"], 2);
+        Ok(())
+    }
+}
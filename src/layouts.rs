@@ -0,0 +1,148 @@
+use rayon::prelude::*;
+use std::{collections::HashMap, fmt, path::Path};
+
+use crate::{output, Error, Parsed};
+
+type RenderFn<T> = dyn Fn(&T) -> String + Send + Sync;
+
+/// Items that declare which layout they should be rendered with, usually
+/// through a `layout` frontmatter key.
+pub trait HasLayout {
+    /// The name of the layout, or `None` to use the default.
+    fn layout(&self) -> Option<&str>;
+}
+
+/// A set of named render functions to pick from per item, see
+/// [`Parsed::render_each_by_layout`].
+pub struct Layouts<T> {
+    default: String,
+    render_fns: HashMap<String, Box<RenderFn<T>>>,
+}
+
+impl<T> Layouts<T> {
+    /// Create an empty set, using the layout `default` for items that don't
+    /// declare one.
+    #[must_use]
+    pub fn new(default: impl Into<String>) -> Self {
+        Self {
+            default: default.into(),
+            render_fns: HashMap::new(),
+        }
+    }
+
+    /// Add a layout, replacing any previous layout with the same name.
+    #[must_use]
+    pub fn layout<S: Into<String>>(
+        mut self,
+        name: impl Into<String>,
+        render_fn: impl Fn(&T) -> S + Send + Sync + 'static,
+    ) -> Self {
+        self.render_fns
+            .insert(name.into(), Box::new(move |item| render_fn(item).into()));
+        self
+    }
+}
+
+impl<T: HasLayout> Layouts<T> {
+    fn render_fn(&self, item: &T) -> Result<&RenderFn<T>, Error> {
+        let name = item.layout().unwrap_or(&self.default);
+        self.render_fns
+            .get(name)
+            .map(AsRef::as_ref)
+            .ok_or_else(|| Error::UnknownLayout(name.to_string()))
+    }
+}
+
+impl<T> fmt::Debug for Layouts<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut layouts = self.render_fns.keys().collect::<Vec<_>>();
+        layouts.sort();
+        f.debug_struct("Layouts")
+            .field("default", &self.default)
+            .field("layouts", &layouts)
+            .finish()
+    }
+}
+
+impl<T: HasLayout + Send + Sync> Parsed<T> {
+    /// Render individual items in parallel, picking the render function by
+    /// the layout each item declares.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an item declares a layout that doesn't exist, in
+    /// which case nothing is written, or if any file cannot be written.
+    pub fn render_each_by_layout<P: AsRef<Path>>(
+        self,
+        layouts: &Layouts<T>,
+        build_path_fn: impl Fn(&T) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let files = self
+            .items
+            .par_iter()
+            .map(|item| {
+                let render_fn = layouts.render_fn(item)?;
+                let content = render_fn(item).into_bytes();
+                Ok((build_path_fn(item).as_ref().to_path_buf(), content))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        output::write_batch(files)?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs};
+
+    struct Page {
+        name: &'static str,
+        layout: Option<&'static str>,
+    }
+
+    impl HasLayout for Page {
+        fn layout(&self) -> Option<&str> {
+            self.layout
+        }
+    }
+
+    #[test]
+    fn test_render_each_by_layout() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_each_by_layout");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let layouts = Layouts::new("post")
+            .layout("post", |page: &Page| format!("post {}", page.name))
+            .layout("note", |page: &Page| format!("note {}", page.name));
+        let pages = Parsed::from(vec![
+            Page {
+                name: "a",
+                layout: None,
+            },
+            Page {
+                name: "b",
+                layout: Some("note"),
+            },
+        ]);
+        let pages = pages.render_each_by_layout(&layouts, |page| dir.join(page.name))?;
+        assert_eq!(fs::read_to_string(dir.join("a"))?, "post a");
+        assert_eq!(fs::read_to_string(dir.join("b"))?, "note b");
+
+        let mut items = pages.into_vec();
+        items.push(Page {
+            name: "c",
+            layout: Some("gallery"),
+        });
+        let result =
+            Parsed::from(items).render_each_by_layout(&layouts, |page| dir.join(page.name));
+        assert!(matches!(result, Err(Error::UnknownLayout(name)) if name == "gallery"));
+        assert!(!dir.join("c").exists());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
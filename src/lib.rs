@@ -34,24 +34,281 @@
 
 use rayon::prelude::*;
 use std::{
-    fmt, fs, io,
+    collections::HashMap,
+    fmt, fs,
+    hash::Hash,
+    io,
     path::{Path, PathBuf},
 };
 
+mod anchor_manifest;
+mod asset_graph;
+mod base_url;
+mod bookmarks;
+mod budget;
+mod build;
+mod cache;
+mod clean;
+mod clock;
+mod collection;
+mod conditional;
+mod content_types;
+mod copy;
+mod critical_css;
+mod dedup;
+mod deps;
+mod freshness;
+mod hosting;
+mod html;
+mod integrity;
+mod layouts;
+mod manifest;
+mod output;
+mod outputs;
+mod page;
+mod partials;
+mod permalinks;
+mod profile;
+mod query;
+mod redirects;
+mod scaffold;
+mod series;
+mod sites;
+mod sort;
+mod stats;
+mod sync;
+mod url_path;
+mod workspace;
+
+pub use anchor_manifest::{AnchorChange, AnchorManifest};
+pub use asset_graph::AssetGraph;
+pub use base_url::{restyle_links, rewrite_links, rewrite_links_in, LinkStyle};
+pub use bookmarks::{parse_bookmarks, Bookmark};
+pub use budget::{page_weights, BudgetViolation, PageWeight, WeightBudget, WeightKind};
+pub use build::{
+    par_steps, BoxedStep, Build, BuildConfig, BuildContext, BuildReport, StepOutcome, StepReport,
+};
+pub use cache::RenderCache;
+pub use clean::{clean, mark_build, Clean, BUILD_MARKER};
+pub use clock::{Clock, FixedClock, SystemClock};
+pub use collection::Collection;
+pub use conditional::OnEmpty;
+pub use content_types::{ContentType, ContentTypeManifest, ContentTypes};
+pub use copy::{copy_dir_with, CopyMode, CopyOptions, SymlinkPolicy};
+pub use critical_css::{critical_css, inline_critical_css};
+pub use deps::{DepGraph, DepKind, Deps};
+pub use freshness::{Freshness, FreshnessReport, FreshnessSource, StalePage};
+pub use hosting::{Colophon, ErrorPage, Host, Human, Humans};
+pub use integrity::{
+    verify, write_integrity, IntegrityIssue, IntegrityViolation, INTEGRITY_MANIFEST,
+};
+pub use layouts::{HasLayout, Layouts};
+pub use manifest::AssetManifest;
+pub use outputs::{HasSource, OutputRegistry};
+pub use page::Page;
+pub use partials::Partials;
+pub use permalinks::HasPermalink;
+/// Derive [`Page`](trait@Page) for a frontmatter struct. Fields named
+/// `title`, `url`, `date`, `updated`, `summary` and `tags` are used for the
+/// accessors of the same name, other fields can be marked with e.g.
+/// `#[page(summary)]`. The URL path can also be built from fields with a
+/// format string. Dates and summaries can be `String` or `Option<String>`,
+/// tags `Vec<String>`.
+///
+/// The derived `url` panics if the URL path is invalid.
+///
+/// ```
+/// use pichu::Page;
+///
+/// #[derive(serde::Deserialize, Page)]
+/// #[page(url = "/blog/{slug}/")]
+/// struct Post {
+///     title: String,
+///     slug: String,
+///     date: String,
+///     #[page(summary)]
+///     description: Option<String>,
+///     tags: Vec<String>,
+/// }
+///
+/// let post: Post = serde_json::from_str(
+///     r#"{"title": "Hello", "slug": "hello", "date": "2024-05-01", "description": null, "tags": ["rust"]}"#,
+/// )?;
+/// assert_eq!(post.url().as_str(), "/blog/hello/");
+/// assert_eq!(post.date(), Some("2024-05-01"));
+/// assert_eq!(post.summary(), None);
+/// assert_eq!(post.tags(), ["rust"]);
+/// # Ok::<(), serde_json::Error>(())
+/// ```
+#[cfg(feature = "derive")]
+pub use pichu_derive::Page;
+pub use profile::BuildProfile;
+pub use query::{Query, Site};
+pub use redirects::{HasAliases, Redirect, Redirects};
+pub use scaffold::{new_content, new_content_with, Archetype, ScaffoldError};
+pub use series::{InSeries, SeriesIndex, SeriesPosition};
+pub use sites::{Sites, SitesReport};
+#[cfg(feature = "collation")]
+pub use sort::collated_cmp;
+pub use sort::natural_cmp;
+pub use url_path::{check_links, rebase, UrlPath, UrlStyle};
+pub use workspace::{workspace, Workspace};
+
+#[cfg(feature = "markdown")]
+mod anchors;
+#[cfg(feature = "markdown")]
+pub use anchors::Anchors;
+#[cfg(feature = "sanitize")]
+mod comments;
+#[cfg(feature = "sanitize")]
+pub use comments::{parse_comment, Comment};
+
+#[cfg(feature = "markdown")]
+mod content_tree;
+#[cfg(feature = "markdown")]
+pub use content_tree::ContentTree;
+#[cfg(feature = "markdown")]
+mod code_blocks;
+#[cfg(feature = "markdown")]
+pub use code_blocks::CodeBlocks;
+#[cfg(feature = "markdown")]
+mod highlight;
+#[cfg(feature = "markdown")]
+pub use highlight::{configure_highlighting, highlight_css, Highlighting};
+#[cfg(feature = "markdown")]
+mod image_probe;
+#[cfg(feature = "markdown")]
+mod includes;
 #[cfg(feature = "markdown")]
 mod markdown;
+/// The markdown renderer, re-exported for use with
+/// [`MarkdownOptions::with_plugins`].
+#[cfg(feature = "markdown")]
+pub use comrak;
 #[cfg(feature = "markdown")]
-pub use markdown::{parse_markdown, Markdown, MarkdownError};
+pub use markdown::{
+    parse_markdown, parse_markdown_meta_only, parse_markdown_with, LazyMarkdown, Markdown,
+    MarkdownError, MarkdownOptions,
+};
+
+pub mod lint;
+pub mod nav;
+
+#[cfg(feature = "dates")]
+pub mod dates;
+#[cfg(feature = "dates")]
+pub mod de;
+
+#[cfg(feature = "slug")]
+mod slug;
+#[cfg(feature = "slug")]
+pub use slug::{slugify, slugify_with, SlugOptions, Transliteration};
+
+#[cfg(feature = "seo")]
+mod jsonld;
+#[cfg(feature = "seo")]
+pub use jsonld::{Article, BreadcrumbList, Faq, JsonLd, JsonLdError, Person};
+
+#[cfg(feature = "seo")]
+mod meta;
+#[cfg(feature = "seo")]
+pub use meta::{PageKind, PageMeta};
+#[cfg(feature = "seo")]
+mod sitemap;
+#[cfg(feature = "seo")]
+pub use sitemap::{LastMod, Sitemap};
+
+#[cfg(feature = "feeds")]
+mod feeds;
+#[cfg(feature = "feeds")]
+pub use feeds::{FeedEntry, FeedError, FeedReader};
+
+#[cfg(feature = "fonts")]
+mod fonts;
+#[cfg(feature = "fonts")]
+pub use fonts::{subset_fonts, used_chars, FontError, FontOptions, SubsetFont, WebFont};
+
+#[cfg(feature = "encodings")]
+mod encodings;
+#[cfg(feature = "encodings")]
+pub use encoding_rs;
+#[cfg(feature = "encodings")]
+pub use encodings::{OutputEncoding, OutputEncodings};
+
+#[cfg(feature = "sqlite")]
+mod sqlite;
+#[cfg(feature = "sqlite")]
+pub use rusqlite;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteError, SqliteRow, SQLITE_TABLE};
+
+#[cfg(feature = "favicon")]
+mod favicon;
+#[cfg(feature = "favicon")]
+pub use favicon::{render_favicons, FaviconError, FaviconOptions, Favicons};
+
+#[cfg(feature = "gallery")]
+mod gallery;
+#[cfg(feature = "gallery")]
+pub use gallery::{parse_photo, GalleryError, GalleryOptions, Photo};
+
+#[cfg(feature = "images")]
+mod images;
+#[cfg(feature = "images")]
+pub use images::{ImageError, ImageFormat, ImageOptions, Images, ProcessedImage};
 
 #[cfg(feature = "sass")]
 mod sass;
 #[cfg(feature = "sass")]
-pub use sass::{render_sass, SassError};
+pub use sass::{render_sass, render_sass_with, SassError, SassOptions};
+
+#[cfg(feature = "js")]
+mod js;
+#[cfg(feature = "js")]
+pub use js::{bundle_js, render_js, JsBundle, JsError, JsOptions};
+
+#[cfg(feature = "encrypt")]
+mod encrypt;
+#[cfg(feature = "encrypt")]
+pub use encrypt::{EncryptError, Encryption, IsPrivate};
 
 #[cfg(feature = "watch")]
 mod watch;
 #[cfg(feature = "watch")]
-pub use watch::watch;
+pub use watch::{
+    watch, watch_changes, watch_until, watch_with, ChangeEvent, ChangeRouter, ChangeSet,
+    RebuildRoutes, WatchOptions,
+};
+
+#[cfg(any(feature = "watch", feature = "serve"))]
+mod shutdown;
+#[cfg(any(feature = "watch", feature = "serve"))]
+pub use shutdown::Shutdown;
+
+#[cfg(feature = "serve")]
+mod livereload;
+#[cfg(feature = "serve")]
+mod preview_diff;
+#[cfg(feature = "serve")]
+mod proxy;
+#[cfg(feature = "serve")]
+mod serve;
+#[cfg(feature = "serve")]
+pub use livereload::LiveReload;
+#[cfg(feature = "serve")]
+pub use preview_diff::{changed_pages, preview_diff, ChangeKind, PageChange};
+#[cfg(feature = "serve")]
+pub use serve::{serve, Listener, Overlay, ServeError, Server};
+
+#[cfg(feature = "https")]
+mod tls;
+#[cfg(feature = "https")]
+pub use tls::Tls;
+
+#[cfg(feature = "cli")]
+mod cli;
+#[cfg(feature = "cli")]
+pub use cli::{cli, Cli, CliError, CliOptions};
 
 /// The error type returned in this crate.
 #[derive(thiserror::Error, Debug)]
@@ -71,9 +328,94 @@ pub enum Error {
     /// Error occurred during render.
     #[error("render error: {0:?}")]
     Render(Box<dyn fmt::Debug + Send + Sync>),
+    /// The sort key of an item couldn't be computed.
+    #[error("sort key error for item {index}: {error}")]
+    SortKey {
+        /// The position of the item before sorting.
+        index: usize,
+        /// The error returned by the key function.
+        #[source]
+        error: Box<dyn std::error::Error + Send + Sync>,
+    },
     /// File already exists at the destination path.
     #[error("file exists: {0}")]
     FileExists(PathBuf),
+    /// No partial with the given name is registered.
+    #[error("unknown partial: {0}")]
+    UnknownPartial(String),
+    /// No layout with the given name is registered.
+    #[error("unknown layout: {0}")]
+    UnknownLayout(String),
+    /// The directory isn't marked as pichu output, so it won't be deleted
+    /// or marked.
+    #[error("not a pichu build directory: {0}")]
+    NotBuildDir(PathBuf),
+    /// The directory is the filesystem root, the home directory or
+    /// contains the current directory, so it's never used as build output.
+    #[error("refusing to use as build directory: {0}")]
+    ProtectedDir(PathBuf),
+    /// Two source files render to the same output path.
+    #[error("output {} claimed by both {} and {}", path.display(), first.display(), second.display())]
+    OutputConflict {
+        /// The output path.
+        path: PathBuf,
+        /// The source that claimed the path first.
+        first: PathBuf,
+        /// The source that claimed the path second.
+        second: PathBuf,
+    },
+    /// Two items share a key that should be unique, e.g. a slug.
+    #[error("duplicate key {key} in {} and {}", first.display(), second.display())]
+    Duplicate {
+        /// The key, formatted with `Debug`.
+        key: String,
+        /// The source of the first item with the key.
+        first: PathBuf,
+        /// The source of the second item with the key.
+        second: PathBuf,
+    },
+    /// A glob matched fewer files than expected.
+    #[error("{pattern} matched {found} files, expected at least {min}")]
+    TooFewMatches {
+        /// The glob pattern.
+        pattern: String,
+        /// The minimum number of files expected.
+        min: usize,
+        /// The number of files matched.
+        found: usize,
+    },
+    /// A globbed path isn't inside the root set with [`Glob::with_root`].
+    #[error("{} is outside of root {}", path.display(), root.display())]
+    OutsideRoot {
+        /// The path outside the root.
+        path: PathBuf,
+        /// The root.
+        root: PathBuf,
+    },
+    /// A symlink was found where none are allowed.
+    #[error("symlink not allowed: {0}")]
+    Symlink(PathBuf),
+    /// Pages are over their weight budget.
+    #[error("{} over weight budget: {}", .0.len(), .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    OverBudget(Vec<BudgetViolation>),
+    /// Files don't match the integrity manifest, see [`verify`].
+    #[error("{} files failed verification: {}", .0.len(), .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Integrity(Vec<IntegrityViolation>),
+    /// Lint rules found problems, see [`lint::Linter::fail_on`].
+    #[error("{}", .0.to_string().trim_end())]
+    Lint(lint::LintReport),
+    /// There were no items to render to the path.
+    #[error("nothing to render to {0}")]
+    Empty(PathBuf),
+    /// A path isn't a valid URL path.
+    #[error("invalid url path: {0}")]
+    InvalidUrlPath(String),
+    /// Build steps are misconfigured, e.g. depend on each other in a cycle.
+    #[error("invalid build steps: {0}")]
+    InvalidSteps(String),
+    /// One or more build steps failed, as `(step, error)` pairs.
+    #[error("steps failed: {}", .0.iter().map(|(step, e)| format!("{step}: {e:?}")).collect::<Vec<_>>().join(", "))]
+    Steps(Vec<(String, Box<dyn fmt::Debug + Send + Sync>)>),
 }
 
 /// Like [`fs::write`], but creates directories as necessary.
@@ -82,11 +424,7 @@ pub enum Error {
 ///
 /// Returns an error if the parent directory cannot be created or if the file cannot be written.
 pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), io::Error> {
-    if let Some(parent) = path.as_ref().parent() {
-        fs::create_dir_all(parent)?;
-    }
-    fs::write(path, contents)?;
-    Ok(())
+    output::write_optimistic(path.as_ref(), contents.as_ref())
 }
 
 /// Copy the contents of a directory into another, recursively.
@@ -96,34 +434,38 @@ pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> Result<(), i
 ///
 /// Returns an error if directories cannot be created, files cannot be copied, or if a file already exists at the destination.
 pub fn copy_dir(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Error> {
-    fs::create_dir_all(to.as_ref())?;
-    fs::read_dir(from.as_ref())?
-        .map(|entry| {
-            let entry = entry?;
-            let file_name = entry.file_name();
-
-            let file_name_str = file_name.to_string_lossy();
-            if file_name_str.starts_with('.') && file_name_str != ".well-known" {
-                return Ok(());
-            }
-
-            let new_path = to.as_ref().join(file_name);
-            if entry.path().is_dir() {
-                fs::create_dir(&new_path)?;
-                copy_dir(entry.path(), &new_path)?;
-            } else {
-                if new_path.exists() {
-                    return Err(Error::FileExists(new_path));
-                }
-
-                let path = entry.path();
-                fs::copy(path, new_path)?;
-            }
+    copy_dir_with(from, to, &CopyOptions::default())
+}
 
-            Ok(())
-        })
-        .collect::<Result<Vec<()>, Error>>()?;
-    Ok(())
+/// Copy a single file, e.g. `resume.pdf`, to `to` within the output
+/// directory, creating directories as necessary and replacing an existing
+/// file. If `fingerprint` is set, a `[hash]` placeholder in the file name
+/// is replaced with a hash of the contents, for cache busting, otherwise
+/// it's removed. The copy is registered in the manifest under the
+/// destination without the placeholder, e.g. `files/resume.pdf`, and its
+/// URL is returned.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read or written, or
+/// [`Error::InvalidUrlPath`] if the destination isn't inside the output
+/// directory.
+pub fn copy_file(
+    manifest: &AssetManifest,
+    from: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+    fingerprint: bool,
+) -> Result<UrlPath, Error> {
+    let (from, out_dir, to) = (from.as_ref(), out_dir.as_ref(), to.as_ref());
+    let contents = fs::read(from)?;
+    let hash = fingerprint.then(|| manifest::content_hash(&contents));
+    let dest = out_dir.join(manifest::fill_hash(to, hash.as_deref()));
+    write(&dest, contents)?;
+    let name = manifest::fill_hash(to, None)
+        .to_string_lossy()
+        .replace('\\', "/");
+    manifest.register_output(name, out_dir, &dest)
 }
 
 /// Get a list of paths that match the given glob.
@@ -133,20 +475,106 @@ pub fn copy_dir(from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<(), Erro
 /// Returns an error if the glob pattern is invalid or if there are filesystem access issues.
 pub fn glob(glob: impl AsRef<str>) -> Result<Glob, Error> {
     let paths = glob::glob(glob.as_ref())?.collect::<Result<Vec<PathBuf>, glob::GlobError>>()?;
-    Ok(Glob { paths })
+    Ok(Glob {
+        pattern: glob.as_ref().to_string(),
+        paths,
+        root: None,
+    })
 }
 
 /// A list of paths, probably created by [`glob`].
 #[derive(Debug)]
 pub struct Glob {
+    pattern: String,
     paths: Vec<PathBuf>,
+    root: Option<PathBuf>,
 }
 
 impl Glob {
+    /// Apply the symlink policy to the matched paths, which include files
+    /// in symlinked directories by default. Only symlinks below the part of
+    /// the pattern without wildcards are considered, so e.g. a symlinked
+    /// temporary directory doesn't count.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Symlink`] with the first symlink found if the policy
+    /// is [`SymlinkPolicy::Error`].
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Result<Self, Error> {
+        if matches!(symlinks, SymlinkPolicy::Follow | SymlinkPolicy::Preserve) {
+            return Ok(self);
+        }
+        let base = literal_base(&self.pattern);
+        let mut kept = Vec::with_capacity(self.paths.len());
+        for path in self.paths {
+            let link = path
+                .ancestors()
+                .take_while(|ancestor| *ancestor != base && ancestor.starts_with(&base))
+                .find(|ancestor| ancestor.is_symlink());
+            match (link, symlinks) {
+                (Some(link), SymlinkPolicy::Error) => {
+                    return Err(Error::Symlink(link.to_path_buf()))
+                }
+                (Some(_), _) => {}
+                (None, _) => kept.push(path),
+            }
+        }
+        self.paths = kept;
+        Ok(self)
+    }
+
+    /// Compute relative paths of the parsed items against `root` instead of
+    /// the part of the pattern without wildcards, e.g. to keep `2024/` in
+    /// the relative path of `content/blog/2024/hello.md` globbed with
+    /// `content/blog/*/*.md`, see `Markdown::relative_path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutsideRoot`] with the first path that isn't inside
+    /// the root.
+    pub fn with_root(mut self, root: impl Into<PathBuf>) -> Result<Self, Error> {
+        let root = root.into();
+        if let Some(path) = self.paths.iter().find(|path| !path.starts_with(&root)) {
+            return Err(Error::OutsideRoot {
+                path: path.clone(),
+                root,
+            });
+        }
+        self.root = Some(root);
+        Ok(self)
+    }
+
+    /// The directory relative paths are computed against: the one set with
+    /// [`Glob::with_root`], or else the part of the pattern without
+    /// wildcards, e.g. `content/blog` for `content/blog/**/*.md`.
+    #[must_use]
+    pub fn root(&self) -> PathBuf {
+        self.root
+            .clone()
+            .unwrap_or_else(|| literal_base(&self.pattern))
+    }
+
+    /// Make sure the glob matched at least `min` files, so a mistyped or
+    /// moved pattern fails the build instead of leaving out a section.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TooFewMatches`] if fewer files matched.
+    pub fn expect_min(self, min: usize) -> Result<Self, Error> {
+        if self.paths.len() < min {
+            return Err(Error::TooFewMatches {
+                pattern: self.pattern,
+                min,
+                found: self.paths.len(),
+            });
+        }
+        Ok(self)
+    }
+
     /// Parse the files using the provided parse function.
     pub fn parse<T: Send + Sync>(self, parse_fn: impl Fn(PathBuf) -> T + Send + Sync) -> Parsed<T> {
         let items = self.paths.into_par_iter().map(parse_fn).collect::<Vec<T>>();
-        Parsed { items }
+        Parsed::from(items)
     }
 
     /// Parse the files in parallel using the provided `parse_fn`.
@@ -164,17 +592,62 @@ impl Glob {
             .map(parse_fn)
             .collect::<Result<Vec<T>, E>>()
             .map_err(|e| Error::Parse(Box::new(e)))?;
-        Ok(Parsed { items })
+        Ok(Parsed::from(items))
     }
 }
 
+/// The leading components of a glob pattern without wildcards.
+pub(crate) fn literal_base(pattern: &str) -> PathBuf {
+    Path::new(pattern)
+        .components()
+        .take_while(|component| {
+            !component
+                .as_os_str()
+                .to_string_lossy()
+                .contains(['*', '?', '['])
+        })
+        .collect()
+}
+
 /// Parsed is a list of parsed items, ready to be sorted and rendered.
+///
+/// Items are kept in a deterministic order, even though they're processed
+/// in parallel: parsed items are in the order of their paths, which
+/// [`glob`] sorts alphabetically, and sorts are stable, so items with equal
+/// keys keep their relative order. Functions called with all items, like
+/// the one passed to [`Parsed::render_all`], see them in this order, and
+/// functions called per item, like the one passed to
+/// [`Parsed::render_each_indexed`], get their position in it.
+/// Use [`Parsed::unordered`] to trade this for speed where it doesn't
+/// matter.
 #[derive(Debug, Clone)]
 pub struct Parsed<T: Send + Sync> {
     items: Vec<T>,
+    unordered: bool,
+}
+
+impl<T: Send + Sync> From<Vec<T>> for Parsed<T> {
+    fn from(items: Vec<T>) -> Self {
+        Self {
+            items,
+            unordered: false,
+        }
+    }
 }
 
 impl<T: Send + Sync> Parsed<T> {
+    /// Allow sorts to put items with equal keys in any order, which is
+    /// faster. Use it when keys are unique or the order of ties doesn't
+    /// matter. This only affects sorting; items are always parsed,
+    /// rendered and passed to functions in their current order. The
+    /// setting is kept by transformations like [`Parsed::permalinks`], but
+    /// a `Parsed` created with [`From`] is ordered again.
+    #[must_use]
+    pub fn unordered(mut self) -> Self {
+        self.unordered = true;
+        self
+    }
+
     /// Sort the items by the key provided, ascending.
     #[must_use]
     pub fn sort_by_key<K, F>(mut self, f: F) -> Self
@@ -182,19 +655,27 @@ impl<T: Send + Sync> Parsed<T> {
         F: Fn(&T) -> K + Sync,
         K: Ord,
     {
-        self.items.par_sort_by_key(f);
+        if self.unordered {
+            self.items.par_sort_unstable_by_key(f);
+        } else {
+            self.items.par_sort_by_key(f);
+        }
         self
     }
 
-    /// Sort the items by the key provided, descending.
+    /// Sort the items by the key provided, descending. Items with equal
+    /// keys keep their relative order.
     #[must_use]
     pub fn sort_by_key_reverse<K, F>(mut self, f: F) -> Self
     where
         F: Fn(&T) -> K + Sync,
         K: Ord,
     {
-        self.items.par_sort_by_key(f);
-        self.items.reverse();
+        if self.unordered {
+            self.items.par_sort_unstable_by(|a, b| f(b).cmp(&f(a)));
+        } else {
+            self.items.par_sort_by(|a, b| f(b).cmp(&f(a)));
+        }
         self
     }
 
@@ -208,16 +689,41 @@ impl<T: Send + Sync> Parsed<T> {
         render_fn: impl Fn(&T) -> S + Send + Sync,
         build_path_fn: impl Fn(&T) -> P + Send + Sync,
     ) -> Result<Self, Error> {
-        self.items
+        let files = self
+            .items
             .par_iter()
             .map(|item| {
-                let content = render_fn(item);
-                (item, content)
+                let content = render_fn(item).into().into_bytes();
+                (build_path_fn(item).as_ref().to_path_buf(), content)
             })
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .map(|(item, content)| write(build_path_fn(item), content.into()).map_err(Error::IO))
-            .collect::<Result<Vec<_>, Error>>()?;
+            .collect::<Vec<_>>();
+        output::write_batch(files)?;
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_each`], but also passes the position of the
+    /// item and the number of items to the render function, e.g. to show
+    /// "post 3 of 120" or render the first item differently.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be written to the filesystem.
+    pub fn render_each_indexed<P: AsRef<Path>, S: Into<String> + Send>(
+        self,
+        render_fn: impl Fn(usize, usize, &T) -> S + Send + Sync,
+        build_path_fn: impl Fn(&T) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let total = self.items.len();
+        let files = self
+            .items
+            .par_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                let content = render_fn(index, total, item).into().into_bytes();
+                (build_path_fn(item).as_ref().to_path_buf(), content)
+            })
+            .collect::<Vec<_>>();
+        output::write_batch(files)?;
         Ok(self)
     }
 
@@ -235,17 +741,78 @@ impl<T: Send + Sync> Parsed<T> {
         render_fn: impl Fn(&T) -> Result<S, E> + Send + Sync,
         build_path_fn: impl Fn(&T) -> P + Send + Sync,
     ) -> Result<Self, Error> {
-        self.items
+        let files = self
+            .items
             .par_iter()
             .map(|item| {
-                let content = render_fn(item)?;
-                Ok((item, content))
+                let content = render_fn(item)?.into().into_bytes();
+                Ok((build_path_fn(item).as_ref().to_path_buf(), content))
             })
             .collect::<Result<Vec<_>, E>>()
-            .map_err(|e| Error::Render(Box::new(e)))?
-            .into_par_iter()
-            .map(|(item, content)| write(build_path_fn(item), content.into()).map_err(Error::IO))
-            .collect::<Result<Vec<_>, Error>>()?;
+            .map_err(|e| Error::Render(Box::new(e)))?;
+        output::write_batch(files)?;
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_each`], but skips rendering and writing items
+    /// whose key is unchanged since the last render recorded in `cache`.
+    /// The key should cover everything the output depends on, e.g. the item
+    /// itself plus a version or hash of the template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be written to the filesystem.
+    pub fn render_each_cached<K: Hash, P: AsRef<Path>, S: Into<String> + Send>(
+        self,
+        cache: &RenderCache,
+        key_fn: impl Fn(&T) -> K + Send + Sync,
+        render_fn: impl Fn(&T) -> S + Send + Sync,
+        build_path_fn: impl Fn(&T) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let (files, keys): (Vec<_>, Vec<_>) = self
+            .items
+            .par_iter()
+            .filter_map(|item| {
+                let path = build_path_fn(item).as_ref().to_path_buf();
+                let key = cache::fingerprint(&key_fn(item));
+                if cache.is_fresh(&path, &key) {
+                    return None;
+                }
+                let content = render_fn(item).into().into_bytes();
+                Some(((path.clone(), content), (path, key)))
+            })
+            .unzip();
+        output::write_batch(files)?;
+        cache.insert(keys);
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_each`], but the render function can declare the
+    /// inputs it used, which are recorded per output in `graph`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be written to the filesystem.
+    pub fn render_each_with_deps<P: AsRef<Path>, S: Into<String> + Send>(
+        self,
+        graph: &DepGraph,
+        render_fn: impl Fn(&T, &mut Deps) -> S + Send + Sync,
+        build_path_fn: impl Fn(&T) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let (files, deps): (Vec<_>, Vec<_>) = self
+            .items
+            .par_iter()
+            .map(|item| {
+                let path = build_path_fn(item).as_ref().to_path_buf();
+                let mut deps = Deps::new();
+                let content = render_fn(item, &mut deps).into().into_bytes();
+                ((path.clone(), content), (path, deps))
+            })
+            .unzip();
+        output::write_batch(files)?;
+        for (path, deps) in deps {
+            graph.record(path, deps);
+        }
         Ok(self)
     }
 
@@ -264,6 +831,79 @@ impl<T: Send + Sync> Parsed<T> {
         Ok(self)
     }
 
+    /// Like [`Parsed::render_all`], for aggregates that aren't text, e.g. an
+    /// icon sprite PNG, a combined PDF or a search database.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written to the filesystem.
+    pub fn render_all_bytes<B: Into<Vec<u8>>>(
+        self,
+        render_fn: impl Fn(&Vec<T>) -> B,
+        dest_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let content = render_fn(&self.items);
+        write(dest_path, content.into())?;
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_all`], but renders once and writes the result to
+    /// every destination within the output directory, e.g. to keep serving a
+    /// page at a legacy URL. Each destination is registered in the manifest
+    /// under its path relative to the output directory, like
+    /// `blog/index.html`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be written to the filesystem.
+    pub fn render_all_to<S: Into<String>, P: AsRef<Path>>(
+        self,
+        manifest: &AssetManifest,
+        out_dir: impl AsRef<Path>,
+        render_fn: impl Fn(&Vec<T>) -> S,
+        dest_paths: impl IntoIterator<Item = P>,
+    ) -> Result<Self, Error> {
+        let out_dir = out_dir.as_ref();
+        let content = render_fn(&self.items).into().into_bytes();
+        let files = dest_paths
+            .into_iter()
+            .map(|path| (out_dir.join(path), content.clone()))
+            .collect::<Vec<_>>();
+        let outputs = files
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        output::write_batch(files)?;
+        for output in outputs {
+            let name = output
+                .strip_prefix(out_dir)
+                .unwrap_or(&output)
+                .to_string_lossy()
+                .replace('\\', "/");
+            manifest.register_output(name, out_dir, &output)?;
+        }
+        Ok(self)
+    }
+
+    /// Like [`Parsed::render_all`], but the render function can declare the
+    /// inputs it used, which are recorded for `dest_path` in `graph`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written to the filesystem.
+    pub fn render_all_with_deps<S: Into<String>>(
+        self,
+        graph: &DepGraph,
+        render_fn: impl Fn(&Vec<T>, &mut Deps) -> S,
+        dest_path: impl AsRef<Path>,
+    ) -> Result<Self, Error> {
+        let mut deps = Deps::new();
+        let content = render_fn(&self.items, &mut deps);
+        write(&dest_path, content.into())?;
+        graph.record(dest_path.as_ref(), deps);
+        Ok(self)
+    }
+
     /// Render all items into a single destination.
     ///
     /// # Errors
@@ -279,6 +919,22 @@ impl<T: Send + Sync> Parsed<T> {
         Ok(self)
     }
 
+    /// Call the function with all items and pass them on unchanged, e.g. to
+    /// log how many there are or check an invariant mid-chain.
+    #[must_use]
+    pub fn inspect(self, inspect_fn: impl FnOnce(&[T])) -> Self {
+        inspect_fn(&self.items);
+        self
+    }
+
+    /// Call the function with each item, in order, and pass them on
+    /// unchanged.
+    #[must_use]
+    pub fn tap_each(self, mut tap_fn: impl FnMut(&T)) -> Self {
+        self.items.iter().for_each(&mut tap_fn);
+        self
+    }
+
     /// Extract the underlying `Vec<T>` for further processing.
     #[must_use]
     pub fn into_vec(self) -> Vec<T> {
@@ -290,6 +946,54 @@ impl<T: Send + Sync> Parsed<T> {
     pub fn first(&self) -> Option<&T> {
         self.items.first()
     }
+
+    /// Build a map from a key of each item to the item, e.g. slug to post,
+    /// for constant-time lookups while rendering another collection. Keys
+    /// are computed in parallel. If items share a key, the last one wins.
+    ///
+    /// ```
+    /// let posts = pichu::Parsed::from(vec![("hello", "Hello"), ("bye", "Bye")]);
+    /// let by_slug = posts.index_by(|(slug, _)| *slug);
+    /// assert_eq!(by_slug["bye"].1, "Bye");
+    /// ```
+    #[must_use]
+    pub fn index_by<K: Hash + Eq + Send>(
+        &self,
+        key_fn: impl Fn(&T) -> K + Send + Sync,
+    ) -> HashMap<K, &T> {
+        self.items
+            .par_iter()
+            .map(|item| (key_fn(item), item))
+            .collect::<Vec<_>>()
+            .into_iter()
+            .collect()
+    }
+
+    /// Build a map from each of several keys of the items to the items
+    /// having it, e.g. tag to posts. Keys are computed in parallel; the
+    /// items of a key keep their order, and a key listed twice on an item
+    /// maps to it once.
+    #[must_use]
+    pub fn multi_index_by<K: Hash + Eq + Send, I: IntoIterator<Item = K>>(
+        &self,
+        keys_fn: impl Fn(&T) -> I + Send + Sync,
+    ) -> HashMap<K, Vec<&T>> {
+        let keys = self
+            .items
+            .par_iter()
+            .map(|item| keys_fn(item).into_iter().collect::<Vec<_>>())
+            .collect::<Vec<_>>();
+        let mut index = HashMap::<K, Vec<&T>>::new();
+        for (item, keys) in self.items.iter().zip(keys) {
+            for key in keys {
+                let items = index.entry(key).or_default();
+                if !items.last().is_some_and(|last| std::ptr::eq(*last, item)) {
+                    items.push(item);
+                }
+            }
+        }
+        index
+    }
 }
 
 #[cfg(test)]
@@ -305,6 +1009,31 @@ mod tests {
         title: String,
     }
 
+    #[test]
+    fn test_index_by() {
+        let posts = Parsed::from(vec![
+            ("a", vec!["rust", "web"]),
+            ("b", vec!["rust", "rust"]),
+            ("a", vec!["go"]),
+        ]);
+
+        let by_slug = posts.index_by(|(slug, _)| *slug);
+        assert_eq!(by_slug.len(), 2);
+        assert_eq!(by_slug["a"].1, ["go"]);
+
+        let by_tag = posts.multi_index_by(|(_, tags)| tags.clone());
+        let slugs = |tag| {
+            by_tag[tag]
+                .iter()
+                .map(|(slug, _)| *slug)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(slugs("rust"), ["a", "b"]);
+        assert_eq!(slugs("web"), ["a"]);
+        assert_eq!(slugs("go"), ["a"]);
+        assert!(!by_tag.contains_key("zig"));
+    }
+
     #[test]
     fn test_write() -> Result<(), Box<dyn std::error::Error>> {
         let dir = env::temp_dir().join("pichu_test_write");
@@ -366,6 +1095,20 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_expect_min() -> Result<(), Error> {
+        assert!(glob("examples/content/blog/*.md")?.expect_min(1).is_ok());
+        assert!(matches!(
+            glob("examples/content/blgo/*.md")?.expect_min(1),
+            Err(Error::TooFewMatches {
+                min: 1,
+                found: 0,
+                ..
+            })
+        ));
+        Ok(())
+    }
+
     #[test]
     fn test_parse() -> Result<(), Box<dyn std::error::Error>> {
         let parsed = glob("examples/content/blog/*.md")?
@@ -378,6 +1121,86 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_ordering() -> Result<(), Box<dyn std::error::Error>> {
+        use std::{thread, time::Duration};
+
+        // Later paths are parsed faster, so they finish first.
+        let paths = (0..64u64)
+            .map(|i| PathBuf::from(format!("{i:03}.md")))
+            .collect::<Vec<_>>();
+        let parse = |path: &Path| {
+            let i = path
+                .file_stem()
+                .and_then(|stem| stem.to_str()?.parse::<u64>().ok())
+                .unwrap_or_default();
+            thread::sleep(Duration::from_micros((64 - i) * 100));
+            i
+        };
+        let glob = || Glob {
+            pattern: String::new(),
+            paths: paths.clone(),
+            root: None,
+        };
+        let expected = (0..64).collect::<Vec<_>>();
+        assert_eq!(glob().parse(|path| parse(&path)).items, expected);
+        let parsed = glob().try_parse(|path| Ok::<_, Error>(parse(path)))?;
+        assert_eq!(parsed.items, expected);
+
+        // Ties keep their order, also when sorting descending.
+        let by_rest = |items: &[u64]| {
+            let mut sorted = items.to_vec();
+            sorted.sort_by_key(|i| i % 4);
+            sorted
+        };
+        let parsed = parsed.sort_by_key(|i| i % 4);
+        assert_eq!(parsed.items, by_rest(&expected));
+        let parsed = Parsed::from(expected.clone()).sort_by_key_reverse(|i| i % 4);
+        let mut descending = by_rest(&expected);
+        descending.sort_by_key(|i| std::cmp::Reverse(i % 4));
+        assert_eq!(parsed.items, descending);
+        let parsed = Parsed::from(expected.clone()).try_sort_by_key(|i| Ok::<_, Error>(i % 4))?;
+        assert_eq!(parsed.items, by_rest(&expected));
+        let parsed = Parsed::from(expected.clone()).sort_by_key_natural(|i| format!("v{}", i % 4));
+        assert_eq!(parsed.items, by_rest(&expected));
+
+        // Unordered sorts only guarantee the order of keys.
+        let parsed = Parsed::from(expected.clone())
+            .unordered()
+            .sort_by_key_reverse(|i| i % 4);
+        assert!(parsed.items.is_sorted_by_key(|i| std::cmp::Reverse(i % 4)));
+
+        // Render functions see the items in order.
+        let dir = env::temp_dir().join("pichu_test_ordering");
+        Parsed::from(expected.clone())
+            .render_each_indexed(
+                |index, _, item| format!("{index} {item}"),
+                |item| dir.join(format!("{item}.html")),
+            )?
+            .render_all(
+                |items| {
+                    items
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(",")
+                },
+                dir.join("all.html"),
+            )?;
+        assert_eq!(fs::read_to_string(dir.join("42.html"))?, "42 42");
+        assert_eq!(
+            fs::read_to_string(dir.join("all.html"))?,
+            expected
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[test]
     fn test_try_parse() -> Result<(), Box<dyn std::error::Error>> {
         #[derive(Deserialize)]
@@ -396,6 +1219,65 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_render_all_to() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_all_to");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let manifest = AssetManifest::new();
+        let renders = std::cell::Cell::new(0);
+        Parsed::from(vec!["a", "b"]).render_all_to(
+            &manifest,
+            &dir,
+            |items| {
+                renders.set(renders.get() + 1);
+                items.join(", ")
+            },
+            ["index.html", "blog/index.html"],
+        )?;
+        assert_eq!(renders.get(), 1);
+        assert_eq!(manifest.get("index.html").as_deref(), Some("/"));
+        assert_eq!(manifest.get("blog/index.html").as_deref(), Some("/blog/"));
+        assert_eq!(fs::read_to_string(dir.join("index.html"))?, "a, b");
+        assert_eq!(fs::read_to_string(dir.join("blog/index.html"))?, "a, b");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_all_bytes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_all_bytes");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        Parsed::from(vec![0xFF_u8, 0x00, 0xFE])
+            .render_all_bytes(|items| items.repeat(2), dir.join("sprite.bin"))?;
+        assert_eq!(
+            fs::read(dir.join("sprite.bin"))?,
+            [0xFF, 0x00, 0xFE].repeat(2)
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_inspect_tap_each() {
+        let mut count = 0;
+        let mut seen = Vec::new();
+        let parsed = Parsed::from(vec![3, 1, 2])
+            .inspect(|items| count = items.len())
+            .sort_by_key(|item| *item)
+            .tap_each(|item| seen.push(*item));
+        assert_eq!(count, 3);
+        assert_eq!(seen, [1, 2, 3]);
+        assert_eq!(parsed.into_vec(), [1, 2, 3]);
+    }
+
     #[test]
     fn test_render_each_all() -> Result<(), Box<dyn std::error::Error>> {
         let dir = env::temp_dir().join("pichu_test_render_each_all");
@@ -427,6 +1309,75 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_render_each_indexed() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_each_indexed");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        Parsed::from(vec!["a", "b", "c"]).render_each_indexed(
+            |index, total, item| format!("{item}: {} of {total}", index + 1),
+            |item| dir.join(format!("{item}.html")),
+        )?;
+        assert_eq!(fs::read_to_string(dir.join("a.html"))?, "a: 1 of 3");
+        assert_eq!(fs::read_to_string(dir.join("c.html"))?, "c: 3 of 3");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_each_cached() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let dir = env::temp_dir().join("pichu_test_render_each_cached");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let renders = AtomicUsize::new(0);
+        let render = |cache: &RenderCache, template_version: u32| {
+            glob("examples/content/blog/*.md")?
+                .parse_markdown::<Blog>()?
+                .render_each_cached(
+                    cache,
+                    |blog| (blog.markdown.clone(), template_version),
+                    |blog| {
+                        renders.fetch_add(1, Ordering::SeqCst);
+                        format!("<h1>{}</h1>", blog.frontmatter.title)
+                    },
+                    |blog| dir.join(format!("blog/{}.html", blog.basename)),
+                )
+                .map(|_| ())
+        };
+
+        let cache = RenderCache::new();
+        render(&cache, 1)?;
+        render(&cache, 1)?;
+        assert_eq!(renders.load(Ordering::SeqCst), 1);
+
+        // A different key renders again
+        render(&cache, 2)?;
+        assert_eq!(renders.load(Ordering::SeqCst), 2);
+
+        // So does a deleted output
+        fs::remove_file(dir.join("blog/hello-world.html"))?;
+        render(&cache, 2)?;
+        assert_eq!(renders.load(Ordering::SeqCst), 3);
+
+        // The cache survives a round trip to disk, with fingerprints that
+        // don't depend on the Rust release
+        assert_eq!(cache::fingerprint(&"hello"), "dcbcddea18259109");
+        cache.save(dir.join("render-cache"))?;
+        let cache = RenderCache::load(dir.join("render-cache"))?;
+        render(&cache, 2)?;
+        assert_eq!(renders.load(Ordering::SeqCst), 3);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
     #[test]
     fn test_try_render_each_all() -> Result<(), Box<dyn std::error::Error>> {
         #[derive(thiserror::Error, Debug)]
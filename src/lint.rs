@@ -0,0 +1,550 @@
+//! Content linting with custom rules, run in parallel over a collection.
+//!
+//! ```no_run
+//! # #[derive(serde::Deserialize, Debug)]
+//! # struct Post { title: String, tags: Vec<String> }
+//! use pichu::{lint::{self, Diagnostic, Linter, Severity}, Markdown};
+//!
+//! let posts = pichu::glob("content/blog/*.md")?.parse_markdown::<Post>()?;
+//! let report = Linter::new()
+//!     .rule("title", lint::required("missing title", |post: &Markdown<Post>| {
+//!         !post.frontmatter.title.is_empty()
+//!     }))
+//!     .rule("tags", |post: &Markdown<Post>| {
+//!         if post.frontmatter.tags.is_empty() {
+//!             vec![Diagnostic::warning("no tags")]
+//!         } else {
+//!             Vec::new()
+//!         }
+//!     })
+//!     .rule("code-width", lint::code_line_length(80))
+//!     .rule("words", lint::banned_words(&["simply", "obviously"]))
+//!     .rule("spelling", lint::prose(|_text| {
+//!         // Call a spellchecker here, returning (offset, message) pairs.
+//!         Vec::new()
+//!     }))
+//!     .fail_on(Severity::Error)
+//!     .check(&posts)?;
+//! eprint!("{report}");
+//! # Ok::<(), pichu::Error>(())
+//! ```
+
+use rayon::prelude::*;
+use std::{fmt, path::PathBuf};
+
+use crate::{Error, HasSource, Parsed};
+
+type RuleFn<T> = dyn Fn(&T) -> Vec<Diagnostic> + Send + Sync;
+
+/// How bad a [`Diagnostic`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Severity {
+    /// Worth a look, but fine to publish.
+    Warning,
+    /// Shouldn't be published.
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Warning => "warning",
+            Self::Error => "error",
+        })
+    }
+}
+
+/// A problem found by a lint rule. Rules create them with
+/// [`Diagnostic::warning`] or [`Diagnostic::error`], the [`Linter`] fills in
+/// the source file and the name of the rule.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// The source file of the item.
+    pub path: PathBuf,
+    /// The line in the source, if known.
+    pub line: Option<usize>,
+    /// The name of the rule.
+    pub rule: String,
+    /// How bad the problem is.
+    pub severity: Severity,
+    /// What's wrong.
+    pub message: String,
+}
+
+impl Diagnostic {
+    /// A diagnostic with the given severity.
+    #[must_use]
+    pub fn new(severity: Severity, message: impl Into<String>) -> Self {
+        Self {
+            path: PathBuf::new(),
+            line: None,
+            rule: String::new(),
+            severity,
+            message: message.into(),
+        }
+    }
+
+    /// A warning.
+    #[must_use]
+    pub fn warning(message: impl Into<String>) -> Self {
+        Self::new(Severity::Warning, message)
+    }
+
+    /// An error.
+    #[must_use]
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Severity::Error, message)
+    }
+
+    /// Set the line in the source, starting at 1.
+    #[must_use]
+    pub fn line(mut self, line: usize) -> Self {
+        self.line = Some(line);
+        self
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.path.display())?;
+        if let Some(line) = self.line {
+            write!(f, ":{line}")?;
+        }
+        write!(f, ": {}[{}]: {}", self.severity, self.rule, self.message)
+    }
+}
+
+/// The diagnostics of a [`Linter`] run, sorted by path and line. Displays
+/// as one diagnostic per line followed by a summary.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LintReport {
+    /// The diagnostics.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl LintReport {
+    /// The number of diagnostics with the given severity.
+    #[must_use]
+    pub fn count(&self, severity: Severity) -> usize {
+        self.diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == severity)
+            .count()
+    }
+
+    /// Returns true if no rule found a problem.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.diagnostics.is_empty()
+    }
+}
+
+impl fmt::Display for LintReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for diagnostic in &self.diagnostics {
+            writeln!(f, "{diagnostic}")?;
+        }
+        writeln!(
+            f,
+            "{} errors, {} warnings",
+            self.count(Severity::Error),
+            self.count(Severity::Warning)
+        )
+    }
+}
+
+/// A set of named lint rules.
+pub struct Linter<T> {
+    rules: Vec<(String, Box<RuleFn<T>>)>,
+    fail_on: Option<Severity>,
+}
+
+impl<T> Linter<T> {
+    /// Create a linter without rules.
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            fail_on: None,
+        }
+    }
+
+    /// Add a rule, called for every item.
+    #[must_use]
+    pub fn rule(
+        mut self,
+        name: impl Into<String>,
+        rule_fn: impl Fn(&T) -> Vec<Diagnostic> + Send + Sync + 'static,
+    ) -> Self {
+        self.rules.push((name.into(), Box::new(rule_fn)));
+        self
+    }
+
+    /// Make [`Linter::check`] fail if there's a diagnostic of the given
+    /// severity or worse, e.g. to exit with a non-zero code in CI. By
+    /// default it never fails.
+    #[must_use]
+    pub fn fail_on(mut self, severity: Severity) -> Self {
+        self.fail_on = Some(severity);
+        self
+    }
+}
+
+impl<T: HasSource + Send + Sync> Linter<T> {
+    /// Run all rules on all items in parallel.
+    #[must_use]
+    pub fn lint(&self, items: &Parsed<T>) -> LintReport {
+        let mut diagnostics = items
+            .items
+            .par_iter()
+            .flat_map_iter(|item| {
+                self.rules.iter().flat_map(move |(name, rule_fn)| {
+                    rule_fn(item).into_iter().map(move |mut diagnostic| {
+                        item.source().clone_into(&mut diagnostic.path);
+                        diagnostic.rule.clone_from(name);
+                        diagnostic
+                    })
+                })
+            })
+            .collect::<Vec<_>>();
+        diagnostics.sort_by(|a, b| (&a.path, a.line).cmp(&(&b.path, b.line)));
+        LintReport { diagnostics }
+    }
+
+    /// Run all rules on all items and return the report.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Lint`] with the report if there's a diagnostic of
+    /// the severity set with [`Linter::fail_on`] or worse.
+    pub fn check(&self, items: &Parsed<T>) -> Result<LintReport, Error> {
+        let report = self.lint(items);
+        let failed = self.fail_on.is_some_and(|fail_on| {
+            report
+                .diagnostics
+                .iter()
+                .any(|diagnostic| diagnostic.severity >= fail_on)
+        });
+        if failed {
+            return Err(Error::Lint(report));
+        }
+        Ok(report)
+    }
+}
+
+impl<T> Default for Linter<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for Linter<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Linter")
+            .field(
+                "rules",
+                &self.rules.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+            )
+            .field("fail_on", &self.fail_on)
+            .finish()
+    }
+}
+
+/// A rule reporting an error with the message if the check returns false,
+/// e.g. for missing titles or tags.
+pub fn required<T>(
+    message: impl Into<String>,
+    check: impl Fn(&T) -> bool + Send + Sync,
+) -> impl Fn(&T) -> Vec<Diagnostic> + Send + Sync {
+    let message = message.into();
+    move |item| {
+        if check(item) {
+            Vec::new()
+        } else {
+            vec![Diagnostic::error(message.clone())]
+        }
+    }
+}
+
+/// A rule warning about lines in fenced code blocks longer than `max`
+/// characters, which would scroll on small screens.
+#[cfg(feature = "markdown")]
+pub fn code_line_length<T>(
+    max: usize,
+) -> impl Fn(&crate::Markdown<T>) -> Vec<Diagnostic> + Send + Sync {
+    move |markdown| {
+        let mut fence: Option<&str> = None;
+        let mut diagnostics = Vec::new();
+        for (i, line) in markdown.markdown.lines().enumerate() {
+            let trimmed = line.trim_start();
+            match fence {
+                Some(marker) if trimmed.starts_with(marker) => fence = None,
+                Some(_) => {
+                    let length = line.chars().count();
+                    if length > max {
+                        diagnostics.push(
+                            Diagnostic::warning(format!(
+                                "code line is {length} characters long, more than {max}"
+                            ))
+                            .line(markdown.line_offset + i + 1),
+                        );
+                    }
+                }
+                None if trimmed.starts_with("```") => fence = Some("```"),
+                None if trimmed.starts_with("~~~") => fence = Some("~~~"),
+                None => {}
+            }
+        }
+        diagnostics
+    }
+}
+
+/// A rule warning about words or phrases in the markdown, ignoring case.
+#[cfg(feature = "markdown")]
+pub fn banned_words<T>(
+    words: &[&str],
+) -> impl Fn(&crate::Markdown<T>) -> Vec<Diagnostic> + Send + Sync {
+    let words = words
+        .iter()
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<_>>();
+    move |markdown| {
+        let mut diagnostics = Vec::new();
+        for (i, line) in markdown.markdown.lines().enumerate() {
+            let line = line.to_lowercase();
+            for word in &words {
+                if contains_word(&line, word) {
+                    diagnostics.push(
+                        Diagnostic::warning(format!("banned word: {word}"))
+                            .line(markdown.line_offset + i + 1),
+                    );
+                }
+            }
+        }
+        diagnostics
+    }
+}
+
+/// A block of prose in markdown, like a paragraph, heading or table cell,
+/// as plain text without markup, code or HTML. Line breaks in the block are
+/// kept, so offsets in the text can be mapped back to lines of the file.
+#[cfg(feature = "markdown")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TextBlock {
+    /// The plain text.
+    pub text: String,
+    /// The line of the file the block starts on.
+    pub line: usize,
+    /// The column the block starts at, starting at 1.
+    pub column: usize,
+}
+
+#[cfg(feature = "markdown")]
+impl TextBlock {
+    /// The line of the file a byte offset in the text is on.
+    #[must_use]
+    pub fn line_at(&self, offset: usize) -> usize {
+        let before = self.text.get(..offset).unwrap_or(&self.text);
+        self.line + before.matches('\n').count()
+    }
+}
+
+/// The blocks of prose in a markdown file, in order, to pass to external
+/// prose checkers like spellcheckers. See [`prose`] to turn their findings
+/// into diagnostics.
+#[cfg(feature = "markdown")]
+#[must_use]
+pub fn text_blocks<T>(markdown: &crate::Markdown<T>) -> Vec<TextBlock> {
+    use comrak::{nodes::NodeValue, parse_document, Arena, Options};
+
+    let mut options = Options::default();
+    options.extension.table = true;
+    options.extension.strikethrough = true;
+    options.extension.footnotes = true;
+    options.extension.tasklist = true;
+    let arena = Arena::new();
+    let root = parse_document(&arena, &markdown.markdown, &options);
+    root.descendants()
+        .filter_map(|node| {
+            let ast = node.data.borrow();
+            if !matches!(
+                ast.value,
+                NodeValue::Paragraph | NodeValue::Heading(_) | NodeValue::TableCell
+            ) {
+                return None;
+            }
+            let mut text = String::new();
+            for inline in node.descendants().skip(1) {
+                match &inline.data.borrow().value {
+                    NodeValue::Text(t) => text.push_str(t),
+                    NodeValue::SoftBreak | NodeValue::LineBreak => text.push('\n'),
+                    _ => {}
+                }
+            }
+            (!text.trim().is_empty()).then(|| TextBlock {
+                text,
+                line: markdown.line_offset + ast.sourcepos.start.line,
+                column: ast.sourcepos.start.column,
+            })
+        })
+        .collect()
+}
+
+/// A rule running a prose checker on every [`TextBlock`], which returns
+/// byte offsets in the text with a message, e.g. for misspelled words.
+/// Findings are reported as warnings on the line they're on.
+#[cfg(feature = "markdown")]
+pub fn prose<T>(
+    check: impl Fn(&str) -> Vec<(usize, String)> + Send + Sync,
+) -> impl Fn(&crate::Markdown<T>) -> Vec<Diagnostic> + Send + Sync {
+    move |markdown| {
+        text_blocks(markdown)
+            .into_iter()
+            .flat_map(|block| {
+                check(&block.text)
+                    .into_iter()
+                    .map(move |(offset, message)| {
+                        Diagnostic::warning(message).line(block.line_at(offset))
+                    })
+            })
+            .collect()
+    }
+}
+
+/// Whether `word` appears in `text` on its own, not as part of a longer word.
+#[cfg(feature = "markdown")]
+fn contains_word(text: &str, word: &str) -> bool {
+    text.match_indices(word).any(|(start, _)| {
+        let before = text[..start].chars().next_back();
+        let after = text[start + word.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    struct Page {
+        path: PathBuf,
+        title: &'static str,
+    }
+
+    impl HasSource for Page {
+        fn source(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    #[test]
+    fn test_linter() -> Result<(), Error> {
+        let pages = Parsed::from(vec![
+            Page {
+                path: PathBuf::from("b.md"),
+                title: "",
+            },
+            Page {
+                path: PathBuf::from("a.md"),
+                title: "Hello world, hello",
+            },
+        ]);
+        let linter = Linter::new()
+            .rule(
+                "title",
+                required("missing title", |page: &Page| !page.title.is_empty()),
+            )
+            .rule("hello", |page: &Page| {
+                page.title
+                    .match_indices("ello")
+                    .map(|(i, _)| Diagnostic::warning("hello again").line(i))
+                    .collect()
+            });
+
+        let report = linter.check(&pages)?;
+        assert_eq!(report.count(Severity::Error), 1);
+        assert_eq!(report.count(Severity::Warning), 2);
+        assert_eq!(
+            report.to_string(),
+            "a.md:1: warning[hello]: hello again\n\
+             a.md:14: warning[hello]: hello again\n\
+             b.md: error[title]: missing title\n\
+             1 errors, 2 warnings\n"
+        );
+
+        let linter = linter.fail_on(Severity::Error);
+        assert!(matches!(linter.check(&pages), Err(Error::Lint(r)) if r == report));
+        Ok(())
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_markdown_rules() {
+        let markdown = crate::Markdown {
+            frontmatter: (),
+            path: PathBuf::from("post.md"),
+            basename: "post".to_string(),
+            relative_path: PathBuf::from("post.md"),
+            markdown:
+                "Simply put:\n\n```\nshort\nthis line is long\n```\n\nsimplystic, not simply.\n"
+                    .to_string(),
+            line_offset: 0,
+            includes: Vec::new(),
+            html: String::new(),
+            raw_frontmatter: None,
+        };
+        let lines = |diagnostics: Vec<Diagnostic>| {
+            diagnostics
+                .iter()
+                .map(|diagnostic| diagnostic.line)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lines(code_line_length(10)(&markdown)), [Some(5)]);
+        assert_eq!(
+            lines(banned_words(&["simply"])(&markdown)),
+            [Some(1), Some(8)]
+        );
+
+        let blocks = text_blocks(&markdown);
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[1].text, "simplystic, not simply.");
+        assert_eq!((blocks[1].line, blocks[1].column), (8, 1));
+    }
+
+    #[cfg(feature = "markdown")]
+    #[test]
+    fn test_prose() {
+        let markdown = crate::Markdown {
+            frontmatter: (),
+            path: PathBuf::from("post.md"),
+            basename: "post".to_string(),
+            relative_path: PathBuf::from("post.md"),
+            markdown: "# Teh title\n\n> Some *emphasized* text with `teh code`\n> and a [teh link](/teh).\n\n```\nteh\n```\n"
+                .to_string(),
+            line_offset: 3,
+            includes: Vec::new(),
+            html: String::new(),
+            raw_frontmatter: None,
+        };
+        let blocks = text_blocks(&markdown);
+        let texts = blocks
+            .iter()
+            .map(|block| block.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            texts,
+            ["Teh title", "Some emphasized text with \nand a teh link."]
+        );
+        let spellcheck = |text: &str| {
+            text.match_indices("teh")
+                .chain(text.match_indices("Teh"))
+                .map(|(offset, _)| (offset, "did you mean the?".to_string()))
+                .collect()
+        };
+        let lines = prose(spellcheck)(&markdown)
+            .into_iter()
+            .map(|diagnostic| diagnostic.line)
+            .collect::<Vec<_>>();
+        assert_eq!(lines, [Some(4), Some(7)]);
+    }
+}
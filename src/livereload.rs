@@ -0,0 +1,237 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt, fs,
+    hash::{Hash, Hasher},
+    io,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use tiny_http::{Header, Request, Response};
+use tungstenite::{handshake::derive_accept_key, protocol::Role, Message, WebSocket};
+
+use crate::{sync::lock, UrlPath};
+
+/// The path browsers connect to for reload messages.
+pub(crate) const ENDPOINT: &str = "/__pichu/livereload";
+
+/// Injected into served HTML pages. Swaps stylesheets for `css` messages
+/// and reloads the page for everything else.
+const SCRIPT: &str = r#"<script>(() => {
+  const ws = new WebSocket((location.protocol === "https:" ? "wss://" : "ws://") + location.host + "/__pichu/livereload");
+  ws.onmessage = (event) => {
+    const message = JSON.parse(event.data);
+    if (message.type !== "css") return location.reload();
+    for (const link of document.querySelectorAll('link[rel="stylesheet"]')) {
+      const url = new URL(link.href);
+      if (!message.paths.includes(url.pathname)) continue;
+      url.searchParams.set("pichu", Date.now());
+      link.href = url.href;
+    }
+  };
+})();</script>"#;
+
+type Socket = WebSocket<Box<dyn tiny_http::ReadWrite + Send>>;
+
+/// Tells browsers connected to a [`Server`](crate::Server) to reload after a
+/// rebuild. If only stylesheets changed, they are swapped in place instead,
+/// keeping scroll position and form state.
+/// Cloning a handle shares its connections.
+#[derive(Clone, Default)]
+pub struct LiveReload {
+    sockets: Arc<Mutex<Vec<Socket>>>,
+    snapshot: Arc<Mutex<HashMap<PathBuf, u64>>>,
+}
+
+impl LiveReload {
+    /// Create a handle without connections.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Remember the contents of the output directory, to compare against in
+    /// [`LiveReload::changed`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn snapshot(&self, out_dir: impl AsRef<Path>) -> Result<(), io::Error> {
+        *lock(&self.snapshot) = hash_files(out_dir.as_ref())?;
+        Ok(())
+    }
+
+    /// Compare the output directory against the last snapshot and notify
+    /// browsers: stylesheets are swapped if only `.css` files changed,
+    /// otherwise pages are reloaded. Returns false and sends nothing if
+    /// nothing changed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read.
+    pub fn changed(&self, out_dir: impl AsRef<Path>) -> Result<bool, io::Error> {
+        let Some(message) = self.update(out_dir.as_ref())? else {
+            return Ok(false);
+        };
+        self.send(&message);
+        Ok(true)
+    }
+
+    /// Replace the snapshot with the current files, returning the message
+    /// for browsers if anything changed.
+    fn update(&self, out_dir: &Path) -> Result<Option<serde_json::Value>, io::Error> {
+        let files = hash_files(out_dir)?;
+        let mut snapshot = lock(&self.snapshot);
+        let mut changed = files
+            .iter()
+            .filter(|(path, hash)| snapshot.get(*path) != Some(hash))
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        changed.extend(
+            snapshot
+                .keys()
+                .filter(|path| !files.contains_key(*path))
+                .cloned(),
+        );
+        *snapshot = files;
+        drop(snapshot);
+
+        if changed.is_empty() {
+            return Ok(None);
+        }
+        if changed
+            .iter()
+            .all(|path| path.extension().is_some_and(|ext| ext == "css"))
+        {
+            changed.sort();
+            let paths = changed
+                .iter()
+                .filter_map(|path| UrlPath::from_output(out_dir, path).ok())
+                .map(|url| url.to_string())
+                .collect::<Vec<_>>();
+            Ok(Some(serde_json::json!({ "type": "css", "paths": paths })))
+        } else {
+            Ok(Some(serde_json::json!({ "type": "reload" })))
+        }
+    }
+
+    /// Reload all connected pages.
+    pub fn reload(&self) {
+        self.send(&serde_json::json!({ "type": "reload" }));
+    }
+
+    fn send(&self, message: &serde_json::Value) {
+        let message = Message::text(message.to_string());
+        // Disconnected browsers fail to receive and are dropped.
+        lock(&self.sockets).retain_mut(|socket| socket.send(message.clone()).is_ok());
+    }
+
+    /// Accept a websocket connection on [`ENDPOINT`].
+    pub(crate) fn connect(&self, request: Request) -> Result<(), io::Error> {
+        let Some(key) = request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv("Sec-WebSocket-Key"))
+            .map(|header| derive_accept_key(header.value.as_bytes()))
+        else {
+            return request.respond(Response::from_string("Bad Request").with_status_code(400));
+        };
+
+        // tiny_http adds the `Connection` and `Upgrade` headers.
+        let response = Response::empty(101).with_header(header("Sec-WebSocket-Accept", &key));
+        let stream = request.upgrade("websocket", response);
+        lock(&self.sockets).push(WebSocket::from_raw_socket(stream, Role::Server, None));
+        Ok(())
+    }
+}
+
+impl fmt::Debug for LiveReload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LiveReload")
+            .field("connections", &lock(&self.sockets).len())
+            .finish_non_exhaustive()
+    }
+}
+
+/// Insert the reload script into an HTML page, before `</body>` if present.
+pub(crate) fn inject(html: &str) -> String {
+    match html.rfind("</body>") {
+        Some(index) => format!("{}{SCRIPT}{}", &html[..index], &html[index..]),
+        None => format!("{html}{SCRIPT}"),
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field, value).unwrap_or_else(|()| unreachable!("valid header"))
+}
+
+fn hash_files(dir: &Path) -> Result<HashMap<PathBuf, u64>, io::Error> {
+    let mut files = HashMap::new();
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(files),
+        Err(e) => return Err(e),
+    };
+    for entry in entries {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(hash_files(&path)?);
+        } else {
+            let mut hasher = DefaultHasher::new();
+            fs::read(&path)?.hash(&mut hasher);
+            files.insert(path, hasher.finish());
+        }
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_inject() {
+        assert!(inject("<body><p>Hi</p></body>").ends_with("</script></body>"));
+        assert!(inject("<p>Hi</p>").starts_with("<p>Hi</p><script>"));
+    }
+
+    #[test]
+    fn test_changed() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("pichu_test_livereload");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("index.html"), "<h1>Hi</h1>")?;
+        crate::write(dir.join("style.css"), "h1 {}")?;
+        let live_reload = LiveReload::new();
+        live_reload.snapshot(&dir)?;
+        assert_eq!(live_reload.update(&dir)?, None);
+
+        // Only a stylesheet changed, so it's swapped in place.
+        fs::write(dir.join("style.css"), "h1 { color: red }")?;
+        assert_eq!(
+            live_reload.update(&dir)?,
+            Some(serde_json::json!({ "type": "css", "paths": ["/style.css"] }))
+        );
+        assert_eq!(*lock(&live_reload.snapshot), hash_files(&dir)?);
+
+        // A page changed along with a stylesheet, so pages are reloaded.
+        fs::write(dir.join("style.css"), "h1 { color: blue }")?;
+        fs::write(dir.join("index.html"), "<h1>Hello</h1>")?;
+        assert_eq!(
+            live_reload.update(&dir)?,
+            Some(serde_json::json!({ "type": "reload" }))
+        );
+
+        // Removing a page reloads too.
+        fs::remove_file(dir.join("index.html"))?;
+        assert_eq!(
+            live_reload.update(&dir)?,
+            Some(serde_json::json!({ "type": "reload" }))
+        );
+        assert!(!live_reload.changed(&dir)?);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
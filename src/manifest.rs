@@ -0,0 +1,212 @@
+use std::{
+    collections::BTreeMap,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{output::write_atomic, sync::lock, Error, UrlPath};
+
+/// Maps logical asset names (e.g. `main.css`) to the URLs they were written
+/// to (e.g. `/main.4f2a9c1e.css`), so templates can reference fingerprinted
+/// assets by name.
+/// Can be shared between threads and written to disk as JSON for deploy tools.
+#[derive(Debug, Default)]
+pub struct AssetManifest {
+    entries: Mutex<BTreeMap<String, String>>,
+}
+
+impl AssetManifest {
+    /// Create an empty manifest.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a manifest previously written with [`AssetManifest::save`].
+    /// Returns an empty manifest if the file doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file exists but cannot be read or isn't a valid manifest.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, io::Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => return Err(e),
+        };
+        let entries = serde_json::from_str(&contents)?;
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Write the manifest to disk as a JSON object of names to URLs.
+    /// The file is replaced in one step, so an interrupted save keeps the
+    /// previous manifest intact.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        let json = serde_json::to_string_pretty(&*lock(&self.entries))?;
+        write_atomic(path.as_ref(), json.as_bytes())
+    }
+
+    /// Register an asset, replacing any previous URL for the same name.
+    pub fn register(&self, name: impl Into<String>, url: impl Into<String>) {
+        lock(&self.entries).insert(name.into(), url.into());
+    }
+
+    /// Register an asset under the URL of the file it was written to within
+    /// the output directory. [`copy_file`](crate::copy_file) does this for
+    /// the files it copies.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the file isn't inside the output
+    /// directory.
+    pub fn register_output(
+        &self,
+        name: impl Into<String>,
+        out_dir: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+    ) -> Result<UrlPath, Error> {
+        let url = UrlPath::from_output(out_dir, path)?;
+        self.register(name, url.as_str());
+        Ok(url)
+    }
+
+    /// The URL an asset was written to, if it has been registered.
+    #[must_use]
+    pub fn get(&self, name: &str) -> Option<String> {
+        lock(&self.entries).get(name).cloned()
+    }
+
+    /// A snapshot of all registered assets, sorted by name.
+    #[must_use]
+    pub fn entries(&self) -> BTreeMap<String, String> {
+        lock(&self.entries).clone()
+    }
+}
+
+/// A short hash of file contents, for cache busting.
+pub(crate) fn content_hash(contents: &[u8]) -> String {
+    blake3::hash(contents)
+        .to_string()
+        .chars()
+        .take(16)
+        .collect()
+}
+
+/// Replace a `[hash]` placeholder in the file name of the path with the
+/// hash, or remove it together with a preceding `.` or `-` if there is
+/// none.
+pub(crate) fn fill_hash(path: &Path, hash: Option<&str>) -> PathBuf {
+    let Some(file_name) = path.file_name().and_then(|name| name.to_str()) else {
+        return path.to_path_buf();
+    };
+    let file_name = match hash {
+        Some(hash) => file_name.replace("[hash]", hash),
+        None => file_name
+            .replace(".[hash]", "")
+            .replace("-[hash]", "")
+            .replace("[hash]", ""),
+    };
+    path.with_file_name(file_name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_save_load() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_manifest_save_load");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let manifest = AssetManifest::new();
+        manifest.register("main.css", "/main.4f2a9c1e.css");
+        manifest.save(dir.join("manifest.json"))?;
+
+        let manifest = AssetManifest::load(dir.join("manifest.json"))?;
+        assert_eq!(
+            manifest.get("main.css").as_deref(),
+            Some("/main.4f2a9c1e.css")
+        );
+        assert_eq!(manifest.get("main.js"), None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_copy_file() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_manifest_copy_file");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("static/resume.pdf"), "%PDF")?;
+        let out_dir = dir.join("dist");
+
+        let manifest = AssetManifest::new();
+        let url = crate::copy_file(
+            &manifest,
+            dir.join("static/resume.pdf"),
+            &out_dir,
+            "files/resume.[hash].pdf",
+            true,
+        )?;
+        assert_ne!(url.as_str(), "/files/resume.pdf");
+        assert_eq!(fs::read_to_string(url.to_output(&out_dir))?, "%PDF");
+        assert_eq!(
+            manifest.get("files/resume.pdf").as_deref(),
+            Some(url.as_str())
+        );
+
+        let url = crate::copy_file(
+            &manifest,
+            dir.join("static/resume.pdf"),
+            &out_dir,
+            "files/resume.[hash].pdf",
+            false,
+        )?;
+        assert_eq!(url.as_str(), "/files/resume.pdf");
+        assert_eq!(
+            manifest.get("files/resume.pdf").as_deref(),
+            Some("/files/resume.pdf")
+        );
+        let url = crate::copy_file(
+            &manifest,
+            dir.join("static/resume.pdf"),
+            &out_dir,
+            "a/b/cv.pdf",
+            true,
+        )?;
+        assert_eq!(fs::read_to_string(url.to_output(&out_dir))?, "%PDF");
+        assert!(manifest
+            .register_output("cv.pdf", &out_dir, dir.join("static/resume.pdf"))
+            .is_err());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_fill_hash() {
+        let path = Path::new("dist/v[hash]/main.[hash].css");
+        assert_eq!(
+            fill_hash(path, Some("4f2a")),
+            Path::new("dist/v[hash]/main.4f2a.css")
+        );
+        assert_eq!(fill_hash(path, None), Path::new("dist/v[hash]/main.css"));
+        assert_eq!(
+            fill_hash(Path::new("app-[hash].js"), None),
+            Path::new("app.js")
+        );
+    }
+}
@@ -1,15 +1,34 @@
-use comrak::{markdown_to_html_with_plugins, plugins::syntect::SyntectAdapter};
-use gray_matter::{engine::YAML, Matter};
-use serde::de::DeserializeOwned;
+use comrak::{
+    adapters::SyntaxHighlighterAdapter,
+    format_html_with_plugins,
+    nodes::{Ast, AstNode, LineColumn, NodeHtmlBlock, NodeValue},
+    parse_document,
+    plugins::syntect::SyntectAdapter,
+    Arena,
+};
+use gray_matter::{
+    engine::{Engine, YAML},
+    Pod,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use std::{
-    fmt,
-    fs::File,
-    io::{self, Read},
-    path::PathBuf,
-    sync::LazyLock,
+    cell::RefCell,
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
 };
+use syntect::{parsing::SyntaxSet, LoadingError};
 
-use crate::{Error, Glob, Parsed};
+use crate::{
+    anchors::AnchorAdapter,
+    code_blocks::FenceInfo,
+    highlight::{configured_highlighting, default_adapter, Highlighter},
+    html::escape,
+    image_probe::ImageProbe,
+    includes::expand_includes,
+    literal_base, Anchors, CodeBlocks, Collection, Error, Glob, HasAliases, HasLayout,
+    HasPermalink, HasSource, Highlighting, InSeries, Page, Parsed, UrlPath, UrlStyle,
+};
 
 /// Error type for markdown parsing operations.
 #[derive(thiserror::Error, Debug)]
@@ -26,6 +45,25 @@ pub enum MarkdownError {
     /// The file path has no file stem (filename without extension).
     #[error("no file stem for: {0}")]
     NoFileStem(PathBuf),
+    /// An included file could not be read.
+    #[error("failed to include {0}: {1}")]
+    Include(PathBuf, io::Error),
+    /// Files include each other, starting with the file being parsed.
+    #[error("include cycle: {0:?}")]
+    IncludeCycle(Vec<PathBuf>),
+    /// Syntax definitions could not be loaded.
+    #[error("failed to load syntaxes from {0}: {1}")]
+    Syntaxes(PathBuf, LoadingError),
+    /// There's no highlighting theme with the name.
+    #[error("unknown highlighting theme: {0}")]
+    UnknownTheme(String),
+    /// The default highlighting was set or used before
+    /// [`configure_highlighting`](crate::configure_highlighting).
+    #[error("highlighting is already configured")]
+    HighlightingConfigured,
+    /// Failed to deserialize a comment file.
+    #[error("failed to deserialize comment {0}: {1}")]
+    Comment(PathBuf, serde_json::error::Error),
 }
 
 impl From<MarkdownError> for Box<dyn std::error::Error + Send> {
@@ -34,20 +72,601 @@ impl From<MarkdownError> for Box<dyn std::error::Error + Send> {
     }
 }
 
-/// `SyntectAdapter::new` loads a few binary files from disk, better to do this only once.
-static SYNTECT_ADAPTER: LazyLock<SyntectAdapter> = LazyLock::new(|| SyntectAdapter::new(None));
+type PluginsFn = dyn Fn(&mut comrak::Plugins<'_>) + Send + Sync;
+type CodeFenceFn = dyn Fn(&str) -> String + Send + Sync;
+
+/// Options for rendering markdown, see [`parse_markdown_with`].
+///
+/// ```
+/// use pichu::comrak::adapters::{HeadingAdapter, HeadingMeta};
+/// use pichu::comrak::nodes::Sourcepos;
+/// use std::io::{self, Write};
+///
+/// /// Render headings without IDs.
+/// struct PlainHeadings;
+///
+/// impl HeadingAdapter for PlainHeadings {
+///     fn enter(&self, output: &mut dyn Write, heading: &HeadingMeta, _: Option<Sourcepos>) -> io::Result<()> {
+///         write!(output, "<h{}>", heading.level)
+///     }
+///
+///     fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+///         write!(output, "</h{}>", heading.level)
+///     }
+/// }
+///
+/// let options = pichu::MarkdownOptions::new()
+///     .with_plugins(|plugins| plugins.render.heading_adapter = Some(&PlainHeadings));
+/// ```
+#[derive(Clone, Default)]
+pub struct MarkdownOptions {
+    plugins: Vec<Arc<PluginsFn>>,
+    code_fences: Vec<(String, Arc<CodeFenceFn>)>,
+    #[cfg(feature = "emoji")]
+    emoji_shortcodes: bool,
+    anchors: Option<Anchors>,
+    code_blocks: Option<CodeBlocks>,
+    include_root: Option<PathBuf>,
+    image_probe: Option<ImageProbe>,
+    syntaxes: Option<Arc<SyntectAdapter>>,
+    syntax_set: Option<SyntaxSet>,
+    highlighting: Option<Highlighting>,
+    syntax_aliases: Vec<(String, String)>,
+    #[cfg(feature = "sanitize")]
+    sanitize: bool,
+    raw_frontmatter: bool,
+}
+
+impl MarkdownOptions {
+    /// Create the default options, rendering like [`parse_markdown`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register comrak render plugins, like a custom heading adapter or a
+    /// different syntax highlighter. The function is called with the
+    /// default plugins before each render, and can replace any of them.
+    /// Plugins need to live for `'static`, e.g. in a `static` or
+    /// [`LazyLock`](std::sync::LazyLock).
+    #[must_use]
+    pub fn with_plugins(
+        mut self,
+        plugins_fn: impl Fn(&mut comrak::Plugins<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.plugins.push(Arc::new(plugins_fn));
+        self
+    }
+
+    /// Render code fences of the given language with the function instead
+    /// of highlighting them. The function is called with the code and
+    /// returns the HTML replacing the whole block, e.g. an SVG rendered at
+    /// build time.
+    /// Registering a language again replaces the previous function.
+    #[must_use]
+    pub fn code_fence(
+        mut self,
+        lang: impl Into<String>,
+        render_fn: impl Fn(&str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        let lang = lang.into();
+        self.code_fences.retain(|(l, _)| *l != lang);
+        self.code_fences.push((lang, Arc::new(render_fn)));
+        self
+    }
+
+    /// Expand GitHub-style shortcodes like `:rocket:` to emoji. Unknown
+    /// shortcodes are left as they are.
+    #[cfg(feature = "emoji")]
+    #[must_use]
+    pub fn emoji_shortcodes(mut self, emoji_shortcodes: bool) -> Self {
+        self.emoji_shortcodes = emoji_shortcodes;
+        self
+    }
+
+    /// Sanitize the rendered HTML, for content that isn't trusted, like
+    /// comments or webmentions. Scripts, event handlers, styles and
+    /// unknown elements are removed, including those produced by
+    /// [`MarkdownOptions::code_fence`], while the markup of highlighted code,
+    /// including the colors of [`Highlighting::Theme`], heading anchors and
+    /// footnotes is kept. Ids and the links to them are prefixed with
+    /// `user-content-`, so they can't clash with the page's own.
+    #[cfg(feature = "sanitize")]
+    #[must_use]
+    pub fn sanitize(mut self, sanitize: bool) -> Self {
+        self.sanitize = sanitize;
+        self
+    }
+
+    /// Customize the markup of heading anchors and footnote back-references.
+    #[must_use]
+    pub fn anchors(mut self, anchors: Anchors) -> Self {
+        self.anchors = Some(anchors);
+        self
+    }
+
+    /// Replace lines like `{{ include "shared/warning.md" }}` with the
+    /// contents of the file, relative to the given root, e.g. `content`.
+    /// Included files can include others, but not themselves, and their
+    /// frontmatter is ignored. The files are listed in
+    /// [`Markdown::includes`].
+    #[must_use]
+    pub fn includes(mut self, root: impl Into<PathBuf>) -> Self {
+        self.include_root = Some(root.into());
+        self
+    }
+
+    /// Add `width` and `height` attributes to local images, so browsers can
+    /// reserve their space before they load. URLs starting with `/` are
+    /// looked up in the given root, e.g. `static`, others relative to the
+    /// markdown file. Images that can't be read are left as they are.
+    #[must_use]
+    pub fn probe_images(mut self, root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        match &mut self.image_probe {
+            Some(image_probe) => image_probe.root = root,
+            None => {
+                self.image_probe = Some(ImageProbe {
+                    root,
+                    #[cfg(feature = "images")]
+                    placeholder: false,
+                });
+            }
+        }
+        self
+    }
+
+    /// Also set the background of probed images to their average color, as
+    /// a placeholder while they load. Requires
+    /// [`MarkdownOptions::probe_images`].
+    #[cfg(feature = "images")]
+    #[must_use]
+    pub fn image_placeholders(mut self, placeholders: bool) -> Self {
+        if let Some(image_probe) = &mut self.image_probe {
+            image_probe.placeholder = placeholders;
+        }
+        self
+    }
+
+    /// Wrap highlighted code blocks in a container, with a header showing
+    /// the `filename=` attribute of the fence, e.g. ```` ```rust,filename=main.rs ````.
+    /// Without a container, the attribute is ignored.
+    #[must_use]
+    pub fn code_blocks(mut self, code_blocks: CodeBlocks) -> Self {
+        self.code_blocks = Some(code_blocks);
+        self
+    }
+
+    /// Highlight code fences with the `.sublime-syntax` definitions in `dir`
+    /// in addition to the built-in ones, e.g. for Svelte or a custom DSL.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a definition in `dir` can't be read or parsed.
+    pub fn syntaxes(mut self, dir: impl AsRef<Path>) -> Result<Self, MarkdownError> {
+        let dir = dir.as_ref();
+        let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+        builder
+            .add_from_folder(dir, true)
+            .map_err(|e| MarkdownError::Syntaxes(dir.to_path_buf(), e))?;
+        self.syntax_set = Some(builder.build());
+        self.build_adapter()?;
+        Ok(self)
+    }
+
+    /// Highlight code fences like this instead of the default set with
+    /// [`configure_highlighting`](crate::configure_highlighting).
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the theme doesn't exist.
+    pub fn highlighting(mut self, highlighting: Highlighting) -> Result<Self, MarkdownError> {
+        self.highlighting = Some(highlighting);
+        self.build_adapter()?;
+        Ok(self)
+    }
+
+    /// Build the adapter for custom syntaxes or highlighting, falling back
+    /// to the configured default highlighting.
+    fn build_adapter(&mut self) -> Result<(), MarkdownError> {
+        let highlighting = self
+            .highlighting
+            .as_ref()
+            .or_else(|| configured_highlighting())
+            .cloned()
+            .unwrap_or_default();
+        let adapter = highlighting.adapter(self.syntax_set.clone())?;
+        self.syntaxes = Some(Arc::new(adapter));
+        Ok(())
+    }
+
+    /// Keep the frontmatter as a JSON value in
+    /// [`Markdown::raw_frontmatter`], for generic tooling like linters or
+    /// migration scripts that inspect fields the frontmatter type doesn't
+    /// declare.
+    #[must_use]
+    pub fn raw_frontmatter(mut self, raw_frontmatter: bool) -> Self {
+        self.raw_frontmatter = raw_frontmatter;
+        self
+    }
+
+    /// Highlight code fences in `alias` like those in `lang`, e.g. `jsx` like
+    /// `js`. `lang` is a name or file extension of a syntax. The fence keeps
+    /// its `language-{alias}` class.
+    #[must_use]
+    pub fn syntax_alias(mut self, alias: impl Into<String>, lang: impl Into<String>) -> Self {
+        self.syntax_aliases.push((alias.into(), lang.into()));
+        self
+    }
+
+    /// Render `mermaid` code fences as `<pre class="mermaid">`, to be turned
+    /// into diagrams by mermaid.js in the browser.
+    #[must_use]
+    pub fn mermaid(self) -> Self {
+        self.code_fence("mermaid", |code| {
+            format!("<pre class=\"mermaid\">{}</pre>\n", escape(code))
+        })
+    }
+}
+
+impl fmt::Debug for MarkdownOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MarkdownOptions")
+            .field("plugins", &self.plugins.len())
+            .field(
+                "code_fences",
+                &self.code_fences.iter().map(|(l, _)| l).collect::<Vec<_>>(),
+            )
+            .field("syntax_aliases", &self.syntax_aliases)
+            .field("highlighting", &self.highlighting)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Prefixed to the ids in sanitized HTML and to the links to them, so
+/// untrusted content can't clobber the ids of the page around it.
+#[cfg(feature = "sanitize")]
+const ID_PREFIX: &str = "user-content-";
+
+/// Allows what markdown renders to, plus the attributes pichu adds and the
+/// colors of highlighted code.
+#[cfg(feature = "sanitize")]
+static SANITIZER: std::sync::LazyLock<ammonia::Builder<'static>> = std::sync::LazyLock::new(|| {
+    let mut builder = ammonia::Builder::default();
+    builder
+        .add_tags(["section"])
+        .add_generic_attributes([
+            "class",
+            "id",
+            "aria-hidden",
+            "aria-label",
+            "data-footnotes",
+            "data-footnote-ref",
+            "data-footnote-backref",
+            "data-footnote-backref-idx",
+        ])
+        .add_tag_attributes("pre", ["style"])
+        .add_tag_attributes("span", ["style"])
+        .filter_style_properties(
+            [
+                "color",
+                "background-color",
+                "font-weight",
+                "font-style",
+                "text-decoration",
+            ]
+            .into(),
+        )
+        .id_prefix(Some(ID_PREFIX))
+        .attribute_filter(|_, attribute, value| match value.strip_prefix('#') {
+            Some(id) if attribute == "href" && !id.starts_with(ID_PREFIX) => {
+                Some(format!("#{ID_PREFIX}{id}").into())
+            }
+            _ => Some(value.into()),
+        });
+    builder
+});
 
 /// A parsed markdown file.
-#[derive(Debug, Clone)]
+///
+/// Serializes to an object with the field names below, e.g. to cache parsed
+/// files or to emit them as JSON. Fields are only ever added, so data
+/// serialized by an older version can still be deserialized, and the struct
+/// is non-exhaustive so adding them isn't a breaking change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[non_exhaustive]
 pub struct Markdown<T> {
     /// The parsed frontmatter.
     pub frontmatter: T,
+    /// The path of the source file.
+    pub path: PathBuf,
     /// Filename without extension.
     pub basename: String,
-    /// The raw markdown content (without frontmatter).
+    /// The path relative to the root of its collection, e.g.
+    /// `2024/hello.md` for `content/blog/2024/hello.md` parsed from a
+    /// [`Glob::with_root`] of `content/blog`. Without a root, it's relative
+    /// to the part of the glob pattern without wildcards, or just the file
+    /// name when parsed on its own.
+    #[serde(default)]
+    pub relative_path: PathBuf,
+    /// The raw markdown content (without frontmatter), with include
+    /// directives left as they are.
     pub markdown: String,
-    /// The rendered HTML content.
+    /// The number of lines before the markdown content in the file, to map
+    /// lines of [`Markdown::markdown`] to lines of the file.
+    #[serde(default)]
+    pub line_offset: usize,
+    /// The files included with [`MarkdownOptions::includes`], so pages can
+    /// be rebuilt when they change.
+    #[serde(default)]
+    pub includes: Vec<PathBuf>,
+    /// The rendered HTML content, empty if parsed with
+    /// [`parse_markdown_meta_only`].
     pub html: String,
+    /// The frontmatter as parsed from YAML, including fields `T` doesn't
+    /// declare, if enabled with [`MarkdownOptions::raw_frontmatter`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_frontmatter: Option<serde_json::Value>,
+}
+
+impl<T> Markdown<T> {
+    /// The URL path of the page, mirroring [`Markdown::relative_path`], e.g.
+    /// `/2024/hello/` for `2024/hello.md`. See [`UrlPath::from_source`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the relative path isn't a valid
+    /// URL path.
+    pub fn url(&self, style: UrlStyle) -> Result<UrlPath, Error> {
+        UrlPath::from_source("", &self.relative_path, style)
+    }
+
+    /// Compute the relative path against `root`, keeping it as is for paths
+    /// outside of it.
+    fn relative_to(mut self, root: &Path) -> Self {
+        if let Ok(relative) = self.path.strip_prefix(root) {
+            self.relative_path = relative.to_path_buf();
+        }
+        self
+    }
+}
+
+impl<T> HasSource for Markdown<T> {
+    fn source(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl<T: HasAliases> HasAliases for Markdown<T> {
+    fn aliases(&self) -> &[String] {
+        self.frontmatter.aliases()
+    }
+}
+
+impl<T: HasPermalink> HasPermalink for Markdown<T> {
+    fn permalink(&self) -> Option<&str> {
+        self.frontmatter.permalink()
+    }
+}
+
+impl<T: Page> Page for Markdown<T> {
+    fn title(&self) -> &str {
+        self.frontmatter.title()
+    }
+
+    fn url(&self) -> UrlPath {
+        self.frontmatter.url()
+    }
+
+    fn date(&self) -> Option<&str> {
+        self.frontmatter.date()
+    }
+
+    fn updated(&self) -> Option<&str> {
+        self.frontmatter.updated()
+    }
+
+    fn summary(&self) -> Option<&str> {
+        self.frontmatter.summary()
+    }
+
+    fn tags(&self) -> &[String] {
+        self.frontmatter.tags()
+    }
+}
+
+impl<T: HasLayout> HasLayout for Markdown<T> {
+    fn layout(&self) -> Option<&str> {
+        self.frontmatter.layout()
+    }
+}
+
+impl<T: InSeries> InSeries for Markdown<T> {
+    fn series(&self) -> Option<&str> {
+        self.frontmatter.series()
+    }
+}
+
+#[cfg(feature = "encrypt")]
+impl<T: crate::IsPrivate> crate::IsPrivate for Markdown<T> {
+    fn is_private(&self) -> bool {
+        self.frontmatter.is_private()
+    }
+}
+
+/// A markdown file whose HTML is rendered on first access, from
+/// [`Glob::parse_markdown_lazy`]. Collections only used for listings never
+/// pay for rendering, while pages rendered individually get their HTML on
+/// demand, also from parallel render functions.
+///
+/// The HTML and the included files are rendered together, by whichever of
+/// [`LazyMarkdown::html`], [`LazyMarkdown::includes`] and
+/// [`LazyMarkdown::into_markdown`] is called first, and kept afterwards.
+/// Threads calling them at the same time may each render, but all get the
+/// same result. Clones keep what was rendered so far.
+#[derive(Debug, Clone)]
+pub struct LazyMarkdown<T> {
+    /// The parsed frontmatter.
+    pub frontmatter: T,
+    /// The path of the source file.
+    pub path: PathBuf,
+    /// Filename without extension.
+    pub basename: String,
+    /// The path relative to the root of its collection, see
+    /// [`Markdown::relative_path`].
+    pub relative_path: PathBuf,
+    /// The raw markdown content (without frontmatter), with include
+    /// directives left as they are.
+    pub markdown: String,
+    /// The number of lines before the markdown content in the file.
+    pub line_offset: usize,
+    /// The frontmatter as parsed from YAML, see
+    /// [`Markdown::raw_frontmatter`].
+    pub raw_frontmatter: Option<serde_json::Value>,
+    options: Arc<MarkdownOptions>,
+    rendered: OnceLock<(String, Vec<PathBuf>)>,
+}
+
+impl<T> LazyMarkdown<T> {
+    fn new(markdown: Markdown<T>, options: Arc<MarkdownOptions>) -> Self {
+        Self {
+            frontmatter: markdown.frontmatter,
+            path: markdown.path,
+            basename: markdown.basename,
+            relative_path: markdown.relative_path,
+            markdown: markdown.markdown,
+            line_offset: markdown.line_offset,
+            raw_frontmatter: markdown.raw_frontmatter,
+            options,
+            rendered: OnceLock::new(),
+        }
+    }
+
+    /// The rendered HTML content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an included file cannot be read.
+    pub fn html(&self) -> Result<&str, MarkdownError> {
+        Ok(&self.rendered()?.0)
+    }
+
+    /// The files included with [`MarkdownOptions::includes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an included file cannot be read.
+    pub fn includes(&self) -> Result<&[PathBuf], MarkdownError> {
+        Ok(&self.rendered()?.1)
+    }
+
+    /// Whether the HTML was rendered already.
+    #[must_use]
+    pub fn is_rendered(&self) -> bool {
+        self.rendered.get().is_some()
+    }
+
+    /// The URL path of the page, see [`Markdown::url`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the relative path isn't a valid
+    /// URL path.
+    pub fn url(&self, style: UrlStyle) -> Result<UrlPath, Error> {
+        UrlPath::from_source("", &self.relative_path, style)
+    }
+
+    /// Turn this into a [`Markdown`] with the rendered HTML.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an included file cannot be read.
+    pub fn into_markdown(self) -> Result<Markdown<T>, MarkdownError> {
+        self.rendered()?;
+        let (html, includes) = self.rendered.into_inner().unwrap_or_default();
+        Ok(Markdown {
+            frontmatter: self.frontmatter,
+            path: self.path,
+            basename: self.basename,
+            relative_path: self.relative_path,
+            markdown: self.markdown,
+            line_offset: self.line_offset,
+            includes,
+            html,
+            raw_frontmatter: self.raw_frontmatter,
+        })
+    }
+
+    fn rendered(&self) -> Result<&(String, Vec<PathBuf>), MarkdownError> {
+        if let Some(rendered) = self.rendered.get() {
+            return Ok(rendered);
+        }
+        let rendered = render_file(&self.markdown, &self.path, &self.options)?;
+        Ok(self.rendered.get_or_init(|| rendered))
+    }
+}
+
+impl<T> HasSource for LazyMarkdown<T> {
+    fn source(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl<T: HasAliases> HasAliases for LazyMarkdown<T> {
+    fn aliases(&self) -> &[String] {
+        self.frontmatter.aliases()
+    }
+}
+
+impl<T: HasPermalink> HasPermalink for LazyMarkdown<T> {
+    fn permalink(&self) -> Option<&str> {
+        self.frontmatter.permalink()
+    }
+}
+
+impl<T: Page> Page for LazyMarkdown<T> {
+    fn title(&self) -> &str {
+        self.frontmatter.title()
+    }
+
+    fn url(&self) -> UrlPath {
+        self.frontmatter.url()
+    }
+
+    fn date(&self) -> Option<&str> {
+        self.frontmatter.date()
+    }
+
+    fn updated(&self) -> Option<&str> {
+        self.frontmatter.updated()
+    }
+
+    fn summary(&self) -> Option<&str> {
+        self.frontmatter.summary()
+    }
+
+    fn tags(&self) -> &[String] {
+        self.frontmatter.tags()
+    }
+}
+
+impl<T: HasLayout> HasLayout for LazyMarkdown<T> {
+    fn layout(&self) -> Option<&str> {
+        self.frontmatter.layout()
+    }
+}
+
+impl<T: InSeries> InSeries for LazyMarkdown<T> {
+    fn series(&self) -> Option<&str> {
+        self.frontmatter.series()
+    }
+}
+
+#[cfg(feature = "encrypt")]
+impl<T: crate::IsPrivate> crate::IsPrivate for LazyMarkdown<T> {
+    fn is_private(&self) -> bool {
+        self.frontmatter.is_private()
+    }
 }
 
 impl Glob {
@@ -62,7 +681,93 @@ impl Glob {
     pub fn parse_markdown<T: DeserializeOwned + fmt::Debug + Send + Sync>(
         self,
     ) -> Result<Parsed<Markdown<T>>, Error> {
-        self.try_parse::<Markdown<T>, MarkdownError>(parse_markdown)
+        let root = self.root();
+        self.try_parse::<Markdown<T>, MarkdownError>(|path| {
+            Ok(parse_markdown(path)?.relative_to(&root))
+        })
+    }
+
+    /// Parse the paths as Markdown files, rendering with the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any markdown file cannot be parsed or if frontmatter is missing/invalid.
+    #[cfg(feature = "markdown")]
+    pub fn parse_markdown_with<T: DeserializeOwned + fmt::Debug + Send + Sync>(
+        self,
+        options: &MarkdownOptions,
+    ) -> Result<Parsed<Markdown<T>>, Error> {
+        let root = self.root();
+        self.try_parse::<Markdown<T>, MarkdownError>(|path| {
+            Ok(parse_markdown_with(path, options)?.relative_to(&root))
+        })
+    }
+
+    /// Parse the paths as Markdown files without rendering them, for
+    /// pipelines that only need the frontmatter or raw markdown, like
+    /// archive listings. See [`parse_markdown_meta_only`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any markdown file cannot be parsed or if frontmatter is missing/invalid.
+    #[cfg(feature = "markdown")]
+    pub fn parse_markdown_meta_only<T: DeserializeOwned + fmt::Debug + Send + Sync>(
+        self,
+    ) -> Result<Parsed<Markdown<T>>, Error> {
+        let root = self.root();
+        self.try_parse::<Markdown<T>, MarkdownError>(|path| {
+            Ok(parse_markdown_meta_only(path)?.relative_to(&root))
+        })
+    }
+
+    /// Parse the paths as Markdown files, rendering each with the given
+    /// options only once its HTML is accessed, see [`LazyMarkdown`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any markdown file cannot be parsed or if frontmatter is missing/invalid.
+    #[cfg(feature = "markdown")]
+    pub fn parse_markdown_lazy<T: DeserializeOwned + fmt::Debug + Send + Sync>(
+        self,
+        options: &MarkdownOptions,
+    ) -> Result<Parsed<LazyMarkdown<T>>, Error> {
+        let root = self.root();
+        let options = Arc::new(options.clone());
+        self.try_parse::<LazyMarkdown<T>, MarkdownError>(|path| {
+            let markdown = parse(path, &options, false)?.relative_to(&root);
+            Ok(LazyMarkdown::new(markdown, Arc::clone(&options)))
+        })
+    }
+}
+
+impl<T: DeserializeOwned + Send + Sync + 'static> Collection<Markdown<T>> {
+    /// Glob and parse Markdown files into a [`Collection`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or if any markdown file cannot be parsed.
+    pub fn markdown(pattern: impl AsRef<str>) -> Result<Self, Error> {
+        let root = literal_base(pattern.as_ref());
+        Collection::new(pattern, move |path| {
+            Ok::<_, MarkdownError>(parse_markdown(path)?.relative_to(&root))
+        })
+    }
+
+    /// Glob and parse Markdown files into a [`Collection`], rendering with
+    /// the given options.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid or if any markdown file cannot be parsed.
+    pub fn markdown_with(
+        pattern: impl AsRef<str>,
+        options: MarkdownOptions,
+    ) -> Result<Self, Error> {
+        let root = literal_base(pattern.as_ref());
+        Ok(Collection::new(pattern, move |path| {
+            Ok::<_, MarkdownError>(parse_markdown_with(path, &options)?.relative_to(&root))
+        })?
+        .with_inputs(|markdown: &Markdown<T>| markdown.includes.clone()))
     }
 }
 
@@ -71,27 +776,68 @@ impl Glob {
 /// # Errors
 ///
 /// Returns an error if the file cannot be read, frontmatter is missing/invalid, or path has no file stem.
+#[allow(clippy::ptr_arg)] // Matches the signature expected by `Glob::try_parse`.
 pub fn parse_markdown<T: DeserializeOwned>(path: &PathBuf) -> Result<Markdown<T>, MarkdownError> {
-    let mut file = File::open(path).map_err(MarkdownError::IO)?;
-    let mut contents = String::new();
-    file.read_to_string(&mut contents)
-        .map_err(MarkdownError::IO)?;
-
-    let matter = Matter::<YAML>::new();
-    let markdown = matter.parse(&contents);
-    let frontmatter: T = markdown
-        .data
-        .ok_or(MarkdownError::MissingFrontmatter(path.clone()))?
-        .deserialize()
-        .map_err(|e| MarkdownError::DeserializeFrontmatter(path.clone(), e))?;
-
-    let syntect_adapter = &*SYNTECT_ADAPTER;
-    let markdown_context = MarkdownContext::new(syntect_adapter);
-    let html = markdown_to_html_with_plugins(
-        &markdown.content,
-        &markdown_context.options,
-        &markdown_context.plugins,
-    );
+    parse_markdown_with(path, &MarkdownOptions::default())
+}
+
+/// Parse a markdown file at the given path, rendering with the given options.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, frontmatter is missing/invalid, or path has no file stem.
+pub fn parse_markdown_with<T: DeserializeOwned>(
+    path: &Path,
+    options: &MarkdownOptions,
+) -> Result<Markdown<T>, MarkdownError> {
+    parse(path, options, true)
+}
+
+/// Parse the frontmatter and raw markdown of a file at the given path,
+/// without rendering it. [`Markdown::html`] is empty and include directives
+/// aren't expanded.
+///
+/// # Errors
+///
+/// Returns an error if the file cannot be read, frontmatter is missing/invalid, or path has no file stem.
+pub fn parse_markdown_meta_only<T: DeserializeOwned>(
+    path: &Path,
+) -> Result<Markdown<T>, MarkdownError> {
+    parse(path, &MarkdownOptions::default(), false)
+}
+
+/// Parse a markdown file with the options, rendering it if `render` is set.
+fn parse<T: DeserializeOwned>(
+    path: &Path,
+    options: &MarkdownOptions,
+    render: bool,
+) -> Result<Markdown<T>, MarkdownError> {
+    let path = &path.to_path_buf();
+    let contents = &fs::read_to_string(path)?;
+    let (frontmatter, content) = split_frontmatter(contents)
+        .filter(|(frontmatter, _)| !frontmatter.trim().is_empty())
+        .ok_or_else(|| MarkdownError::MissingFrontmatter(path.clone()))?;
+    let line_offset = contents[..contents.len() - content.len()]
+        .matches('\n')
+        .count();
+
+    let data = YAML::parse(frontmatter);
+    if data == Pod::Null {
+        return Err(MarkdownError::MissingFrontmatter(path.clone()));
+    }
+    let deserialize_error = |e| MarkdownError::DeserializeFrontmatter(path.clone(), e);
+    let raw_frontmatter = if options.raw_frontmatter {
+        Some(data.deserialize().map_err(deserialize_error)?)
+    } else {
+        None
+    };
+    let frontmatter: T = data.deserialize().map_err(deserialize_error)?;
+
+    let (html, includes) = if render {
+        render_file(content, path, options)?
+    } else {
+        (String::new(), Vec::new())
+    };
 
     let basename = path
         .file_stem()
@@ -101,19 +847,179 @@ pub fn parse_markdown<T: DeserializeOwned>(path: &PathBuf) -> Result<Markdown<T>
 
     Ok(Markdown {
         frontmatter,
+        path: path.clone(),
         basename,
-        markdown: markdown.content,
+        relative_path: path.file_name().map(PathBuf::from).unwrap_or_default(),
+        markdown: content.to_string(),
+        line_offset,
+        includes,
         html,
+        raw_frontmatter,
     })
 }
 
+/// Render the markdown content of the file at `path` to HTML, returning
+/// it with the included files.
+fn render_file(
+    content: &str,
+    path: &Path,
+    options: &MarkdownOptions,
+) -> Result<(String, Vec<PathBuf>), MarkdownError> {
+    let dir = path.parent().unwrap_or(Path::new(""));
+    match &options.include_root {
+        Some(root) => {
+            let (expanded, includes) = expand_includes(content, root, path)?;
+            Ok((render_at(&expanded, dir, options)?, includes))
+        }
+        None => Ok((render_at(content, dir, options)?, Vec::new())),
+    }
+}
+
+/// Render markdown to HTML.
+#[cfg(test)]
+fn render(content: &str, options: &MarkdownOptions) -> Result<String, io::Error> {
+    render_at(content, Path::new(""), options)
+}
+
+/// Render markdown in the directory `dir` to HTML.
+pub(crate) fn render_at(
+    content: &str,
+    dir: &Path,
+    options: &MarkdownOptions,
+) -> Result<String, io::Error> {
+    let highlighter = Highlighter {
+        adapter: options
+            .syntaxes
+            .as_deref()
+            .unwrap_or_else(|| default_adapter()),
+        aliases: &options.syntax_aliases,
+    };
+    let anchor_adapter = options.anchors.as_ref().map(AnchorAdapter::new);
+    let mut markdown_context = MarkdownContext::new(&highlighter);
+    if let Some(anchor_adapter) = &anchor_adapter {
+        markdown_context.plugins.render.heading_adapter = Some(anchor_adapter);
+    }
+    for plugins_fn in &options.plugins {
+        plugins_fn(&mut markdown_context.plugins);
+    }
+    #[cfg(feature = "emoji")]
+    {
+        markdown_context.options.extension.shortcodes = options.emoji_shortcodes;
+    }
+
+    let arena = Arena::new();
+    let root = parse_document(&arena, content, &markdown_context.options);
+    transform_code_blocks(&arena, root, options);
+    if let Some(image_probe) = &options.image_probe {
+        let images = root
+            .descendants()
+            .filter(|node| matches!(node.data.borrow().value, NodeValue::Image(_)))
+            .collect::<Vec<_>>();
+        for image in images {
+            image_probe.apply(image, dir);
+        }
+    }
+
+    let mut html = Vec::new();
+    format_html_with_plugins(
+        root,
+        &markdown_context.options,
+        &mut html,
+        &markdown_context.plugins,
+    )?;
+    let html =
+        String::from_utf8(html).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    let html = match &options.anchors {
+        Some(anchors) => anchors.rewrite_backrefs(html),
+        None => html,
+    };
+    #[cfg(feature = "sanitize")]
+    if options.sanitize {
+        return Ok(SANITIZER.clean(&html).to_string());
+    }
+    Ok(html)
+}
+
+/// Apply the code fence hooks and wrap highlighted code blocks.
+fn transform_code_blocks<'a>(
+    arena: &'a Arena<AstNode<'a>>,
+    root: &'a AstNode<'a>,
+    options: &MarkdownOptions,
+) {
+    let html_block = |literal: String, start: LineColumn| -> &'a AstNode<'a> {
+        let value = NodeValue::HtmlBlock(NodeHtmlBlock {
+            block_type: 0,
+            literal,
+        });
+        arena.alloc(AstNode::new(RefCell::new(Ast::new(value, start))))
+    };
+
+    let code_blocks = root
+        .descendants()
+        .filter(|node| matches!(node.data.borrow().value, NodeValue::CodeBlock(_)))
+        .collect::<Vec<_>>();
+    for node in code_blocks {
+        let mut ast = node.data.borrow_mut();
+        let start = ast.sourcepos.start;
+        let NodeValue::CodeBlock(block) = &mut ast.value else {
+            continue;
+        };
+        let info = FenceInfo::parse(&block.info);
+        if let Some((_, render_fn)) = options.code_fences.iter().find(|(l, _)| l == info.lang) {
+            let literal = render_fn(&block.literal);
+            ast.value = NodeValue::HtmlBlock(NodeHtmlBlock {
+                block_type: 0,
+                literal,
+            });
+            continue;
+        }
+
+        let wrap = options
+            .code_blocks
+            .as_ref()
+            .map(|code_blocks| code_blocks.wrap(&block.literal, info.filename));
+        block.info = info.to_info_string();
+        drop(ast);
+        if let Some((open, close)) = wrap {
+            node.insert_before(html_block(open, start));
+            node.insert_after(html_block(close, start));
+        }
+    }
+}
+
+/// Split a document into its YAML frontmatter and the content following it,
+/// without copying either.
+/// Returns `None` if the document doesn't start with a `---` line or the
+/// frontmatter is never closed.
+pub(crate) fn split_frontmatter(contents: &str) -> Option<(&str, &str)> {
+    let mut lines = contents.split_inclusive('\n');
+    let first = lines.next()?;
+    if first.trim_end() != "---" {
+        return None;
+    }
+
+    let start = first.len();
+    let mut offset = start;
+    for line in lines {
+        if line.trim_end() == "---" {
+            let content = &contents[offset + line.len()..];
+            return Some((
+                &contents[start..offset],
+                content.trim_start_matches(['\r', '\n']),
+            ));
+        }
+        offset += line.len();
+    }
+    None
+}
+
 pub struct MarkdownContext<'a> {
     plugins: comrak::Plugins<'a>,
     options: comrak::Options<'a>,
 }
 
 impl<'a> MarkdownContext<'a> {
-    fn new(syntect_adapter: &'a SyntectAdapter) -> Self {
+    fn new(highlighter: &'a dyn SyntaxHighlighterAdapter) -> Self {
         let render = comrak::RenderOptions {
             unsafe_: true,
             ..Default::default()
@@ -138,7 +1044,7 @@ impl<'a> MarkdownContext<'a> {
             parse,
         };
         let render_plugins = comrak::RenderPlugins {
-            codefence_syntax_highlighter: Some(syntect_adapter),
+            codefence_syntax_highlighter: Some(highlighter),
             ..Default::default()
         };
         let plugins = comrak::Plugins {
@@ -148,3 +1054,480 @@ impl<'a> MarkdownContext<'a> {
         Self { plugins, options }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fmt::Write, fs};
+
+    #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+    struct Page {
+        title: String,
+    }
+
+    #[test]
+    fn test_split_frontmatter() {
+        assert_eq!(
+            split_frontmatter("---\ntitle: foo\n---\n\nHello\n"),
+            Some(("title: foo\n", "Hello\n"))
+        );
+        assert_eq!(
+            split_frontmatter("---\r\ntitle: foo\r\n---\r\nHello"),
+            Some(("title: foo\r\n", "Hello"))
+        );
+        assert_eq!(split_frontmatter("---\ntitle: foo\n"), None);
+        assert_eq!(split_frontmatter("Hello\n---\n"), None);
+    }
+
+    #[test]
+    fn test_serialize() -> Result<(), serde_json::Error> {
+        let markdown = Markdown {
+            frontmatter: Page {
+                title: "Hello".to_string(),
+            },
+            path: PathBuf::from("posts/hello.md"),
+            basename: "hello".to_string(),
+            relative_path: PathBuf::from("hello.md"),
+            markdown: "*Hi*".to_string(),
+            line_offset: 3,
+            includes: Vec::new(),
+            html: "<p><em>Hi</em></p>\n".to_string(),
+            raw_frontmatter: None,
+        };
+        let json = serde_json::to_string(&markdown)?;
+        assert_eq!(
+            json,
+            r#"{"frontmatter":{"title":"Hello"},"path":"posts/hello.md","basename":"hello","relative_path":"hello.md","markdown":"*Hi*","line_offset":3,"includes":[],"html":"<p><em>Hi</em></p>\n"}"#
+        );
+
+        let parsed: Markdown<Page> = serde_json::from_str(
+            r#"{"frontmatter":{"title":"Hello"},"path":"posts/hello.md","basename":"hello","markdown":"*Hi*","html":""}"#,
+        )?;
+        assert_eq!(parsed.frontmatter, markdown.frontmatter);
+        assert!(parsed.includes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_markdown_large() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_parse_markdown_large");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+
+        let mut contents = String::from("---\ntitle: Large\n---\n");
+        let mut paragraph = 0;
+        while contents.len() < 1024 * 1024 {
+            writeln!(contents, "Paragraph {paragraph}\n")?;
+            paragraph += 1;
+        }
+        let path = dir.join("large.md");
+        fs::write(&path, &contents)?;
+
+        let markdown = parse_markdown::<Page>(&path)?;
+        assert_eq!(markdown.frontmatter.title, "Large");
+        assert_eq!(markdown.line_offset, 3);
+        assert!(markdown.markdown.starts_with("Paragraph 0\n"));
+        assert!(markdown.html.contains("<p>Paragraph 1</p>"));
+
+        let meta = parse_markdown_meta_only::<Page>(&path)?;
+        assert_eq!(meta.frontmatter.title, "Large");
+        assert_eq!(meta.markdown, markdown.markdown);
+        assert!(meta.html.is_empty());
+        assert!(meta.raw_frontmatter.is_none());
+
+        let options = MarkdownOptions::new().raw_frontmatter(true);
+        let raw = parse_markdown_with::<Page>(&path, &options)?;
+        assert_eq!(
+            raw.raw_frontmatter,
+            Some(serde_json::json!({"title": "Large"}))
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_relative_path() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_relative_path");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("blog/2024/hello.md"), "---\ntitle: Hello\n---\n")?;
+        crate::write(
+            dir.join("blog/2025/05/world.md"),
+            "---\ntitle: World\n---\n",
+        )?;
+
+        let pattern = dir.join("blog/**/*.md");
+        let pattern = pattern.to_string_lossy();
+        let posts = crate::glob(&pattern)?
+            .with_root(dir.join("blog"))?
+            .parse_markdown::<Page>()?;
+        let relative = posts
+            .items
+            .iter()
+            .map(|post| post.relative_path.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            relative,
+            [Path::new("2024/hello.md"), Path::new("2025/05/world.md")]
+        );
+        assert_eq!(
+            posts.items[1].url(UrlStyle::Pretty)?.as_str(),
+            "/2025/05/world/"
+        );
+
+        let posts = crate::glob(&pattern)?.parse_markdown::<Page>()?;
+        assert_eq!(posts.items[0].relative_path, Path::new("2024/hello.md"));
+        let posts = crate::glob(&pattern)?.parse_markdown_meta_only::<Page>()?;
+        assert_eq!(posts.items[1].relative_path, Path::new("2025/05/world.md"));
+        let posts = Collection::<Markdown<Page>>::markdown(&pattern)?;
+        assert_eq!(
+            posts.parsed().items[0].relative_path,
+            Path::new("2024/hello.md")
+        );
+        let post = parse_markdown::<Page>(&dir.join("blog/2024/hello.md"))?;
+        assert_eq!(post.relative_path, Path::new("hello.md"));
+
+        assert!(matches!(
+            crate::glob(&pattern)?.with_root(dir.join("blog/2024")),
+            Err(Error::OutsideRoot { .. })
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_plugins() -> Result<(), io::Error> {
+        use comrak::adapters::{HeadingAdapter, HeadingMeta};
+        use std::io::Write;
+
+        struct Titles;
+
+        impl HeadingAdapter for Titles {
+            fn enter(
+                &self,
+                output: &mut dyn Write,
+                heading: &HeadingMeta,
+                _: Option<comrak::nodes::Sourcepos>,
+            ) -> io::Result<()> {
+                write!(output, "<h{} class=\"title\">", heading.level)
+            }
+
+            fn exit(&self, output: &mut dyn Write, heading: &HeadingMeta) -> io::Result<()> {
+                write!(output, "</h{}>", heading.level)
+            }
+        }
+
+        assert!(render("# Hello", &MarkdownOptions::new())?.contains(r#"id="hello""#));
+        let options = MarkdownOptions::new()
+            .with_plugins(|plugins| plugins.render.heading_adapter = Some(&Titles));
+        assert_eq!(
+            render("# Hello", &options)?,
+            "<h1 class=\"title\">Hello</h1>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_fence() -> Result<(), io::Error> {
+        let content = "```mermaid\ngraph TD; A-->B\n```\n\n```rust\nfn main() {}\n```\n";
+        let html = render(content, &MarkdownOptions::new().mermaid())?;
+        assert!(html.starts_with("<pre class=\"mermaid\">graph TD; A--&gt;B\n</pre>\n"));
+
+        let options = MarkdownOptions::new()
+            .mermaid()
+            .code_fence("mermaid", |code| format!("<svg>{}</svg>", code.len()));
+        assert!(render(content, &options)?.starts_with("<svg>16</svg>"));
+        Ok(())
+    }
+
+    #[cfg(feature = "emoji")]
+    #[test]
+    fn test_emoji_shortcodes() -> Result<(), io::Error> {
+        let content = "Launch :rocket: at 10:30, :not_an_emoji:";
+        assert_eq!(
+            render(content, &MarkdownOptions::new())?,
+            "<p>Launch :rocket: at 10:30, :not_an_emoji:</p>\n"
+        );
+        assert_eq!(
+            render(content, &MarkdownOptions::new().emoji_shortcodes(true))?,
+            "<p>Launch 🚀 at 10:30, :not_an_emoji:</p>\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_anchors() -> Result<(), io::Error> {
+        let content = "## Setup\n\n## Setup\n\nText[^1]\n\n[^1]: Note\n";
+        let options = MarkdownOptions::new().anchors(
+            Anchors::new()
+                .id_prefix("post-")
+                .permalink("#", "permalink")
+                .footnote_backref_label("Back to content"),
+        );
+        let html = render(content, &options)?;
+        assert!(html.starts_with(concat!(
+            r##"<h2 id="post-setup">Setup <a href="#post-setup" class="permalink" aria-label="Permalink: Setup">#</a></h2>"##,
+            "\n",
+            r##"<h2 id="post-setup-1">Setup <a href="#post-setup-1" class="permalink" aria-label="Permalink: Setup">#</a></h2>"##,
+        )));
+        assert!(html.contains(r#"aria-label="Back to content">↩</a>"#));
+
+        let options = MarkdownOptions::new().anchors(Anchors::new().slug_fn(str::to_uppercase));
+        assert!(render(content, &options)?.contains(r#"<h2 id="SETUP-1">Setup</h2>"#));
+        Ok(())
+    }
+
+    #[cfg(feature = "sanitize")]
+    #[test]
+    fn test_sanitize() -> Result<(), io::Error> {
+        let content = "## Hi\n\n<img src=x onerror=alert(1)> [link](javascript:alert(1))[^1]\n\n```rust\nfn main() {}\n```\n\n[^1]: Note\n";
+        let unsafe_html = render(content, &MarkdownOptions::new())?;
+        assert!(unsafe_html.contains("onerror"));
+
+        let html = render(content, &MarkdownOptions::new().sanitize(true))?;
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("onerror"));
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains(r#"id="user-content-hi""#));
+        assert!(html.contains(r##"href="#user-content-hi""##));
+        assert!(html.contains(r#"<code class="language-rust">"#));
+        assert!(html.contains("data-footnote-backref"));
+
+        let content =
+            "<p id=\"location\" style=\"position:fixed\">Hi</p>\n\n```rust\nfn main() {}\n```\n";
+        let options = MarkdownOptions::new()
+            .sanitize(true)
+            .highlighting(Highlighting::Theme("InspiredGitHub".to_string()))
+            .map_err(io::Error::other)?;
+        let html = render(content, &options)?;
+        assert!(html.contains(r#"<p id="user-content-location">Hi</p>"#));
+        assert!(html.contains(r#"<pre style="background-color:#"#));
+        assert!(html.contains(r#"<span style="font-weight:bold;color:#"#));
+        let html = render(
+            "<span style=\"color:red;position:fixed\">Hi</span>",
+            &options,
+        )?;
+        assert!(html.contains(r#"<span style="color:red">Hi</span>"#));
+        Ok(())
+    }
+
+    #[test]
+    fn test_code_blocks() -> Result<(), io::Error> {
+        let content = "```rust,filename=main.rs\nfn main() {}\n```\n\n```\nplain\n```\n";
+        let html = render(content, &MarkdownOptions::new())?;
+        assert!(html.contains(r#"<code class="language-rust">"#));
+        assert!(!html.contains("main.rs"));
+
+        let options = MarkdownOptions::new().code_blocks(
+            CodeBlocks::new()
+                .copy_button(|code| format!("<button data-len=\"{}\">Copy</button>", code.len())),
+        );
+        let html = render(content, &options)?;
+        assert!(html.starts_with(concat!(
+            "<div class=\"code-block\">\n",
+            "<div class=\"code-block-header\"><span class=\"code-block-filename\">main.rs</span><button data-len=\"13\">Copy</button></div>\n",
+            "<pre class=\"syntax-highlighting\"><code class=\"language-rust\">",
+        )));
+        assert_eq!(html.matches("<div class=\"code-block\">").count(), 2);
+        assert!(html.ends_with("</code></pre>\n</div>\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_includes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_markdown_includes");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("shared"))?;
+        fs::write(dir.join("shared/note.md"), "*Note*")?;
+        fs::write(
+            dir.join("post.md"),
+            "---\ntitle: Post\n---\n{{ include \"shared/note.md\" }}\n",
+        )?;
+
+        let options = MarkdownOptions::new().includes(&dir);
+        let mut posts = Collection::<Markdown<Page>>::markdown_with(
+            dir.join("*.md").to_string_lossy(),
+            options,
+        )?;
+        let post = &posts.parsed().items[0];
+        assert_eq!(post.html, "<p><em>Note</em></p>\n");
+        assert_eq!(post.includes, vec![dir.join("shared/note.md")]);
+
+        fs::write(dir.join("shared/note.md"), "*Updated*")?;
+        assert!(posts.update(&[dir.join("shared/note.md")])?);
+        assert_eq!(posts.parsed().items[0].html, "<p><em>Updated</em></p>\n");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "encrypt")]
+    #[test]
+    fn test_is_private() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::IsPrivate;
+
+        #[derive(Deserialize, Debug)]
+        struct Note {
+            #[serde(default)]
+            private: bool,
+        }
+
+        impl IsPrivate for Note {
+            fn is_private(&self) -> bool {
+                self.private
+            }
+        }
+
+        let dir = env::temp_dir().join("pichu_test_markdown_is_private");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("public.md"), "---\ntitle: Public\n---\n")?;
+        crate::write(dir.join("private.md"), "---\nprivate: true\n---\n")?;
+
+        let options = MarkdownOptions::new();
+        let notes = crate::glob(dir.join("*.md").to_string_lossy())?
+            .parse_markdown_lazy::<Note>(&options)?
+            .into_vec();
+        let private = notes
+            .iter()
+            .map(|note| (note.basename.as_str(), note.is_private()))
+            .collect::<Vec<_>>();
+        assert_eq!(private, [("private", true), ("public", false)]);
+        let note = notes
+            .into_iter()
+            .next()
+            .ok_or("no notes")?
+            .into_markdown()?;
+        assert!(note.is_private());
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_markdown_lazy() -> Result<(), Box<dyn std::error::Error>> {
+        use rayon::prelude::*;
+
+        let dir = env::temp_dir().join("pichu_test_parse_markdown_lazy");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("shared/note.md"), "*Note*")?;
+        crate::write(
+            dir.join("posts/post.md"),
+            "---\ntitle: Post\ntags: [rust]\n---\nHi\n\n{{ include \"shared/note.md\" }}\n",
+        )?;
+
+        let options = MarkdownOptions::new().includes(&dir).raw_frontmatter(true);
+        let posts = crate::glob(dir.join("posts/*.md").to_string_lossy())?
+            .parse_markdown_lazy::<Page>(&options)?;
+        let post = &posts.items[0];
+        assert_eq!(post.frontmatter.title, "Post");
+        assert_eq!(post.relative_path, Path::new("post.md"));
+        assert!(!post.is_rendered());
+        let raw = post.raw_frontmatter.as_ref().ok_or("no raw frontmatter")?;
+        assert_eq!(raw["tags"][0], "rust");
+
+        let html = posts
+            .items
+            .par_iter()
+            .map(|post| post.html().map(str::to_owned))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(html, ["<p>Hi</p>\n<p><em>Note</em></p>\n"]);
+        assert!(post.is_rendered());
+        assert!(post.clone().is_rendered());
+        assert_eq!(post.includes()?, [dir.join("shared/note.md")]);
+
+        let markdown = posts.into_vec().remove(0).into_markdown()?;
+        assert_eq!(markdown.html, html[0]);
+        assert_eq!(markdown.includes, [dir.join("shared/note.md")]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_probe_images() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_markdown_probe_images");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("static"))?;
+        fs::create_dir_all(dir.join("posts"))?;
+        // Only the header of a 3x2 PNG, which is all that's read.
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend([0, 0, 0, 3, 0, 0, 0, 2, 8, 2, 0, 0, 0]);
+        fs::write(dir.join("static/cat.png"), &png)?;
+        fs::write(dir.join("posts/my%20dog.png"), &png)?;
+        fs::write(
+            dir.join("posts/post.md"),
+            "---\ntitle: Post\n---\n![A *cat*](/cat.png \"Cat\") ![Dog](my%2520dog.png) ![Missing](/missing.png) ![Remote](https://example.com/cat.png)\n",
+        )?;
+
+        let options = MarkdownOptions::new().probe_images(dir.join("static"));
+        let post: Markdown<Page> = parse_markdown_with(&dir.join("posts/post.md"), &options)?;
+        assert_eq!(
+            post.html,
+            concat!(
+                "<p><img src=\"/cat.png\" alt=\"A cat\" title=\"Cat\" width=\"3\" height=\"2\" /> ",
+                "<img src=\"my%2520dog.png\" alt=\"Dog\" width=\"3\" height=\"2\" /> ",
+                "<img src=\"/missing.png\" alt=\"Missing\" /> ",
+                "<img src=\"https://example.com/cat.png\" alt=\"Remote\" /></p>\n",
+            )
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_syntaxes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_markdown_syntaxes");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+        fs::write(
+            dir.join("Greeting.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Greeting\nfile_extensions: [greet]\nscope: source.greet\ncontexts:\n  main:\n    - match: '\\bhello\\b'\n      scope: keyword.greet\n",
+        )?;
+
+        let content = "```greet\nhello world\n```\n\n```jsx\nconst a = 1;\n```\n";
+        let html = render(content, &MarkdownOptions::new())?;
+        assert!(!html.contains("keyword greet"));
+        assert!(!html.contains("source js"));
+
+        let options = MarkdownOptions::new()
+            .syntaxes(&dir)?
+            .syntax_alias("jsx", "js");
+        let html = render(content, &options)?;
+        assert!(html.contains(r#"<span class="keyword greet">hello</span>"#));
+        assert!(html.contains(r#"<code class="language-jsx"><span class="source js">"#));
+
+        assert!(matches!(
+            MarkdownOptions::new().syntaxes(dir.join("missing")),
+            Err(MarkdownError::Syntaxes(..))
+        ));
+
+        let options = options.highlighting(Highlighting::Theme("InspiredGitHub".to_string()))?;
+        let html = render(content, &options)?;
+        assert!(html.contains(r#"<span style="font-weight:bold;color:#"#));
+        assert!(!html.contains("keyword greet"));
+        assert!(matches!(
+            MarkdownOptions::new().highlighting(Highlighting::Theme("missing".to_string())),
+            Err(MarkdownError::UnknownTheme(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,267 @@
+use std::fmt::Write;
+
+use crate::{
+    html::escape,
+    jsonld::{Article, BreadcrumbList, JsonLd, JsonLdError, Person},
+    Page,
+};
+
+/// The kind of page, used for `og:type`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PageKind {
+    /// A generic page, like an index or about page.
+    #[default]
+    Website,
+    /// An article, like a blog post.
+    Article,
+}
+
+/// Metadata for a page, rendered into `<head>` tags by [`PageMeta::to_html`].
+/// Usually populated from the frontmatter and the page's URL.
+#[derive(Debug, Clone, Default)]
+pub struct PageMeta {
+    /// The page title.
+    pub title: String,
+    /// The absolute, canonical URL of the page.
+    pub url: String,
+    /// A short description of the page.
+    pub description: Option<String>,
+    /// The name of the site.
+    pub site_name: Option<String>,
+    /// The absolute URL of an image representing the page.
+    pub image: Option<String>,
+    /// The kind of page.
+    pub kind: PageKind,
+    /// The author's name.
+    pub author: Option<String>,
+    /// When the page was published, as an ISO 8601 date or datetime.
+    pub published: Option<String>,
+    /// When the page was last modified, as an ISO 8601 date or datetime.
+    pub modified: Option<String>,
+    /// The site's Twitter handle, including the `@`.
+    pub twitter_site: Option<String>,
+    /// The breadcrumb trail leading to this page as `(name, url)` pairs,
+    /// excluding the page itself.
+    pub breadcrumbs: Vec<(String, String)>,
+}
+
+impl PageMeta {
+    /// Create metadata for the page with the given title and canonical URL.
+    #[must_use]
+    pub fn new(title: impl Into<String>, url: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            url: url.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Create metadata from a [`Page`], with its summary as description.
+    /// Pages with a date are articles.
+    #[must_use]
+    pub fn from_page(page: &impl Page, base_url: &str) -> Self {
+        Self {
+            title: page.title().to_string(),
+            url: page.url().to_url(base_url),
+            description: page.summary().map(str::to_string),
+            kind: if page.date().is_some() {
+                PageKind::Article
+            } else {
+                PageKind::Website
+            },
+            published: page.date().map(str::to_string),
+            modified: page.updated().map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    /// Render the canonical link, Open Graph and Twitter card tags and
+    /// JSON-LD structured data, ready to be placed in `<head>`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an article has no `published` date, which
+    /// schema.org requires.
+    pub fn to_html(&self) -> Result<String, JsonLdError> {
+        let mut html = String::new();
+        let mut tag = |attr: &str, name: &str, content: &str| {
+            let _ = writeln!(
+                html,
+                r#"<meta {attr}="{name}" content="{}">"#,
+                escape(content)
+            );
+        };
+
+        if let Some(description) = &self.description {
+            tag("name", "description", description);
+        }
+        if let Some(author) = &self.author {
+            tag("name", "author", author);
+        }
+
+        tag(
+            "property",
+            "og:type",
+            match self.kind {
+                PageKind::Website => "website",
+                PageKind::Article => "article",
+            },
+        );
+        tag("property", "og:title", &self.title);
+        tag("property", "og:url", &self.url);
+        if let Some(description) = &self.description {
+            tag("property", "og:description", description);
+        }
+        if let Some(site_name) = &self.site_name {
+            tag("property", "og:site_name", site_name);
+        }
+        if let Some(image) = &self.image {
+            tag("property", "og:image", image);
+        }
+        if self.kind == PageKind::Article {
+            if let Some(published) = &self.published {
+                tag("property", "article:published_time", published);
+            }
+            if let Some(modified) = &self.modified {
+                tag("property", "article:modified_time", modified);
+            }
+        }
+
+        let card = if self.image.is_some() {
+            "summary_large_image"
+        } else {
+            "summary"
+        };
+        tag("name", "twitter:card", card);
+        if let Some(twitter_site) = &self.twitter_site {
+            tag("name", "twitter:site", twitter_site);
+        }
+        tag("name", "twitter:title", &self.title);
+        if let Some(description) = &self.description {
+            tag("name", "twitter:description", description);
+        }
+        if let Some(image) = &self.image {
+            tag("name", "twitter:image", image);
+        }
+
+        let _ = writeln!(
+            html,
+            r#"<link rel="canonical" href="{}">"#,
+            escape(&self.url)
+        );
+        for data in self.json_ld()? {
+            let _ = writeln!(html, "{data}");
+        }
+        Ok(html)
+    }
+
+    fn json_ld(&self) -> Result<Vec<JsonLd>, JsonLdError> {
+        let mut data = Vec::new();
+        if self.kind == PageKind::Article {
+            let mut article = Article::new().headline(&self.title).url(&self.url);
+            if let Some(description) = &self.description {
+                article = article.description(description);
+            }
+            if let Some(image) = &self.image {
+                article = article.image(image);
+            }
+            if let Some(author) = &self.author {
+                article = article.author(Person::new(author));
+            }
+            if let Some(published) = &self.published {
+                article = article.date_published(published);
+            }
+            if let Some(modified) = &self.modified {
+                article = article.date_modified(modified);
+            }
+            data.push(article.build()?);
+        }
+
+        if !self.breadcrumbs.is_empty() {
+            let breadcrumbs = self
+                .breadcrumbs
+                .iter()
+                .chain([&(self.title.clone(), self.url.clone())])
+                .fold(BreadcrumbList::new(), |list, (name, url)| {
+                    list.item(name, url)
+                });
+            data.push(breadcrumbs.build()?);
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_html() -> Result<(), JsonLdError> {
+        let meta = PageMeta {
+            description: Some(r#"Say "hi" <3"#.to_string()),
+            kind: PageKind::Article,
+            published: Some("2024-05-01".to_string()),
+            breadcrumbs: vec![("Blog".to_string(), "https://example.com/blog/".to_string())],
+            ..PageMeta::new("Hello</script>", "https://example.com/blog/hello/")
+        };
+        let html = meta.to_html()?;
+
+        assert!(html.contains(r#"<link rel="canonical" href="https://example.com/blog/hello/">"#));
+        assert!(html.contains(r#"<meta property="og:type" content="article">"#));
+        assert!(html.contains(r#"<meta name="description" content="Say &quot;hi&quot; &lt;3">"#));
+        assert!(html.contains(r#"<meta property="article:published_time" content="2024-05-01">"#));
+        assert!(html.contains(r#"<meta name="twitter:card" content="summary">"#));
+        assert!(html.contains(r#""@type":"BreadcrumbList""#));
+        assert!(html.contains(r#""position":2"#));
+        assert!(!html.contains("Hello</script>"));
+
+        let undated = PageMeta {
+            published: None,
+            ..meta
+        };
+        assert!(matches!(
+            undated.to_html(),
+            Err(JsonLdError::MissingField {
+                field: "datePublished",
+                ..
+            })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_from_page() {
+        struct Post {
+            title: String,
+            date: String,
+        }
+
+        impl Page for Post {
+            fn title(&self) -> &str {
+                &self.title
+            }
+
+            fn url(&self) -> crate::UrlPath {
+                crate::UrlPath::new("/blog/hello/").unwrap_or_else(|_| crate::UrlPath::root())
+            }
+
+            fn date(&self) -> Option<&str> {
+                Some(&self.date)
+            }
+
+            fn summary(&self) -> Option<&str> {
+                self.title.get(..2)
+            }
+        }
+
+        let post = Post {
+            title: "Hi there".to_string(),
+            date: "2024-05-01".to_string(),
+        };
+        let meta = PageMeta::from_page(&post, "https://example.com");
+        assert_eq!(meta.url, "https://example.com/blog/hello/");
+        assert_eq!(meta.kind, PageKind::Article);
+        assert_eq!(meta.description.as_deref(), Some("Hi"));
+        assert_eq!(meta.published.as_deref(), Some("2024-05-01"));
+    }
+}
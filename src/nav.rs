@@ -0,0 +1,169 @@
+//! Navigation helpers, like breadcrumbs from the section index pages.
+//!
+//! ```
+//! use pichu::{nav::{self, Sections}, UrlPath};
+//!
+//! let sections = Sections::new()
+//!     .section(UrlPath::root(), "Home")
+//!     .section(UrlPath::new("/blog/")?, "Blog");
+//! let crumbs = nav::breadcrumbs(&UrlPath::new("/blog/2024/hello/")?, &sections);
+//! assert_eq!(crumbs, [
+//!     ("Home".to_string(), UrlPath::root()),
+//!     ("Blog".to_string(), UrlPath::new("/blog/")?),
+//! ]);
+//! # Ok::<(), pichu::Error>(())
+//! ```
+
+use std::{collections::BTreeMap, fs, path::Path};
+
+use crate::{html::unescape, Error, UrlPath};
+
+/// The titles of section index pages, by URL path.
+#[derive(Debug, Clone, Default)]
+pub struct Sections {
+    titles: BTreeMap<UrlPath, String>,
+}
+
+impl Sections {
+    /// Create an empty section tree.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a section with its title.
+    #[must_use]
+    pub fn section(mut self, url: UrlPath, title: impl Into<String>) -> Self {
+        self.titles.insert(url, title.into());
+        self
+    }
+
+    /// Read the sections from the rendered `index.html` pages in the output
+    /// directory, titled by their `<title>`. Pages without a title are
+    /// skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the output directory can't be read.
+    pub fn from_output(out_dir: impl AsRef<Path>) -> Result<Self, Error> {
+        let out_dir = out_dir.as_ref();
+        let pattern =
+            Path::new(&glob::Pattern::escape(&out_dir.to_string_lossy())).join("**/index.html");
+        let mut sections = Self::new();
+        for path in glob::glob(&pattern.to_string_lossy())? {
+            let path = path?;
+            let Some(title) = title(&fs::read_to_string(&path)?) else {
+                continue;
+            };
+            sections
+                .titles
+                .insert(UrlPath::from_output(out_dir, &path)?, title);
+        }
+        Ok(sections)
+    }
+
+    /// The title of the section at the URL path.
+    #[must_use]
+    pub fn title(&self, url: &UrlPath) -> Option<&str> {
+        self.titles.get(url).map(String::as_str)
+    }
+}
+
+/// The trail of sections from the root to the page at `url`, as
+/// `(title, url)` pairs. Sections missing from `sections` are left out, the
+/// page itself is only included if it's a section.
+#[must_use]
+pub fn breadcrumbs(url: &UrlPath, sections: &Sections) -> Vec<(String, UrlPath)> {
+    let segments = url.segments().collect::<Vec<_>>();
+    let dirs = if url.is_dir() {
+        segments.len()
+    } else {
+        segments.len().saturating_sub(1)
+    };
+    (0..=dirs)
+        .filter_map(|depth| {
+            let section = UrlPath::new(format!("{}/", segments[..depth].join("/"))).ok()?;
+            let title = sections.title(&section)?;
+            Some((title.to_string(), section))
+        })
+        .collect()
+}
+
+/// Breadcrumbs as structured data, with URLs made absolute with the base
+/// URL, e.g. `https://example.com`.
+#[cfg(feature = "seo")]
+#[must_use]
+pub fn breadcrumb_list(crumbs: &[(String, UrlPath)], base_url: &str) -> crate::BreadcrumbList {
+    crumbs
+        .iter()
+        .fold(crate::BreadcrumbList::new(), |list, (title, url)| {
+            list.item(title, url.to_url(base_url))
+        })
+}
+
+/// The text of the first `<title>` element.
+fn title(html: &str) -> Option<String> {
+    let start = html.find("<title")?;
+    let start = start + html[start..].find('>')? + 1;
+    let end = start + html[start..].find("</title>")?;
+    let title = unescape(html[start..end].trim());
+    (!title.is_empty()).then_some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_breadcrumbs() -> Result<(), Error> {
+        let dir = env::temp_dir().join("pichu_test_breadcrumbs[1]");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("index.html"), "<title>Home</title>")?;
+        crate::write(
+            dir.join("blog/index.html"),
+            "<html><head><title lang=\"en\"> Notes &amp; Posts </title>",
+        )?;
+        crate::write(dir.join("blog/hello/index.html"), "<title>Hello</title>")?;
+        crate::write(dir.join("blog/untitled/index.html"), "<h1>Untitled</h1>")?;
+
+        let sections = Sections::from_output(&dir)?;
+        assert_eq!(
+            sections.title(&UrlPath::new("/blog/")?),
+            Some("Notes & Posts")
+        );
+        assert_eq!(
+            breadcrumbs(&UrlPath::new("/blog/hello/")?, &sections),
+            [
+                ("Home".to_string(), UrlPath::root()),
+                ("Notes & Posts".to_string(), UrlPath::new("/blog/")?),
+                ("Hello".to_string(), UrlPath::new("/blog/hello/")?),
+            ]
+        );
+        assert_eq!(
+            breadcrumbs(&UrlPath::new("/blog/untitled/feed.xml")?, &sections).len(),
+            2
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[cfg(feature = "seo")]
+    #[test]
+    fn test_breadcrumb_list() -> Result<(), Box<dyn std::error::Error>> {
+        let crumbs = [
+            ("Home".to_string(), UrlPath::root()),
+            ("Blog".to_string(), UrlPath::new("/blog/")?),
+        ];
+        let json_ld = breadcrumb_list(&crumbs, "https://example.com/").build()?;
+        assert_eq!(
+            json_ld.value()["itemListElement"][1]["item"],
+            "https://example.com/blog/"
+        );
+        Ok(())
+    }
+}
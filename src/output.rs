@@ -0,0 +1,120 @@
+use rayon::prelude::*;
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use crate::workspace::temp_path;
+
+#[cfg(feature = "encodings")]
+use crate::encodings::encode;
+
+/// Without the `encodings` feature, files are written as is.
+#[cfg(not(feature = "encodings"))]
+fn encode<'a>(_path: &Path, contents: &'a [u8]) -> std::borrow::Cow<'a, [u8]> {
+    std::borrow::Cow::Borrowed(contents)
+}
+
+/// Write `contents` to `path`, assuming the parent directory exists and only
+/// creating it if the write fails because it doesn't.
+/// This saves a `create_dir_all` per file, which adds up on network
+/// filesystems and Windows.
+pub(crate) fn write_optimistic(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    write_encoded(path, &encode(path, contents))
+}
+
+fn write_encoded(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    match fs::write(path, contents) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            let Some(parent) = path.parent() else {
+                return Err(e);
+            };
+            fs::create_dir_all(parent)?;
+            fs::write(path, contents)
+        }
+        res => res,
+    }
+}
+
+/// Write `contents` to a temporary file next to `path` and move it into
+/// place, so an interrupted write never leaves a truncated file behind.
+/// Every write uses its own temporary file, so concurrent writes to the
+/// same path don't interfere.
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<(), io::Error> {
+    let tmp = temp_path(path);
+    write_encoded(&tmp, &encode(path, contents))?;
+    fs::rename(&tmp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })
+}
+
+/// Write a batch of files in parallel.
+/// Every distinct parent directory is created once up front, instead of once
+/// per file.
+pub(crate) fn write_batch(files: Vec<(PathBuf, Vec<u8>)>) -> Result<(), io::Error> {
+    let parents = files
+        .iter()
+        .filter_map(|(path, _)| path.parent())
+        .filter(|parent| !parent.as_os_str().is_empty())
+        .collect::<BTreeSet<_>>();
+    for parent in parents {
+        fs::create_dir_all(parent)?;
+    }
+
+    files
+        .into_par_iter()
+        .map(|(path, contents)| fs::write(&path, encode(&path, &contents)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::env;
+
+    #[test]
+    fn test_write_batch() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_write_batch");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let files = (0..20)
+            .map(|i| {
+                let path = dir.join(format!("{}/{i}.txt", i % 3));
+                (path, i.to_string().into_bytes())
+            })
+            .collect();
+        write_batch(files)?;
+
+        assert_eq!(fs::read_to_string(dir.join("1/7.txt"))?, "7");
+        assert_eq!(fs::read_dir(dir.join("2"))?.count(), 6);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_atomic() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_write_atomic");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(&dir)?;
+
+        let path = dir.join("manifest.json");
+        (0..20)
+            .into_par_iter()
+            .map(|i| write_atomic(&path, i.to_string().as_bytes()))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(fs::read_to_string(&path)?.parse::<u32>()? < 20);
+        // No temporary files are left behind.
+        assert_eq!(fs::read_dir(&dir)?.count(), 1);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
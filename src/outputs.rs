@@ -0,0 +1,150 @@
+use std::{
+    collections::HashMap,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{sync::lock, Error, Parsed};
+
+/// Items that were parsed from a source file.
+pub trait HasSource {
+    /// The path of the source file.
+    fn source(&self) -> &Path;
+}
+
+/// Tracks which source file each output path was rendered from, so two
+/// sources claiming the same output (e.g. a blog post and a page both
+/// rendering `about/index.html`) are reported instead of one silently
+/// overwriting the other.
+/// Can be shared between threads and collections. A [`Build`](crate::Build)
+/// has one per run in [`BuildContext::outputs`](crate::BuildContext::outputs).
+#[derive(Debug, Default)]
+pub struct OutputRegistry {
+    claims: Mutex<HashMap<PathBuf, PathBuf>>,
+}
+
+impl OutputRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Claim an output path for a source file. Claiming the same path again
+    /// for the same source is fine, e.g. when re-rendering on change.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutputConflict`] if another source already claimed
+    /// the path.
+    pub fn claim(&self, output: impl AsRef<Path>, source: impl AsRef<Path>) -> Result<(), Error> {
+        let output = normalize(output.as_ref());
+        let source = source.as_ref();
+        let mut claims = lock(&self.claims);
+        match claims.get(&output) {
+            Some(first) if first != source => Err(Error::OutputConflict {
+                path: output,
+                first: first.clone(),
+                second: source.to_owned(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                claims.insert(output, source.to_owned());
+                Ok(())
+            }
+        }
+    }
+
+    /// The source file that claimed an output path, if any.
+    #[must_use]
+    pub fn owner(&self, output: impl AsRef<Path>) -> Option<PathBuf> {
+        lock(&self.claims).get(&normalize(output.as_ref())).cloned()
+    }
+
+    /// Release all outputs claimed by a source, e.g. after it was deleted
+    /// or its output path changed.
+    pub fn release(&self, source: impl AsRef<Path>) {
+        let source = source.as_ref();
+        lock(&self.claims).retain(|_, owner| owner != source);
+    }
+
+    /// Release all claims.
+    pub fn clear(&self) {
+        lock(&self.claims).clear();
+    }
+}
+
+/// Normalize an output path lexically, so `dist/./about/index.html` and
+/// `dist/about/index.html` are the same output.
+fn normalize(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|component| !matches!(component, Component::CurDir))
+        .collect()
+}
+
+impl<T: HasSource + Send + Sync> Parsed<T> {
+    /// Claim the output path of every item in the registry, before
+    /// rendering. Use the same path function as for rendering.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutputConflict`] with both source files if an
+    /// output path was already claimed by a different source.
+    pub fn claim_outputs<P: AsRef<Path>>(
+        self,
+        registry: &OutputRegistry,
+        path_fn: impl Fn(&T) -> P,
+    ) -> Result<Self, Error> {
+        for item in &self.items {
+            registry.claim(path_fn(item), item.source())?;
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Page {
+        path: PathBuf,
+        slug: &'static str,
+    }
+
+    impl HasSource for Page {
+        fn source(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    #[test]
+    fn test_claim_outputs() -> Result<(), Error> {
+        let registry = OutputRegistry::new();
+        let blog = Parsed::from(vec![Page {
+            path: PathBuf::from("content/blog/about.md"),
+            slug: "about",
+        }]);
+        let pages = Parsed::from(vec![Page {
+            path: PathBuf::from("content/pages/about.md"),
+            slug: "about",
+        }]);
+
+        let blog =
+            blog.claim_outputs(&registry, |page| format!("dist/{}/index.html", page.slug))?;
+        blog.claim_outputs(&registry, |page| format!("dist/./{}/index.html", page.slug))?;
+        let err = pages
+            .claim_outputs(&registry, |page| format!("dist/{}/index.html", page.slug))
+            .err();
+        assert_eq!(
+            err.map(|e| e.to_string()),
+            Some(
+                "output dist/about/index.html claimed by both content/blog/about.md and content/pages/about.md"
+                    .to_string()
+            )
+        );
+
+        registry.release("content/blog/about.md");
+        assert_eq!(registry.owner("dist/about/index.html"), None);
+        Ok(())
+    }
+}
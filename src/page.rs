@@ -0,0 +1,113 @@
+use crate::{Parsed, UrlPath};
+
+/// Items that are pages of the site, with the metadata shared by feeds,
+/// sitemaps and `<head>` tags. Implement it once instead of passing a
+/// mapping closure to each of them.
+///
+/// ```
+/// use pichu::{Page, UrlPath};
+///
+/// struct Post {
+///     title: String,
+///     slug: String,
+///     date: String,
+/// }
+///
+/// impl Page for Post {
+///     fn title(&self) -> &str {
+///         &self.title
+///     }
+///
+///     fn url(&self) -> UrlPath {
+///         UrlPath::new(format!("/blog/{}/", self.slug)).unwrap_or_else(|_| UrlPath::root())
+///     }
+///
+///     fn date(&self) -> Option<&str> {
+///         Some(&self.date)
+///     }
+/// }
+/// ```
+///
+/// With the `derive` feature, it can be derived for frontmatter, see
+/// [`derive@Page`].
+pub trait Page {
+    /// The title of the page.
+    fn title(&self) -> &str;
+
+    /// The URL path of the page.
+    fn url(&self) -> UrlPath;
+
+    /// When the page was published, as an ISO 8601 date or datetime.
+    fn date(&self) -> Option<&str> {
+        None
+    }
+
+    /// When the page was last updated, as an ISO 8601 date or datetime.
+    fn updated(&self) -> Option<&str> {
+        None
+    }
+
+    /// A short summary of the page.
+    fn summary(&self) -> Option<&str> {
+        None
+    }
+
+    /// The tags of the page.
+    fn tags(&self) -> &[String] {
+        &[]
+    }
+}
+
+impl<T: Page + Send + Sync> Parsed<T> {
+    /// Sort the pages by date, newest first, with undated pages last. Uses
+    /// the date as written, so dates should be in the same format.
+    #[must_use]
+    pub fn sort_by_date(self) -> Self {
+        self.sort_by_key_reverse(|page| page.date().map(str::to_owned))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Post {
+        title: &'static str,
+        date: Option<&'static str>,
+    }
+
+    impl Page for Post {
+        fn title(&self) -> &str {
+            self.title
+        }
+
+        fn url(&self) -> UrlPath {
+            UrlPath::root()
+        }
+
+        fn date(&self) -> Option<&str> {
+            self.date
+        }
+    }
+
+    #[test]
+    fn test_sort_by_date() {
+        let posts = Parsed::from(vec![
+            Post {
+                title: "draft",
+                date: None,
+            },
+            Post {
+                title: "old",
+                date: Some("2023-01-01"),
+            },
+            Post {
+                title: "new",
+                date: Some("2024-05-01"),
+            },
+        ])
+        .sort_by_date();
+        let titles = posts.items.iter().map(Page::title).collect::<Vec<_>>();
+        assert_eq!(titles, ["new", "old", "draft"]);
+    }
+}
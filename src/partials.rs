@@ -0,0 +1,122 @@
+use std::{collections::HashMap, fmt};
+
+use crate::Error;
+
+type PartialFn<C> = dyn Fn(&C) -> String + Send + Sync;
+type LayoutFn<C> = dyn Fn(&C, &str) -> String + Send + Sync;
+
+/// A registry of named partials (e.g. a header or footer) and layouts
+/// wrapping a page's body, shared between render functions.
+/// `C` is the context passed to each partial, like the page being rendered
+/// or the site configuration.
+///
+/// ```
+/// let mut partials = pichu::Partials::<str>::new();
+/// partials.register("header", |title| format!("<header>{title}</header>"));
+/// partials.register_layout("base", |title, body| format!("<title>{title}</title>{body}"));
+///
+/// let body = partials.render("header", "Hello")?;
+/// assert_eq!(
+///     partials.layout("base", "Hello", &body)?,
+///     "<title>Hello</title><header>Hello</header>"
+/// );
+/// # Ok::<(), pichu::Error>(())
+/// ```
+pub struct Partials<C: ?Sized = ()> {
+    partials: HashMap<String, Box<PartialFn<C>>>,
+    layouts: HashMap<String, Box<LayoutFn<C>>>,
+}
+
+impl<C: ?Sized> Default for Partials<C> {
+    fn default() -> Self {
+        Self {
+            partials: HashMap::new(),
+            layouts: HashMap::new(),
+        }
+    }
+}
+
+impl<C: ?Sized> Partials<C> {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a partial, replacing any previous partial with the same name.
+    pub fn register(
+        &mut self,
+        name: impl Into<String>,
+        partial_fn: impl Fn(&C) -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.partials.insert(name.into(), Box::new(partial_fn));
+        self
+    }
+
+    /// Register a layout, which receives the context and the body to wrap.
+    pub fn register_layout(
+        &mut self,
+        name: impl Into<String>,
+        layout_fn: impl Fn(&C, &str) -> String + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.layouts.insert(name.into(), Box::new(layout_fn));
+        self
+    }
+
+    /// Render the partial `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no partial with that name is registered.
+    pub fn render(&self, name: &str, context: &C) -> Result<String, Error> {
+        let partial_fn = self
+            .partials
+            .get(name)
+            .ok_or_else(|| Error::UnknownPartial(name.to_string()))?;
+        Ok(partial_fn(context))
+    }
+
+    /// Wrap `body` in the layout `name`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no layout with that name is registered.
+    pub fn layout(&self, name: &str, context: &C, body: &str) -> Result<String, Error> {
+        let layout_fn = self
+            .layouts
+            .get(name)
+            .ok_or_else(|| Error::UnknownLayout(name.to_string()))?;
+        Ok(layout_fn(context, body))
+    }
+}
+
+impl<C: ?Sized> fmt::Debug for Partials<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut partials = self.partials.keys().collect::<Vec<_>>();
+        partials.sort();
+        let mut layouts = self.layouts.keys().collect::<Vec<_>>();
+        layouts.sort();
+        f.debug_struct("Partials")
+            .field("partials", &partials)
+            .field("layouts", &layouts)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown() {
+        let partials = Partials::<()>::new();
+        assert!(matches!(
+            partials.render("header", &()),
+            Err(Error::UnknownPartial(name)) if name == "header"
+        ));
+        assert!(matches!(
+            partials.layout("base", &(), ""),
+            Err(Error::UnknownLayout(name)) if name == "base"
+        ));
+    }
+}
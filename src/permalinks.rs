@@ -0,0 +1,133 @@
+use std::path::Path;
+
+use crate::{Error, HasSource, OutputRegistry, Parsed, UrlPath, UrlStyle};
+
+/// Items that can override their computed URL path, usually through a
+/// `path` or `url` frontmatter key, e.g. to keep the old URL of a page when
+/// migrating a site.
+pub trait HasPermalink {
+    /// The URL path of the item, or `None` to use the computed one.
+    fn permalink(&self) -> Option<&str>;
+}
+
+impl<T: HasPermalink + HasSource + Send + Sync> Parsed<T> {
+    /// Pair each item with its URL path: its permalink if it declares one,
+    /// or else the one computed by `url_fn`, converted to the site's URL
+    /// style. Permalinks are kept as declared, as they usually preserve old
+    /// URLs. Claims the output path of every item in the registry, so a
+    /// permalink can't overwrite another page.
+    ///
+    /// ```no_run
+    /// # #[derive(serde::Deserialize, Debug)]
+    /// # struct Page { path: Option<String> }
+    /// # impl pichu::HasPermalink for Page {
+    /// #     fn permalink(&self) -> Option<&str> { self.path.as_deref() }
+    /// # }
+    /// use pichu::{OutputRegistry, UrlStyle};
+    ///
+    /// let registry = OutputRegistry::new();
+    /// pichu::glob("content/**/*.md")?
+    ///     .parse_markdown::<Page>()?
+    ///     .permalinks("dist", &registry, UrlStyle::Pretty, |page| page.url(UrlStyle::Pretty))?
+    ///     .render_each_at(
+    ///         "dist",
+    ///         |(page, _)| page.html.clone(),
+    ///         |(_, url)| Ok(url.clone()),
+    ///     )?;
+    /// # Ok::<(), pichu::Error>(())
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a URL path is invalid, or
+    /// [`Error::OutputConflict`] with both source files if two items end up
+    /// at the same output path.
+    pub fn permalinks(
+        self,
+        out_dir: impl AsRef<Path>,
+        registry: &OutputRegistry,
+        style: UrlStyle,
+        url_fn: impl Fn(&T) -> Result<UrlPath, Error>,
+    ) -> Result<Parsed<(T, UrlPath)>, Error> {
+        let unordered = self.unordered;
+        let mut items = Vec::with_capacity(self.items.len());
+        for item in self.items {
+            let url = match item.permalink() {
+                Some(permalink) => UrlPath::new(permalink)?,
+                None => url_fn(&item)?.with_style(style),
+            };
+            registry.claim(url.to_output(out_dir.as_ref()), item.source())?;
+            items.push((item, url));
+        }
+        Ok(Parsed { items, unordered })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    struct Page {
+        path: PathBuf,
+        slug: &'static str,
+        permalink: Option<&'static str>,
+    }
+
+    impl HasSource for Page {
+        fn source(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl HasPermalink for Page {
+        fn permalink(&self) -> Option<&str> {
+            self.permalink
+        }
+    }
+
+    fn page(slug: &'static str, permalink: Option<&'static str>) -> Page {
+        Page {
+            path: PathBuf::from(format!("content/{slug}.md")),
+            slug,
+            permalink,
+        }
+    }
+
+    #[test]
+    fn test_permalinks() -> Result<(), Error> {
+        let url = |page: &Page| UrlPath::new(format!("/{}/", page.slug));
+        let pages = Parsed::from(vec![
+            page("hello", None),
+            page("old", Some("/archive/old-post.html")),
+        ])
+        .permalinks("dist", &OutputRegistry::new(), UrlStyle::Pretty, url)?;
+        let urls = pages
+            .items
+            .iter()
+            .map(|(_, url)| url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(urls, ["/hello/", "/archive/old-post.html"]);
+
+        let pages = Parsed::from(vec![page("hello", None), page("about", Some("/about/"))])
+            .permalinks("dist", &OutputRegistry::new(), UrlStyle::Html, url)?;
+        let urls = pages
+            .items
+            .iter()
+            .map(|(_, url)| url.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(urls, ["/hello.html", "/about/"]);
+
+        let pages = Parsed::from(vec![page("hello", None), page("world", Some("/hello/"))]);
+        assert!(matches!(
+            pages.permalinks("dist", &OutputRegistry::new(), UrlStyle::Pretty, url),
+            Err(Error::OutputConflict { .. })
+        ));
+        let pages = Parsed::from(vec![page("hello", Some("/../etc/"))]);
+        assert!(matches!(
+            pages.permalinks("dist", &OutputRegistry::new(), UrlStyle::Pretty, url),
+            Err(Error::InvalidUrlPath(_))
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+use tiny_http::{Request, Response};
+
+use crate::{
+    html::escape,
+    serve::{request_path, resolve, with_content_type},
+    Error, ServeError, UrlPath,
+};
+
+/// How a page differs between two builds, see [`changed_pages`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ChangeKind {
+    /// The page only exists in the new build.
+    Added,
+    /// The page only exists in the old build.
+    Removed,
+    /// The page exists in both builds with different contents.
+    Modified,
+}
+
+/// A page that differs between two builds, see [`changed_pages`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PageChange {
+    /// The URL path of the page.
+    pub url: UrlPath,
+    /// How the page changed.
+    pub kind: ChangeKind,
+}
+
+/// The HTML pages that were added, removed or modified between two output
+/// directories, sorted by URL path.
+///
+/// # Errors
+///
+/// Returns an error if a directory or page cannot be read.
+pub fn changed_pages(
+    old_dir: impl AsRef<Path>,
+    new_dir: impl AsRef<Path>,
+) -> Result<Vec<PageChange>, Error> {
+    let (old_dir, new_dir) = (old_dir.as_ref(), new_dir.as_ref());
+    let mut pages = BTreeMap::<UrlPath, (Option<PathBuf>, Option<PathBuf>)>::new();
+    for page in html_pages(old_dir)? {
+        let url = UrlPath::from_output(old_dir, &page)?;
+        pages.entry(url).or_default().0 = Some(page);
+    }
+    for page in html_pages(new_dir)? {
+        let url = UrlPath::from_output(new_dir, &page)?;
+        pages.entry(url).or_default().1 = Some(page);
+    }
+
+    let mut changes = Vec::new();
+    for (url, paths) in pages {
+        let kind = match paths {
+            (Some(old), Some(new)) => {
+                if fs::read(old)? == fs::read(new)? {
+                    continue;
+                }
+                ChangeKind::Modified
+            }
+            (Some(_), None) => ChangeKind::Removed,
+            (None, _) => ChangeKind::Added,
+        };
+        changes.push(PageChange { url, kind });
+    }
+    Ok(changes)
+}
+
+/// Serve a UI on `127.0.0.1` at the port that lists the pages changed
+/// between two builds, e.g. of the main branch and a pull request, with an
+/// inline or side-by-side diff of each page's HTML. Both builds are read on
+/// every request, so they can be rebuilt while the server is running. Runs
+/// until the process exits.
+///
+/// ```no_run
+/// pichu::preview_diff("dist-main", "dist", 8081)?;
+/// # Ok::<(), pichu::ServeError>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the server cannot bind to the port.
+pub fn preview_diff(
+    old_dir: impl AsRef<Path>,
+    new_dir: impl AsRef<Path>,
+    port: u16,
+) -> Result<(), ServeError> {
+    let server = tiny_http::Server::http(("127.0.0.1", port)).map_err(ServeError::Start)?;
+    for request in server.incoming_requests() {
+        // A client hanging up shouldn't take the server down.
+        let _ = respond(old_dir.as_ref(), new_dir.as_ref(), request);
+    }
+    Ok(())
+}
+
+fn respond(old_dir: &Path, new_dir: &Path, request: Request) -> Result<(), std::io::Error> {
+    let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+    let side_by_side = query.split('&').any(|param| param == "view=side");
+    let response = match request_path(path) {
+        Some(path) => route(old_dir, new_dir, &path, side_by_side),
+        None => Ok(None),
+    };
+    match response {
+        Ok(Some((name, contents))) => {
+            let response = Response::from_data(contents);
+            request.respond(with_content_type(response, Path::new(&name)))
+        }
+        Ok(None) => request.respond(Response::from_string("Not Found").with_status_code(404)),
+        Err(e) => request.respond(Response::from_string(e.to_string()).with_status_code(500)),
+    }
+}
+
+/// The file name, for the content type, and contents of the response to a
+/// request path. The pages of both builds are served below `/old/` and
+/// `/new/`.
+fn route(
+    old_dir: &Path,
+    new_dir: &Path,
+    path: &str,
+    side_by_side: bool,
+) -> Result<Option<(String, Vec<u8>)>, Error> {
+    if path == "/" {
+        let changes = changed_pages(old_dir, new_dir)?;
+        return Ok(Some((
+            "index.html".to_string(),
+            index(&changes).into_bytes(),
+        )));
+    }
+    if let Some(url) = path.strip_prefix("/diff") {
+        let Ok(url) = UrlPath::new(url) else {
+            return Ok(None);
+        };
+        let read = |dir: &Path| -> Result<String, Error> {
+            match resolve(dir, url.as_str()) {
+                Some(file) => Ok(fs::read_to_string(file)?),
+                None => Ok(String::new()),
+            }
+        };
+        let (old, new) = (read(old_dir)?, read(new_dir)?);
+        let page = diff_page(&url, &diff_lines(&old, &new), side_by_side);
+        return Ok(Some(("index.html".to_string(), page.into_bytes())));
+    }
+    for (prefix, dir) in [("/old", old_dir), ("/new", new_dir)] {
+        if let Some(file) = path
+            .strip_prefix(prefix)
+            .filter(|rest| rest.starts_with('/'))
+            .and_then(|rest| resolve(dir, rest))
+        {
+            let name = file.to_string_lossy().into_owned();
+            return Ok(Some((name, fs::read(file)?)));
+        }
+    }
+    Ok(None)
+}
+
+fn html_pages(dir: &Path) -> Result<Vec<PathBuf>, Error> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let pattern = Path::new(&glob::Pattern::escape(&dir.to_string_lossy())).join("**/*.html");
+    Ok(glob::glob(&pattern.to_string_lossy())?.collect::<Result<Vec<_>, _>>()?)
+}
+
+/// A line of a diff between two pages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// The lines of an HTML document, also breaking between adjacent tags so
+/// minified pages don't diff as a single line.
+fn lines(html: &str) -> Vec<&str> {
+    let mut lines = Vec::new();
+    for line in html.lines() {
+        let mut rest = line;
+        while let Some(end) = rest.find("><") {
+            lines.push(&rest[..=end]);
+            rest = &rest[end + 1..];
+        }
+        lines.push(rest);
+    }
+    lines
+}
+
+/// The line diff between two documents, using the longest common
+/// subsequence. Very large changes are shown as a removal and an addition.
+fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<Line<'a>> {
+    /// The most cells of the LCS table computed, about 16 MB.
+    const MAX_CELLS: usize = 4_000_000;
+
+    let (old, new) = (lines(old), lines(new));
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (
+        &old[prefix..old.len() - suffix],
+        &new[prefix..new.len() - suffix],
+    );
+
+    let mut diff = old[..prefix]
+        .iter()
+        .map(|line| Line::Same(line))
+        .collect::<Vec<_>>();
+    if old_mid.len().saturating_mul(new_mid.len()) > MAX_CELLS {
+        diff.extend(old_mid.iter().map(|line| Line::Removed(line)));
+        diff.extend(new_mid.iter().map(|line| Line::Added(line)));
+    } else {
+        // lengths[i][j] is the LCS length of old_mid[i..] and new_mid[j..].
+        let width = new_mid.len() + 1;
+        let mut lengths = vec![0u32; (old_mid.len() + 1) * width];
+        for i in (0..old_mid.len()).rev() {
+            for j in (0..new_mid.len()).rev() {
+                lengths[i * width + j] = if old_mid[i] == new_mid[j] {
+                    lengths[(i + 1) * width + j + 1] + 1
+                } else {
+                    lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < old_mid.len() || j < new_mid.len() {
+            if i < old_mid.len() && j < new_mid.len() && old_mid[i] == new_mid[j] {
+                diff.push(Line::Same(old_mid[i]));
+                i += 1;
+                j += 1;
+            } else if j == new_mid.len()
+                || (i < old_mid.len() && lengths[(i + 1) * width + j] >= lengths[i * width + j + 1])
+            {
+                diff.push(Line::Removed(old_mid[i]));
+                i += 1;
+            } else {
+                diff.push(Line::Added(new_mid[j]));
+                j += 1;
+            }
+        }
+    }
+    diff.extend(
+        old[old.len() - suffix..]
+            .iter()
+            .map(|line| Line::Same(line)),
+    );
+    diff
+}
+
+const STYLE: &str = "body{font:14px/1.4 system-ui,sans-serif;margin:2rem}\
+table{border-collapse:collapse;width:100%;table-layout:fixed}\
+td{font:12px/1.4 monospace;white-space:pre-wrap;word-break:break-all;vertical-align:top;padding:0 .5rem}\
+.added{background:#e6ffec}.removed{background:#ffebe9}.kind{width:6rem}";
+
+fn layout(title: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>{}</title>\
+         <style>{STYLE}</style></head><body>{body}</body></html>",
+        escape(title)
+    )
+}
+
+fn index(changes: &[PageChange]) -> String {
+    let mut body = format!("<h1>{} changed pages</h1><table>", changes.len());
+    for change in changes {
+        let (class, label) = match change.kind {
+            ChangeKind::Added => ("added", "added"),
+            ChangeKind::Removed => ("removed", "removed"),
+            ChangeKind::Modified => ("", "modified"),
+        };
+        let url = escape(change.url.as_str());
+        let _ = write!(
+            body,
+            "<tr><td class=\"kind {class}\">{label}</td><td><a href=\"/diff{url}\">{url}</a></td></tr>"
+        );
+    }
+    body.push_str("</table>");
+    layout("Changed pages", &body)
+}
+
+fn diff_page(url: &UrlPath, diff: &[Line<'_>], side_by_side: bool) -> String {
+    let url = escape(url.as_str());
+    let mut body = format!(
+        "<p><a href=\"/\">All changes</a> · <a href=\"?view=inline\">Inline</a> · \
+         <a href=\"?view=side\">Side by side</a> · <a href=\"/old{url}\">Old page</a> · \
+         <a href=\"/new{url}\">New page</a></p><h1>{url}</h1><table>"
+    );
+    for line in diff {
+        let _ = match (line, side_by_side) {
+            (Line::Same(line), false) => write!(body, "<tr><td>  {}</td></tr>", escape(line)),
+            (Line::Removed(line), false) => {
+                write!(
+                    body,
+                    "<tr><td class=\"removed\">- {}</td></tr>",
+                    escape(line)
+                )
+            }
+            (Line::Added(line), false) => {
+                write!(body, "<tr><td class=\"added\">+ {}</td></tr>", escape(line))
+            }
+            (Line::Same(line), true) => {
+                let line = escape(line);
+                write!(body, "<tr><td>{line}</td><td>{line}</td></tr>")
+            }
+            (Line::Removed(line), true) => write!(
+                body,
+                "<tr><td class=\"removed\">{}</td><td></td></tr>",
+                escape(line)
+            ),
+            (Line::Added(line), true) => write!(
+                body,
+                "<tr><td></td><td class=\"added\">{}</td></tr>",
+                escape(line)
+            ),
+        };
+    }
+    body.push_str("</table>");
+    layout(&url, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            diff_lines(
+                "<ul><li>a</li><li>b</li></ul>",
+                "<ul><li>a</li><li>c</li></ul>"
+            ),
+            [
+                Line::Same("<ul>"),
+                Line::Same("<li>a</li>"),
+                Line::Removed("<li>b</li>"),
+                Line::Added("<li>c</li>"),
+                Line::Same("</ul>"),
+            ]
+        );
+        assert_eq!(
+            diff_lines("a\nb\nc\n", "a\nx\nc\nd\n"),
+            [
+                Line::Same("a"),
+                Line::Removed("b"),
+                Line::Added("x"),
+                Line::Same("c"),
+                Line::Added("d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_changed_pages() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_changed_pages");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        crate::write(old.join("index.html"), "<h1>Home</h1>")?;
+        crate::write(new.join("index.html"), "<h1>Home</h1>")?;
+        crate::write(old.join("about/index.html"), "<p>Old</p>")?;
+        crate::write(new.join("about/index.html"), "<p>New</p>")?;
+        crate::write(old.join("draft/index.html"), "")?;
+        crate::write(new.join("blog/index.html"), "")?;
+        crate::write(new.join("style.css"), "")?;
+
+        let changes = changed_pages(&old, &new)?;
+        assert_eq!(
+            changes
+                .iter()
+                .map(|change| (change.url.as_str(), change.kind))
+                .collect::<Vec<_>>(),
+            [
+                ("/about/", ChangeKind::Modified),
+                ("/blog/", ChangeKind::Added),
+                ("/draft/", ChangeKind::Removed),
+            ]
+        );
+
+        let page = |path: &str, side_by_side| -> Result<String, Error> {
+            let (_, contents) = route(&old, &new, path, side_by_side)?.unwrap_or_default();
+            Ok(String::from_utf8_lossy(&contents).into_owned())
+        };
+        assert!(page("/", false)?.contains("<a href=\"/diff/about/\">/about/</a>"));
+        let diff = page("/diff/about/", true)?;
+        assert!(diff.contains("<td class=\"removed\">&lt;p&gt;Old&lt;/p&gt;</td><td></td>"));
+        assert_eq!(page("/new/about/", false)?, "<p>New</p>");
+        assert_eq!(route(&old, &new, "/old/blog/", false)?, None);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,122 @@
+use std::{env, fmt, str::FromStr};
+
+use crate::Parsed;
+
+/// The environment a site is built for. Development builds preview drafts
+/// and skip minification and fingerprinting, production builds don't.
+/// Defaults to production, like the `build` command of the `cli`, so
+/// drafts are only published on purpose.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BuildProfile {
+    /// A local build, e.g. while watching or serving.
+    Dev,
+    /// A build to be deployed.
+    #[default]
+    Prod,
+}
+
+impl BuildProfile {
+    /// The base URL used for development builds.
+    pub const DEV_BASE_URL: &'static str = "http://localhost:8080";
+
+    /// Read the profile from the `PICHU_PROFILE` environment variable,
+    /// defaulting to [`BuildProfile::Prod`] if it's unset or invalid.
+    #[must_use]
+    pub fn from_env() -> Self {
+        env::var("PICHU_PROFILE")
+            .ok()
+            .and_then(|profile| profile.parse().ok())
+            .unwrap_or_default()
+    }
+
+    /// Returns true for [`BuildProfile::Prod`].
+    #[must_use]
+    pub fn is_prod(self) -> bool {
+        self == Self::Prod
+    }
+
+    /// Whether drafts should be previewed.
+    #[must_use]
+    pub fn include_drafts(self) -> bool {
+        !self.is_prod()
+    }
+
+    /// Whether assets should be minified.
+    #[must_use]
+    pub fn minify(self) -> bool {
+        self.is_prod()
+    }
+
+    /// Whether asset file names should contain a content hash.
+    #[must_use]
+    pub fn fingerprint(self) -> bool {
+        self.is_prod()
+    }
+
+    /// The base URL to build with: the given production URL, or
+    /// [`BuildProfile::DEV_BASE_URL`] for development builds.
+    #[must_use]
+    pub fn base_url(self, prod_url: &str) -> &str {
+        match self {
+            Self::Dev => Self::DEV_BASE_URL,
+            Self::Prod => prod_url,
+        }
+    }
+}
+
+impl FromStr for BuildProfile {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dev" | "development" => Ok(Self::Dev),
+            "prod" | "production" => Ok(Self::Prod),
+            _ => Err(format!("unknown build profile: {s}")),
+        }
+    }
+}
+
+impl fmt::Display for BuildProfile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Dev => "dev",
+            Self::Prod => "prod",
+        })
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Remove drafts from the items, so they are never written to the
+    /// output directory. To preview them in development builds, render them
+    /// into an overlay with `Parsed::render_drafts` (`serve` feature) when
+    /// [`BuildProfile::include_drafts`] is set instead.
+    #[must_use]
+    pub fn drafts(mut self, is_draft: impl Fn(&T) -> bool) -> Self {
+        self.items.retain(|item| !is_draft(item));
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile() {
+        assert_eq!(BuildProfile::default(), BuildProfile::Prod);
+        assert_eq!("production".parse(), Ok(BuildProfile::Prod));
+        assert!("staging".parse::<BuildProfile>().is_err());
+        assert_eq!(
+            BuildProfile::Dev.base_url("https://example.com"),
+            "http://localhost:8080"
+        );
+        assert_eq!(
+            BuildProfile::Prod.base_url("https://example.com"),
+            "https://example.com"
+        );
+
+        let posts = Parsed::from(vec![("hello", false), ("wip", true)]);
+        let posts = posts.drafts(|post| post.1);
+        assert_eq!(posts.items, [("hello", false)]);
+    }
+}
@@ -0,0 +1,166 @@
+use std::{
+    io::{self, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+use tiny_http::{Header, Request, Response};
+
+use crate::ServeError;
+
+/// Forwards requests below a path prefix to a backend, see
+/// [`Server::proxy`](crate::Server::proxy).
+#[derive(Debug, Clone)]
+pub(crate) struct Proxy {
+    prefix: String,
+    /// The backend's `host:port`.
+    authority: String,
+    /// The backend's base path, without trailing slash.
+    base_path: String,
+}
+
+impl Proxy {
+    /// Parse an `http://host:port/path` target.
+    pub(crate) fn new(prefix: &str, target: &str) -> Result<Self, ServeError> {
+        let rest = target.strip_prefix("http://").ok_or_else(|| {
+            ServeError::InvalidProxy(format!("{target}: only http:// is supported"))
+        })?;
+        let (authority, base_path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        if authority.is_empty() {
+            return Err(ServeError::InvalidProxy(format!("{target}: missing host")));
+        }
+        let authority = if authority.contains(':') {
+            authority.to_string()
+        } else {
+            format!("{authority}:80")
+        };
+        Ok(Self {
+            prefix: format!("/{}", prefix.trim_matches('/')),
+            authority,
+            base_path: base_path.trim_end_matches('/').to_string(),
+        })
+    }
+
+    pub(crate) fn prefix(&self) -> &str {
+        &self.prefix
+    }
+
+    /// Returns true if the request path is the prefix or below it.
+    pub(crate) fn matches(&self, path: &str) -> bool {
+        path.strip_prefix(&self.prefix)
+            .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || self.prefix == "/")
+    }
+
+    /// Forward the request, responding with 502 if the backend fails.
+    pub(crate) fn forward(&self, mut request: Request) -> Result<(), io::Error> {
+        match self.send(&mut request) {
+            Ok(response) => request.respond(response),
+            Err(e) => request
+                .respond(Response::from_string(format!("Bad Gateway: {e}")).with_status_code(502)),
+        }
+    }
+
+    fn send(&self, request: &mut Request) -> Result<Response<io::Cursor<Vec<u8>>>, io::Error> {
+        let mut body = Vec::new();
+        request.as_reader().read_to_end(&mut body)?;
+
+        // HTTP/1.0 keeps the backend from using chunked encoding, and the
+        // response ends when it closes the connection.
+        let mut head = Vec::new();
+        write!(
+            head,
+            "{} {}{} HTTP/1.0\r\nHost: {}\r\n",
+            request.method(),
+            self.base_path,
+            request.url(),
+            self.authority
+        )?;
+        for header in request.headers() {
+            if !["Host", "Connection", "Content-Length", "Transfer-Encoding"]
+                .iter()
+                .any(|field| header.field.equiv(field))
+            {
+                write!(head, "{}: {}\r\n", header.field, header.value)?;
+            }
+        }
+        write!(
+            head,
+            "Content-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+
+        let mut stream = TcpStream::connect(&self.authority)?;
+        stream.set_read_timeout(Some(Duration::from_secs(30)))?;
+        stream.write_all(&head)?;
+        stream.write_all(&body)?;
+        let mut raw = Vec::new();
+        stream.read_to_end(&mut raw)?;
+        parse_response(&raw)
+    }
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response<io::Cursor<Vec<u8>>>, io::Error> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "invalid response from backend");
+    let split = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(invalid)?;
+    let head = std::str::from_utf8(&raw[..split]).map_err(|_| invalid())?;
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split(' ').nth(1))
+        .and_then(|status| status.parse::<u16>().ok())
+        .ok_or_else(invalid)?;
+
+    let mut response = Response::from_data(raw[split + 4..].to_vec()).with_status_code(status);
+    for line in lines {
+        let Some((field, value)) = line.split_once(':') else {
+            continue;
+        };
+        if ["Connection", "Content-Length", "Transfer-Encoding"]
+            .iter()
+            .any(|skip| field.eq_ignore_ascii_case(skip))
+        {
+            continue;
+        }
+        if let Ok(header) = Header::from_bytes(field.trim(), value.trim()) {
+            response.add_header(header);
+        }
+    }
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_proxy() -> Result<(), ServeError> {
+        let proxy = Proxy::new("/api/", "http://localhost:3000/v1/")?;
+        assert_eq!(proxy.authority, "localhost:3000");
+        assert_eq!(proxy.base_path, "/v1");
+        assert!(proxy.matches("/api"));
+        assert!(proxy.matches("/api/users"));
+        assert!(!proxy.matches("/apidocs"));
+        assert_eq!(
+            Proxy::new("/api", "http://backend")?.authority,
+            "backend:80"
+        );
+        assert!(Proxy::new("/api", "https://localhost:3000").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_response() -> Result<(), io::Error> {
+        let response = parse_response(
+            b"HTTP/1.0 201 Created\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}",
+        )?;
+        assert_eq!(response.status_code().0, 201);
+        assert_eq!(response.headers().len(), 1);
+        assert!(parse_response(b"garbage").is_err());
+        Ok(())
+    }
+}
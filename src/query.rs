@@ -0,0 +1,371 @@
+use std::{
+    any::{Any, TypeId},
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap},
+    fmt,
+    sync::OnceLock,
+};
+
+use crate::{Page, Parsed};
+
+type FilterFn<'a, T> = Box<dyn Fn(&T) -> bool + 'a>;
+type SortFn<'a, T> = Box<dyn Fn(&T, &T) -> Ordering + 'a>;
+
+/// The collections of a site, registered by item type, to query from render
+/// functions. Indexes by tag and date are built on first use and shared, so
+/// queries like "the five newest posts tagged `rust`" don't scan every item
+/// on every page. Can be shared between threads.
+///
+/// ```
+/// use pichu::{Page, Parsed, Site, UrlPath};
+///
+/// struct Post {
+///     title: String,
+///     date: String,
+///     tags: Vec<String>,
+/// }
+///
+/// impl Page for Post {
+///     fn title(&self) -> &str { &self.title }
+///     fn url(&self) -> UrlPath { UrlPath::root() }
+///     fn date(&self) -> Option<&str> { Some(&self.date) }
+///     fn tags(&self) -> &[String] { &self.tags }
+/// }
+///
+/// let post = |title: &str, date: &str, tag: &str| Post {
+///     title: title.to_string(),
+///     date: date.to_string(),
+///     tags: vec![tag.to_string()],
+/// };
+/// let site = Site::new().collection(Parsed::from(vec![
+///     post("One", "2024-01-01", "rust"),
+///     post("Two", "2024-02-01", "go"),
+///     post("Three", "2024-03-01", "rust"),
+/// ]));
+///
+/// let latest = site
+///     .query::<Post>()
+///     .tagged("rust")
+///     .newest_first()
+///     .filter(|post| !post.title.is_empty())
+///     .limit(1)
+///     .collect();
+/// assert_eq!(latest[0].title, "Three");
+/// ```
+#[derive(Default)]
+pub struct Site {
+    collections: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+/// A registered collection with its lazily built indexes.
+struct Entry<T> {
+    items: Vec<T>,
+    /// Item indexes by tag, ascending.
+    tags: OnceLock<BTreeMap<String, Vec<usize>>>,
+    /// Item indexes, newest first.
+    by_date: OnceLock<Vec<usize>>,
+}
+
+impl<T: Page> Entry<T> {
+    fn tags(&self) -> &BTreeMap<String, Vec<usize>> {
+        self.tags.get_or_init(|| {
+            let mut tags = BTreeMap::<String, Vec<usize>>::new();
+            for (index, item) in self.items.iter().enumerate() {
+                for tag in item.tags() {
+                    let indexes = tags.entry(tag.clone()).or_default();
+                    // Tags listed twice on an item are indexed once.
+                    if indexes.last() != Some(&index) {
+                        indexes.push(index);
+                    }
+                }
+            }
+            tags
+        })
+    }
+
+    fn by_date(&self) -> &[usize] {
+        self.by_date.get_or_init(|| {
+            let mut indexes = (0..self.items.len()).collect::<Vec<_>>();
+            // Items without a date come last.
+            indexes.sort_by(|&a, &b| self.items[b].date().cmp(&self.items[a].date()));
+            indexes
+        })
+    }
+}
+
+impl Site {
+    /// Create a site without collections.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register the items as the collection of their type, replacing any
+    /// registered before.
+    #[must_use]
+    pub fn collection<T: Send + Sync + 'static>(mut self, items: Parsed<T>) -> Self {
+        let entry = Entry {
+            items: items.into_vec(),
+            tags: OnceLock::new(),
+            by_date: OnceLock::new(),
+        };
+        self.collections.insert(TypeId::of::<T>(), Box::new(entry));
+        self
+    }
+
+    /// All items of a type, in the order they were registered. Empty if no
+    /// collection of the type is registered.
+    #[must_use]
+    pub fn items<T: 'static>(&self) -> &[T] {
+        self.entry::<T>().map_or(&[], |entry| &entry.items)
+    }
+
+    /// Start a query over the items of a type.
+    #[must_use]
+    pub fn query<T: 'static>(&self) -> Query<'_, T> {
+        Query {
+            entry: self.entry(),
+            tag: None,
+            newest_first: false,
+            filters: Vec::new(),
+            sort: None,
+            limit: None,
+        }
+    }
+
+    /// The tags of the items of a type with their number of items, sorted
+    /// by tag.
+    #[must_use]
+    pub fn tags<T: Page + 'static>(&self) -> Vec<(&str, usize)> {
+        self.entry::<T>().map_or_else(Vec::new, |entry| {
+            entry
+                .tags()
+                .iter()
+                .map(|(tag, indexes)| (tag.as_str(), indexes.len()))
+                .collect()
+        })
+    }
+
+    fn entry<T: 'static>(&self) -> Option<&Entry<T>> {
+        self.collections
+            .get(&TypeId::of::<T>())
+            .and_then(|entry| entry.downcast_ref())
+    }
+}
+
+impl fmt::Debug for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Site")
+            .field("collections", &self.collections.len())
+            .finish()
+    }
+}
+
+/// A query over a collection of a [`Site`]. Nothing is computed until it's
+/// run with [`Query::collect`], [`Query::first`] or [`Query::count`].
+pub struct Query<'a, T> {
+    entry: Option<&'a Entry<T>>,
+    tag: Option<&'a str>,
+    newest_first: bool,
+    filters: Vec<FilterFn<'a, T>>,
+    sort: Option<SortFn<'a, T>>,
+    limit: Option<usize>,
+}
+
+impl<'a, T> Query<'a, T> {
+    /// Only keep items matching the predicate.
+    #[must_use]
+    pub fn filter(mut self, predicate: impl Fn(&T) -> bool + 'a) -> Self {
+        self.filters.push(Box::new(predicate));
+        self
+    }
+
+    /// Sort the results with the comparator, replacing any earlier order.
+    #[must_use]
+    pub fn sort_by(mut self, compare: impl Fn(&T, &T) -> Ordering + 'a) -> Self {
+        self.sort = Some(Box::new(compare));
+        self.newest_first = false;
+        self
+    }
+
+    /// Sort the results by the key, replacing any earlier order.
+    #[must_use]
+    pub fn sort_by_key<K: Ord>(self, key_fn: impl Fn(&T) -> K + 'a) -> Self {
+        self.sort_by(move |a, b| key_fn(a).cmp(&key_fn(b)))
+    }
+
+    /// Return at most `limit` results.
+    #[must_use]
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Run the query.
+    #[must_use]
+    pub fn collect(self) -> Vec<&'a T>
+    where
+        T: Page,
+    {
+        let Some(entry) = self.entry else {
+            return Vec::new();
+        };
+        let tagged = self
+            .tag
+            .map(|tag| entry.tags().get(tag).map_or(&[][..], Vec::as_slice));
+        let indexes: Box<dyn Iterator<Item = usize>> = match (tagged, self.newest_first) {
+            (Some(tagged), true) => Box::new(
+                entry
+                    .by_date()
+                    .iter()
+                    .copied()
+                    .filter(move |index| tagged.binary_search(index).is_ok()),
+            ),
+            (Some(tagged), false) => Box::new(tagged.iter().copied()),
+            (None, true) => Box::new(entry.by_date().iter().copied()),
+            (None, false) => Box::new(0..entry.items.len()),
+        };
+        let filters = self.filters;
+        let items = indexes
+            .map(|index| &entry.items[index])
+            .filter(|item| filters.iter().all(|filter| filter(item)));
+
+        match (self.sort, self.limit) {
+            // Without a custom order, stop as soon as there are enough items.
+            (None, Some(limit)) => items.take(limit).collect(),
+            (None, None) => items.collect(),
+            (Some(sort), limit) => {
+                let mut items = items.collect::<Vec<_>>();
+                items.sort_by(|a, b| sort(a, b));
+                items.truncate(limit.unwrap_or(usize::MAX));
+                items
+            }
+        }
+    }
+
+    /// Run the query, returning the first result.
+    #[must_use]
+    pub fn first(self) -> Option<&'a T>
+    where
+        T: Page,
+    {
+        self.limit(1).collect().into_iter().next()
+    }
+
+    /// Run the query, returning the number of results.
+    #[must_use]
+    pub fn count(self) -> usize
+    where
+        T: Page,
+    {
+        self.collect().len()
+    }
+}
+
+impl<'a, T: Page> Query<'a, T> {
+    /// Only keep items with the tag, using the tag index.
+    #[must_use]
+    pub fn tagged(mut self, tag: &'a str) -> Self {
+        self.tag = Some(tag);
+        self
+    }
+
+    /// Order the results by [`Page::date`], newest first, using the date
+    /// index. Items without a date come last.
+    #[must_use]
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self.sort = None;
+        self
+    }
+}
+
+impl<T> fmt::Debug for Query<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Query")
+            .field("tag", &self.tag)
+            .field("newest_first", &self.newest_first)
+            .field("filters", &self.filters.len())
+            .field("limit", &self.limit)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::UrlPath;
+
+    struct Post {
+        title: &'static str,
+        date: Option<&'static str>,
+        tags: Vec<String>,
+    }
+
+    impl Page for Post {
+        fn title(&self) -> &str {
+            self.title
+        }
+
+        fn url(&self) -> UrlPath {
+            UrlPath::root()
+        }
+
+        fn date(&self) -> Option<&str> {
+            self.date
+        }
+
+        fn tags(&self) -> &[String] {
+            &self.tags
+        }
+    }
+
+    #[test]
+    fn test_query() {
+        let post = |title, date, tags: &[&str]| Post {
+            title,
+            date,
+            tags: tags.iter().map(ToString::to_string).collect(),
+        };
+        let site = Site::new().collection(Parsed::from(vec![
+            post("a", Some("2024-01-01"), &["rust", "web"]),
+            post("b", None, &["rust", "rust"]),
+            post("c", Some("2024-03-01"), &["web"]),
+            post("d", Some("2024-02-01"), &["rust"]),
+        ]));
+        let titles = |posts: Vec<&Post>| posts.iter().map(|p| p.title).collect::<Vec<_>>();
+
+        assert_eq!(titles(site.query::<Post>().collect()), ["a", "b", "c", "d"]);
+        assert_eq!(
+            titles(site.query::<Post>().newest_first().collect()),
+            ["c", "d", "a", "b"]
+        );
+        assert_eq!(
+            titles(
+                site.query::<Post>()
+                    .tagged("rust")
+                    .newest_first()
+                    .limit(2)
+                    .collect()
+            ),
+            ["d", "a"]
+        );
+        assert_eq!(
+            titles(
+                site.query::<Post>()
+                    .tagged("rust")
+                    .filter(|post| post.title != "a")
+                    .sort_by_key(|post| std::cmp::Reverse(post.title))
+                    .collect()
+            ),
+            ["d", "b"]
+        );
+        assert_eq!(site.query::<Post>().tagged("go").count(), 0);
+        assert_eq!(
+            site.query::<Post>().newest_first().first().map(|p| p.title),
+            Some("c")
+        );
+        assert_eq!(site.tags::<Post>(), [("rust", 3), ("web", 2)]);
+        assert!(site.items::<String>().is_empty());
+    }
+}
@@ -0,0 +1,231 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::{html::escape, output, sync::lock, Error, HasSource, OutputRegistry, Parsed, UrlPath};
+
+/// Items that can be reached through additional URLs, usually through an
+/// `aliases` frontmatter key.
+pub trait HasAliases {
+    /// Old or alternative URL paths that should redirect to the item.
+    fn aliases(&self) -> &[String];
+}
+
+/// A redirect from an old URL path.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Redirect {
+    /// The URL path to redirect to.
+    pub to: UrlPath,
+    /// The source file that declared the redirect, if any.
+    pub source: Option<PathBuf>,
+}
+
+/// Redirects from old URL paths to their canonical ones, written as HTML
+/// stubs or host rules.
+/// Can be shared between threads and collections.
+#[derive(Debug, Default)]
+pub struct Redirects {
+    redirects: Mutex<BTreeMap<UrlPath, Redirect>>,
+}
+
+impl Redirects {
+    /// Create an empty set of redirects.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a redirect. Adding the same redirect again is fine.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutputConflict`] if the path already redirects
+    /// somewhere else.
+    pub fn add(&self, from: UrlPath, redirect: Redirect) -> Result<(), Error> {
+        let mut redirects = lock(&self.redirects);
+        match redirects.get(&from) {
+            Some(existing) if existing.to != redirect.to => Err(Error::OutputConflict {
+                path: PathBuf::from(from.as_str()),
+                first: existing.source.clone().unwrap_or_default(),
+                second: redirect.source.unwrap_or_default(),
+            }),
+            Some(_) => Ok(()),
+            None => {
+                redirects.insert(from, redirect);
+                Ok(())
+            }
+        }
+    }
+
+    /// All redirects, sorted by the path redirected from.
+    #[must_use]
+    pub fn entries(&self) -> Vec<(UrlPath, Redirect)> {
+        lock(&self.redirects)
+            .iter()
+            .map(|(from, redirect)| (from.clone(), redirect.clone()))
+            .collect()
+    }
+
+    /// Write an HTML stub for every redirect into the output directory,
+    /// claiming its path in the registry so aliases can't overwrite real
+    /// pages. Claim the pages' outputs first.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::OutputConflict`] if an alias collides with a page,
+    /// or an error if a stub cannot be written.
+    pub fn write_stubs(
+        &self,
+        out_dir: impl AsRef<Path>,
+        registry: &OutputRegistry,
+    ) -> Result<(), Error> {
+        let out_dir = out_dir.as_ref();
+        let files = self
+            .entries()
+            .into_iter()
+            .map(|(from, redirect)| {
+                let path = from.to_output(out_dir);
+                let source = redirect
+                    .source
+                    .unwrap_or_else(|| PathBuf::from(from.as_str()));
+                registry.claim(&path, source)?;
+                Ok((path, stub(&redirect.to).into_bytes()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        output::write_batch(files)?;
+        Ok(())
+    }
+
+    /// The redirects as Netlify/Cloudflare Pages `_redirects` rules.
+    #[must_use]
+    pub fn to_redirects_file(&self) -> String {
+        let mut rules = String::new();
+        for (from, redirect) in lock(&self.redirects).iter() {
+            let _ = writeln!(rules, "{from} {} 301", redirect.to);
+        }
+        rules
+    }
+}
+
+/// An HTML page redirecting to the given path, for hosts without redirect
+/// rules.
+fn stub(to: &UrlPath) -> String {
+    let to = escape(to.as_str());
+    format!(
+        "<!doctype html>\n<html><head><meta charset=\"utf-8\"><title>Redirecting…</title>\
+         <link rel=\"canonical\" href=\"{to}\"><meta http-equiv=\"refresh\" content=\"0; url={to}\">\
+         </head><body><a href=\"{to}\">Redirecting to {to}</a></body></html>\n"
+    )
+}
+
+impl<T: HasAliases + HasSource + Send + Sync> Parsed<T> {
+    /// Collect the aliases of all items as redirects to their URL paths.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if an alias or URL path is invalid, or if an alias
+    /// already redirects somewhere else.
+    pub fn collect_aliases(
+        self,
+        redirects: &Redirects,
+        url_fn: impl Fn(&T) -> Result<UrlPath, Error>,
+    ) -> Result<Self, Error> {
+        for item in &self.items {
+            if item.aliases().is_empty() {
+                continue;
+            }
+            let to = url_fn(item)?;
+            for alias in item.aliases() {
+                let from = UrlPath::new(alias)?;
+                if from == to {
+                    continue;
+                }
+                redirects.add(
+                    from,
+                    Redirect {
+                        to: to.clone(),
+                        source: Some(item.source().to_owned()),
+                    },
+                )?;
+            }
+        }
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    struct Post {
+        path: PathBuf,
+        slug: &'static str,
+        aliases: Vec<String>,
+    }
+
+    impl HasSource for Post {
+        fn source(&self) -> &Path {
+            &self.path
+        }
+    }
+
+    impl HasAliases for Post {
+        fn aliases(&self) -> &[String] {
+            &self.aliases
+        }
+    }
+
+    #[test]
+    fn test_collect_aliases() -> Result<(), Error> {
+        let out_dir = env::temp_dir().join("pichu_test_redirects");
+        let registry = OutputRegistry::new();
+        let redirects = Redirects::new();
+        let url = |post: &Post| UrlPath::new(format!("/blog/{}/", post.slug));
+        let posts = Parsed::from(vec![
+            Post {
+                path: PathBuf::from("hello.md"),
+                slug: "hello",
+                aliases: vec!["/2024/05/hello/".to_string(), "/blog/hello/".to_string()],
+            },
+            Post {
+                path: PathBuf::from("world.md"),
+                slug: "world",
+                aliases: vec!["/blog/hello/".to_string()],
+            },
+        ]);
+        let posts = posts.claim_outputs(&registry, |post| {
+            url(post)
+                .map(|url| url.to_output(&out_dir))
+                .unwrap_or_default()
+        })?;
+        posts.collect_aliases(&redirects, url)?;
+
+        assert_eq!(
+            redirects.to_redirects_file(),
+            "/2024/05/hello/ /blog/hello/ 301\n/blog/hello/ /blog/world/ 301\n"
+        );
+        assert!(matches!(
+            redirects.write_stubs(&out_dir, &registry),
+            Err(Error::OutputConflict { .. })
+        ));
+
+        let redirects = Redirects::new();
+        redirects.add(
+            UrlPath::new("/old/")?,
+            Redirect {
+                to: UrlPath::new("/blog/hello/")?,
+                source: None,
+            },
+        )?;
+        redirects.write_stubs(&out_dir, &registry)?;
+        let stub = fs::read_to_string(out_dir.join("old/index.html"))?;
+        assert!(stub.contains(r#"<meta http-equiv="refresh" content="0; url=/blog/hello/">"#));
+
+        fs::remove_dir_all(out_dir)?;
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use std::{io, path::Path};
 
-use crate::write;
+use crate::{manifest::content_hash, write, BuildProfile};
 
 /// Error type for SASS/SCSS compilation operations.
 #[derive(thiserror::Error, Debug)]
@@ -13,6 +13,23 @@ pub enum SassError {
     SassCompile(#[from] Box<grass::Error>),
 }
 
+/// Options for [`render_sass_with`].
+#[derive(Debug, Clone, Default)]
+pub struct SassOptions {
+    /// Minify the output.
+    pub minify: bool,
+}
+
+impl SassOptions {
+    /// Options for the profile, minifying for production.
+    #[must_use]
+    pub fn for_profile(profile: BuildProfile) -> Self {
+        Self {
+            minify: profile.minify(),
+        }
+    }
+}
+
 /// Render a SASS/SCSS file to the destination.
 /// Other SASS/SCSS files next to the provided one will be available for
 /// inclusion.
@@ -21,17 +38,33 @@ pub enum SassError {
 ///
 /// Returns an error if the SASS file cannot be compiled or if the output cannot be written.
 pub fn render_sass(source: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<String, SassError> {
+    render_sass_with(source, path, &SassOptions::default())
+}
+
+/// Render a SASS/SCSS file to the destination like [`render_sass`],
+/// minifying it if configured.
+///
+/// # Errors
+///
+/// Returns an error if the SASS file cannot be compiled or if the output cannot be written.
+pub fn render_sass_with(
+    source: impl AsRef<Path>,
+    path: impl AsRef<Path>,
+    options: &SassOptions,
+) -> Result<String, SassError> {
     let source = source.as_ref();
-    let options = match source.parent() {
-        Some(parent) => grass::Options::default().load_path(parent),
-        None => grass::Options::default(),
+    let style = if options.minify {
+        grass::OutputStyle::Compressed
+    } else {
+        grass::OutputStyle::Expanded
+    };
+    let grass_options = grass::Options::default().style(style);
+    let grass_options = match source.parent() {
+        Some(parent) => grass_options.load_path(parent),
+        None => grass_options,
     };
-    let css = grass::from_path(source, &options)?;
-    let hash: String = blake3::hash(css.as_bytes())
-        .to_string()
-        .chars()
-        .take(16)
-        .collect();
+    let css = grass::from_path(source, &grass_options)?;
+    let hash = content_hash(css.as_bytes());
     write(path, css)?;
     Ok(hash)
 }
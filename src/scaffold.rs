@@ -0,0 +1,223 @@
+use std::{
+    collections::BTreeMap, fs, io, io::Write as _, path::PathBuf, sync::RwLock, time::UNIX_EPOCH,
+};
+
+use crate::{clock::civil_from_days, sync, Clock, SystemClock};
+
+/// The archetypes registered with [`Archetype::register`], by kind.
+static ARCHETYPES: RwLock<BTreeMap<String, Archetype>> = RwLock::new(BTreeMap::new());
+
+const DEFAULT_TEMPLATE: &str =
+    "---\ntitle: \"{title}\"\ndate: {date}\nslug: {slug}\ndraft: true\n---\n\n";
+
+/// Error type for [`new_content`].
+#[derive(thiserror::Error, Debug)]
+pub enum ScaffoldError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// No archetype is registered for the kind.
+    #[error("unknown content kind: {0}")]
+    UnknownKind(String),
+    /// The name is empty after slugifying it.
+    #[error("invalid name: {0:?}")]
+    InvalidName(String),
+    /// The file already exists and was left untouched.
+    #[error("{0} already exists")]
+    Exists(PathBuf),
+}
+
+/// A template for new content of a kind, like a blog post, see
+/// [`new_content`].
+///
+/// The path can contain the placeholders `{slug}` and `{date}`, the
+/// current date as `YYYY-MM-DD`, and the template also `{title}`, escaped
+/// for double-quoted YAML and TOML strings.
+///
+/// ```no_run
+/// use pichu::Archetype;
+///
+/// Archetype::new("content/blog/{date}-{slug}.md").register("blog");
+/// Archetype::new("content/notes/{slug}.md")
+///     .template("+++\ntitle = \"{title}\"\ndate = {date}\n+++\n")
+///     .register("note");
+///
+/// let path = pichu::new_content("blog", "my-post-title")?;
+/// println!("Created {}", path.display());
+/// # Ok::<(), pichu::ScaffoldError>(())
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Archetype {
+    path: String,
+    template: String,
+}
+
+impl Archetype {
+    /// Create new content at the path, with YAML frontmatter containing
+    /// the title, date and slug and `draft: true`.
+    #[must_use]
+    pub fn new(path: impl Into<String>) -> Self {
+        Self {
+            path: path.into(),
+            template: DEFAULT_TEMPLATE.to_string(),
+        }
+    }
+
+    /// Use the template for the file contents instead.
+    #[must_use]
+    pub fn template(mut self, template: impl Into<String>) -> Self {
+        self.template = template.into();
+        self
+    }
+
+    /// Register the archetype for the kind, replacing any registered
+    /// before.
+    pub fn register(self, kind: impl Into<String>) {
+        sync::write(&ARCHETYPES).insert(kind.into(), self);
+    }
+}
+
+/// Create a file for new content of the kind from its registered
+/// [`Archetype`], returning its path. The name can be a title like
+/// `My Post Title` or a slug like `my-post-title`; slugs are turned into a
+/// title by replacing dashes with spaces.
+///
+/// # Errors
+///
+/// Returns an error if the kind isn't registered, the file already exists
+/// or it cannot be written.
+pub fn new_content(kind: &str, name: &str) -> Result<PathBuf, ScaffoldError> {
+    new_content_with(kind, name, &SystemClock)
+}
+
+/// Like [`new_content`], with the date from the clock.
+///
+/// # Errors
+///
+/// Returns an error if the kind isn't registered, the file already exists
+/// or it cannot be written.
+pub fn new_content_with(
+    kind: &str,
+    name: &str,
+    clock: &impl Clock,
+) -> Result<PathBuf, ScaffoldError> {
+    let archetype = sync::read(&ARCHETYPES)
+        .get(kind)
+        .cloned()
+        .ok_or_else(|| ScaffoldError::UnknownKind(kind.to_string()))?;
+
+    let slug = slug(name);
+    if slug.is_empty() {
+        return Err(ScaffoldError::InvalidName(name.to_string()));
+    }
+    let title = if name.contains(char::is_whitespace) {
+        name.trim().to_string()
+    } else {
+        let words = name.replace(['-', '_'], " ");
+        let mut chars = words.chars();
+        chars
+            .next()
+            .map(|first| first.to_uppercase().chain(chars).collect())
+            .unwrap_or_default()
+    };
+    let secs = clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let (year, month, day) = civil_from_days(i64::try_from(secs / 86_400).unwrap_or_default());
+    let date = format!("{year:04}-{month:02}-{day:02}");
+    let placeholders = |text: &str, title: &str| {
+        text.replace("{title}", title)
+            .replace("{slug}", &slug)
+            .replace("{date}", &date)
+    };
+
+    let path = PathBuf::from(placeholders(&archetype.path, &slug));
+    let contents = placeholders(
+        &archetype.template,
+        &title.replace('\\', "\\\\").replace('"', "\\\""),
+    );
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut file = match fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+    {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {
+            return Err(ScaffoldError::Exists(path))
+        }
+        Err(e) => return Err(e.into()),
+    };
+    file.write_all(contents.as_bytes())?;
+    Ok(path)
+}
+
+#[cfg(feature = "slug")]
+fn slug(name: &str) -> String {
+    crate::slugify(name)
+}
+
+/// Lowercase ASCII letters and digits, separated by single dashes.
+#[cfg(not(feature = "slug"))]
+fn slug(name: &str) -> String {
+    name.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FixedClock;
+    use std::env;
+
+    #[test]
+    fn test_new_content() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_new_content");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        let pattern = dir.to_string_lossy();
+        Archetype::new(format!("{pattern}/blog/{{date}}-{{slug}}.md")).register("test-blog");
+        Archetype::new(format!("{pattern}/notes/{{slug}}.toml"))
+            .template("title = \"{title}\"\n")
+            .register("test-note");
+        // 2024-09-30
+        let clock = FixedClock::from_unix(1_727_654_400);
+
+        let path = new_content_with("test-blog", "my-post-title", &clock)?;
+        assert_eq!(path, dir.join("blog/2024-09-30-my-post-title.md"));
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "---\ntitle: \"My post title\"\ndate: 2024-09-30\nslug: my-post-title\ndraft: true\n---\n\n"
+        );
+        assert!(matches!(
+            new_content_with("test-blog", "my-post-title", &clock),
+            Err(ScaffoldError::Exists(_))
+        ));
+
+        let path = new_content_with("test-note", "Say \"Hi\" Again", &clock)?;
+        assert_eq!(path, dir.join("notes/say-hi-again.toml"));
+        assert_eq!(
+            fs::read_to_string(&path)?,
+            "title = \"Say \\\"Hi\\\" Again\"\n"
+        );
+
+        assert!(matches!(
+            new_content("test-page", "about"),
+            Err(ScaffoldError::UnknownKind(_))
+        ));
+        assert!(matches!(
+            new_content("test-note", "--"),
+            Err(ScaffoldError::InvalidName(_))
+        ));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+use rayon::prelude::*;
+use std::{collections::BTreeMap, path::Path};
+
+use crate::{output, Error, Parsed};
+
+/// Items that can be part of a series, usually through a `series`
+/// frontmatter key.
+pub trait InSeries {
+    /// The name of the series this item belongs to, if any.
+    fn series(&self) -> Option<&str>;
+}
+
+/// The position of an item within its series.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SeriesPosition<'a> {
+    /// The name of the series.
+    pub series: &'a str,
+    /// The position in the series, starting at 1.
+    pub number: usize,
+    /// The number of items in the series.
+    pub total: usize,
+    /// The key of the previous item in the series.
+    pub previous: Option<&'a str>,
+    /// The key of the next item in the series.
+    pub next: Option<&'a str>,
+}
+
+/// The series of a collection, with each series' items identified by a key
+/// like the basename. Created by [`Parsed::series_index`].
+/// Since it owns its data, it can be used from within
+/// [`Parsed::render_each`] of the same collection.
+#[derive(Debug, Clone, Default)]
+pub struct SeriesIndex {
+    series: BTreeMap<String, Vec<String>>,
+}
+
+impl SeriesIndex {
+    /// The position of the item with the given key in its series, or `None`
+    /// if it's not part of a series.
+    #[must_use]
+    pub fn position(&self, key: &str) -> Option<SeriesPosition<'_>> {
+        self.series.iter().find_map(|(name, keys)| {
+            let index = keys.iter().position(|k| k == key)?;
+            Some(SeriesPosition {
+                series: name,
+                number: index + 1,
+                total: keys.len(),
+                previous: index
+                    .checked_sub(1)
+                    .and_then(|i| keys.get(i))
+                    .map(String::as_str),
+                next: keys.get(index + 1).map(String::as_str),
+            })
+        })
+    }
+
+    /// The names of all series, sorted.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.series.keys().map(String::as_str)
+    }
+
+    /// The keys of the items in the given series, in order.
+    #[must_use]
+    pub fn keys(&self, series: &str) -> &[String] {
+        self.series.get(series).map_or(&[], Vec::as_slice)
+    }
+}
+
+impl<T: InSeries + Send + Sync> Parsed<T> {
+    /// Group the items declaring a series, keeping their current order.
+    /// Sort the collection first, e.g. by date or part number.
+    pub fn series_index(&self, key_fn: impl Fn(&T) -> String) -> SeriesIndex {
+        let mut series: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for item in &self.items {
+            if let Some(name) = item.series() {
+                series
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(key_fn(item));
+            }
+        }
+        SeriesIndex { series }
+    }
+
+    /// Render an index page per series in parallel. The render function
+    /// receives the series name and its items in their current order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any file cannot be written to the filesystem.
+    pub fn render_series<P: AsRef<Path>, S: Into<String> + Send>(
+        self,
+        render_fn: impl Fn(&str, &[&T]) -> S + Send + Sync,
+        build_path_fn: impl Fn(&str) -> P + Send + Sync,
+    ) -> Result<Self, Error> {
+        let mut series: BTreeMap<&str, Vec<&T>> = BTreeMap::new();
+        for item in &self.items {
+            if let Some(name) = item.series() {
+                series.entry(name).or_default().push(item);
+            }
+        }
+
+        let files = series
+            .into_par_iter()
+            .map(|(name, items)| {
+                let content = render_fn(name, &items).into().into_bytes();
+                (build_path_fn(name).as_ref().to_path_buf(), content)
+            })
+            .collect::<Vec<_>>();
+        output::write_batch(files)?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::{env, fs};
+
+    struct Post {
+        slug: &'static str,
+        series: Option<&'static str>,
+    }
+
+    impl InSeries for Post {
+        fn series(&self) -> Option<&str> {
+            self.series
+        }
+    }
+
+    fn posts() -> Parsed<Post> {
+        let post = |slug, series| Post { slug, series };
+        Parsed::from(vec![
+            post("raytracer-1", Some("raytracer")),
+            post("hello", None),
+            post("raytracer-2", Some("raytracer")),
+            post("rust-1", Some("rust")),
+            post("raytracer-3", Some("raytracer")),
+        ])
+    }
+
+    #[test]
+    fn test_series_index() {
+        let index = posts().series_index(|post| post.slug.to_string());
+        assert_eq!(
+            index.position("raytracer-2"),
+            Some(SeriesPosition {
+                series: "raytracer",
+                number: 2,
+                total: 3,
+                previous: Some("raytracer-1"),
+                next: Some("raytracer-3"),
+            })
+        );
+        assert_eq!(
+            index.position("rust-1").map(|p| (p.previous, p.next)),
+            Some((None, None))
+        );
+        assert_eq!(index.position("hello"), None);
+        assert_eq!(index.names().collect::<Vec<_>>(), vec!["raytracer", "rust"]);
+    }
+
+    #[test]
+    fn test_render_series() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_render_series");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        posts().render_series(
+            |name, posts| {
+                let slugs = posts.iter().map(|post| post.slug).collect::<Vec<_>>();
+                format!("{name}: {}", slugs.join(", "))
+            },
+            |name| dir.join(format!("{name}.html")),
+        )?;
+        assert_eq!(
+            fs::read_to_string(dir.join("raytracer.html"))?,
+            "raytracer: raytracer-1, raytracer-2, raytracer-3"
+        );
+        assert_eq!(fs::read_to_string(dir.join("rust.html"))?, "rust: rust-1");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,719 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, Read},
+    mem,
+    path::{Component, Path, PathBuf},
+    sync::{Arc, RwLock},
+    thread,
+};
+use tiny_http::{Header, Request, Response};
+
+use crate::{
+    livereload::{self, LiveReload},
+    proxy::Proxy,
+    shutdown::POLL_INTERVAL,
+    sync,
+    url_path::percent_decode,
+    ContentTypes, Error, Parsed, Shutdown, UrlPath,
+};
+
+#[cfg(feature = "https")]
+use crate::Tls;
+
+/// Errors that can occur while serving.
+#[derive(thiserror::Error, Debug)]
+pub enum ServeError {
+    /// The server could not be started.
+    #[error("failed to start server: {0}")]
+    Start(Box<dyn std::error::Error + Send + Sync>),
+    /// A response could not be sent.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// A proxy target isn't a valid `http://` URL.
+    #[error("invalid proxy target: {0}")]
+    InvalidProxy(String),
+    /// The TLS certificate could not be generated.
+    #[cfg(feature = "https")]
+    #[error("certificate error: {0}")]
+    Certificate(String),
+}
+
+/// Pages kept in memory and served by [`Server`] in front of the output
+/// directory, e.g. drafts that must never be written to disk.
+/// Cloning an overlay shares its pages.
+#[derive(Clone, Default)]
+pub struct Overlay {
+    pages: Arc<RwLock<HashMap<UrlPath, Vec<u8>>>>,
+}
+
+impl Overlay {
+    /// Create an empty overlay.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace a page.
+    pub fn insert(&self, url: UrlPath, contents: impl Into<Vec<u8>>) {
+        sync::write(&self.pages).insert(url, contents.into());
+    }
+
+    /// Remove a page.
+    pub fn remove(&self, url: &UrlPath) {
+        sync::write(&self.pages).remove(url);
+    }
+
+    /// Remove all pages.
+    pub fn clear(&self) {
+        sync::write(&self.pages).clear();
+    }
+
+    /// Replace all pages with the pages of another overlay, leaving it
+    /// empty. Render a rebuild into a new overlay and swap it in with this,
+    /// so requests in the meantime still get the previous pages instead of
+    /// a 404.
+    pub fn replace_with(&self, other: &Overlay) {
+        if Arc::ptr_eq(&self.pages, &other.pages) {
+            return;
+        }
+        let pages = mem::take(&mut *sync::write(&other.pages));
+        *sync::write(&self.pages) = pages;
+    }
+
+    /// The contents of a page, if present.
+    #[must_use]
+    pub fn get(&self, url: &UrlPath) -> Option<Vec<u8>> {
+        sync::read(&self.pages).get(url).cloned()
+    }
+
+    /// Look up the page for a request path, trying the directory form of
+    /// paths without a trailing slash.
+    fn resolve(&self, path: &str) -> Option<(UrlPath, Vec<u8>)> {
+        let url = UrlPath::new(path).ok()?;
+        if let Some(contents) = self.get(&url) {
+            return Some((url, contents));
+        }
+        let dir = UrlPath::new(format!("{path}/")).ok()?;
+        self.get(&dir).map(|contents| (dir, contents))
+    }
+}
+
+impl PartialEq for Overlay {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.pages, &other.pages)
+    }
+}
+
+impl Eq for Overlay {}
+
+impl fmt::Debug for Overlay {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let pages = sync::read(&self.pages);
+        f.debug_set().entries(pages.keys()).finish()
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Render drafts into the overlay instead of the output directory and
+    /// remove them from the items, so they can be previewed with [`Server`]
+    /// but never end up in a build.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the URL path of a draft is invalid.
+    pub fn render_drafts<S: Into<String>>(
+        mut self,
+        overlay: &Overlay,
+        is_draft: impl Fn(&T) -> bool,
+        render_fn: impl Fn(&T) -> S,
+        url_fn: impl Fn(&T) -> Result<UrlPath, Error>,
+    ) -> Result<Self, Error> {
+        let (drafts, items) = self.items.into_iter().partition(|item| is_draft(item));
+        self.items = items;
+        for draft in &drafts {
+            overlay.insert(url_fn(draft)?, render_fn(draft).into());
+        }
+        Ok(self)
+    }
+}
+
+/// A development server for the output directory. Directories serve their
+/// `index.html` and missing files the top-level `404.html`, if present.
+#[derive(Debug, Clone)]
+pub struct Server {
+    dir: PathBuf,
+    overlay: Option<Overlay>,
+    live_reload: Option<LiveReload>,
+    port_attempts: u16,
+    open: bool,
+    not_found: PathBuf,
+    spa_fallback: bool,
+    proxies: Vec<(String, String)>,
+    #[cfg(feature = "https")]
+    tls: Option<Tls>,
+}
+
+/// A [`Server`] bound to an address, see [`Server::bind`].
+pub struct Listener {
+    server: tiny_http::Server,
+    config: Server,
+    proxies: Vec<Proxy>,
+}
+
+impl Server {
+    /// Create a server for the directory.
+    #[must_use]
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        Self {
+            dir: dir.as_ref().to_owned(),
+            overlay: None,
+            live_reload: None,
+            port_attempts: 1,
+            open: false,
+            not_found: PathBuf::from("404.html"),
+            spa_fallback: false,
+            proxies: Vec::new(),
+            #[cfg(feature = "https")]
+            tls: None,
+        }
+    }
+
+    /// Serve the overlay's pages in front of the directory.
+    #[must_use]
+    pub fn overlay(mut self, overlay: &Overlay) -> Self {
+        self.overlay = Some(overlay.clone());
+        self
+    }
+
+    /// Inject a script into HTML pages that reloads them, or swaps their
+    /// stylesheets, when notified through the handle.
+    #[must_use]
+    pub fn live_reload(mut self, live_reload: &LiveReload) -> Self {
+        self.live_reload = Some(live_reload.clone());
+        self
+    }
+
+    /// If the port is in use, try up to `attempts` ports counting up from
+    /// it. Defaults to 1, only trying the given port.
+    #[must_use]
+    pub fn port_attempts(mut self, attempts: u16) -> Self {
+        self.port_attempts = attempts.max(1);
+        self
+    }
+
+    /// Open the site in the default browser once the server is listening.
+    #[must_use]
+    pub fn open(mut self, open: bool) -> Self {
+        self.open = open;
+        self
+    }
+
+    /// The page served for missing files, relative to the directory.
+    /// Defaults to `404.html`.
+    #[must_use]
+    pub fn not_found(mut self, path: impl Into<PathBuf>) -> Self {
+        self.not_found = path.into();
+        self
+    }
+
+    /// Serve the top-level `index.html` for missing paths without a file
+    /// extension, for single-page apps with client-side routing.
+    #[must_use]
+    pub fn spa_fallback(mut self, spa_fallback: bool) -> Self {
+        self.spa_fallback = spa_fallback;
+        self
+    }
+
+    /// Forward requests below the path prefix to a backend, e.g.
+    /// `proxy("/api", "http://localhost:3000")`, so a site and its API can be
+    /// developed on the same origin. Only plain `http://` targets are
+    /// supported. The full request path is appended to the target.
+    #[must_use]
+    pub fn proxy(mut self, prefix: impl Into<String>, target: impl Into<String>) -> Self {
+        self.proxies.push((prefix.into(), target.into()));
+        self
+    }
+
+    /// Serve over HTTPS, e.g. for web APIs only available in secure
+    /// contexts.
+    #[cfg(feature = "https")]
+    #[must_use]
+    pub fn https(mut self, tls: Tls) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Bind to the given address, e.g. `127.0.0.1:8080`, counting up the
+    /// port if configured with [`Server::port_attempts`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a proxy target is invalid or the server cannot
+    /// bind to the address.
+    pub fn bind(&self, addr: &str) -> Result<Listener, ServeError> {
+        let mut proxies = self
+            .proxies
+            .iter()
+            .map(|(prefix, target)| Proxy::new(prefix, target))
+            .collect::<Result<Vec<_>, _>>()?;
+        // Longer prefixes are more specific.
+        proxies.sort_by_key(|proxy| std::cmp::Reverse(proxy.prefix().len()));
+
+        let (host, port) = match addr.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse::<u16>().ok()),
+            None => (addr, None),
+        };
+        let mut attempt = 0;
+        let server = loop {
+            let addr = match port {
+                Some(port) => format!("{host}:{}", port.saturating_add(attempt)),
+                None => addr.to_string(),
+            };
+            match self.listen(&addr) {
+                Ok(server) => break server,
+                Err(e)
+                    if attempt + 1 < self.port_attempts
+                        && port.is_some()
+                        && is_addr_in_use(&*e) =>
+                {
+                    attempt += 1;
+                }
+                Err(e) => return Err(ServeError::Start(e)),
+            }
+        };
+        Ok(Listener {
+            server,
+            config: self.clone(),
+            proxies,
+        })
+    }
+
+    #[cfg(not(feature = "https"))]
+    #[allow(clippy::unused_self)] // Used with the https feature.
+    fn listen(&self, addr: &str) -> Result<tiny_http::Server, BoxedError> {
+        tiny_http::Server::http(addr)
+    }
+
+    #[cfg(feature = "https")]
+    fn listen(&self, addr: &str) -> Result<tiny_http::Server, BoxedError> {
+        match &self.tls {
+            Some(tls) => tiny_http::Server::https(addr, tls.load()?),
+            None => tiny_http::Server::http(addr),
+        }
+    }
+
+    /// Serve on the given address, e.g. `127.0.0.1:8080`, until the process
+    /// exits.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the server cannot bind to the address.
+    pub fn run(&self, addr: &str) -> Result<(), ServeError> {
+        self.bind(addr)?.run()
+    }
+
+    fn respond(&self, request: Request) -> Result<(), io::Error> {
+        let path = request_path(request.url());
+        if let (Some(live_reload), Some(livereload::ENDPOINT)) =
+            (&self.live_reload, path.as_deref())
+        {
+            return live_reload.connect(request);
+        }
+
+        if let Some((url, contents)) = self
+            .overlay
+            .as_ref()
+            .zip(path.as_deref())
+            .and_then(|(overlay, path)| overlay.resolve(path))
+        {
+            let name = if url.is_dir() {
+                "index.html"
+            } else {
+                url.as_str()
+            };
+            return self.send(request, 200, Path::new(name), Body::Memory(contents));
+        }
+
+        if let Some(file_path) = path.as_deref().and_then(|path| resolve(&self.dir, path)) {
+            let file = File::open(&file_path)?;
+            return self.send(request, 200, &file_path, Body::File(file));
+        }
+
+        if self.spa_fallback
+            && path
+                .as_deref()
+                .is_some_and(|path| Path::new(path).extension().is_none())
+        {
+            let index = self.dir.join("index.html");
+            if let Ok(file) = File::open(&index) {
+                return self.send(request, 200, &index, Body::File(file));
+            }
+        }
+
+        let not_found = self.dir.join(&self.not_found);
+        match File::open(&not_found) {
+            Ok(file) => self.send(request, 404, &not_found, Body::File(file)),
+            Err(_) => request.respond(Response::from_string("Not Found").with_status_code(404)),
+        }
+    }
+
+    fn send(
+        &self,
+        request: Request,
+        status: u16,
+        name: &Path,
+        body: Body,
+    ) -> Result<(), io::Error> {
+        let inject =
+            self.live_reload.is_some() && name.extension().is_some_and(|ext| ext == "html");
+        match body {
+            Body::File(file) if !inject => {
+                let response = Response::from_file(file).with_status_code(status);
+                request.respond(with_content_type(response, name))
+            }
+            body => {
+                let mut contents = match body {
+                    Body::Memory(contents) => contents,
+                    Body::File(mut file) => {
+                        let mut contents = Vec::new();
+                        file.read_to_end(&mut contents)?;
+                        contents
+                    }
+                };
+                if inject {
+                    contents = livereload::inject(&String::from_utf8_lossy(&contents)).into_bytes();
+                }
+                let response = Response::from_data(contents).with_status_code(status);
+                request.respond(with_content_type(response, name))
+            }
+        }
+    }
+}
+
+impl Listener {
+    /// The URL the server is listening on.
+    #[must_use]
+    pub fn url(&self) -> String {
+        #[cfg(feature = "https")]
+        let scheme = if self.config.tls.is_some() {
+            "https"
+        } else {
+            "http"
+        };
+        #[cfg(not(feature = "https"))]
+        let scheme = "http";
+        match self.server.server_addr().to_ip() {
+            Some(addr) => format!("{scheme}://{addr}"),
+            None => String::new(),
+        }
+    }
+
+    /// Serve until the process exits, opening the browser if configured.
+    ///
+    /// # Errors
+    ///
+    /// Currently never fails, as failing requests don't stop the server.
+    pub fn run(self) -> Result<(), ServeError> {
+        self.run_until(&Shutdown::new())
+    }
+
+    /// Like [`Listener::run`], but returns once a shutdown is requested,
+    /// e.g. with [`Shutdown::ctrl_c`]. Requests being handled are answered
+    /// first, then the [`Shutdown::on_stop`] functions are called.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if receiving a request failed or an
+    /// [`Shutdown::on_stop`] function failed.
+    pub fn run_until(self, shutdown: &Shutdown) -> Result<(), ServeError> {
+        self.accept_until(shutdown)?;
+        if shutdown.is_requested() {
+            shutdown.stopped()?;
+        }
+        Ok(())
+    }
+
+    /// Answer requests until a shutdown is requested, without calling the
+    /// [`Shutdown::on_stop`] functions.
+    pub(crate) fn accept_until(&self, shutdown: &Shutdown) -> Result<(), ServeError> {
+        if self.config.open {
+            // Not being able to open a browser, e.g. over SSH, is fine.
+            let _ = open::that_detached(self.url());
+        }
+        // Proxied requests wait on the backend, so they get their own
+        // thread instead of holding up everything else. They are answered
+        // before returning.
+        thread::scope(|scope| {
+            while !shutdown.is_requested() {
+                let Some(request) = self.server.recv_timeout(POLL_INTERVAL)? else {
+                    continue;
+                };
+                let path = request_path(request.url()).unwrap_or_default();
+                // A client hanging up shouldn't take the server down.
+                match self.proxies.iter().find(|proxy| proxy.matches(&path)) {
+                    Some(proxy) => {
+                        scope.spawn(|| proxy.forward(request));
+                    }
+                    None => {
+                        let _ = self.config.respond(request);
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+}
+
+impl fmt::Debug for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Listener")
+            .field("url", &self.url())
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
+type BoxedError = Box<dyn std::error::Error + Send + Sync + 'static>;
+
+fn is_addr_in_use(error: &(dyn std::error::Error + Send + Sync + 'static)) -> bool {
+    error
+        .downcast_ref::<io::Error>()
+        .is_some_and(|e| e.kind() == io::ErrorKind::AddrInUse)
+}
+
+/// A response body, either in memory or streamed from a file.
+enum Body {
+    Memory(Vec<u8>),
+    File(File),
+}
+
+/// Serve the files in the given directory on the given address, e.g.
+/// `127.0.0.1:8080`, until the process exits. See [`Server`] for options.
+///
+/// # Errors
+///
+/// Returns an error if the server cannot bind to the address.
+pub fn serve(dir: impl AsRef<Path>, addr: &str) -> Result<(), ServeError> {
+    Server::new(dir).run(addr)
+}
+
+pub(crate) fn with_content_type<R: io::Read>(response: Response<R>, path: &Path) -> Response<R> {
+    let content_type = ContentTypes::new().get(path);
+    let header = if content_type.mime == "application/octet-stream" {
+        mime_guess::from_path(path)
+            .first_or_octet_stream()
+            .essence_str()
+            .to_string()
+    } else {
+        content_type.header()
+    };
+    match Header::from_bytes("Content-Type", header) {
+        Ok(header) => response.with_header(header),
+        Err(()) => response,
+    }
+}
+
+/// The decoded path of a request URL, without query and fragment.
+pub(crate) fn request_path(url: &str) -> Option<String> {
+    percent_decode(url.split(['?', '#']).next().unwrap_or_default())
+}
+
+/// Map a decoded request path to a file in `dir`, rejecting paths that
+/// escape it.
+pub(crate) fn resolve(dir: &Path, path: &str) -> Option<PathBuf> {
+    let relative = Path::new(path.trim_start_matches('/'));
+    if relative
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+
+    let mut path = dir.join(relative);
+    if path.is_dir() {
+        path.push("index.html");
+    }
+    path.is_file().then_some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, io::Write, sync::mpsc, time::Duration};
+
+    /// How long a test waits for a request to arrive.
+    const TIMEOUT: Duration = Duration::from_secs(20);
+
+    #[test]
+    fn test_resolve() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("pichu_test_serve_resolve");
+        fs::create_dir_all(dir.join("blog/hello world"))?;
+        fs::write(dir.join("index.html"), "home")?;
+        fs::write(dir.join("blog/hello world/index.html"), "post")?;
+        fs::write(dir.join("style.css"), "")?;
+
+        let resolve = |url: &str| request_path(url).and_then(|path| resolve(&dir, &path));
+        assert_eq!(resolve("/"), Some(dir.join("index.html")));
+        assert_eq!(resolve("/style.css?v=1"), Some(dir.join("style.css")));
+        assert_eq!(
+            resolve("/blog/hello%20world/"),
+            Some(dir.join("blog/hello world/index.html"))
+        );
+        assert_eq!(resolve("/missing.html"), None);
+        assert_eq!(resolve("/../pichu_test_serve_resolve/index.html"), None);
+        assert_eq!(resolve("/%2e%2e/index.html"), None);
+
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    /// The port a listener is bound to.
+    fn port(listener: &Listener) -> Result<u16, Box<dyn std::error::Error>> {
+        let url = listener.url();
+        let (_, port) = url.rsplit_once(':').ok_or("no port")?;
+        Ok(port.parse()?)
+    }
+
+    #[test]
+    fn test_listener() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_serve_listener");
+        crate::write(dir.join("index.html"), "app")?;
+        crate::write(dir.join("missing.html"), "missing")?;
+
+        // Let the OS pick free ports, so tests running in parallel can't
+        // collide.
+        let busy = Server::new(&dir).bind("127.0.0.1:0")?;
+        let busy_port = port(&busy)?;
+        assert_eq!(busy.url(), format!("http://127.0.0.1:{busy_port}"));
+        let busy_addr = format!("127.0.0.1:{busy_port}");
+        assert!(Server::new(&dir).bind(&busy_addr).is_err());
+
+        let backend = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let backend_addr = backend.local_addr()?;
+        let listener = Server::new(&dir)
+            .port_attempts(5)
+            .spa_fallback(true)
+            .not_found("missing.html")
+            .proxy("/api", format!("http://{backend_addr}"))
+            .bind(&busy_addr)?;
+        let listener_port = port(&listener)?;
+        assert!(listener_port > busy_port);
+
+        let shutdown = Shutdown::new();
+        let server = std::thread::spawn({
+            let shutdown = shutdown.clone();
+            move || listener.run_until(&shutdown)
+        });
+        // The backend holds the proxied request until the other requests
+        // were answered, which only works if it doesn't block the server.
+        let (received_tx, received) = mpsc::channel();
+        let (release, release_rx) = mpsc::channel::<()>();
+        let backend = std::thread::spawn(move || -> Result<(), io::Error> {
+            let (mut stream, _) = backend.accept()?;
+            let mut request = [0; 1024];
+            let len = stream.read(&mut request)?;
+            let path = String::from_utf8_lossy(&request[..len])
+                .lines()
+                .next()
+                .unwrap_or_default()
+                .to_string();
+            let _ = received_tx.send(());
+            release_rx
+                .recv_timeout(TIMEOUT)
+                .map_err(|_| io::Error::other("proxied request held up the server"))?;
+            write!(
+                stream,
+                "HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\n{path}"
+            )
+        });
+
+        let get = move |path: &str| -> Result<String, io::Error> {
+            let mut stream = std::net::TcpStream::connect(("127.0.0.1", listener_port))?;
+            write!(
+                stream,
+                "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+            )?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response)?;
+            Ok(response)
+        };
+        let proxied = std::thread::spawn(move || get("/api/ping?x=1"));
+        let responses = received
+            .recv_timeout(TIMEOUT)
+            .map_err(|_| io::Error::other("proxied request didn't reach the backend"))
+            .and_then(|()| {
+                ["/dashboard/settings", "/logo.png"]
+                    .map(get)
+                    .into_iter()
+                    .collect::<Result<Vec<_>, _>>()
+            });
+        if responses.is_ok() {
+            let _ = release.send(());
+        } else {
+            // Wake up the backend if the proxied request never reached it.
+            let _ = std::net::TcpStream::connect(backend_addr);
+        }
+        backend.join().map_err(|_| "backend panicked")??;
+        let proxied = proxied.join().map_err(|_| "client panicked")??;
+        shutdown.request();
+        server.join().map_err(|_| "server panicked")??;
+
+        let responses = responses?;
+        assert!(responses[0].starts_with("HTTP/1.1 200") && responses[0].ends_with("app"));
+        assert!(responses[1].starts_with("HTTP/1.1 404") && responses[1].ends_with("missing"));
+        assert!(proxied.ends_with("GET /api/ping?x=1 HTTP/1.0"), "{proxied}");
+
+        drop(busy);
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_until_on_stop() -> Result<(), Box<dyn std::error::Error>> {
+        let listener = Server::new(env::temp_dir()).bind("127.0.0.1:0")?;
+        let shutdown = Shutdown::new();
+        let (saved_tx, saved) = mpsc::channel();
+        shutdown.on_stop(move || {
+            let _ = saved_tx.send(());
+            Ok(())
+        });
+        let server = std::thread::spawn({
+            let shutdown = shutdown.clone();
+            move || listener.run_until(&shutdown)
+        });
+        assert!(saved.try_recv().is_err());
+        shutdown.request();
+        server.join().map_err(|_| "server panicked")??;
+        assert!(saved.try_recv().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_drafts() -> Result<(), Error> {
+        let overlay = Overlay::new();
+        let posts = Parsed::from(vec![("hello", false), ("wip", true)]).render_drafts(
+            &overlay,
+            |post| post.1,
+            |post| format!("<h1>{}</h1>", post.0),
+            |post| UrlPath::new(format!("/blog/{}/", post.0)),
+        )?;
+
+        assert_eq!(posts.items, [("hello", false)]);
+        let (url, contents) = overlay
+            .resolve("/blog/wip")
+            .unwrap_or((UrlPath::root(), Vec::new()));
+        assert_eq!(url.as_str(), "/blog/wip/");
+        assert_eq!(contents, b"<h1>wip</h1>");
+        assert!(overlay.resolve("/blog/hello/").is_none());
+
+        let rebuilt = Overlay::new();
+        rebuilt.insert(UrlPath::new("/blog/next/")?, "<h1>next</h1>");
+        overlay.replace_with(&rebuilt);
+        assert!(overlay.resolve("/blog/wip/").is_none());
+        assert!(overlay.resolve("/blog/next/").is_some());
+        assert!(rebuilt.resolve("/blog/next/").is_none());
+        Ok(())
+    }
+}
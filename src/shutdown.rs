@@ -0,0 +1,147 @@
+use std::{
+    fmt, io, mem,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, OnceLock,
+    },
+    time::Duration,
+};
+
+use crate::sync::lock;
+
+/// How often blocking loops check whether a shutdown was requested.
+pub(crate) const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static CTRL_C: OnceLock<Shutdown> = OnceLock::new();
+
+/// A flag to stop [`watch_until`](crate::watch_until) and
+/// [`Listener::run_until`](crate::Listener::run_until) from another thread
+/// or a signal handler. Clones share the same flag.
+///
+/// Stopping is cooperative: a build triggered by a change runs to completion
+/// first, so the cache and manifest it saves are never left half-written.
+/// Use [`Shutdown::on_stop`] to save them once more after the last build.
+///
+/// ```no_run
+/// use std::sync::Arc;
+///
+/// let cache = Arc::new(pichu::RenderCache::load("target/render-cache")?);
+/// let manifest = Arc::new(pichu::AssetManifest::load("dist/manifest.json")?);
+/// let shutdown = pichu::Shutdown::ctrl_c()?;
+/// shutdown.on_stop({
+///     let (cache, manifest) = (Arc::clone(&cache), Arc::clone(&manifest));
+///     move || {
+///         cache.save("target/render-cache")?;
+///         manifest.save("dist/manifest.json")
+///     }
+/// });
+/// pichu::watch_until(["content"], &shutdown, |_| {
+///     // Rebuild with the cache and manifest.
+/// })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Default)]
+pub struct Shutdown {
+    requested: Arc<AtomicBool>,
+    on_stop: Arc<Mutex<Vec<OnStop>>>,
+}
+
+type OnStop = Box<dyn FnOnce() -> Result<(), io::Error> + Send>;
+
+impl Shutdown {
+    /// Create a flag that is only set by [`Shutdown::request`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The flag set when the process receives Ctrl-C (or `SIGTERM` on Unix).
+    /// The signal handler is installed on the first call, later calls return
+    /// the same flag.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if another signal handler has already been installed.
+    pub fn ctrl_c() -> Result<Self, io::Error> {
+        if let Some(shutdown) = CTRL_C.get() {
+            return Ok(shutdown.clone());
+        }
+        let shutdown = Self::new();
+        let handler = shutdown.clone();
+        ctrlc::set_handler(move || handler.request()).map_err(io::Error::other)?;
+        Ok(CTRL_C.get_or_init(|| shutdown).clone())
+    }
+
+    /// Request a shutdown.
+    pub fn request(&self) {
+        self.requested.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether a shutdown was requested.
+    #[must_use]
+    pub fn is_requested(&self) -> bool {
+        self.requested.load(Ordering::SeqCst)
+    }
+
+    /// Call the function once [`watch_until`](crate::watch_until),
+    /// [`Listener::run_until`](crate::Listener::run_until) or the `watch`
+    /// and `serve` commands of [`Cli`](crate::Cli) stopped because of this
+    /// flag, after the last build finished, e.g. to save a
+    /// [`RenderCache`](crate::RenderCache) and an
+    /// [`AssetManifest`](crate::AssetManifest).
+    pub fn on_stop(&self, on_stop: impl FnOnce() -> Result<(), io::Error> + Send + 'static) {
+        lock(&self.on_stop).push(Box::new(on_stop));
+    }
+
+    /// Call the functions registered with [`Shutdown::on_stop`], each only
+    /// once, returning the first error.
+    pub(crate) fn stopped(&self) -> Result<(), io::Error> {
+        let on_stop = mem::take(&mut *lock(&self.on_stop));
+        on_stop
+            .into_iter()
+            .map(|on_stop| on_stop())
+            .fold(Ok(()), Result::and)
+    }
+}
+
+impl fmt::Debug for Shutdown {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Shutdown")
+            .field("requested", &self.is_requested())
+            .field("on_stop", &lock(&self.on_stop).len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shutdown() {
+        let shutdown = Shutdown::new();
+        let clone = shutdown.clone();
+        assert!(!shutdown.is_requested());
+        clone.request();
+        assert!(shutdown.is_requested());
+        assert!(!Shutdown::new().is_requested());
+    }
+
+    #[test]
+    fn test_on_stop() {
+        let shutdown = Shutdown::new();
+        let saved = Arc::new(AtomicBool::new(false));
+        shutdown.clone().on_stop({
+            let saved = Arc::clone(&saved);
+            move || {
+                saved.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        shutdown.on_stop(|| Err(io::Error::other("disk full")));
+        assert!(shutdown.stopped().is_err());
+        assert!(saved.load(Ordering::SeqCst));
+        // Each function is only called once.
+        assert!(shutdown.stopped().is_ok());
+    }
+}
@@ -0,0 +1,256 @@
+use rayon::prelude::*;
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs, io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use crate::{
+    clock::rfc3339, freshness::git_lastmod, html::escape, output::write_atomic, sync::lock,
+    BuildProfile, HasSource, Page, Parsed, UrlPath, UrlStyle,
+};
+
+/// Where a [`Sitemap`] takes the `lastmod` of a page from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LastMod {
+    /// The date of the last commit changing the page's source file, if it's
+    /// tracked in git.
+    Git,
+    /// When the page's output file in the output directory was last
+    /// written. Outputs skipped by
+    /// [`Parsed::render_each_cached`](crate::Parsed::render_each_cached)
+    /// keep their time, so this is when the page last changed.
+    Output(PathBuf),
+    /// The page's [`Page::updated`] or else [`Page::date`].
+    Date,
+}
+
+/// Collects the pages of a site, e.g. from several collections, and writes
+/// them as a `sitemap.xml`. Can be shared between threads.
+///
+/// ```no_run
+/// use pichu::{LastMod, Sitemap};
+///
+/// # #[derive(Debug, serde::Deserialize)]
+/// # struct Post { title: String }
+/// # impl pichu::Page for Post {
+/// #     fn title(&self) -> &str { &self.title }
+/// #     fn url(&self) -> pichu::UrlPath { pichu::UrlPath::root() }
+/// # }
+/// # fn main() -> Result<(), pichu::Error> {
+/// let sitemap = Sitemap::new("https://example.com")
+///     .lastmod([LastMod::Git, LastMod::Output("dist".into()), LastMod::Date]);
+/// let posts = pichu::glob("content/blog/*.md")?
+///     .parse_markdown::<Post>()?
+///     .in_sitemap(&sitemap);
+/// // Render the posts ...
+/// # drop(posts);
+/// sitemap.save("dist/sitemap.xml")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct Sitemap {
+    base_url: String,
+    sources: Vec<LastMod>,
+    url_style: Option<UrlStyle>,
+    entries: Mutex<BTreeMap<UrlPath, Option<String>>>,
+}
+
+impl Sitemap {
+    /// Create an empty sitemap for the site at the base URL, taking
+    /// `lastmod` from [`LastMod::Date`].
+    #[must_use]
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            sources: vec![LastMod::Date],
+            url_style: None,
+            entries: Mutex::default(),
+        }
+    }
+
+    /// Create an empty sitemap for the site at the production URL, or at
+    /// [`BuildProfile::DEV_BASE_URL`] for development builds.
+    #[must_use]
+    pub fn for_profile(profile: BuildProfile, prod_url: &str) -> Self {
+        Self::new(profile.base_url(prod_url))
+    }
+
+    /// Where to take the `lastmod` of pages from, trying each source in
+    /// order until one has a date. Pages without one have no `lastmod`.
+    #[must_use]
+    pub fn lastmod(mut self, sources: impl IntoIterator<Item = LastMod>) -> Self {
+        self.sources = sources.into_iter().collect();
+        self
+    }
+
+    /// List pages in the site's URL style, whatever style their URL paths
+    /// are given in, see [`UrlPath::with_style`].
+    #[must_use]
+    pub fn url_style(mut self, style: UrlStyle) -> Self {
+        self.url_style = Some(style);
+        self
+    }
+
+    /// Add a page, replacing any previous entry for the URL path.
+    pub fn insert(&self, url: UrlPath, lastmod: Option<String>) {
+        lock(&self.entries).insert(self.styled(url), lastmod);
+    }
+
+    fn styled(&self, url: UrlPath) -> UrlPath {
+        match self.url_style {
+            Some(style) => url.with_style(style),
+            None => url,
+        }
+    }
+
+    /// Add pages, looking up their `lastmod` from the configured sources.
+    pub fn extend<'a, T: Page + HasSource + Sync + 'a>(
+        &self,
+        pages: impl IntoParallelIterator<Item = &'a T>,
+    ) {
+        let entries = pages
+            .into_par_iter()
+            .map(|page| (self.styled(page.url()), self.lastmod_of(page)))
+            .collect::<Vec<_>>();
+        lock(&self.entries).extend(entries);
+    }
+
+    fn lastmod_of<T: Page + HasSource>(&self, page: &T) -> Option<String> {
+        self.sources.iter().find_map(|source| match source {
+            LastMod::Git => git_lastmod(page.source()),
+            LastMod::Output(out_dir) => {
+                let modified = fs::metadata(page.url().to_output(out_dir))
+                    .and_then(|metadata| metadata.modified())
+                    .ok()?;
+                let secs = modified.duration_since(UNIX_EPOCH).ok()?.as_secs();
+                Some(rfc3339(i64::try_from(secs).ok()?))
+            }
+            LastMod::Date => page.updated().or(page.date()).map(str::to_owned),
+        })
+    }
+
+    /// The sitemap as XML, with pages sorted by URL path.
+    #[must_use]
+    pub fn render(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <urlset xmlns=\"http://www.sitemaps.org/schemas/sitemap/0.9\">\n",
+        );
+        for (url, lastmod) in lock(&self.entries).iter() {
+            let _ = write!(
+                xml,
+                "<url><loc>{}</loc>",
+                escape(&format!("{}{url}", self.base_url))
+            );
+            if let Some(lastmod) = lastmod {
+                let _ = write!(xml, "<lastmod>{}</lastmod>", escape(lastmod));
+            }
+            xml.push_str("</url>\n");
+        }
+        xml.push_str("</urlset>\n");
+        xml
+    }
+
+    /// Write the sitemap to disk, replacing the file in one step.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be written.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), io::Error> {
+        write_atomic(path.as_ref(), self.render().as_bytes())
+    }
+}
+
+impl<T: Page + HasSource + Send + Sync> Parsed<T> {
+    /// Add the pages to the sitemap, see [`Sitemap::extend`].
+    #[must_use]
+    pub fn in_sitemap(self, sitemap: &Sitemap) -> Self {
+        sitemap.extend(&self.items);
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    struct Post {
+        source: PathBuf,
+        slug: String,
+        date: Option<String>,
+    }
+
+    impl Page for Post {
+        fn title(&self) -> &str {
+            &self.slug
+        }
+
+        fn url(&self) -> UrlPath {
+            UrlPath::new(format!("/{}/", self.slug)).unwrap_or_else(|_| UrlPath::root())
+        }
+
+        fn date(&self) -> Option<&str> {
+            self.date.as_deref()
+        }
+    }
+
+    impl HasSource for Post {
+        fn source(&self) -> &Path {
+            &self.source
+        }
+    }
+
+    #[test]
+    fn test_sitemap() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("pichu_test_sitemap");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        crate::write(dir.join("dist/built/index.html"), "")?;
+        let post = |slug: &str, date: Option<&str>| Post {
+            source: dir.join("content").join(format!("{slug}.md")),
+            slug: slug.to_string(),
+            date: date.map(str::to_owned),
+        };
+
+        let dev = Sitemap::for_profile(BuildProfile::Dev, "https://example.com")
+            .url_style(UrlStyle::Html);
+        assert_eq!(dev.base_url, BuildProfile::DEV_BASE_URL);
+        dev.insert(UrlPath::new("/blog/").map_err(io::Error::other)?, None);
+        assert!(dev
+            .render()
+            .contains("<loc>http://localhost:8080/blog.html</loc>"));
+
+        let sitemap = Sitemap::new("https://example.com/").lastmod([
+            LastMod::Git,
+            LastMod::Output(dir.join("dist")),
+            LastMod::Date,
+        ]);
+        let _ = Parsed::from(vec![
+            post("built", Some("2020-01-01")),
+            post("dated", Some("2024-05-01")),
+            post("undated", None),
+        ])
+        .in_sitemap(&sitemap);
+        sitemap.insert(UrlPath::root(), None);
+        sitemap.save(dir.join("dist/sitemap.xml"))?;
+
+        let xml = fs::read_to_string(dir.join("dist/sitemap.xml"))?;
+        assert!(xml.contains("<url><loc>https://example.com/</loc></url>"));
+        assert!(xml.contains("<url><loc>https://example.com/built/</loc><lastmod>20"));
+        assert!(!xml.contains("2020-01-01"));
+        assert!(xml.contains(
+            "<url><loc>https://example.com/dated/</loc><lastmod>2024-05-01</lastmod></url>"
+        ));
+        assert!(xml.contains("<url><loc>https://example.com/undated/</loc></url>"));
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
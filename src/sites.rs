@@ -0,0 +1,234 @@
+use rayon::prelude::*;
+use std::time::{Duration, Instant};
+
+use crate::{copy_dir, Build, BuildConfig, BuildReport, Error, StepOutcome};
+
+/// Builds several sites from one process, e.g. the `www`, `docs` and `blog`
+/// subdomains. Each site is a [`Build`] with its config namespaced by
+/// [`BuildConfig::for_site`]. Shared steps, e.g. an asset pipeline, run once
+/// before the sites, and their output and [`AssetManifest`](crate::AssetManifest)
+/// entries are copied into every site. Sites build in parallel.
+///
+/// ```no_run
+/// use pichu::{BuildConfig, Sites};
+///
+/// # fn main() -> Result<(), pichu::Error> {
+/// let report = Sites::new(BuildConfig::default())
+///     .shared(|build| build.step("fonts", |ctx| pichu::copy_dir("shared/fonts", &ctx.config.out_dir)))
+///     .site("www", |build| build.step("static", |ctx| pichu::copy_dir("www/static", &ctx.config.out_dir)))
+///     .site("docs", |build| build.step("static", |ctx| pichu::copy_dir("docs/static", &ctx.config.out_dir)))
+///     .run()?
+///     .into_result()?;
+/// println!("Built {} sites in {:?}", report.sites.len(), report.duration);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Sites {
+    config: BuildConfig,
+    shared: Option<Build>,
+    builds: Vec<(String, Build)>,
+    error: Option<Error>,
+}
+
+/// The combined result of [`Sites::run`].
+#[derive(Debug)]
+pub struct SitesReport {
+    /// The report of the shared steps, if any.
+    pub shared: Option<BuildReport>,
+    /// The report of each site, in the order they were added. Empty if the
+    /// shared steps failed.
+    pub sites: Vec<(String, BuildReport)>,
+    /// How long all builds took.
+    pub duration: Duration,
+}
+
+impl SitesReport {
+    /// Returns true if the shared steps and all sites succeeded.
+    #[must_use]
+    pub fn is_success(&self) -> bool {
+        self.shared.as_ref().is_none_or(BuildReport::is_success)
+            && self.sites.iter().all(|(_, report)| report.is_success())
+    }
+
+    /// Turn failed steps into an [`Error::Steps`], with step names prefixed
+    /// by their site, e.g. `docs/search`, or `shared/`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any step failed.
+    pub fn into_result(self) -> Result<Self, Error> {
+        if self.is_success() {
+            return Ok(self);
+        }
+        let failed = self
+            .shared
+            .into_iter()
+            .map(|report| ("shared".to_string(), report))
+            .chain(self.sites)
+            .flat_map(|(site, report)| {
+                report
+                    .steps
+                    .into_iter()
+                    .filter_map(move |step| match step.outcome {
+                        StepOutcome::Failed(e) => Some((format!("{site}/{}", step.name), e)),
+                        _ => None,
+                    })
+            })
+            .collect();
+        Err(Error::Steps(failed))
+    }
+}
+
+impl Sites {
+    /// Create a multi-site build from the base config, whose output
+    /// directory contains one directory per site.
+    #[must_use]
+    pub fn new(config: BuildConfig) -> Self {
+        Self {
+            config,
+            shared: None,
+            builds: Vec::new(),
+            error: None,
+        }
+    }
+
+    /// Configure the steps shared by all sites, building into the `shared`
+    /// namespace.
+    #[must_use]
+    pub fn shared(mut self, f: impl FnOnce(Build) -> Build) -> Self {
+        match Build::new(self.config.for_site("shared")) {
+            Ok(build) => self.shared = Some(f(build)),
+            Err(e) => self.error = self.error.or(Some(e)),
+        }
+        self
+    }
+
+    /// Add a site and configure its steps.
+    #[must_use]
+    pub fn site(mut self, name: impl Into<String>, f: impl FnOnce(Build) -> Build) -> Self {
+        let name = name.into();
+        match Build::new(self.config.for_site(&name)) {
+            Ok(build) => self.builds.push((name, f(build))),
+            Err(e) => self.error = self.error.or(Some(e)),
+        }
+        self
+    }
+
+    /// Mark the base output directory as pichu output, then run the shared
+    /// steps and all sites in parallel.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidSteps`] if two sites have the same name or a
+    /// site is named `shared`, or an error if a render cache could not be
+    /// loaded, a build could not be finished or the shared output could not
+    /// be copied. Failing steps are reported in the [`SitesReport`] instead.
+    pub fn run(self) -> Result<SitesReport, Error> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        for (index, (name, _)) in self.builds.iter().enumerate() {
+            if name == "shared" || self.builds[..index].iter().any(|(n, _)| n == name) {
+                return Err(Error::InvalidSteps(format!("duplicate site {name}")));
+            }
+        }
+        crate::mark_build(&self.config.out_dir)?;
+        let start = Instant::now();
+
+        let shared = match &self.shared {
+            Some(build) => {
+                let report = build.run()?;
+                if !report.is_success() {
+                    return Ok(SitesReport {
+                        shared: Some(report),
+                        sites: Vec::new(),
+                        duration: start.elapsed(),
+                    });
+                }
+                Some((build, report))
+            }
+            None => None,
+        };
+
+        let sites = self
+            .builds
+            .par_iter()
+            .map(|(name, build)| {
+                if let Some((shared, _)) = &shared {
+                    let ctx = shared.context();
+                    // Mark before copying, as only empty directories can be.
+                    crate::mark_build(&build.context().config.out_dir)?;
+                    copy_dir(&ctx.config.out_dir, &build.context().config.out_dir)?;
+                    for (name, url) in ctx.manifest.entries() {
+                        build.context().manifest.register(name, url);
+                    }
+                }
+                Ok((name.clone(), build.run()?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(SitesReport {
+            shared: shared.map(|(_, report)| report),
+            sites,
+            duration: start.elapsed(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs};
+
+    #[test]
+    fn test_sites() -> Result<(), Error> {
+        let out_dir = env::temp_dir().join("pichu_test_sites");
+        let config = BuildConfig {
+            out_dir: out_dir.clone(),
+            cache_path: Some(out_dir.join(".cache/render.json")),
+            ..Default::default()
+        };
+        assert_eq!(
+            config.for_site("docs").cache_path,
+            Some(out_dir.join(".cache/docs/render.json"))
+        );
+
+        let report = Sites::new(config.clone())
+            .shared(|build| {
+                build.step("css", |ctx| -> Result<(), Error> {
+                    crate::write(ctx.config.out_dir.join("main.css"), "body{}")?;
+                    ctx.manifest.register("main.css", "/main.css");
+                    Ok(())
+                })
+            })
+            .site("www", |build| {
+                build.step("index", |ctx| -> Result<(), Error> {
+                    let css = ctx.manifest.get("main.css").unwrap_or_default();
+                    crate::write(ctx.config.out_dir.join("index.html"), css)?;
+                    Ok(())
+                })
+            })
+            .site("docs", |build| build.step("search", |_| Err("no index")))
+            .run()?;
+
+        assert_eq!(
+            fs::read_to_string(out_dir.join("www/index.html"))?,
+            "/main.css"
+        );
+        assert!(out_dir.join("docs/main.css").is_file());
+        assert!(matches!(
+            report.into_result(),
+            Err(Error::Steps(failed)) if failed.len() == 1 && failed[0].0 == "docs/search"
+        ));
+        assert!(matches!(
+            Sites::new(config)
+                .site("www", |build| build)
+                .site("www", |build| build)
+                .run(),
+            Err(Error::InvalidSteps(_))
+        ));
+
+        crate::clean(out_dir)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,128 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// How non-ASCII characters are handled by [`slugify_with`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transliteration {
+    /// Transliterate everything to ASCII, e.g. `ä` to `a` and `東京` to
+    /// `dong-jing`.
+    #[default]
+    Ascii,
+    /// Like [`Transliteration::Ascii`], but with German umlauts and `ß`
+    /// expanded to `ae`, `oe`, `ue` and `ss`.
+    German,
+    /// Keep non-ASCII letters and digits, e.g. for CJK titles. Only
+    /// punctuation and whitespace are replaced.
+    Passthrough,
+}
+
+/// Options for [`slugify_with`].
+#[derive(Debug, Clone)]
+pub struct SlugOptions {
+    /// How non-ASCII characters are handled.
+    pub transliteration: Transliteration,
+    /// The separator replacing whitespace and punctuation, defaults to `-`.
+    pub separator: char,
+    /// The maximum length in characters, if any.
+    pub max_len: Option<usize>,
+}
+
+impl Default for SlugOptions {
+    fn default() -> Self {
+        Self {
+            transliteration: Transliteration::default(),
+            separator: '-',
+            max_len: None,
+        }
+    }
+}
+
+/// Turn a title into a lowercase, ASCII-only URL slug, e.g.
+/// `Über Café & Crème` into `uber-cafe-creme`.
+#[must_use]
+pub fn slugify(s: &str) -> String {
+    slugify_with(s, &SlugOptions::default())
+}
+
+/// Turn a title into a URL slug with the given options. The input is
+/// NFC-normalized first, so composed and decomposed characters produce the
+/// same slug on every platform.
+#[must_use]
+pub fn slugify_with(s: &str, options: &SlugOptions) -> String {
+    let normalized: String = s.nfc().collect();
+    let text = match options.transliteration {
+        Transliteration::Ascii => deunicode::deunicode(&normalized),
+        Transliteration::German => deunicode::deunicode(&expand_german(&normalized)),
+        Transliteration::Passthrough => normalized,
+    };
+
+    let mut slug = String::with_capacity(text.len());
+    let mut pending_separator = false;
+    for c in text.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            if pending_separator && !slug.is_empty() {
+                slug.push(options.separator);
+            }
+            pending_separator = false;
+            slug.push(c);
+        } else if c != '\'' && c != '’' {
+            // Apostrophes are dropped so "don't" becomes "dont".
+            pending_separator = true;
+        }
+    }
+
+    if let Some(max_len) = options.max_len {
+        if let Some((index, _)) = slug.char_indices().nth(max_len) {
+            slug.truncate(index);
+            slug.truncate(slug.trim_end_matches(options.separator).len());
+        }
+    }
+    slug
+}
+
+fn expand_german(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            'ä' => out.push_str("ae"),
+            'ö' => out.push_str("oe"),
+            'ü' => out.push_str("ue"),
+            'Ä' => out.push_str("Ae"),
+            'Ö' => out.push_str("Oe"),
+            'Ü' => out.push_str("Ue"),
+            'ß' => out.push_str("ss"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Über Café & Crème"), "uber-cafe-creme");
+        assert_eq!(slugify("Don't panic!"), "dont-panic");
+        // Decomposed "é" (e + combining acute) slugs like the composed one.
+        assert_eq!(slugify("Cafe\u{301}"), slugify("Café"));
+        assert_eq!(slugify("東京"), "dong-jing");
+    }
+
+    #[test]
+    fn test_slugify_with() {
+        let german = SlugOptions {
+            transliteration: Transliteration::German,
+            ..Default::default()
+        };
+        assert_eq!(slugify_with("Grüße aus Köln", &german), "gruesse-aus-koeln");
+
+        let passthrough = SlugOptions {
+            transliteration: Transliteration::Passthrough,
+            separator: '_',
+            max_len: Some(5),
+        };
+        assert_eq!(slugify_with("東京 2024, Tag 1", &passthrough), "東京_20");
+        assert_eq!(slugify_with("東京 — 2", &passthrough), "東京_2");
+    }
+}
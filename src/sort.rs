@@ -0,0 +1,194 @@
+use rayon::prelude::*;
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+use crate::{Error, Parsed};
+
+/// Compare strings with numeric-aware ordering, so `part-2` sorts before
+/// `part-10`. Numbers compare by value, everything else by character.
+#[must_use]
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    // Equal numbers with fewer leading zeros go first, if nothing else differs.
+    let mut tie = Ordering::Equal;
+    loop {
+        match (a.peek(), b.peek()) {
+            (None, None) => return tie,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let (x, y) = (take_digits(&mut a), take_digits(&mut b));
+                let (x_trimmed, y_trimmed) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_trimmed
+                    .len()
+                    .cmp(&y_trimmed.len())
+                    .then_with(|| x_trimmed.cmp(y_trimmed));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                tie = tie.then(x.len().cmp(&y.len()));
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.cmp(y);
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+fn take_digits(chars: &mut Peekable<Chars<'_>>) -> String {
+    let mut digits = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        digits.push(c);
+    }
+    digits
+}
+
+/// Compare strings like [`natural_cmp`], but ignoring case and accents so
+/// `Émile` sorts between `Edgar` and `Felix`. Exact differences only break
+/// ties.
+#[cfg(feature = "collation")]
+#[must_use]
+pub fn collated_cmp(a: &str, b: &str) -> Ordering {
+    natural_cmp(&fold(a), &fold(b)).then_with(|| natural_cmp(a, b))
+}
+
+#[cfg(feature = "collation")]
+fn fold(s: &str) -> String {
+    use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+
+    s.nfkd()
+        .filter(|c| !is_combining_mark(*c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+#[cfg(feature = "collation")]
+#[derive(PartialEq, Eq)]
+struct CollationKey(String, String);
+
+#[cfg(feature = "collation")]
+impl Ord for CollationKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        natural_cmp(&self.0, &other.0).then_with(|| natural_cmp(&self.1, &other.1))
+    }
+}
+
+#[cfg(feature = "collation")]
+impl PartialOrd for CollationKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Sort the items by the key provided, ascending, where computing the
+    /// key can fail, e.g. when parsing a date. The key is computed once per
+    /// item.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::SortKey`] with the position of the item, before
+    /// sorting, if the key function fails for any item.
+    pub fn try_sort_by_key<K, E, F>(mut self, f: F) -> Result<Self, Error>
+    where
+        F: Fn(&T) -> Result<K, E> + Sync,
+        K: Ord + Send,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let keys = self
+            .items
+            .par_iter()
+            .enumerate()
+            .map(|(index, item)| {
+                f(item).map_err(|e| Error::SortKey {
+                    index,
+                    error: Box::new(e),
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut keyed = keys.into_iter().zip(self.items).collect::<Vec<_>>();
+        if self.unordered {
+            keyed.par_sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        } else {
+            keyed.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+        }
+        self.items = keyed.into_iter().map(|(_, item)| item).collect();
+        Ok(self)
+    }
+
+    /// Sort the items by the string key provided, ascending, comparing
+    /// numbers by value like [`natural_cmp`].
+    #[must_use]
+    pub fn sort_by_key_natural<K, F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> K + Sync,
+        K: AsRef<str>,
+    {
+        let cmp = |a: &T, b: &T| natural_cmp(f(a).as_ref(), f(b).as_ref());
+        if self.unordered {
+            self.items.par_sort_unstable_by(cmp);
+        } else {
+            self.items.par_sort_by(cmp);
+        }
+        self
+    }
+
+    /// Sort the items by the string key provided, ascending, ignoring case
+    /// and accents like [`collated_cmp`].
+    #[cfg(feature = "collation")]
+    #[must_use]
+    pub fn sort_by_key_collated<K, F>(mut self, f: F) -> Self
+    where
+        F: Fn(&T) -> K + Sync,
+        K: AsRef<str>,
+    {
+        self.items.par_sort_by_cached_key(|item| {
+            let key = f(item);
+            CollationKey(fold(key.as_ref()), key.as_ref().to_owned())
+        });
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_natural_cmp() {
+        let mut chapters = vec!["part-10", "part-2", "part-1b", "part-01", "part-1", "intro"];
+        chapters.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            chapters,
+            ["intro", "part-1", "part-01", "part-1b", "part-2", "part-10"]
+        );
+
+        let parsed = Parsed::from(vec!["v1.10", "v1.9"]).sort_by_key_natural(|item| *item);
+        assert_eq!(parsed.items, ["v1.9", "v1.10"]);
+    }
+
+    #[test]
+    fn test_try_sort_by_key() -> Result<(), Error> {
+        let parsed = Parsed::from(vec!["2024-03-01", "2023-12-24", "2024-01-15"])
+            .try_sort_by_key(|item| item.replace('-', "").parse::<u32>())?;
+        assert_eq!(parsed.items, ["2023-12-24", "2024-01-15", "2024-03-01"]);
+
+        let result = Parsed::from(vec!["2024-03-01", "yesterday"])
+            .try_sort_by_key(|item| item.replace('-', "").parse::<u32>());
+        assert!(matches!(result, Err(Error::SortKey { index: 1, ref error })
+            if error.to_string() == "invalid digit found in string"));
+        Ok(())
+    }
+
+    #[cfg(feature = "collation")]
+    #[test]
+    fn test_collated() {
+        let parsed = Parsed::from(vec!["felix", "Émile", "edgar", "Zoë", "emile"])
+            .sort_by_key_collated(|item| *item);
+        assert_eq!(parsed.items, ["edgar", "emile", "Émile", "felix", "Zoë"]);
+    }
+}
@@ -0,0 +1,303 @@
+use rayon::prelude::*;
+use rusqlite::{types::Value, Connection};
+use serde::Serialize;
+use std::{fs, io, path::Path};
+
+use crate::{workspace::persist, Parsed};
+
+/// The table [`Parsed::export_sqlite`] writes items to.
+pub const SQLITE_TABLE: &str = "items";
+
+/// Error type for [`Parsed::export_sqlite`].
+#[derive(thiserror::Error, Debug)]
+pub enum SqliteError {
+    /// I/O error.
+    #[error("io error: {0}")]
+    IO(#[from] io::Error),
+    /// The database couldn't be written.
+    #[error("sqlite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
+
+/// The columns of an item in a database export, see
+/// [`Parsed::export_sqlite`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SqliteRow {
+    columns: Vec<(String, Value)>,
+}
+
+impl SqliteRow {
+    /// Create an empty row.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set a column, replacing any previous value.
+    #[must_use]
+    pub fn column(mut self, name: impl Into<String>, value: impl Into<Value>) -> Self {
+        let name = name.into();
+        let value = value.into();
+        match self.columns.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, v)) => *v = value,
+            None => self.columns.push((name, value)),
+        }
+        self
+    }
+
+    /// Add a column for each field of a struct, e.g. frontmatter. Strings,
+    /// numbers and booleans are stored as is, lists and objects as JSON.
+    /// Adds nothing if the value doesn't serialize to an object.
+    #[must_use]
+    pub fn fields(mut self, fields: &impl Serialize) -> Self {
+        let Ok(serde_json::Value::Object(fields)) = serde_json::to_value(fields) else {
+            return self;
+        };
+        for (name, value) in fields {
+            let value = match value {
+                serde_json::Value::Null => Value::Null,
+                serde_json::Value::Bool(b) => Value::Integer(b.into()),
+                serde_json::Value::Number(n) => n
+                    .as_i64()
+                    .map(Value::Integer)
+                    .or_else(|| n.as_f64().map(Value::Real))
+                    .unwrap_or(Value::Text(n.to_string())),
+                serde_json::Value::String(s) => Value::Text(s),
+                value => Value::Text(value.to_string()),
+            };
+            self = self.column(name, value);
+        }
+        self
+    }
+
+    /// The frontmatter fields of a markdown file, plus its `path`,
+    /// `basename`, rendered `html` and plain `text`.
+    #[cfg(feature = "markdown")]
+    #[must_use]
+    pub fn from_markdown<T: Serialize>(markdown: &crate::Markdown<T>) -> Self {
+        Self::new()
+            .fields(&markdown.frontmatter)
+            .column("path", markdown.path.to_string_lossy().into_owned())
+            .column("basename", markdown.basename.clone())
+            .column("html", markdown.html.clone())
+            .column("text", text(&markdown.html))
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Write all items to the [`SQLITE_TABLE`] table of a new `SQLite`
+    /// database, for external tools like Datasette, search services or
+    /// scripts. The mapper returns the columns of each item; columns missing
+    /// from an item are `NULL`. An existing database is replaced.
+    ///
+    /// ```no_run
+    /// use pichu::SqliteRow;
+    ///
+    /// # #[derive(Debug, serde::Deserialize, serde::Serialize)]
+    /// # struct Post { title: String }
+    /// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+    /// pichu::glob("content/blog/*.md")?
+    ///     .parse_markdown::<Post>()?
+    ///     .export_sqlite("dist/content.db", SqliteRow::from_markdown)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be written.
+    pub fn export_sqlite(
+        self,
+        path: impl AsRef<Path>,
+        schema_mapper: impl Fn(&T) -> SqliteRow + Send + Sync,
+    ) -> Result<Self, SqliteError> {
+        let rows = self.items.par_iter().map(schema_mapper).collect::<Vec<_>>();
+        let mut columns: Vec<(&str, &str)> = Vec::new();
+        for (name, value) in rows.iter().flat_map(|row| &row.columns) {
+            let ty = match value {
+                Value::Null => "",
+                Value::Integer(_) => " INTEGER",
+                Value::Real(_) => " REAL",
+                Value::Text(_) => " TEXT",
+                Value::Blob(_) => " BLOB",
+            };
+            match columns.iter_mut().find(|(n, _)| n == name) {
+                // The first value that isn't null declares the type.
+                Some((_, declared)) if declared.is_empty() => *declared = ty,
+                Some(_) => {}
+                None => columns.push((name, ty)),
+            }
+        }
+
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // Built in a workspace and moved into place, so readers never see a
+        // half-written database.
+        let workspace = crate::workspace()?;
+        let tmp = workspace.path().join(SQLITE_TABLE).with_extension("db");
+
+        let mut db = Connection::open(&tmp)?;
+        if !columns.is_empty() {
+            let quote = |name: &str| format!("\"{}\"", name.replace('"', "\"\""));
+            let definitions = columns
+                .iter()
+                .map(|(name, ty)| format!("{}{ty}", quote(name)))
+                .collect::<Vec<_>>();
+            let names = columns
+                .iter()
+                .map(|(name, _)| quote(name))
+                .collect::<Vec<_>>();
+            let placeholders = vec!["?"; columns.len()];
+            let tx = db.transaction()?;
+            tx.execute(
+                &format!("CREATE TABLE {SQLITE_TABLE} ({})", definitions.join(", ")),
+                [],
+            )?;
+            {
+                let mut insert = tx.prepare(&format!(
+                    "INSERT INTO {SQLITE_TABLE} ({}) VALUES ({})",
+                    names.join(", "),
+                    placeholders.join(", ")
+                ))?;
+                for row in &rows {
+                    insert.execute(rusqlite::params_from_iter(columns.iter().map(
+                        |(name, _)| {
+                            row.columns
+                                .iter()
+                                .find(|(n, _)| n == name)
+                                .map_or(&Value::Null, |(_, value)| value)
+                        },
+                    )))?;
+                }
+            }
+            tx.commit()?;
+        }
+        db.close().map_err(|(_, e)| e)?;
+        persist(&tmp, path)?;
+        Ok(self)
+    }
+}
+
+/// The text of an HTML document without tags, scripts and styles, with
+/// whitespace collapsed.
+#[cfg(feature = "markdown")]
+fn text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut pieces = html.split('<');
+    text.push_str(pieces.next().unwrap_or_default());
+    let mut raw_text: Option<String> = None;
+    for piece in pieces {
+        let name = piece
+            .chars()
+            .take_while(|c| c.is_ascii_alphanumeric() || *c == '/')
+            .collect::<String>()
+            .to_ascii_lowercase();
+        match &raw_text {
+            Some(element) if name.strip_prefix('/') == Some(element) => raw_text = None,
+            Some(_) => continue,
+            None if name == "script" || name == "style" => raw_text = Some(name),
+            None => {}
+        }
+        // Tags separate words, e.g. of adjacent paragraphs.
+        text.push(' ');
+        if raw_text.is_none() {
+            text.push_str(&piece[crate::html::tag_end(piece)..]);
+        }
+    }
+    crate::html::unescape(&text)
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[derive(Serialize)]
+    struct Post {
+        title: &'static str,
+        draft: bool,
+        tags: Vec<&'static str>,
+        rating: Option<f64>,
+    }
+
+    #[test]
+    fn test_export_sqlite() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_export_sqlite");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+
+        let posts = Parsed::from(vec![
+            Post {
+                title: "Hello",
+                draft: false,
+                tags: vec!["rust"],
+                rating: None,
+            },
+            Post {
+                title: "Say \"hi\"",
+                draft: true,
+                tags: Vec::new(),
+                rating: Some(4.5),
+            },
+        ]);
+        posts.export_sqlite(dir.join("content.db"), |post| {
+            SqliteRow::new()
+                .fields(post)
+                .column("html", format!("<p>{}</p>", post.title))
+        })?;
+
+        let db = Connection::open(dir.join("content.db"))?;
+        let rows = db
+            .prepare("SELECT title, draft, tags, rating, html FROM items ORDER BY title")?
+            .query_map([], |row| {
+                Ok((
+                    row.get::<_, String>(0)?,
+                    row.get::<_, bool>(1)?,
+                    row.get::<_, String>(2)?,
+                    row.get::<_, Option<f64>>(3)?,
+                    row.get::<_, String>(4)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            rows,
+            [
+                (
+                    "Hello".to_string(),
+                    false,
+                    "[\"rust\"]".to_string(),
+                    None,
+                    "<p>Hello</p>".to_string()
+                ),
+                (
+                    "Say \"hi\"".to_string(),
+                    true,
+                    "[]".to_string(),
+                    Some(4.5),
+                    "<p>Say \"hi\"</p>".to_string()
+                ),
+            ]
+        );
+        let rating_type: String = db.query_row(
+            "SELECT type FROM pragma_table_info('items') WHERE name = 'rating'",
+            [],
+            |row| row.get(0),
+        )?;
+        assert_eq!(rating_type, "REAL");
+
+        #[cfg(feature = "markdown")]
+        assert_eq!(
+            text("<h1>Hi</h1><p>A &amp; <b>B</b></p><script>x</script><p>C</p>"),
+            "Hi A & B C"
+        );
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
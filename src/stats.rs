@@ -0,0 +1,47 @@
+use std::collections::BTreeMap;
+
+use crate::Parsed;
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Count the items per key, e.g. posts per year, ordered by key.
+    #[must_use]
+    pub fn stats_by<K: Ord>(&self, key_fn: impl Fn(&T) -> K) -> BTreeMap<K, usize> {
+        self.stats_by_each(|item| [key_fn(item)])
+    }
+
+    /// Count the items per key, where an item can have any number of keys,
+    /// e.g. posts per tag, ordered by key.
+    #[must_use]
+    pub fn stats_by_each<K: Ord, I: IntoIterator<Item = K>>(
+        &self,
+        keys_fn: impl Fn(&T) -> I,
+    ) -> BTreeMap<K, usize> {
+        let mut stats = BTreeMap::new();
+        for key in self.items.iter().flat_map(keys_fn) {
+            *stats.entry(key).or_default() += 1;
+        }
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats_by() {
+        let parsed = Parsed::from(vec![
+            (2023, vec!["rust"]),
+            (2024, vec!["rust", "web"]),
+            (2023, vec![]),
+        ]);
+        assert_eq!(
+            parsed.stats_by(|(year, _)| *year),
+            BTreeMap::from([(2023, 2), (2024, 1)])
+        );
+        assert_eq!(
+            parsed.stats_by_each(|(_, tags)| tags.clone()),
+            BTreeMap::from([("rust", 2), ("web", 1)])
+        );
+    }
+}
@@ -0,0 +1,483 @@
+use rayon::prelude::*;
+use std::{
+    fmt,
+    path::{Component, Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{output, Error, Parsed};
+
+/// How pages are addressed: `/blog/hello/`, written to
+/// `blog/hello/index.html`, or `/blog/hello.html`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum UrlStyle {
+    /// Directory URLs ending in `/`.
+    #[default]
+    Pretty,
+    /// File URLs ending in `.html`.
+    Html,
+}
+
+/// A normalized site URL path like `/blog/hello/` or `/feed.xml`.
+/// Always starts with `/`, uses `/` as separator on every platform and
+/// contains no empty, `.` or `..` segments. A trailing `/` marks a directory
+/// whose page is written to `index.html`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct UrlPath(String);
+
+impl UrlPath {
+    /// The root path, `/`.
+    #[must_use]
+    pub fn root() -> Self {
+        Self("/".to_string())
+    }
+
+    /// Normalize a path, converting `\` to `/` and dropping empty and `.`
+    /// segments.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the path contains `..`,
+    /// whitespace, control characters or characters reserved in URLs like
+    /// `?` and `#`.
+    pub fn new(path: impl AsRef<str>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        if let Some(c) = path
+            .chars()
+            .find(|c| c.is_whitespace() || c.is_control() || "?#<>\"|^`{}".contains(*c))
+        {
+            return Err(Error::InvalidUrlPath(format!(
+                "{path}: invalid character {c:?}"
+            )));
+        }
+
+        let normalized = path.replace('\\', "/");
+        let mut url = String::from("/");
+        for segment in normalized.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => {
+                    return Err(Error::InvalidUrlPath(format!(
+                        "{path}: parent segments are not allowed"
+                    )))
+                }
+                segment => {
+                    if !url.ends_with('/') {
+                        url.push('/');
+                    }
+                    url.push_str(segment);
+                }
+            }
+        }
+        if normalized.ends_with('/') && !url.ends_with('/') {
+            url.push('/');
+        }
+        Ok(Self(url))
+    }
+
+    /// The URL path of an output file, relative to the output directory.
+    /// `index.html` files map to their directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the file isn't inside the output
+    /// directory or its path isn't a valid URL path.
+    pub fn from_output(out_dir: impl AsRef<Path>, path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(out_dir.as_ref()).map_err(|_| {
+            Error::InvalidUrlPath(format!(
+                "{} is outside of {}",
+                path.display(),
+                out_dir.as_ref().display()
+            ))
+        })?;
+        let segments = relative
+            .components()
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        match segments.split_last() {
+            Some((last, parents)) if last == "index.html" => {
+                Self::new(format!("{}/", parents.join("/")))
+            }
+            _ => Self::new(segments.join("/")),
+        }
+    }
+
+    /// The URL path of the page rendered from a source file, relative to
+    /// the content directory, e.g. `content/docs/a/b.md` is `/docs/a/b/` or
+    /// `/docs/a/b.html`. `index` files map to their directory, `_index` ones
+    /// as well so sections can have a page.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the file isn't inside the content
+    /// directory or its path isn't a valid URL path.
+    pub fn from_source(
+        content_dir: impl AsRef<Path>,
+        path: impl AsRef<Path>,
+        style: UrlStyle,
+    ) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let relative = path.strip_prefix(content_dir.as_ref()).map_err(|_| {
+            Error::InvalidUrlPath(format!(
+                "{} is outside of {}",
+                path.display(),
+                content_dir.as_ref().display()
+            ))
+        })?;
+        let mut segments = relative
+            .parent()
+            .into_iter()
+            .flat_map(Path::components)
+            .filter_map(|component| match component {
+                Component::Normal(segment) => Some(segment.to_string_lossy().into_owned()),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let stem = relative
+            .file_stem()
+            .ok_or_else(|| Error::InvalidUrlPath(format!("{} has no file name", path.display())))?
+            .to_string_lossy();
+        let is_index = stem == "index" || stem == "_index";
+        match style {
+            UrlStyle::Html if is_index => segments.push("index.html".to_string()),
+            UrlStyle::Html => segments.push(format!("{stem}.html")),
+            UrlStyle::Pretty if is_index => {}
+            UrlStyle::Pretty => segments.push(stem.into_owned()),
+        }
+        let mut url = segments.join("/");
+        if style == UrlStyle::Pretty {
+            url.push('/');
+        }
+        Self::new(url)
+    }
+
+    /// The file this URL path is written to within the output directory,
+    /// using the platform's separators. Directories map to `index.html`.
+    #[must_use]
+    pub fn to_output(&self, out_dir: impl AsRef<Path>) -> PathBuf {
+        let mut path = out_dir.as_ref().to_path_buf();
+        path.extend(self.segments());
+        if self.is_dir() {
+            path.push("index.html");
+        }
+        path
+    }
+
+    /// Append a segment or relative path.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidUrlPath`] if the result isn't a valid URL
+    /// path.
+    pub fn join(&self, path: impl AsRef<str>) -> Result<Self, Error> {
+        Self::new(format!("{}/{}", self.0, path.as_ref()))
+    }
+
+    /// The absolute URL for the given base URL, e.g. `https://example.com`.
+    #[must_use]
+    pub fn to_url(&self, base_url: &str) -> String {
+        format!("{}{}", base_url.trim_end_matches('/'), self.0)
+    }
+
+    /// Returns true if the path ends with `/`.
+    #[must_use]
+    pub fn is_dir(&self) -> bool {
+        self.0.ends_with('/')
+    }
+
+    /// Whether the path follows the URL style: with [`UrlStyle::Pretty`]
+    /// pages don't end in `.html`, with [`UrlStyle::Html`] only the root
+    /// ends in `/`. Other files, like `/feed.xml`, follow both.
+    #[must_use]
+    pub fn follows(&self, style: UrlStyle) -> bool {
+        follows(&self.0, style)
+    }
+
+    /// The path in the URL style: with [`UrlStyle::Pretty`] `/about.html`
+    /// and `/about/index.html` become `/about/`, with [`UrlStyle::Html`]
+    /// `/about/` becomes `/about.html`. The root and other files, like
+    /// `/feed.xml`, are kept.
+    #[must_use]
+    pub fn with_style(&self, style: UrlStyle) -> Self {
+        if self.follows(style) {
+            return self.clone();
+        }
+        match style {
+            UrlStyle::Pretty => {
+                let page = self
+                    .0
+                    .strip_suffix(".html")
+                    .or_else(|| self.0.strip_suffix(".htm"))
+                    .unwrap_or(&self.0);
+                let page = page.strip_suffix("/index").unwrap_or(page);
+                Self(format!("{page}/"))
+            }
+            UrlStyle::Html => Self(format!("{}.html", self.0.trim_end_matches('/'))),
+        }
+    }
+
+    /// The path segments.
+    pub fn segments(&self) -> impl Iterator<Item = &str> {
+        self.0.split('/').filter(|segment| !segment.is_empty())
+    }
+
+    /// The path as a string.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for UrlPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for UrlPath {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for UrlPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl TryFrom<&str> for UrlPath {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        Self::new(s)
+    }
+}
+
+impl<T: Send + Sync> Parsed<T> {
+    /// Render individual items in parallel, writing each to the file its
+    /// URL path maps to within the output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if any URL path is invalid or any file cannot be
+    /// written.
+    pub fn render_each_at<S: Into<String> + Send>(
+        self,
+        out_dir: impl AsRef<Path> + Sync,
+        render_fn: impl Fn(&T) -> S + Send + Sync,
+        url_fn: impl Fn(&T) -> Result<UrlPath, Error> + Send + Sync,
+    ) -> Result<Self, Error> {
+        let files = self
+            .items
+            .par_iter()
+            .map(|item| {
+                let path = url_fn(item)?.to_output(&out_dir);
+                Ok((path, render_fn(item).into().into_bytes()))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        output::write_batch(files)?;
+        Ok(self)
+    }
+}
+
+/// The output file for a source file, keeping its directories relative to
+/// the content directory, e.g. `content/docs/a/b.md` is written to
+/// `dist/docs/a/b/index.html` or `dist/docs/a/b.html`.
+/// See [`UrlPath::from_source`].
+///
+/// # Errors
+///
+/// Returns [`Error::InvalidUrlPath`] if the file isn't inside the content
+/// directory or its path isn't a valid URL path.
+pub fn rebase(
+    path: impl AsRef<Path>,
+    content_dir: impl AsRef<Path>,
+    out_dir: impl AsRef<Path>,
+    style: UrlStyle,
+) -> Result<PathBuf, Error> {
+    Ok(UrlPath::from_source(content_dir, path, style)?.to_output(out_dir))
+}
+
+fn follows(path: &str, style: UrlStyle) -> bool {
+    match style {
+        UrlStyle::Pretty => {
+            let file = path.rsplit('/').next().unwrap_or_default();
+            let extension = file.rsplit_once('.').map(|(_, extension)| extension);
+            !matches!(extension, Some("html" | "htm"))
+        }
+        UrlStyle::Html => path == "/" || !path.ends_with('/'),
+    }
+}
+
+/// The links in rendered HTML to pages on the same site that don't follow
+/// the URL style, like `/about.html` with [`UrlStyle::Pretty`], as they
+/// appear in `href` attributes. Useful to catch a mix of styles, e.g. from
+/// hand-written links in markdown.
+#[must_use]
+pub fn check_links(html: &str, style: UrlStyle) -> Vec<String> {
+    let mut violations = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find("href=") {
+        rest = &rest[start + "href=".len()..];
+        let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            continue;
+        };
+        rest = &rest[1..];
+        let Some(end) = rest.find(quote) else {
+            break;
+        };
+        let href = &rest[..end];
+        rest = &rest[end..];
+
+        let path = href.split(['?', '#']).next().unwrap_or_default();
+        let is_external = path.starts_with("//")
+            || path
+                .split('/')
+                .next()
+                .is_some_and(|first| first.contains(':'));
+        if path.is_empty() || is_external {
+            continue;
+        }
+        if !follows(path, style) {
+            violations.push(href.to_string());
+        }
+    }
+    violations
+}
+
+/// Decode `%20`-style escapes in a URL path.
+/// Returns `None` if an escape is invalid or the result isn't UTF-8.
+#[cfg(any(feature = "markdown", feature = "serve"))]
+pub(crate) fn percent_decode(input: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(input.len());
+    let mut iter = input.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_new() -> Result<(), Error> {
+        assert_eq!(
+            UrlPath::new("blog\\hello//./world/")?.as_str(),
+            "/blog/hello/world/"
+        );
+        assert_eq!(UrlPath::new("feed.xml")?.as_str(), "/feed.xml");
+        assert_eq!(UrlPath::new("")?, UrlPath::root());
+        assert!(UrlPath::new("/blog/../etc").is_err());
+        assert!(UrlPath::new("/hello world/").is_err());
+        assert!(UrlPath::new("/search?q=1").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_output() -> Result<(), Error> {
+        let out_dir = Path::new("dist");
+        let url = UrlPath::new("/blog/hello/")?;
+        assert_eq!(
+            url.to_output(out_dir),
+            out_dir.join("blog").join("hello").join("index.html")
+        );
+        assert_eq!(UrlPath::from_output(out_dir, url.to_output(out_dir))?, url);
+        assert_eq!(
+            UrlPath::from_output(out_dir, out_dir.join("feed.xml"))?.as_str(),
+            "/feed.xml"
+        );
+        assert!(UrlPath::from_output(out_dir, "static/feed.xml").is_err());
+        assert_eq!(
+            UrlPath::from_output(out_dir, out_dir.join("index.html"))?,
+            UrlPath::root()
+        );
+        assert_eq!(
+            url.join("cover.png")?.to_url("https://example.com/"),
+            "https://example.com/blog/hello/cover.png"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebase() -> Result<(), Error> {
+        let content = Path::new("content");
+        let dist = Path::new("dist");
+        let source = content.join("docs").join("a").join("b.md");
+        assert_eq!(
+            UrlPath::from_source(content, &source, UrlStyle::Pretty)?.as_str(),
+            "/docs/a/b/"
+        );
+        assert_eq!(
+            rebase(&source, content, dist, UrlStyle::Pretty)?,
+            dist.join("docs").join("a").join("b").join("index.html")
+        );
+        assert_eq!(
+            rebase(&source, content, dist, UrlStyle::Html)?,
+            dist.join("docs").join("a").join("b.html")
+        );
+        assert_eq!(
+            rebase(
+                content.join("docs").join("_index.md"),
+                content,
+                dist,
+                UrlStyle::Pretty
+            )?,
+            dist.join("docs").join("index.html")
+        );
+        assert_eq!(
+            UrlPath::from_source(content, content.join("index.md"), UrlStyle::Html)?.as_str(),
+            "/index.html"
+        );
+        assert_eq!(
+            UrlPath::from_source(content, content.join("index.md"), UrlStyle::Pretty)?,
+            UrlPath::root()
+        );
+        assert!(rebase("static/b.md", content, dist, UrlStyle::Pretty).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_url_style() -> Result<(), Error> {
+        assert!(UrlPath::new("/blog/hello/")?.follows(UrlStyle::Pretty));
+        assert!(!UrlPath::new("/blog/hello/")?.follows(UrlStyle::Html));
+        assert!(UrlPath::new("/blog/hello.html")?.follows(UrlStyle::Html));
+        assert!(!UrlPath::new("/index.html")?.follows(UrlStyle::Pretty));
+        assert!(UrlPath::root().follows(UrlStyle::Html));
+        assert!(UrlPath::new("/feed.xml")?.follows(UrlStyle::Pretty));
+
+        let restyled = |path: &str, style| UrlPath::new(path).map(|url| url.with_style(style));
+        assert_eq!(
+            restyled("/about.html", UrlStyle::Pretty)?.as_str(),
+            "/about/"
+        );
+        assert_eq!(restyled("/a/index.htm", UrlStyle::Pretty)?.as_str(), "/a/");
+        assert_eq!(restyled("/index.html", UrlStyle::Pretty)?, UrlPath::root());
+        assert_eq!(restyled("/blog/", UrlStyle::Html)?.as_str(), "/blog.html");
+        assert_eq!(restyled("/", UrlStyle::Html)?, UrlPath::root());
+        assert_eq!(restyled("/feed.xml", UrlStyle::Html)?.as_str(), "/feed.xml");
+
+        let html = r##"<a href="/about.html#team">About</a> <a href='/blog/'>Blog</a>
+            <a href="https://example.com/a.html">Ext</a> <a href="#top">Top</a>
+            <link href="/main.css"> <a href="hello.html?x=1">Hello</a> <a href="mailto:a@b.c">Mail</a>"##;
+        assert_eq!(
+            check_links(html, UrlStyle::Pretty),
+            ["/about.html#team", "hello.html?x=1"]
+        );
+        assert_eq!(check_links(html, UrlStyle::Html), ["/blog/"]);
+        Ok(())
+    }
+}
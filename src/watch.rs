@@ -1,14 +1,147 @@
-use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
+use notify_debouncer_mini::{
+    new_debouncer_opt,
+    notify::{self, PollWatcher, RecommendedWatcher, RecursiveMode, Watcher},
+    Config, DebounceEventResult, Debouncer,
+};
 use std::{
+    collections::BTreeSet,
+    fmt,
     path::{Path, PathBuf},
-    sync::mpsc,
+    sync::mpsc::{self, Receiver, RecvTimeoutError},
     time::Duration,
 };
 
+use crate::{
+    collection::{absolute, matches, relative_to_cwd},
+    shutdown::POLL_INTERVAL,
+    Error, Shutdown,
+};
+
 #[derive(thiserror::Error, Debug)]
 pub enum WatchError {
     #[error("Notify error: {0}")]
     Notify(#[from] notify_debouncer_mini::notify::Error),
+    #[error("io error: {0}")]
+    IO(#[from] std::io::Error),
+}
+
+/// Options for [`watch_with`].
+///
+/// ```no_run
+/// use std::time::Duration;
+///
+/// // Content on a network share doesn't emit filesystem events.
+/// let options = pichu::WatchOptions::new().poll(Duration::from_secs(1));
+/// pichu::watch_with(["content"], &options, |changed| println!("{changed:?}"))?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct WatchOptions {
+    debounce: Duration,
+    poll: Option<Duration>,
+    follow_symlinks: bool,
+    shutdown: Shutdown,
+    /// Name, absolute pattern and whether the pattern was relative.
+    collections: Vec<(String, glob::Pattern, bool)>,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        Self {
+            debounce: Duration::from_millis(200),
+            poll: None,
+            follow_symlinks: true,
+            shutdown: Shutdown::new(),
+            collections: Vec::new(),
+        }
+    }
+}
+
+impl WatchOptions {
+    /// Create the default options: native filesystem events, debounced by
+    /// 200ms, following symlinks.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set how long to wait for further changes before calling the function.
+    #[must_use]
+    pub fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Scan the paths for changes at the given interval instead of relying
+    /// on filesystem events, which aren't emitted on network filesystems
+    /// like NFS or SSHFS. Modifications are detected by modification time,
+    /// which is compared with one second resolution.
+    #[must_use]
+    pub fn poll(mut self, interval: Duration) -> Self {
+        self.poll = Some(interval);
+        self
+    }
+
+    /// Whether to follow symlinks inside the watched directories and watch
+    /// their targets, e.g. content linked in from elsewhere in a monorepo.
+    /// Changes are reported at the path of the link. Defaults to true.
+    #[must_use]
+    pub fn follow_symlinks(mut self, follow_symlinks: bool) -> Self {
+        self.follow_symlinks = follow_symlinks;
+        self
+    }
+
+    /// Stop watching once a shutdown is requested, see [`watch_until`].
+    #[must_use]
+    pub fn shutdown(mut self, shutdown: &Shutdown) -> Self {
+        self.shutdown = shutdown.clone();
+        self
+    }
+
+    /// Register a collection, usually with the pattern it was globbed with,
+    /// so [`watch_changes`] reports changes to matching files under its name.
+    /// If a file matches several collections, the first one registered wins.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pattern is invalid.
+    pub fn collection(
+        mut self,
+        name: impl Into<String>,
+        pattern: impl AsRef<str>,
+    ) -> Result<Self, Error> {
+        let pattern = Path::new(pattern.as_ref());
+        self.collections.push((
+            name.into(),
+            glob::Pattern::new(&absolute(pattern).to_string_lossy())?,
+            pattern.is_relative(),
+        ));
+        Ok(self)
+    }
+}
+
+/// The files of a collection that changed, passed to [`watch_changes`].
+/// Renaming a file removes the old path and creates the new one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangeSet<'a> {
+    /// The name of the collection registered with
+    /// [`WatchOptions::collection`], or `None` for files not belonging to
+    /// any collection.
+    pub collection: Option<&'a str>,
+    /// Files that didn't exist before.
+    pub created: Vec<PathBuf>,
+    /// Files that existed before and still exist.
+    pub modified: Vec<PathBuf>,
+    /// Files that no longer exist.
+    pub removed: Vec<PathBuf>,
+}
+
+impl ChangeSet<'_> {
+    /// Returns true if no files changed.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.created.is_empty() && self.modified.is_empty() && self.removed.is_empty()
+    }
 }
 
 /// Watch the given paths recursively and call the function on change.
@@ -18,24 +151,679 @@ pub enum WatchError {
 /// Returns an error if the watcher cannot be created, fails to watch or if a watch failed.
 pub fn watch<P: AsRef<Path>>(
     paths: impl IntoIterator<Item = P>,
-    on_change: impl Fn(Vec<PathBuf>),
+    on_change: impl FnMut(Vec<PathBuf>),
+) -> Result<(), WatchError> {
+    watch_with(paths, &WatchOptions::new(), on_change)
+}
+
+/// Like [`watch`], but stops watching and returns once a shutdown is
+/// requested, e.g. with [`Shutdown::ctrl_c`].
+/// A call to the function that is already running is finished first.
+///
+/// # Errors
+///
+/// Returns an error if the watcher cannot be created, fails to watch or if a watch failed.
+pub fn watch_until<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+    shutdown: &Shutdown,
+    on_change: impl FnMut(Vec<PathBuf>),
+) -> Result<(), WatchError> {
+    watch_with(paths, &WatchOptions::new().shutdown(shutdown), on_change)
+}
+
+/// Like [`watch`], with the given options.
+///
+/// # Errors
+///
+/// Returns an error if the watcher cannot be created, fails to watch or if a watch failed.
+pub fn watch_with<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &WatchOptions,
+    on_change: impl FnMut(Vec<PathBuf>),
 ) -> Result<(), WatchError> {
+    let mut notify_config = notify::Config::default().with_follow_symlinks(options.follow_symlinks);
+    if let Some(interval) = options.poll {
+        notify_config = notify_config.with_poll_interval(interval);
+    }
+    let config = Config::default()
+        .with_timeout(options.debounce)
+        .with_notify_config(notify_config);
+
     let (tx, rx) = mpsc::channel::<DebounceEventResult>();
-    let mut debouncer = new_debouncer(Duration::from_millis(200), tx)?;
+    if options.poll.is_some() {
+        let debouncer = new_debouncer_opt::<_, PollWatcher>(config, tx)?;
+        run(debouncer, &rx, paths, &options.shutdown, on_change)
+    } else {
+        let debouncer = new_debouncer_opt::<_, RecommendedWatcher>(config, tx)?;
+        run(debouncer, &rx, paths, &options.shutdown, on_change)
+    }
+}
+
+/// Like [`watch_with`], but calls the function with the changes grouped by
+/// the collections registered with [`WatchOptions::collection`], and sorted
+/// into created, modified and removed files.
+/// The function is called once per collection with changes, in the order
+/// they were registered, followed by the changes to other files.
+/// Paths of collections with relative patterns are relative to the current
+/// directory, like the paths returned by [`glob`](crate::glob).
+///
+/// ```no_run
+/// let options = pichu::WatchOptions::new().collection("posts", "content/posts/*.md")?;
+/// pichu::watch_changes(["content"], &options, |changes| {
+///     if changes.collection == Some("posts") && !changes.removed.is_empty() {
+///         // Prune the outputs of deleted posts.
+///     }
+/// })?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+///
+/// # Errors
+///
+/// Returns an error if the watcher cannot be created, fails to watch or if a watch failed.
+pub fn watch_changes<P: AsRef<Path>>(
+    paths: impl IntoIterator<Item = P>,
+    options: &WatchOptions,
+    mut on_change: impl FnMut(ChangeSet<'_>),
+) -> Result<(), WatchError> {
+    let paths = paths
+        .into_iter()
+        .map(|path| path.as_ref().to_path_buf())
+        .collect::<Vec<_>>();
+    let mut router = ChangeRouter::new(options).existing(paths.iter().flat_map(|path| files(path)));
+
+    watch_with(&paths, options, |changed| {
+        let events = changed.into_iter().filter_map(|path| {
+            if path.is_file() {
+                Some(ChangeEvent::Write(path))
+            } else if path.exists() {
+                None
+            } else {
+                Some(ChangeEvent::Remove(path))
+            }
+        });
+        for set in router.route(events) {
+            on_change(set);
+        }
+    })
+}
+
+/// A change to the filesystem, as reported to a [`ChangeRouter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChangeEvent {
+    /// A file was created or written to.
+    Write(PathBuf),
+    /// A file or directory was removed, or renamed away.
+    Remove(PathBuf),
+}
+
+/// Sorts changes into the [`ChangeSet`]s passed to [`watch_changes`],
+/// keeping track of the existing files to tell created from modified ones.
+/// Fed with synthetic events, it tests which rebuilds a change triggers
+/// without touching the filesystem or waiting for a watcher.
+///
+/// ```
+/// use pichu::{ChangeEvent, ChangeRouter, WatchOptions};
+/// use std::path::Path;
+///
+/// let options = WatchOptions::new().collection("posts", "/site/posts/*.md")?;
+/// let mut router = ChangeRouter::new(&options).existing(["/site/posts/a.md"]);
+/// let sets = router.route([
+///     ChangeEvent::Write("/site/posts/a.md".into()),
+///     ChangeEvent::Write("/site/style.css".into()),
+/// ]);
+/// assert_eq!(sets[0].collection, Some("posts"));
+/// assert_eq!(sets[0].modified, [Path::new("/site/posts/a.md")]);
+/// assert_eq!(sets[1].collection, None);
+/// assert_eq!(sets[1].created, [Path::new("/site/style.css")]);
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ChangeRouter<'a> {
+    options: &'a WatchOptions,
+    /// Files existing before a change, made absolute.
+    known: BTreeSet<PathBuf>,
+}
+
+impl<'a> ChangeRouter<'a> {
+    /// Route changes to the collections registered in the options, assuming
+    /// no files exist yet.
+    #[must_use]
+    pub fn new(options: &'a WatchOptions) -> Self {
+        Self {
+            options,
+            known: BTreeSet::new(),
+        }
+    }
+
+    /// Add files that exist before the first change.
+    #[must_use]
+    pub fn existing<P: AsRef<Path>>(mut self, paths: impl IntoIterator<Item = P>) -> Self {
+        self.known
+            .extend(paths.into_iter().map(|path| absolute(path.as_ref())));
+        self
+    }
+
+    /// Sort a batch of changes, like one call of the function passed to
+    /// [`watch_with`], into the change sets [`watch_changes`] would pass on,
+    /// in the same order. Empty change sets are left out.
+    pub fn route(&mut self, events: impl IntoIterator<Item = ChangeEvent>) -> Vec<ChangeSet<'a>> {
+        let options = self.options;
+        let mut sets = options
+            .collections
+            .iter()
+            .map(|(name, _, _)| ChangeSet {
+                collection: Some(name.as_str()),
+                ..Default::default()
+            })
+            .chain([ChangeSet::default()])
+            .collect::<Vec<_>>();
+
+        let mut written = BTreeSet::new();
+        let mut removed = BTreeSet::new();
+        for event in events {
+            match event {
+                ChangeEvent::Write(path) => {
+                    let path = absolute(&path);
+                    removed.remove(&path);
+                    written.insert(path);
+                }
+                ChangeEvent::Remove(path) => {
+                    let path = absolute(&path);
+                    written.retain(|p: &PathBuf| !p.starts_with(&path));
+                    // A removed directory is only reported once, not per
+                    // file in it.
+                    removed.extend(
+                        self.known
+                            .iter()
+                            .filter(|known| known.starts_with(&path))
+                            .cloned(),
+                    );
+                }
+            }
+        }
+
+        let changes = written
+            .into_iter()
+            .map(|path| (path, true))
+            .chain(removed.into_iter().map(|path| (path, false)))
+            .collect::<BTreeSet<_>>();
+        for (path, exists) in changes {
+            let was_known = self.known.contains(&path);
+            let (index, relative) = options
+                .collections
+                .iter()
+                .position(|(_, pattern, _)| matches(pattern, &path))
+                .map_or((sets.len() - 1, false), |i| (i, options.collections[i].2));
+            let set = &mut sets[index];
+            let reported = if relative {
+                relative_to_cwd(path.clone())
+            } else {
+                path.clone()
+            };
+            if exists {
+                if was_known {
+                    set.modified.push(reported);
+                } else {
+                    set.created.push(reported);
+                    self.known.insert(path);
+                }
+            } else {
+                set.removed.push(reported);
+                self.known.remove(&path);
+            }
+        }
 
+        sets.retain(|set| !set.is_empty());
+        sets
+    }
+}
+
+type RebuildFn<'f> = dyn FnMut(&ChangeSet<'_>) + 'f;
+
+/// Which changes run a rebuild, see [`RebuildRoutes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Trigger {
+    Collection(String),
+    Other,
+    Any,
+}
+
+impl Trigger {
+    fn matches(&self, changes: &ChangeSet<'_>) -> bool {
+        match self {
+            Self::Collection(name) => changes.collection == Some(name.as_str()),
+            Self::Other => changes.collection.is_none(),
+            Self::Any => true,
+        }
+    }
+}
+
+/// The routing table of a watch loop: named rebuild functions and the
+/// [`ChangeSet`]s that run them. Pass the change sets of
+/// [`watch_changes`] to [`RebuildRoutes::dispatch`], and test which
+/// rebuilds a change runs with [`RebuildRoutes::would_fire`], without
+/// running them or touching the filesystem.
+///
+/// ```
+/// use pichu::{ChangeEvent, ChangeRouter, RebuildRoutes, WatchOptions};
+///
+/// let options = WatchOptions::new().collection("posts", "/site/posts/*.md")?;
+/// let routes = RebuildRoutes::new()
+///     .on("posts", "blog", |changes| println!("Rebuilding {:?}", changes.modified))
+///     .on_other("assets", |_| println!("Rebuilding assets"));
+/// let mut router = ChangeRouter::new(&options);
+/// let fired = routes.would_fire(&mut router, [ChangeEvent::Write("/site/posts/a.md".into())]);
+/// assert_eq!(fired, ["blog"]);
+/// # Ok::<(), pichu::Error>(())
+/// ```
+#[derive(Default)]
+pub struct RebuildRoutes<'f> {
+    routes: Vec<(Trigger, String, Box<RebuildFn<'f>>)>,
+}
+
+impl<'f> RebuildRoutes<'f> {
+    /// Create an empty routing table.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Run the rebuild when files of the collection, registered with
+    /// [`WatchOptions::collection`], change.
+    #[must_use]
+    pub fn on(
+        self,
+        collection: impl Into<String>,
+        name: impl Into<String>,
+        rebuild: impl FnMut(&ChangeSet<'_>) + 'f,
+    ) -> Self {
+        self.route(Trigger::Collection(collection.into()), name, rebuild)
+    }
+
+    /// Run the rebuild when files outside of all collections change.
+    #[must_use]
+    pub fn on_other(
+        self,
+        name: impl Into<String>,
+        rebuild: impl FnMut(&ChangeSet<'_>) + 'f,
+    ) -> Self {
+        self.route(Trigger::Other, name, rebuild)
+    }
+
+    /// Run the rebuild on every change set.
+    #[must_use]
+    pub fn on_any(self, name: impl Into<String>, rebuild: impl FnMut(&ChangeSet<'_>) + 'f) -> Self {
+        self.route(Trigger::Any, name, rebuild)
+    }
+
+    fn route(
+        mut self,
+        trigger: Trigger,
+        name: impl Into<String>,
+        rebuild: impl FnMut(&ChangeSet<'_>) + 'f,
+    ) -> Self {
+        self.routes.push((trigger, name.into(), Box::new(rebuild)));
+        self
+    }
+
+    /// Run the rebuilds for the change set in the order they were added,
+    /// returning their names.
+    pub fn dispatch(&mut self, changes: &ChangeSet<'_>) -> Vec<&str> {
+        let mut fired = Vec::new();
+        for (trigger, name, rebuild) in &mut self.routes {
+            if trigger.matches(changes) {
+                rebuild(changes);
+                fired.push(name.as_str());
+            }
+        }
+        fired
+    }
+
+    /// The names of the rebuilds the events would run, routed like
+    /// [`watch_changes`] would with the router, without running them.
+    /// A rebuild is listed once, even if several change sets run it.
+    pub fn would_fire(
+        &self,
+        router: &mut ChangeRouter<'_>,
+        events: impl IntoIterator<Item = ChangeEvent>,
+    ) -> Vec<&str> {
+        let sets = router.route(events);
+        self.routes
+            .iter()
+            .filter(|(trigger, _, _)| sets.iter().any(|set| trigger.matches(set)))
+            .map(|(_, name, _)| name.as_str())
+            .collect()
+    }
+}
+
+impl fmt::Debug for RebuildRoutes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.routes.iter().map(|(trigger, name, _)| (trigger, name)))
+            .finish()
+    }
+}
+
+/// All files at or below `path`, made absolute.
+fn files(path: &Path) -> Vec<PathBuf> {
+    if path.is_file() {
+        return vec![absolute(path)];
+    }
+    let pattern = Path::new(&glob::Pattern::escape(&absolute(path).to_string_lossy())).join("**/*");
+    glob::glob(&pattern.to_string_lossy())
+        .map(|paths| paths.flatten().filter(|path| path.is_file()).collect())
+        .unwrap_or_default()
+}
+
+fn run<T: Watcher, P: AsRef<Path>>(
+    mut debouncer: Debouncer<T>,
+    rx: &Receiver<DebounceEventResult>,
+    paths: impl IntoIterator<Item = P>,
+    shutdown: &Shutdown,
+    mut on_change: impl FnMut(Vec<PathBuf>),
+) -> Result<(), WatchError> {
     for path in paths {
-        debouncer.watcher().watch(
-            path.as_ref(),
-            notify_debouncer_mini::notify::RecursiveMode::Recursive,
-        )?;
+        debouncer
+            .watcher()
+            .watch(path.as_ref(), RecursiveMode::Recursive)?;
     }
 
-    for events_res in rx {
+    while !shutdown.is_requested() {
+        let events_res = match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(events_res) => events_res,
+            Err(RecvTimeoutError::Timeout) => continue,
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
         let changed_paths: Vec<PathBuf> = events_res?.into_iter().map(|event| event.path).collect();
         if !changed_paths.is_empty() {
             on_change(changed_paths);
         }
     }
 
+    if shutdown.is_requested() {
+        shutdown.stopped()?;
+    }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        env, fs, io,
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+        time::Instant,
+    };
+
+    /// How long a test waits for the changes it expects.
+    const TIMEOUT: Duration = Duration::from_secs(20);
+
+    /// Touch `ready` until the watcher sends a change, so changes made
+    /// afterwards happen while it is running, however long it takes to
+    /// start.
+    fn wait_until_watched<T>(ready: &Path, rx: &Receiver<T>) -> Result<(), io::Error> {
+        let deadline = Instant::now() + TIMEOUT;
+        for i in 0.. {
+            fs::write(ready, i.to_string())?;
+            match rx.recv_timeout(Duration::from_millis(100)) {
+                Ok(_) => return Ok(()),
+                Err(RecvTimeoutError::Timeout) if Instant::now() < deadline => {}
+                Err(_) => break,
+            }
+        }
+        Err(io::Error::other("watcher didn't start"))
+    }
+
+    /// Receive until `done` returns true, failing after [`TIMEOUT`] instead
+    /// of blocking if an expected change is never reported.
+    fn receive_until<T>(
+        rx: &Receiver<T>,
+        mut done: impl FnMut(T) -> bool,
+    ) -> Result<(), io::Error> {
+        let deadline = Instant::now() + TIMEOUT;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let received = rx
+                .recv_timeout(timeout)
+                .map_err(|e| io::Error::other(format!("expected changes: {e}")))?;
+            if done(received) {
+                return Ok(());
+            }
+        }
+    }
+
+    #[test]
+    fn test_change_router() -> Result<(), Error> {
+        let options = WatchOptions::new()
+            .collection("posts", "/site/content/posts/*.md")?
+            .collection("pages", "/site/content/**/*.md")?;
+        let mut router = ChangeRouter::new(&options).existing([
+            "/site/content/posts/a.md",
+            "/site/content/posts/b.md",
+            "/site/content/about.md",
+        ]);
+
+        let sets = router.route([
+            ChangeEvent::Write("/site/content/posts/a.md".into()),
+            ChangeEvent::Write("/site/content/posts/c.md".into()),
+            ChangeEvent::Write("/site/content/posts/c.md".into()),
+            ChangeEvent::Write("/site/templates/base.html".into()),
+        ]);
+        assert_eq!(
+            sets,
+            [
+                ChangeSet {
+                    collection: Some("posts"),
+                    created: vec!["/site/content/posts/c.md".into()],
+                    modified: vec!["/site/content/posts/a.md".into()],
+                    removed: vec![],
+                },
+                ChangeSet {
+                    collection: None,
+                    created: vec!["/site/templates/base.html".into()],
+                    ..Default::default()
+                },
+            ]
+        );
+
+        // Created files are known afterwards, removing a directory removes
+        // the files in it.
+        let sets = router.route([
+            ChangeEvent::Write("/site/content/posts/c.md".into()),
+            ChangeEvent::Remove("/site/content".into()),
+        ]);
+        assert_eq!(sets.len(), 2);
+        assert_eq!(sets[0].collection, Some("posts"));
+        assert_eq!(
+            sets[0].removed,
+            [
+                Path::new("/site/content/posts/a.md"),
+                Path::new("/site/content/posts/b.md"),
+                Path::new("/site/content/posts/c.md"),
+            ]
+        );
+        assert!(sets[0].modified.is_empty());
+        assert_eq!(sets[1].collection, Some("pages"));
+        assert_eq!(sets[1].removed, [Path::new("/site/content/about.md")]);
+
+        assert!(router
+            .route([ChangeEvent::Remove("/site/content/posts/a.md".into())])
+            .is_empty());
+
+        // `*` doesn't match across directories, so nested posts belong to
+        // the pages collection.
+        let sets = router.route([ChangeEvent::Write("/site/content/posts/2024/d.md".into())]);
+        assert_eq!(sets.len(), 1);
+        assert_eq!(sets[0].collection, Some("pages"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_rebuild_routes() -> Result<(), Error> {
+        let options = WatchOptions::new()
+            .collection("posts", "/site/content/posts/*.md")?
+            .collection("pages", "/site/content/**/*.md")?;
+        let mut rebuilt = Vec::new();
+        let mut rebuilds = RebuildRoutes::new()
+            .on("posts", "blog", |changes| {
+                rebuilt.extend(changes.created.clone());
+            })
+            .on("posts", "feed", |_| {})
+            .on("pages", "pages", |_| {})
+            .on_other("assets", |_| {})
+            .on_any("search", |_| {});
+
+        let mut router = ChangeRouter::new(&options).existing(["/site/content/posts/a.md"]);
+        let post = || ChangeEvent::Write("/site/content/posts/b.md".into());
+        assert_eq!(
+            rebuilds.would_fire(&mut router, [post()]),
+            ["blog", "feed", "search"]
+        );
+        assert_eq!(
+            rebuilds.would_fire(&mut router, [ChangeEvent::Write("/site/style.css".into())]),
+            ["assets", "search"]
+        );
+        assert!(rebuilds.would_fire(&mut router, []).is_empty());
+
+        let mut router = ChangeRouter::new(&options);
+        for changes in router.route([post()]) {
+            assert_eq!(rebuilds.dispatch(&changes), ["blog", "feed", "search"]);
+        }
+        drop(rebuilds);
+        assert_eq!(rebuilt, [Path::new("/site/content/posts/b.md")]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_until() -> Result<(), WatchError> {
+        let dir = env::temp_dir().join("pichu_test_watch_until");
+        fs::create_dir_all(&dir).map_err(notify_debouncer_mini::notify::Error::io)?;
+
+        let shutdown = Shutdown::new();
+        let saved = Arc::new(AtomicBool::new(false));
+        shutdown.on_stop({
+            let saved = Arc::clone(&saved);
+            move || {
+                saved.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        });
+        let handle = shutdown.clone();
+        let stopper = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(300));
+            handle.request();
+        });
+        watch_until([&dir], &shutdown, |_| {})?;
+        assert!(stopper.join().is_ok());
+        assert!(saved.load(Ordering::SeqCst));
+
+        fs::remove_dir_all(&dir).map_err(notify_debouncer_mini::notify::Error::io)?;
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_watch_with_poll() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_watch_with_poll");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("site"))?;
+        fs::create_dir_all(dir.join("shared"))?;
+        std::os::unix::fs::symlink(dir.join("shared"), dir.join("site/content"))?;
+
+        let shutdown = Shutdown::new();
+        let options = WatchOptions::new()
+            .poll(Duration::from_millis(50))
+            .debounce(Duration::from_millis(50))
+            .shutdown(&shutdown);
+        let (tx, rx) = mpsc::channel();
+        let watcher = thread::spawn({
+            let site = dir.join("site");
+            move || watch_with([site], &options, |changed| drop(tx.send(changed)))
+        });
+
+        let result = wait_until_watched(&dir.join("shared/ready"), &rx).and_then(|()| {
+            fs::write(dir.join("shared/post.md"), "# Hello")?;
+            receive_until(&rx, |changed| {
+                changed.contains(&dir.join("site/content/post.md"))
+            })
+        });
+        shutdown.request();
+        watcher.join().map_err(|_| "watcher panicked")??;
+        result?;
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_watch_changes() -> Result<(), Box<dyn std::error::Error>> {
+        let dir = env::temp_dir().join("pichu_test_watch_changes");
+        if dir.exists() {
+            fs::remove_dir_all(&dir)?;
+        }
+        fs::create_dir_all(dir.join("posts"))?;
+        fs::write(dir.join("posts/a.md"), "a")?;
+        fs::write(dir.join("posts/c.md"), "c")?;
+        fs::write(dir.join("style.css"), "")?;
+
+        let shutdown = Shutdown::new();
+        let options = WatchOptions::new()
+            .debounce(Duration::from_millis(100))
+            .shutdown(&shutdown)
+            .collection("posts", dir.join("posts/*.md").to_string_lossy())?;
+        let (tx, rx) = mpsc::channel();
+        let watcher = thread::spawn({
+            let dir = dir.clone();
+            move || {
+                watch_changes([dir], &options, |changes| {
+                    let posts = changes.collection == Some("posts");
+                    drop(tx.send((posts, changes.created, changes.modified, changes.removed)));
+                })
+            }
+        });
+
+        let ready = dir.join("ready");
+        let mut posts = ChangeSet::default();
+        let mut other = ChangeSet::default();
+        let result = wait_until_watched(&ready, &rx).and_then(|()| {
+            fs::write(dir.join("posts/a.md"), "a, edited")?;
+            fs::write(dir.join("posts/b.md"), "b")?;
+            fs::remove_file(dir.join("posts/c.md"))?;
+            fs::write(dir.join("style.css"), "body {}")?;
+            receive_until(&rx, |(is_posts, created, modified, removed)| {
+                let set = if is_posts { &mut posts } else { &mut other };
+                let changed = |paths: Vec<PathBuf>| paths.into_iter().filter(|p| *p != ready);
+                set.created.extend(changed(created));
+                set.modified.extend(changed(modified));
+                set.removed.extend(changed(removed));
+                posts.created.contains(&dir.join("posts/b.md"))
+                    && posts.modified.contains(&dir.join("posts/a.md"))
+                    && posts.removed.contains(&dir.join("posts/c.md"))
+                    && other.modified.contains(&dir.join("style.css"))
+            })
+        });
+        shutdown.request();
+        watcher.join().map_err(|_| "watcher panicked")??;
+        result?;
+
+        assert_eq!(posts.created, [dir.join("posts/b.md")]);
+        // A new file can be reported as created and then as modified, and an
+        // edited file as modified twice, if its write ends up in the next
+        // batch of events.
+        posts.modified.retain(|path| !posts.created.contains(path));
+        posts.modified.dedup();
+        assert_eq!(posts.modified, [dir.join("posts/a.md")]);
+        assert_eq!(posts.removed, [dir.join("posts/c.md")]);
+        assert!(other.created.is_empty());
+        assert_eq!(other.modified, [dir.join("style.css")]);
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}
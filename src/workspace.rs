@@ -0,0 +1,183 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Counts workspaces created by this process, so their names never repeat.
+static WORKSPACES: AtomicUsize = AtomicUsize::new(0);
+
+/// Counts the paths handed out by [`temp_path`].
+static NAMES: AtomicUsize = AtomicUsize::new(0);
+
+/// A hidden path next to `path` for a temporary file, unique like the
+/// directories of a [`Workspace`], to move over `path` in one step.
+pub(crate) fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = OsString::from(".");
+    file_name.push(path.file_name().unwrap_or_default());
+    file_name.push(format!(
+        ".{}-{}.tmp",
+        process::id(),
+        NAMES.fetch_add(1, Ordering::Relaxed)
+    ));
+    path.with_file_name(file_name)
+}
+
+/// Move a file written in a workspace to `to`. If the workspace is on
+/// another filesystem, the file is copied next to `to` first, so `to` is
+/// still replaced in one step.
+#[cfg(any(feature = "images", feature = "sqlite"))]
+pub(crate) fn persist(from: &Path, to: &Path) -> Result<(), io::Error> {
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    let tmp = temp_path(to);
+    fs::copy(from, &tmp)?;
+    fs::rename(&tmp, to).inspect_err(|_| {
+        let _ = fs::remove_file(&tmp);
+    })?;
+    fs::remove_file(from)
+}
+
+/// Scratch space for a build, e.g. for intermediate files of generated
+/// images. Removed with everything in it when dropped, unless kept with
+/// [`Workspace::keep`]. Can be shared between threads.
+///
+/// ```
+/// let workspace = pichu::workspace()?;
+/// let dir = workspace.dir("og-images")?;
+/// std::fs::write(dir.join("cover.svg"), "<svg/>")?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+#[derive(Debug)]
+pub struct Workspace {
+    root: PathBuf,
+    dirs: AtomicUsize,
+    keep: bool,
+}
+
+/// Create a [`Workspace`] in the system's temporary directory, named after
+/// the process and a counter so parallel builds and threads never share
+/// one.
+///
+/// # Errors
+///
+/// Returns an error if the directory cannot be created.
+pub fn workspace() -> Result<Workspace, io::Error> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.subsec_nanos());
+    loop {
+        let root = env::temp_dir().join(format!(
+            "pichu-{}-{nanos}-{}",
+            process::id(),
+            WORKSPACES.fetch_add(1, Ordering::Relaxed)
+        ));
+        match fs::create_dir(&root) {
+            Ok(()) => {
+                return Ok(Workspace {
+                    root,
+                    dirs: AtomicUsize::new(0),
+                    keep: false,
+                })
+            }
+            // Left behind by an earlier process with the same ID.
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+impl Workspace {
+    /// The root directory of the workspace.
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        &self.root
+    }
+
+    /// Create a new, empty directory in the workspace. The name is used as
+    /// a prefix, so every call returns a different directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be created.
+    pub fn dir(&self, name: &str) -> Result<PathBuf, io::Error> {
+        let dir = self.root.join(format!(
+            "{name}-{}",
+            self.dirs.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir(&dir)?;
+        Ok(dir)
+    }
+
+    /// Keep the workspace on disk instead of removing it, e.g. to inspect
+    /// intermediate files after a failed build. Returns its path.
+    #[must_use]
+    pub fn keep(mut self) -> PathBuf {
+        self.keep = true;
+        self.root.clone()
+    }
+}
+
+impl Drop for Workspace {
+    fn drop(&mut self) {
+        if !self.keep {
+            let _ = fs::remove_dir_all(&self.root);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rayon::prelude::*;
+
+    #[test]
+    fn test_workspace() -> Result<(), io::Error> {
+        let a = workspace()?;
+        let b = workspace()?;
+        assert_ne!(a.path(), b.path());
+        assert!(a.path().starts_with(env::temp_dir()));
+
+        let dirs = (0..32)
+            .into_par_iter()
+            .map(|_| a.dir("images"))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(dirs.iter().all(|dir| dir.is_dir()));
+        let mut unique = dirs.clone();
+        unique.sort();
+        unique.dedup();
+        assert_eq!(unique.len(), dirs.len());
+
+        fs::write(dirs[0].join("file"), "")?;
+        let root = a.path().to_path_buf();
+        drop(a);
+        assert!(!root.exists());
+
+        let kept = b.keep();
+        assert!(kept.exists());
+        fs::remove_dir_all(kept)?;
+        Ok(())
+    }
+
+    #[cfg(any(feature = "images", feature = "sqlite"))]
+    #[test]
+    fn test_persist() -> Result<(), io::Error> {
+        let dir = env::temp_dir().join("pichu_test_persist");
+        fs::create_dir_all(&dir)?;
+        let workspace = workspace()?;
+        let from = workspace.path().join("hero-640.webp");
+        fs::write(&from, "webp")?;
+
+        persist(&from, &dir.join("hero-640.webp"))?;
+        assert!(!from.exists());
+        assert_eq!(fs::read_to_string(dir.join("hero-640.webp"))?, "webp");
+
+        fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+}